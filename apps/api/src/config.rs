@@ -1,5 +1,8 @@
 use anyhow::{Context, Result};
+use domain::QualityTable;
+use presets::Preset;
 use std::env;
+use std::path::Path;
 
 /// Application configuration loaded from environment variables
 #[derive(Debug, Clone)]
@@ -8,6 +11,19 @@ pub struct Config {
     pub port: u16,
     pub cors_allowed_origins: Vec<String>,
     pub rust_log: String,
+    /// Images compressed at once by a batch request (`0` = one per CPU core). Also bounds how
+    /// many decoded images a batch holds in memory.
+    pub batch_concurrency: usize,
+    /// Seconds a finished `/api/jobs` job and its outputs are kept.
+    pub job_ttl_secs: u64,
+    /// Jobs that may be queued or running at once; further submissions get 503. Also the
+    /// number of finished jobs kept (oldest dropped first).
+    pub job_queue_limit: usize,
+    /// Presets from the JSON or TOML file named by `PRESETS_FILE`, offered with the built-ins.
+    pub presets: Vec<Preset>,
+    /// Quality per level and format from `QUALITY_TABLE` (JSON, only the entries that differ
+    /// from the defaults); `None` keeps the defaults.
+    pub quality_table: Option<QualityTable>,
 }
 
 impl Config {
@@ -30,11 +46,46 @@ impl Config {
 
         let rust_log = env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string());
 
+        let batch_concurrency = env::var("BATCH_CONCURRENCY")
+            .unwrap_or_else(|_| "0".to_string())
+            .parse::<usize>()
+            .context("BATCH_CONCURRENCY must be a non-negative integer")?;
+
+        let job_ttl_secs = env::var("JOB_TTL_SECS")
+            .unwrap_or_else(|_| "3600".to_string())
+            .parse::<u64>()
+            .context("JOB_TTL_SECS must be a non-negative integer")?;
+
+        let job_queue_limit = env::var("JOB_QUEUE_LIMIT")
+            .unwrap_or_else(|_| "16".to_string())
+            .parse::<usize>()
+            .context("JOB_QUEUE_LIMIT must be a non-negative integer")?;
+
+        let presets = match env::var("PRESETS_FILE") {
+            Ok(path) if !path.trim().is_empty() => presets::load_presets_file(Path::new(&path))
+                .map_err(anyhow::Error::msg)
+                .context("PRESETS_FILE must name a valid preset file")?,
+            _ => Vec::new(),
+        };
+
+        let quality_table = match env::var("QUALITY_TABLE") {
+            Ok(json) if !json.trim().is_empty() => Some(
+                serde_json::from_str(&json)
+                    .context("QUALITY_TABLE must be a JSON quality table")?,
+            ),
+            _ => None,
+        };
+
         Ok(Config {
             app_env,
             port,
             cors_allowed_origins,
             rust_log,
+            batch_concurrency,
+            job_ttl_secs,
+            job_queue_limit,
+            presets,
+            quality_table,
         })
     }
 
@@ -43,6 +94,9 @@ impl Config {
         if self.port == 0 {
             anyhow::bail!("PORT must be greater than 0");
         }
+        if self.job_queue_limit == 0 {
+            anyhow::bail!("JOB_QUEUE_LIMIT must be greater than 0");
+        }
         Ok(())
     }
 }
@@ -0,0 +1,433 @@
+//! Asynchronous compression jobs: `POST /api/jobs` queues a batch and returns at once, clients
+//! poll `GET /api/jobs/:id` and download each output from `GET /api/jobs/:id/files/:n`.
+//!
+//! Jobs live in memory. At most `queue_limit` jobs may be queued or running, one job runs at a
+//! time (its files in parallel, see [`BatchConcurrency`]), and a finished job is dropped `ttl`
+//! after it finished. Finished jobs hold their outputs, so at most `queue_limit` of them are
+//! kept; the oldest is dropped early when another finishes.
+
+use crate::routes::{
+    content_disposition, read_batch_form, ApiError, BatchConcurrency, Presets, UploadedFile,
+};
+use axum::{
+    extract::{Multipart, Path, Query},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
+use compression::{
+    compress_image_pages_with_cancel, is_cancelled, run_parallel, CancelToken, CompressedImage,
+};
+use domain::TrimRect;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JobCreatedResponse {
+    pub id: String,
+    pub total: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JobStatusResponse {
+    pub id: String,
+    pub status: JobStatus,
+    pub total: usize,
+    /// Files that are done, failed or cancelled
+    pub completed: usize,
+    /// `completed / total` as a percentage
+    pub progress: f64,
+    pub files: Vec<JobFileStatus>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JobFileStatus {
+    /// The `n` in `/api/jobs/:id/files/:n`; files are numbered in upload order from 0.
+    pub index: usize,
+    pub name: String,
+    pub status: FileStatus,
+    pub original_size: usize,
+    /// One entry per output; several when a multi-page input was split (`split_pages`).
+    pub outputs: Vec<JobOutput>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JobOutput {
+    /// 1-based page number of a split input; pass as `?page=` when downloading.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    pub mime_type: String,
+    pub compressed_size: usize,
+    pub savings_percent: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trimmed: Option<TrimRect>,
+    pub bit_depth: u8,
+    /// Download name rendered from the job's `filename_template`.
+    pub suggested_filename: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FileQuery {
+    pub page: Option<u32>,
+}
+
+struct JobFile {
+    name: String,
+    original_size: usize,
+    status: FileStatus,
+    outputs: Vec<CompressedImage>,
+    /// Suggested filename of each output
+    names: Vec<String>,
+    error: Option<String>,
+}
+
+struct Job {
+    status: JobStatus,
+    files: Vec<JobFile>,
+    cancel: CancelToken,
+    finished_at: Option<Instant>,
+}
+
+/// In-memory job table shared by the job handlers (cheap to clone).
+#[derive(Clone)]
+pub struct JobStore {
+    jobs: Arc<Mutex<HashMap<String, Job>>>,
+    /// Lets one job run at a time; the others wait in the queue.
+    runner: Arc<Semaphore>,
+    ttl: Duration,
+    queue_limit: usize,
+}
+
+impl JobStore {
+    pub fn new(ttl: Duration, queue_limit: usize) -> Self {
+        Self {
+            jobs: Arc::new(Mutex::new(HashMap::new())),
+            runner: Arc::new(Semaphore::new(1)),
+            ttl,
+            queue_limit,
+        }
+    }
+
+    /// The job table with expired jobs, and finished jobs beyond `queue_limit`, removed.
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Job>> {
+        let mut jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        let ttl = self.ttl;
+        jobs.retain(|_, job| job.finished_at.is_none_or(|at| at.elapsed() < ttl));
+
+        let mut finished: Vec<(Instant, String)> = jobs
+            .iter()
+            .filter_map(|(id, job)| job.finished_at.map(|at| (at, id.clone())))
+            .collect();
+        if finished.len() > self.queue_limit {
+            finished.sort();
+            let excess = finished.len() - self.queue_limit;
+            for (_, id) in &finished[..excess] {
+                jobs.remove(id);
+            }
+        }
+        jobs
+    }
+
+    fn update_file(&self, id: &str, index: usize, update: impl FnOnce(&mut JobFile)) {
+        if let Some(file) = self
+            .lock()
+            .get_mut(id)
+            .and_then(|job| job.files.get_mut(index))
+        {
+            update(file);
+        }
+    }
+
+    fn set_status(&self, id: &str, status: JobStatus) {
+        if let Some(job) = self.lock().get_mut(id) {
+            job.status = status;
+            if matches!(status, JobStatus::Done | JobStatus::Cancelled) {
+                job.finished_at = Some(Instant::now());
+            }
+        }
+    }
+
+    /// Queue `files` as a new job and start it once the runner is free.
+    fn submit(
+        &self,
+        files: Vec<UploadedFile>,
+        options: domain::CompressionOptions,
+        concurrency: usize,
+    ) -> Result<String, ApiError> {
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let cancel = CancelToken::new();
+        {
+            let mut jobs = self.lock();
+            let pending = jobs
+                .values()
+                .filter(|job| matches!(job.status, JobStatus::Queued | JobStatus::Running))
+                .count();
+            if pending >= self.queue_limit {
+                return Err(ApiError::Unavailable(format!(
+                    "Job queue is full ({} pending); retry later",
+                    pending
+                )));
+            }
+            let entries = files
+                .iter()
+                .map(|file| JobFile {
+                    name: file.name.clone(),
+                    original_size: file.data.len(),
+                    status: FileStatus::Queued,
+                    outputs: Vec::new(),
+                    names: Vec::new(),
+                    error: None,
+                })
+                .collect();
+            jobs.insert(
+                id.clone(),
+                Job {
+                    status: JobStatus::Queued,
+                    files: entries,
+                    cancel: cancel.clone(),
+                    finished_at: None,
+                },
+            );
+        }
+
+        let store = self.clone();
+        let job_id = id.clone();
+        tokio::spawn(async move {
+            let Ok(_permit) = store.runner.clone().acquire_owned().await else {
+                return;
+            };
+            if cancel.is_cancelled() {
+                return;
+            }
+            store.set_status(&job_id, JobStatus::Running);
+
+            let worker_store = store.clone();
+            let worker_id = job_id.clone();
+            let worker_cancel = cancel.clone();
+            let jobs: Vec<(usize, UploadedFile)> = files.into_iter().enumerate().collect();
+            let run = tokio::task::spawn_blocking(move || {
+                run_parallel(jobs, concurrency, |(index, file)| {
+                    if worker_cancel.is_cancelled() {
+                        worker_store
+                            .update_file(&worker_id, index, |f| f.status = FileStatus::Cancelled);
+                        return;
+                    }
+                    worker_store.update_file(&worker_id, index, |f| f.status = FileStatus::Running);
+                    let outcome = compress_image_pages_with_cancel(
+                        &file.data,
+                        &file.ext,
+                        &options,
+                        &worker_cancel,
+                    );
+                    let split = outcome.as_ref().is_ok_and(|outputs| outputs.len() > 1);
+                    let names: Vec<String> = outcome
+                        .iter()
+                        .flatten()
+                        .enumerate()
+                        .map(|(page, output)| {
+                            let page = split.then_some(page as u32 + 1);
+                            output.suggested_filename(&file.name, &options, index + 1, page)
+                        })
+                        .collect();
+                    worker_store.update_file(&worker_id, index, |f| match outcome {
+                        Ok(outputs) => {
+                            f.status = FileStatus::Done;
+                            f.outputs = outputs;
+                            f.names = names;
+                        }
+                        Err(e) if is_cancelled(&e) => f.status = FileStatus::Cancelled,
+                        Err(e) => {
+                            tracing::error!(
+                                "Job {} failed to compress {}: {}",
+                                worker_id,
+                                f.name,
+                                e
+                            );
+                            f.status = FileStatus::Failed;
+                            f.error = Some(e.to_string());
+                        }
+                    });
+                })
+            })
+            .await;
+            if let Err(e) = run {
+                tracing::error!("Job {} worker failed: {}", job_id, e);
+            }
+            let status = if cancel.is_cancelled() {
+                JobStatus::Cancelled
+            } else {
+                JobStatus::Done
+            };
+            store.set_status(&job_id, status);
+        });
+
+        Ok(id)
+    }
+}
+
+fn not_found(id: &str) -> ApiError {
+    ApiError::NotFound(format!("No job '{}' (unknown or expired)", id))
+}
+
+/// POST /api/jobs
+/// Queues the same form `/api/compress/batch` accepts and returns the job id (202 Accepted)
+pub async fn create_job(
+    Extension(store): Extension<JobStore>,
+    Extension(concurrency): Extension<BatchConcurrency>,
+    Extension(presets): Extension<Presets>,
+    mut multipart: Multipart,
+) -> Result<impl IntoResponse, ApiError> {
+    let (files, options) = read_batch_form(&mut multipart, &presets).await?;
+    let total = files.len();
+    let id = store.submit(files, options, concurrency.0)?;
+    Ok((StatusCode::ACCEPTED, Json(JobCreatedResponse { id, total })))
+}
+
+/// GET /api/jobs/:id
+/// Job and per-file status, with output metadata for finished files
+pub async fn get_job(
+    Extension(store): Extension<JobStore>,
+    Path(id): Path<String>,
+) -> Result<Json<JobStatusResponse>, ApiError> {
+    let jobs = store.lock();
+    let job = jobs.get(&id).ok_or_else(|| not_found(&id))?;
+
+    let files: Vec<JobFileStatus> = job
+        .files
+        .iter()
+        .enumerate()
+        .map(|(index, file)| {
+            let split = file.outputs.len() > 1;
+            let outputs = file
+                .outputs
+                .iter()
+                .enumerate()
+                .map(|(page, output)| {
+                    let compressed_size = output.data.len();
+                    let savings_percent = if file.original_size > 0 {
+                        ((compressed_size as f64 - file.original_size as f64)
+                            / file.original_size as f64)
+                            * 100.0
+                    } else {
+                        0.0
+                    };
+                    JobOutput {
+                        page: split.then_some(page as u32 + 1),
+                        mime_type: output.mime_type.clone(),
+                        compressed_size,
+                        savings_percent,
+                        trimmed: output.trimmed,
+                        bit_depth: output.bit_depth,
+                        suggested_filename: file.names.get(page).cloned().unwrap_or_default(),
+                    }
+                })
+                .collect();
+            JobFileStatus {
+                index,
+                name: file.name.clone(),
+                status: file.status,
+                original_size: file.original_size,
+                outputs,
+                error: file.error.clone(),
+            }
+        })
+        .collect();
+
+    let total = files.len();
+    let completed = files
+        .iter()
+        .filter(|file| {
+            matches!(
+                file.status,
+                FileStatus::Done | FileStatus::Failed | FileStatus::Cancelled
+            )
+        })
+        .count();
+    let progress = if total > 0 {
+        completed as f64 * 100.0 / total as f64
+    } else {
+        100.0
+    };
+
+    Ok(Json(JobStatusResponse {
+        id,
+        status: job.status,
+        total,
+        completed,
+        progress,
+        files,
+    }))
+}
+
+/// GET /api/jobs/:id/files/:n
+/// The compressed bytes of file `n` (`?page=k` for page `k` of a split input)
+pub async fn get_job_file(
+    Extension(store): Extension<JobStore>,
+    Path((id, index)): Path<(String, usize)>,
+    Query(query): Query<FileQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let jobs = store.lock();
+    let job = jobs.get(&id).ok_or_else(|| not_found(&id))?;
+    let file = job
+        .files
+        .get(index)
+        .ok_or_else(|| ApiError::NotFound(format!("Job '{}' has no file {}", id, index)))?;
+    if file.status != FileStatus::Done {
+        return Err(ApiError::Conflict(format!(
+            "File {} of job '{}' is not ready ({:?})",
+            index, id, file.status
+        )));
+    }
+    let page = query.page.unwrap_or(1).max(1) as usize;
+    let output = file
+        .outputs
+        .get(page - 1)
+        .ok_or_else(|| ApiError::NotFound(format!("File {} has no page {}", index, page)))?;
+
+    let filename = file
+        .names
+        .get(page - 1)
+        .map_or("compressed", String::as_str);
+    Ok((
+        [
+            (header::CONTENT_TYPE, output.mime_type.clone()),
+            (header::CONTENT_DISPOSITION, content_disposition(filename)),
+        ],
+        output.data.clone(),
+    ))
+}
+
+/// DELETE /api/jobs/:id
+/// Cancels a queued or running job and discards it with its outputs (204 No Content)
+pub async fn delete_job(
+    Extension(store): Extension<JobStore>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let job = store.lock().remove(&id).ok_or_else(|| not_found(&id))?;
+    job.cancel.cancel();
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,15 +1,26 @@
 pub mod config;
+pub mod jobs;
 pub mod routes;
 
 use crate::config::Config;
-use crate::routes::{compress_batch, compress_image};
-use axum::{extract::DefaultBodyLimit, routing::post, Router};
-use http::HeaderValue;
+use crate::jobs::{create_job, delete_job, get_job, get_job_file, JobStore};
+use crate::routes::{
+    compress_batch, compress_image, compress_zip, export_pdf, export_zip, list_formats,
+    list_presets, BatchConcurrency, Presets, METADATA_HEADERS,
+};
+use axum::{
+    extract::DefaultBodyLimit,
+    routing::{get, post},
+    Extension, Router,
+};
+use http::{HeaderName, HeaderValue};
+use std::time::Duration;
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::trace::TraceLayer;
 
 pub fn create_app(config: &Config) -> Router {
-    // Build CORS layer
+    // Build CORS layer; browsers may only read the binary-response metadata headers if exposed
+    let exposed = METADATA_HEADERS.map(HeaderName::from_static);
     let cors = if config.cors_allowed_origins.contains(&"*".to_string())
         || config.app_env == "development"
     {
@@ -17,6 +28,7 @@ pub fn create_app(config: &Config) -> Router {
             .allow_origin(Any)
             .allow_methods(Any)
             .allow_headers(Any)
+            .expose_headers(exposed)
     } else {
         let origins: Vec<HeaderValue> = config
             .cors_allowed_origins
@@ -28,12 +40,30 @@ pub fn create_app(config: &Config) -> Router {
             .allow_origin(origins)
             .allow_methods(Any)
             .allow_headers(Any)
+            .expose_headers(exposed)
     };
 
     // Build application routes
     Router::new()
+        .route("/api/formats", get(list_formats))
+        .route("/api/presets", get(list_presets))
         .route("/api/compress", post(compress_image))
         .route("/api/compress/batch", post(compress_batch))
+        .route("/api/compress/zip", post(compress_zip))
+        .route("/api/pdf", post(export_pdf))
+        .route("/api/zip", post(export_zip))
+        .route("/api/jobs", post(create_job))
+        .route("/api/jobs/:id", get(get_job).delete(delete_job))
+        .route("/api/jobs/:id/files/:n", get(get_job_file))
+        .layer(Extension(JobStore::new(
+            Duration::from_secs(config.job_ttl_secs),
+            config.job_queue_limit,
+        )))
+        .layer(Extension(BatchConcurrency(config.batch_concurrency)))
+        .layer(Extension(Presets::new(
+            &config.presets,
+            config.quality_table,
+        )))
         .layer(DefaultBodyLimit::max(100 * 1024 * 1024)) // 100MB limit
         .layer(cors)
         .layer(TraceLayer::new_for_http())
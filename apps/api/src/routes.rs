@@ -1,12 +1,25 @@
 use axum::{
+    body::Body,
     extract::Multipart,
-    http::StatusCode,
+    http::{header, HeaderMap, StatusCode},
     response::{IntoResponse, Response},
-    Json,
+    Extension, Json,
 };
-use compression::compress_image_inproc;
-use domain::CompressionOptions;
+use compression::{
+    compress_batch as compress_batch_parallel, compress_image_with_cancel, images_to_pdf,
+    supported_input_formats, CancelToken, CompressedImage,
+};
+use domain::{
+    parse_transforms, Adjustments, ColorProfile, CompressionLevel, CompressionOptions, Gravity,
+    PdfImageEncoding, PdfOptions, PdfPageSize, QualityTable, TiffCompression, TrimRect, Watermark,
+    WatermarkKind, MAX_PDF_MARGIN_MM,
+};
+use presets::Preset;
 use serde::{Deserialize, Serialize};
+use std::io::{Cursor, Write};
+use std::sync::Arc;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CompressionResponse {
@@ -15,47 +28,491 @@ pub struct CompressionResponse {
     pub savings_percent: f64,
     pub mime_type: String,
     pub data: Vec<u8>,
+    /// 1-based page number when a multi-page input was split (`split_pages`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    /// Content rectangle kept by a `trim` transform, in input pixels.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trimmed: Option<TrimRect>,
+    /// Bits per channel in `data` (8, 10 for AVIF, 16 for high-depth PNG/TIFF).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bit_depth: Option<u8>,
+    /// Download name rendered from the `filename_template` field (default `{stem}.{ext}`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggested_filename: Option<String>,
+}
+
+impl CompressionResponse {
+    /// Metadata headers describing this result in a binary response; `data` becomes the body.
+    fn metadata_headers(&self) -> Vec<(&'static str, String)> {
+        let mut headers = vec![
+            ("x-original-size", self.original_size.to_string()),
+            ("x-compressed-size", self.compressed_size.to_string()),
+            ("x-savings-percent", self.savings_percent.to_string()),
+        ];
+        if let Some(page) = self.page {
+            headers.push(("x-page", page.to_string()));
+        }
+        if let Some(rect) = self.trimmed {
+            let value = format!("{},{},{},{}", rect.x, rect.y, rect.width, rect.height);
+            headers.push(("x-trimmed", value));
+        }
+        if let Some(bits) = self.bit_depth {
+            headers.push(("x-bit-depth", bits.to_string()));
+        }
+        if let Some(name) = &self.suggested_filename {
+            headers.push(("x-suggested-filename", percent_encode(name)));
+        }
+        headers
+    }
+}
+
+/// Headers carrying result metadata in binary responses: `X-Original-Size`,
+/// `X-Compressed-Size`, `X-Savings-Percent`, `X-Page`, `X-Trimmed` (`x,y,width,height`),
+/// `X-Bit-Depth`, `X-Suggested-Filename` (percent-encoded UTF-8), `X-File-Index` (the input's
+/// position) on `multipart/mixed` batch parts, and `X-Failed-Count` on `/api/compress/zip`
+/// archives.
+pub const METADATA_HEADERS: [&str; 9] = [
+    "x-original-size",
+    "x-compressed-size",
+    "x-savings-percent",
+    "x-page",
+    "x-trimmed",
+    "x-bit-depth",
+    "x-suggested-filename",
+    "x-file-index",
+    "x-failed-count",
+];
+
+/// `value` percent-encoded as UTF-8 (RFC 3986 unreserved characters, spaces and parentheses
+/// kept), so any filename fits in a header.
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'.'
+            | b'_'
+            | b'~'
+            | b' '
+            | b'('
+            | b')' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// `Content-Disposition` for downloading `filename`: a plain ASCII `filename` for old clients
+/// and the exact UTF-8 name in `filename*` (RFC 6266).
+pub(crate) fn content_disposition(filename: &str) -> String {
+    let fallback: String = filename
+        .chars()
+        .map(|c| match c {
+            ' '..='~' if c != '"' && c != '\\' => c,
+            _ => '_',
+        })
+        .collect();
+    format!(
+        "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+        fallback,
+        percent_encode(filename).replace(' ', "%20")
+    )
+}
+
+/// Whether the `Accept` header lists `mime` (parameters and quality values are ignored).
+fn accepts(headers: &HeaderMap, mime: &str) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|item| {
+            let item = item.split(';').next().unwrap_or_default().trim();
+            item.eq_ignore_ascii_case(mime)
+        })
+}
+
+/// Raw image body with its metadata in headers (`Accept: application/octet-stream`).
+fn binary_response(result: CompressionResponse) -> Result<Response, ApiError> {
+    let mut builder = Response::builder().header(header::CONTENT_TYPE, &result.mime_type);
+    for (name, value) in result.metadata_headers() {
+        builder = builder.header(name, value);
+    }
+    builder
+        .body(Body::from(result.data))
+        .map_err(|e| ApiError::InternalError(format!("Failed to build response: {}", e)))
+}
+
+/// `multipart/mixed` body with one part per output (`Accept: multipart/mixed`). Each part has
+/// the output's `Content-Type`, its metadata headers and `X-File-Index`; failed files are
+/// `application/error` parts whose body is the error message, as in the JSON response.
+fn multipart_response(results: Vec<(usize, CompressionResponse)>) -> Result<Response, ApiError> {
+    let boundary = format!("panda-pixel-{}", uuid::Uuid::new_v4().simple());
+    let mut body = Vec::new();
+    for (index, result) in results {
+        body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+        body.extend_from_slice(format!("content-type: {}\r\n", result.mime_type).as_bytes());
+        body.extend_from_slice(format!("x-file-index: {}\r\n", index).as_bytes());
+        for (name, value) in result.metadata_headers() {
+            body.extend_from_slice(format!("{}: {}\r\n", name, value).as_bytes());
+        }
+        body.extend_from_slice(b"\r\n");
+        body.extend_from_slice(&result.data);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+
+    Response::builder()
+        .header(
+            header::CONTENT_TYPE,
+            format!("multipart/mixed; boundary={}", boundary),
+        )
+        .body(Body::from(body))
+        .map_err(|e| ApiError::InternalError(format!("Failed to build response: {}", e)))
+}
+
+/// Deflated ZIP archive of `(name, data)` entries. Names are sanitized and made unique within
+/// the archive with the desktop's ` (n)` rule ([`naming::uniquify_zip_entry_names`]).
+fn build_zip(entries: Vec<(String, Vec<u8>)>) -> Result<Vec<u8>, ApiError> {
+    let names: Vec<String> = entries
+        .iter()
+        .map(|(name, _)| match naming::sanitize_filename(name) {
+            safe if safe.is_empty() => "file".to_string(),
+            safe => safe,
+        })
+        .collect();
+    let names = naming::uniquify_zip_entry_names(&names).map_err(ApiError::BadRequest)?;
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .compression_level(Some(6));
+    let failed = |e: &dyn std::fmt::Display| {
+        ApiError::InternalError(format!("Failed to write ZIP archive: {}", e))
+    };
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, (_, data)) in names.iter().zip(entries) {
+        zip.start_file(name.as_str(), options)
+            .map_err(|e| failed(&e))?;
+        zip.write_all(&data).map_err(|e| failed(&e))?;
+    }
+    let archive = zip.finish().map_err(|e| failed(&e))?;
+    Ok(archive.into_inner())
 }
 
+fn zip_response(archive: Vec<u8>, failed_count: Option<usize>) -> Result<Response, ApiError> {
+    let mut builder = Response::builder()
+        .header(header::CONTENT_TYPE, "application/zip")
+        .header(
+            header::CONTENT_DISPOSITION,
+            "attachment; filename=\"images.zip\"",
+        );
+    if let Some(count) = failed_count {
+        builder = builder.header("x-failed-count", count.to_string());
+    }
+    builder
+        .body(Body::from(archive))
+        .map_err(|e| ApiError::InternalError(format!("Failed to build response: {}", e)))
+}
+
+/// Worker threads for `/api/compress/batch` (`0` = one per core), from `BATCH_CONCURRENCY`.
+#[derive(Debug, Clone, Copy)]
+pub struct BatchConcurrency(pub usize);
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BatchCompressionResponse {
     pub results: Vec<CompressionResponse>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InputFormatInfo {
+    pub name: String,
+    pub mime_type: String,
+    pub extensions: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FormatsResponse {
+    pub input: Vec<InputFormatInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PresetsResponse {
+    pub presets: Vec<Preset>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ErrorResponse {
     pub error: String,
 }
 
-/// Align output container flags with the UI / desktop `output_format` string (single flag on).
-fn apply_output_format(options: &mut CompressionOptions, output_format: &str) {
-    let fmt = output_format.trim();
-    options.to_webp = false;
-    options.to_avif = false;
-    options.to_jpeg = false;
-    options.to_png = false;
-    options.to_tiff = false;
-    options.to_bmp = false;
-    options.to_ico = false;
-    match fmt.to_lowercase().as_str() {
-        "webp" => options.to_webp = true,
-        "avif" => options.to_avif = true,
-        "jpeg" => options.to_jpeg = true,
-        "png" => options.to_png = true,
-        "tiff" => options.to_tiff = true,
-        "bmp" => options.to_bmp = true,
-        "ico" => options.to_ico = true,
-        "original" => {}
-        _ => options.to_webp = true,
+/// `filename_template` form field: blank means the default `{stem}.{ext}`.
+fn parse_filename_template(value: &str) -> Result<Option<String>, ApiError> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    naming::validate_template(value).map_err(ApiError::BadRequest)?;
+    Ok(Some(value.to_string()))
+}
+
+/// Watermark parts of a compress form: a `watermark` image file or `watermark_text`, plus
+/// optional `watermark_color`, `watermark_gravity`, `watermark_margin`, `watermark_scale`,
+/// `watermark_opacity` and `watermark_tile`. Invalid settings are a 400 error.
+#[derive(Default)]
+struct WatermarkFields {
+    image: Option<Vec<u8>>,
+    settings: Vec<(String, String)>,
+}
+
+impl WatermarkFields {
+    fn setting(&self, key: &str) -> Option<&str> {
+        self.settings
+            .iter()
+            .rev()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.trim())
+            .filter(|value| !value.is_empty())
+    }
+
+    fn build(self) -> Result<Option<Watermark>, ApiError> {
+        let kind = match (&self.image, self.setting("watermark_text")) {
+            (Some(data), _) => WatermarkKind::Image { data: data.clone() },
+            (None, Some(text)) => {
+                let color = self.setting("watermark_color").unwrap_or("#ffffff");
+                if domain::parse_hex_color(color).is_none() {
+                    return Err(ApiError::BadRequest(format!(
+                        "Invalid watermark_color '{}'",
+                        color
+                    )));
+                }
+                WatermarkKind::Text {
+                    text: text.to_string(),
+                    color: color.to_string(),
+                }
+            }
+            (None, None) => return Ok(None),
+        };
+
+        let mut watermark = Watermark::new(kind);
+        if let Some(gravity) = self.setting("watermark_gravity") {
+            watermark.gravity = Gravity::parse(gravity).ok_or_else(|| {
+                ApiError::BadRequest(format!("Invalid watermark_gravity '{}'", gravity))
+            })?;
+        }
+        if let Some(margin) = self.parsed("watermark_margin")? {
+            watermark.margin = margin;
+        }
+        if let Some(scale) = self.parsed("watermark_scale")? {
+            watermark.scale = scale;
+        }
+        if let Some(opacity) = self.parsed("watermark_opacity")? {
+            watermark.opacity = opacity;
+        }
+        if let Some(tile) = self.parsed("watermark_tile")? {
+            watermark.tile = tile;
+        }
+        Ok(Some(watermark))
+    }
+
+    /// `key` parsed as `T`; a value that does not parse is a bad request.
+    fn parsed<T: std::str::FromStr>(&self, key: &str) -> Result<Option<T>, ApiError> {
+        self.setting(key)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| ApiError::BadRequest(format!("Invalid {} '{}'", key, value)))
+            })
+            .transpose()
+    }
+}
+
+/// Presets a compress form can name in its `preset` field: the built-ins plus the server's own
+/// from `PRESETS_FILE`, which replace built-ins of the same name. Also carries the server's
+/// quality table (`QUALITY_TABLE`), used when neither the preset nor the form sets one.
+#[derive(Debug, Clone)]
+pub struct Presets {
+    presets: Arc<Vec<Preset>>,
+    quality_table: Option<QualityTable>,
+}
+
+impl Presets {
+    pub fn new(server_presets: &[Preset], quality_table: Option<QualityTable>) -> Self {
+        let mut all = presets::builtin_presets();
+        for preset in server_presets {
+            let key = presets::preset_key(&preset.name);
+            all.retain(|builtin| presets::preset_key(&builtin.name) != key);
+            all.push(preset.clone());
+        }
+        Self {
+            presets: Arc::new(all),
+            quality_table,
+        }
+    }
+
+    fn get(&self, name: &str) -> Result<&Preset, ApiError> {
+        presets::find_preset(&self.presets, name)
+            .ok_or_else(|| ApiError::BadRequest(format!("Unknown preset '{}'", name)))
+    }
+}
+
+/// `quality` form field: 0–100, or empty for the level's quality.
+fn parse_quality(value: &str) -> Result<Option<u8>, ApiError> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    match value.parse::<u8>() {
+        Ok(quality) if quality <= 100 => Ok(Some(quality)),
+        _ => Err(ApiError::BadRequest(format!(
+            "Invalid quality '{}' (expected 0-100)",
+            value
+        ))),
+    }
+}
+
+/// Set one option field of a compress form on `options`. Unknown fields are ignored.
+fn apply_option_field(
+    options: &mut CompressionOptions,
+    name: &str,
+    value: &str,
+) -> Result<(), ApiError> {
+    match name {
+        "filename_template" => options.filename_template = parse_filename_template(value)?,
+        // A level name picks that level's range and quality table row.
+        "png_quality" => {
+            options.png_quality = match CompressionLevel::parse(value) {
+                Some(level) => level.png_range().to_string(),
+                None => value.trim().to_string(),
+            }
+        }
+        "quality" => options.quality = parse_quality(value)?,
+        "quality_table" if value.trim().is_empty() => options.quality_table = None,
+        "quality_table" => {
+            options.quality_table = Some(
+                serde_json::from_str(value)
+                    .map_err(|e| ApiError::BadRequest(format!("Invalid quality_table: {}", e)))?,
+            )
+        }
+        "png_lossy" => options.png_lossy = value.parse().unwrap_or(true),
+        "oxipng" => options.oxipng = value.parse().unwrap_or(false),
+        "to_webp" => options.to_webp = value.parse().unwrap_or(false),
+        "to_avif" => options.to_avif = value.parse().unwrap_or(false),
+        "to_jpeg" => options.to_jpeg = value.parse().unwrap_or(false),
+        "to_png" => options.to_png = value.parse().unwrap_or(false),
+        "to_jxl" => options.to_jxl = value.parse().unwrap_or(false),
+        "jxl_lossless_jpeg" => options.jxl_lossless_jpeg = value.parse().unwrap_or(false),
+        "tiff_compression" => {
+            options.tiff_compression = TiffCompression::parse(value).ok_or_else(|| {
+                ApiError::BadRequest(format!(
+                    "Invalid tiff_compression '{}' (expected none, lzw, deflate or packbits)",
+                    value
+                ))
+            })?
+        }
+        "split_pages" => options.split_pages = value.parse().unwrap_or(false),
+        "dither" => options.dither = value.parse().unwrap_or(false),
+        "color_profile" => {
+            options.color_profile = ColorProfile::parse(value).ok_or_else(|| {
+                ApiError::BadRequest(format!(
+                    "Invalid color_profile '{}' (expected srgb, keep or ignore)",
+                    value
+                ))
+            })?
+        }
+        "transforms" => {
+            options.transforms = parse_transforms(value).map_err(ApiError::BadRequest)?
+        }
+        name if Adjustments::FIELDS.contains(&name) => options
+            .adjustments
+            .set_field(name, value)
+            .map_err(ApiError::BadRequest)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Option fields of a compress form. They are applied once the whole form is read, on top of
+/// the options of the `preset` field when there is one, so a preset can come before or after
+/// the fields that override it.
+#[derive(Default)]
+struct OptionFields {
+    preset: Option<String>,
+    output_format: Option<String>,
+    settings: Vec<(String, String)>,
+    watermark: WatermarkFields,
+}
+
+impl OptionFields {
+    fn push(&mut self, name: String, value: String) {
+        let trimmed = value.trim();
+        match name.as_str() {
+            "preset" if !trimmed.is_empty() => self.preset = Some(trimmed.to_string()),
+            "output_format" if !trimmed.is_empty() => {
+                self.output_format = Some(trimmed.to_string())
+            }
+            "preset" | "output_format" => {}
+            _ if name.starts_with("watermark_") => self.watermark.settings.push((name, value)),
+            _ => self.settings.push((name, value)),
+        }
+    }
+
+    fn build(self, presets: &Presets) -> Result<CompressionOptions, ApiError> {
+        let mut options = match &self.preset {
+            Some(name) => presets.get(name)?.options.clone(),
+            None => CompressionOptions::default(),
+        };
+        for (name, value) in &self.settings {
+            apply_option_field(&mut options, name, value)?;
+        }
+        if let Some(fmt) = &self.output_format {
+            options.set_output_format(fmt);
+        }
+        if let Some(watermark) = self.watermark.build()? {
+            options.watermark = Some(watermark);
+        }
+        if options.quality_table.is_none() {
+            options.quality_table = presets.quality_table;
+        }
+        Ok(options)
     }
 }
 
+/// GET /api/presets
+/// Lists the presets the `preset` form field accepts
+pub async fn list_presets(Extension(presets): Extension<Presets>) -> Json<PresetsResponse> {
+    Json(PresetsResponse {
+        presets: presets.presets.as_ref().clone(),
+    })
+}
+
+/// GET /api/formats
+/// Lists the input formats this server build can decode
+pub async fn list_formats() -> Json<FormatsResponse> {
+    let input = supported_input_formats()
+        .into_iter()
+        .map(|f| InputFormatInfo {
+            name: f.name.to_string(),
+            mime_type: f.mime_type.to_string(),
+            extensions: f.extensions.iter().map(|e| e.to_string()).collect(),
+        })
+        .collect();
+    Json(FormatsResponse { input })
+}
+
 /// POST /api/compress
-/// Compresses a single image file
-pub async fn compress_image(mut multipart: Multipart) -> Result<impl IntoResponse, ApiError> {
-    let mut file_data: Option<(Vec<u8>, String)> = None;
-    let mut options = CompressionOptions::default();
-    let mut output_format: Option<String> = None;
+/// Compresses a single image file. Responds with JSON, or with the raw image and its metadata
+/// in [`METADATA_HEADERS`] when the request sends `Accept: application/octet-stream`.
+pub async fn compress_image(
+    Extension(presets): Extension<Presets>,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> Result<Response, ApiError> {
+    let mut file_data: Option<(Vec<u8>, String, String)> = None;
+    let mut fields = OptionFields::default();
 
     // Parse multipart form data
     while let Some(field) = multipart
@@ -81,89 +538,120 @@ pub async fn compress_image(mut multipart: Multipart) -> Result<impl IntoRespons
                 .await
                 .map_err(|e| ApiError::BadRequest(format!("Failed to read file data: {}", e)))?;
 
-            file_data = Some((data.to_vec(), ext));
-        } else if name == "png_quality" {
-            if let Ok(value) = field.text().await {
-                options.png_quality = value;
-            }
-        } else if name == "png_lossy" {
-            if let Ok(value) = field.text().await {
-                options.png_lossy = value.parse().unwrap_or(true);
-            }
-        } else if name == "oxipng" {
-            if let Ok(value) = field.text().await {
-                options.oxipng = value.parse().unwrap_or(false);
-            }
-        } else if name == "to_webp" {
-            if let Ok(value) = field.text().await {
-                options.to_webp = value.parse().unwrap_or(false);
-            }
-        } else if name == "to_avif" {
-            if let Ok(value) = field.text().await {
-                options.to_avif = value.parse().unwrap_or(false);
-            }
-        } else if name == "to_jpeg" {
-            if let Ok(value) = field.text().await {
-                options.to_jpeg = value.parse().unwrap_or(false);
-            }
-        } else if name == "to_png" {
-            if let Ok(value) = field.text().await {
-                options.to_png = value.parse().unwrap_or(false);
-            }
-        } else if name == "output_format" {
-            if let Ok(bytes) = field.bytes().await {
-                let value = String::from_utf8_lossy(&bytes).trim().to_string();
-                if !value.is_empty() {
-                    output_format = Some(value);
-                }
-            }
+            file_data = Some((data.to_vec(), filename, ext));
+        } else if name == "watermark" {
+            let data = field.bytes().await.map_err(|e| {
+                ApiError::BadRequest(format!("Failed to read watermark data: {}", e))
+            })?;
+            fields.watermark.image = Some(data.to_vec());
+        } else if let Ok(value) = field.text().await {
+            fields.push(name, value);
         }
     }
 
-    if let Some(fmt) = output_format {
-        apply_output_format(&mut options, &fmt);
-    }
+    let options = fields.build(&presets)?;
 
-    let (file_bytes, ext) = file_data.ok_or_else(|| {
+    let (file_bytes, filename, ext) = file_data.ok_or_else(|| {
         ApiError::BadRequest("Missing 'file' field in multipart form".to_string())
     })?;
 
     let original_size = file_bytes.len();
 
-    // Compress the image
-    let (compressed_bytes, mime_type) = compress_image_inproc(&file_bytes, &ext, &options)
-        .map_err(|e| {
-            // Check if error is due to invalid image format or decoding
-            let msg = e.to_string();
-            if msg.contains("format") || msg.contains("decode") || msg.contains("load") {
-                ApiError::BadRequest(format!("Invalid image data: {}", msg))
-            } else {
-                ApiError::InternalError(format!("Compression failed: {}", msg))
-            }
-        })?;
+    // Compress the image off the async runtime. If the client disconnects, this future is
+    // dropped and the guard cancels the job at its next stage boundary.
+    let cancel = CancelToken::new();
+    let _abort = cancel.drop_guard();
+    let (output, suggested_filename) = tokio::task::spawn_blocking(move || {
+        compress_image_with_cancel(&file_bytes, &ext, &options, &cancel).map(|output| {
+            let name = output.suggested_filename(&filename, &options, 1, None);
+            (output, name)
+        })
+    })
+    .await
+    .map_err(|e| ApiError::InternalError(format!("Compression worker failed: {}", e)))?
+    .map_err(|e| {
+        // Check if error is due to invalid image format or decoding
+        let msg = e.to_string();
+        if msg.contains("format")
+            || msg.contains("decode")
+            || msg.contains("load")
+            || msg.contains("transform")
+        {
+            ApiError::BadRequest(format!("Invalid image data: {}", msg))
+        } else {
+            ApiError::InternalError(format!("Compression failed: {}", msg))
+        }
+    })?;
 
-    let compressed_size = compressed_bytes.len();
+    let compressed_size = output.data.len();
     let savings_percent = if original_size > 0 {
         ((compressed_size as f64 - original_size as f64) / original_size as f64) * 100.0
     } else {
         0.0
     };
 
-    Ok(Json(CompressionResponse {
+    let result = CompressionResponse {
         original_size,
         compressed_size,
         savings_percent,
-        mime_type,
-        data: compressed_bytes,
-    }))
+        mime_type: output.mime_type,
+        data: output.data,
+        page: None,
+        trimmed: output.trimmed,
+        bit_depth: Some(output.bit_depth),
+        suggested_filename: Some(suggested_filename),
+    };
+    if accepts(&headers, "application/octet-stream") {
+        return binary_response(result);
+    }
+    Ok(Json(result).into_response())
 }
 
-/// POST /api/compress/batch
-/// Compresses multiple image files
-pub async fn compress_batch(mut multipart: Multipart) -> Result<impl IntoResponse, ApiError> {
-    let mut files: Vec<(Vec<u8>, String)> = Vec::new();
-    let mut options = CompressionOptions::default();
-    let mut output_format: Option<String> = None;
+/// Pair every output of a batch with its suggested filename. `names` are the upload names in
+/// input order; inputs are numbered from 1 for `{index}`.
+pub(crate) fn with_suggested_names(
+    names: &[String],
+    outcomes: Vec<anyhow::Result<Vec<CompressedImage>>>,
+    options: &CompressionOptions,
+) -> Vec<anyhow::Result<Vec<(CompressedImage, String)>>> {
+    names
+        .iter()
+        .zip(outcomes)
+        .enumerate()
+        .map(|(file_index, (name, outcome))| {
+            outcome.map(|outputs| {
+                let split = outputs.len() > 1;
+                outputs
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, output)| {
+                        let page = split.then_some(index as u32 + 1);
+                        let suggested =
+                            output.suggested_filename(name, options, file_index + 1, page);
+                        (output, suggested)
+                    })
+                    .collect()
+            })
+        })
+        .collect()
+}
+
+/// One image from a `file*` part of a batch form.
+pub(crate) struct UploadedFile {
+    pub name: String,
+    /// Lowercase extension taken from `name`
+    pub ext: String,
+    pub data: Vec<u8>,
+}
+
+/// Read a batch form: `file`, `file1`, … parts plus the option fields `/api/compress/batch`
+/// accepts. Fails when no file was uploaded.
+pub(crate) async fn read_batch_form(
+    multipart: &mut Multipart,
+    presets: &Presets,
+) -> Result<(Vec<UploadedFile>, CompressionOptions), ApiError> {
+    let mut files: Vec<UploadedFile> = Vec::new();
+    let mut fields = OptionFields::default();
 
     // Parse multipart form data
     while let Some(field) = multipart
@@ -173,97 +661,311 @@ pub async fn compress_batch(mut multipart: Multipart) -> Result<impl IntoRespons
     {
         let name = field.name().unwrap_or("").to_string();
 
-        if name.starts_with("file") {
+        // `filename_template` is an option field, not a `file*` part.
+        if name.starts_with("file") && name != "filename_template" {
             // Extract filename before consuming field
-            let filename = field.file_name().unwrap_or("image").to_string();
+            let name = field.file_name().unwrap_or("image").to_string();
 
             // Extract extension
-            let ext = filename
-                .split('.')
-                .next_back()
-                .unwrap_or("png")
-                .to_lowercase();
+            let ext = name.split('.').next_back().unwrap_or("png").to_lowercase();
 
             let data = field
                 .bytes()
                 .await
                 .map_err(|e| ApiError::BadRequest(format!("Failed to read file data: {}", e)))?;
 
-            files.push((data.to_vec(), ext));
-        } else if name == "png_quality" {
-            if let Ok(value) = field.text().await {
-                options.png_quality = value;
-            }
-        } else if name == "png_lossy" {
-            if let Ok(value) = field.text().await {
-                options.png_lossy = value.parse().unwrap_or(true);
-            }
-        } else if name == "oxipng" {
-            if let Ok(value) = field.text().await {
-                options.oxipng = value.parse().unwrap_or(false);
-            }
-        } else if name == "output_format" {
-            if let Ok(bytes) = field.bytes().await {
-                let value = String::from_utf8_lossy(&bytes).trim().to_string();
-                if !value.is_empty() {
-                    output_format = Some(value);
-                }
-            }
+            files.push(UploadedFile {
+                name,
+                ext,
+                data: data.to_vec(),
+            });
+        } else if name == "watermark" {
+            let data = field.bytes().await.map_err(|e| {
+                ApiError::BadRequest(format!("Failed to read watermark data: {}", e))
+            })?;
+            fields.watermark.image = Some(data.to_vec());
+        } else if let Ok(value) = field.text().await {
+            fields.push(name, value);
         }
     }
 
-    if let Some(fmt) = output_format {
-        apply_output_format(&mut options, &fmt);
-    }
+    let options = fields.build(presets)?;
 
     if files.is_empty() {
         return Err(ApiError::BadRequest("No files provided".to_string()));
     }
 
-    let mut results = Vec::new();
+    Ok((files, options))
+}
+
+/// POST /api/compress/batch
+/// Compresses multiple image files. Responds with JSON, or with a `multipart/mixed` body of raw
+/// images when the request sends `Accept: multipart/mixed`.
+pub async fn compress_batch(
+    Extension(concurrency): Extension<BatchConcurrency>,
+    Extension(presets): Extension<Presets>,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> Result<Response, ApiError> {
+    let (uploads, options) = read_batch_form(&mut multipart, &presets).await?;
+    let names: Vec<String> = uploads.iter().map(|file| file.name.clone()).collect();
+    let files: Vec<(Vec<u8>, String)> = uploads
+        .into_iter()
+        .map(|file| (file.data, file.ext))
+        .collect();
+
+    let sizes: Vec<usize> = files.iter().map(|(bytes, _)| bytes.len()).collect();
+    // Dropping this future (client disconnected) cancels the files not yet finished.
+    let cancel = CancelToken::new();
+    let _abort = cancel.drop_guard();
+    let outcomes = tokio::task::spawn_blocking(move || {
+        let outcomes = compress_batch_parallel(files, &options, concurrency.0, &cancel);
+        with_suggested_names(&names, outcomes, &options)
+    })
+    .await
+    .map_err(|e| ApiError::InternalError(format!("Batch worker failed: {}", e)))?;
 
-    for (file_bytes, ext) in files {
-        let original_size = file_bytes.len();
-
-        match compress_image_inproc(&file_bytes, &ext, &options) {
-            Ok((compressed_bytes, mime_type)) => {
-                let compressed_size = compressed_bytes.len();
-                let savings_percent = if original_size > 0 {
-                    ((compressed_size as f64 - original_size as f64) / original_size as f64) * 100.0
-                } else {
-                    0.0
-                };
-
-                results.push(CompressionResponse {
-                    original_size,
-                    compressed_size,
-                    savings_percent,
-                    mime_type,
-                    data: compressed_bytes,
-                });
+    let mut results = Vec::new();
+    for (file_index, (original_size, outcome)) in sizes.into_iter().zip(outcomes).enumerate() {
+        match outcome {
+            Ok(outputs) => {
+                let split = outputs.len() > 1;
+                for (index, (output, suggested_filename)) in outputs.into_iter().enumerate() {
+                    let compressed_size = output.data.len();
+                    let savings_percent = if original_size > 0 {
+                        ((compressed_size as f64 - original_size as f64) / original_size as f64)
+                            * 100.0
+                    } else {
+                        0.0
+                    };
+
+                    results.push((
+                        file_index,
+                        CompressionResponse {
+                            original_size,
+                            compressed_size,
+                            savings_percent,
+                            mime_type: output.mime_type,
+                            data: output.data,
+                            page: split.then_some(index as u32 + 1),
+                            trimmed: output.trimmed,
+                            bit_depth: Some(output.bit_depth),
+                            suggested_filename: Some(suggested_filename),
+                        },
+                    ));
+                }
             }
             Err(e) => {
                 // Continue with other files even if one fails
                 let msg = e.to_string();
                 tracing::error!("Failed to compress file: {}", msg);
-                results.push(CompressionResponse {
-                    original_size,
-                    compressed_size: 0,
-                    savings_percent: 0.0,
-                    mime_type: "application/error".to_string(),
-                    data: msg.into_bytes(),
-                });
+                results.push((
+                    file_index,
+                    CompressionResponse {
+                        original_size,
+                        compressed_size: 0,
+                        savings_percent: 0.0,
+                        mime_type: "application/error".to_string(),
+                        data: msg.into_bytes(),
+                        page: None,
+                        trimmed: None,
+                        bit_depth: None,
+                        suggested_filename: None,
+                    },
+                ));
+            }
+        }
+    }
+
+    if accepts(&headers, "multipart/mixed") {
+        return multipart_response(results);
+    }
+    let results = results.into_iter().map(|(_, result)| result).collect();
+    Ok(Json(BatchCompressionResponse { results }).into_response())
+}
+
+/// POST /api/compress/zip
+/// Compresses the uploads like `/api/compress/batch` and returns the outputs as one ZIP archive.
+/// Files that fail are left out and counted in `X-Failed-Count`; if none succeed the request
+/// fails with the first error.
+pub async fn compress_zip(
+    Extension(concurrency): Extension<BatchConcurrency>,
+    Extension(presets): Extension<Presets>,
+    mut multipart: Multipart,
+) -> Result<Response, ApiError> {
+    let (uploads, options) = read_batch_form(&mut multipart, &presets).await?;
+    let names: Vec<String> = uploads.iter().map(|file| file.name.clone()).collect();
+    let files: Vec<(Vec<u8>, String)> = uploads
+        .into_iter()
+        .map(|file| (file.data, file.ext))
+        .collect();
+
+    let cancel = CancelToken::new();
+    let _abort = cancel.drop_guard();
+    let batch_names = names.clone();
+    let outcomes = tokio::task::spawn_blocking(move || {
+        let outcomes = compress_batch_parallel(files, &options, concurrency.0, &cancel);
+        with_suggested_names(&batch_names, outcomes, &options)
+    })
+    .await
+    .map_err(|e| ApiError::InternalError(format!("Batch worker failed: {}", e)))?;
+
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+    for (name, outcome) in names.iter().zip(outcomes) {
+        match outcome {
+            Ok(outputs) => {
+                entries.extend(
+                    outputs
+                        .into_iter()
+                        .map(|(output, suggested)| (suggested, output.data)),
+                );
+            }
+            Err(e) => {
+                tracing::error!("Failed to compress {}: {}", name, e);
+                errors.push(format!("{}: {}", name, e));
             }
         }
     }
+    if entries.is_empty() {
+        return Err(ApiError::BadRequest(format!(
+            "Invalid image data: {}",
+            errors.join("; ")
+        )));
+    }
+
+    let archive = tokio::task::spawn_blocking(move || build_zip(entries))
+        .await
+        .map_err(|e| ApiError::InternalError(format!("ZIP worker failed: {}", e)))??;
+    zip_response(archive, Some(errors.len()))
+}
+
+/// POST /api/zip
+/// Bundles the uploaded files (`file`, `file1`, …) unchanged into one ZIP archive, named after
+/// their upload filenames; used to download results a client already holds
+pub async fn export_zip(mut multipart: Multipart) -> Result<Response, ApiError> {
+    let mut entries: Vec<(String, Vec<u8>)> = Vec::new();
 
-    Ok(Json(BatchCompressionResponse { results }))
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| ApiError::BadRequest(format!("Failed to read multipart field: {}", e)))?
+    {
+        let name = field.name().unwrap_or("").to_string();
+
+        if name.starts_with("file") {
+            let filename = field.file_name().unwrap_or("image").to_string();
+            let data = field
+                .bytes()
+                .await
+                .map_err(|e| ApiError::BadRequest(format!("Failed to read file data: {}", e)))?;
+            entries.push((filename, data.to_vec()));
+        }
+    }
+
+    if entries.is_empty() {
+        return Err(ApiError::BadRequest("No files provided".to_string()));
+    }
+
+    let archive = tokio::task::spawn_blocking(move || build_zip(entries))
+        .await
+        .map_err(|e| ApiError::InternalError(format!("ZIP worker failed: {}", e)))??;
+    zip_response(archive, None)
+}
+
+/// Set one `/api/pdf` option field. Unknown fields are ignored.
+fn apply_pdf_field(options: &mut PdfOptions, name: &str, value: &str) -> Result<(), ApiError> {
+    let invalid = |expected: &str| {
+        ApiError::BadRequest(format!(
+            "Invalid {} '{}' (expected {})",
+            name, value, expected
+        ))
+    };
+    match name {
+        "page_size" => {
+            options.page_size =
+                PdfPageSize::parse(value).ok_or_else(|| invalid("fit, a4 or letter"))?
+        }
+        "margin_mm" => {
+            options.margin_mm = value
+                .parse::<f32>()
+                .ok()
+                .filter(|margin| (0.0..=MAX_PDF_MARGIN_MM).contains(margin))
+                .ok_or_else(|| invalid("0-100 mm"))?
+        }
+        "image_encoding" => {
+            options.image_encoding =
+                PdfImageEncoding::parse(value).ok_or_else(|| invalid("auto, jpeg or flate"))?
+        }
+        "jpeg_quality" => {
+            options.jpeg_quality = value
+                .parse::<u8>()
+                .ok()
+                .filter(|quality| (1..=100).contains(quality))
+                .ok_or_else(|| invalid("1-100"))?
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// POST /api/pdf
+/// Bundles the uploaded images (`file`, `file1`, …) into one PDF, one page per image
+pub async fn export_pdf(mut multipart: Multipart) -> Result<impl IntoResponse, ApiError> {
+    let mut images: Vec<Vec<u8>> = Vec::new();
+    let mut options = PdfOptions::default();
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| ApiError::BadRequest(format!("Failed to read multipart field: {}", e)))?
+    {
+        let name = field.name().unwrap_or("").to_string();
+
+        if name.starts_with("file") {
+            let data = field
+                .bytes()
+                .await
+                .map_err(|e| ApiError::BadRequest(format!("Failed to read file data: {}", e)))?;
+            images.push(data.to_vec());
+        } else if let Ok(value) = field.text().await {
+            let value = value.trim();
+            if !value.is_empty() {
+                apply_pdf_field(&mut options, &name, value)?;
+            }
+        }
+    }
+
+    if images.is_empty() {
+        return Err(ApiError::BadRequest("No files provided".to_string()));
+    }
+
+    let pdf = tokio::task::spawn_blocking(move || images_to_pdf(&images, &options))
+        .await
+        .map_err(|e| ApiError::InternalError(format!("PDF worker failed: {}", e)))?
+        .map_err(|e| ApiError::BadRequest(format!("Invalid image data: {}", e)))?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/pdf"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"images.pdf\"",
+            ),
+        ],
+        pdf,
+    ))
 }
 
 /// API Error type
 #[derive(Debug)]
 pub enum ApiError {
     BadRequest(String),
+    NotFound(String),
+    /// The resource exists but is not in a state that allows the request (e.g. not finished)
+    Conflict(String),
+    /// Temporarily unable to accept the request (e.g. the job queue is full)
+    Unavailable(String),
     InternalError(String),
 }
 
@@ -271,6 +973,9 @@ impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
         let (status, error_message) = match self {
             ApiError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
+            ApiError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            ApiError::Conflict(msg) => (StatusCode::CONFLICT, msg),
+            ApiError::Unavailable(msg) => (StatusCode::SERVICE_UNAVAILABLE, msg),
             ApiError::InternalError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
         };
 
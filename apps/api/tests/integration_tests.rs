@@ -5,6 +5,21 @@ use axum_test::TestServer;
 use image::{ImageBuffer, ImageFormat};
 use std::io::Cursor;
 
+/// Config for an in-process [`TestServer`]; tests override the fields they exercise.
+fn test_config() -> Config {
+    Config {
+        app_env: "test".to_string(),
+        port: 0,
+        cors_allowed_origins: vec!["*".to_string()],
+        rust_log: "error".to_string(),
+        batch_concurrency: 0,
+        job_ttl_secs: 3600,
+        job_queue_limit: 16,
+        presets: Vec::new(),
+        quality_table: None,
+    }
+}
+
 #[tokio::test]
 async fn batch_compress_output_format_webp_sets_mime() {
     let mut png_bytes = Vec::new();
@@ -13,12 +28,7 @@ async fn batch_compress_output_format_webp_sets_mime() {
     img.write_to(&mut Cursor::new(&mut png_bytes), ImageFormat::Png)
         .expect("encode test png");
 
-    let config = Config {
-        app_env: "test".to_string(),
-        port: 0,
-        cors_allowed_origins: vec!["*".to_string()],
-        rust_log: "error".to_string(),
-    };
+    let config = test_config();
     let app = create_app(&config);
     let server = TestServer::new(app).expect("test server");
 
@@ -44,12 +54,7 @@ async fn batch_compress_output_format_webp_sets_mime() {
 #[tokio::test]
 async fn test_compress_endpoint_e2e() {
     // Setup config
-    let config = Config {
-        app_env: "test".to_string(),
-        port: 0, // Not used for TestServer
-        cors_allowed_origins: vec!["*".to_string()],
-        rust_log: "error".to_string(),
-    };
+    let config = test_config();
 
     let app = create_app(&config);
     let server = TestServer::new(app).unwrap();
@@ -77,3 +82,858 @@ async fn test_compress_endpoint_e2e() {
             || response.status_code() == StatusCode::OK
     );
 }
+
+#[cfg(not(feature = "jxl"))]
+#[tokio::test]
+async fn compress_output_format_jxl_without_feature_is_bad_request() {
+    let mut png_bytes = Vec::new();
+    let img: ImageBuffer<image::Rgb<u8>, Vec<u8>> =
+        ImageBuffer::from_pixel(2, 2, image::Rgb([40u8, 80u8, 120u8]));
+    img.write_to(&mut Cursor::new(&mut png_bytes), ImageFormat::Png)
+        .expect("encode test png");
+
+    let config = test_config();
+    let server = TestServer::new(create_app(&config)).expect("test server");
+
+    let form = MultipartForm::new()
+        .add_part("file", Part::bytes(png_bytes).file_name("sample.png"))
+        .add_part("output_format", Part::text("jxl"));
+
+    let response = server.post("/api/compress").multipart(form).await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn formats_endpoint_lists_enabled_input_formats() {
+    let config = test_config();
+    let server = TestServer::new(create_app(&config)).expect("test server");
+
+    let response = server.get("/api/formats").await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    let body: serde_json::Value = response.json();
+    let extensions: Vec<String> = body["input"]
+        .as_array()
+        .expect("input array")
+        .iter()
+        .flat_map(|f| f["extensions"].as_array().cloned().unwrap_or_default())
+        .filter_map(|e| e.as_str().map(str::to_string))
+        .collect();
+    assert!(extensions.iter().any(|e| e == "png"));
+    assert_eq!(
+        extensions.iter().any(|e| e == "tga"),
+        cfg!(feature = "extra-formats")
+    );
+}
+
+#[tokio::test]
+async fn batch_split_pages_returns_one_result_per_tiff_page() {
+    let mut tiff_bytes = Vec::new();
+    {
+        let mut encoder =
+            tiff::encoder::TiffEncoder::new(Cursor::new(&mut tiff_bytes)).expect("tiff encoder");
+        for shade in [30u8, 220u8] {
+            encoder
+                .write_image::<tiff::encoder::colortype::Gray8>(4, 4, &[shade; 16])
+                .expect("write tiff page");
+        }
+    }
+
+    let config = test_config();
+    let server = TestServer::new(create_app(&config)).expect("test server");
+
+    let form = MultipartForm::new()
+        .add_part("file", Part::bytes(tiff_bytes).file_name("scan.tiff"))
+        .add_part("output_format", Part::text("png"))
+        .add_part("split_pages", Part::text("true"));
+
+    let response = server.post("/api/compress/batch").multipart(form).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let v: serde_json::Value = response.json();
+    let results = v["results"].as_array().expect("results array");
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0]["page"], 1);
+    assert_eq!(results[1]["page"], 2);
+    assert_eq!(results[1]["mime_type"], "image/png");
+}
+
+#[tokio::test]
+async fn pdf_endpoint_bundles_images_into_pages() {
+    let mut png_bytes = Vec::new();
+    let img: ImageBuffer<image::Rgb<u8>, Vec<u8>> =
+        ImageBuffer::from_pixel(30, 20, image::Rgb([40u8, 80u8, 120u8]));
+    img.write_to(&mut Cursor::new(&mut png_bytes), ImageFormat::Png)
+        .expect("encode test png");
+
+    let config = test_config();
+    let server = TestServer::new(create_app(&config)).expect("test server");
+
+    let form = MultipartForm::new()
+        .add_part(
+            "file",
+            Part::bytes(png_bytes.clone()).file_name("page1.png"),
+        )
+        .add_part("file1", Part::bytes(png_bytes).file_name("page2.png"))
+        .add_part("page_size", Part::text("letter"))
+        .add_part("margin_mm", Part::text("12.7"));
+
+    let response = server.post("/api/pdf").multipart(form).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert_eq!(response.header("content-type"), "application/pdf");
+    let body = response.as_bytes();
+    assert!(body.starts_with(b"%PDF-"));
+    let text = String::from_utf8_lossy(body);
+    assert!(text.contains("/Count 2"));
+    assert!(text.contains("/MediaBox [0 0 792.00 612.00]"));
+}
+
+#[tokio::test]
+async fn pdf_endpoint_rejects_undecodable_files() {
+    let config = test_config();
+    let server = TestServer::new(create_app(&config)).expect("test server");
+
+    let form =
+        MultipartForm::new().add_part("file", Part::bytes(vec![0u8; 64]).file_name("broken.png"));
+    let response = server.post("/api/pdf").multipart(form).await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn pdf_endpoint_rejects_invalid_options() {
+    let mut png_bytes = Vec::new();
+    ImageBuffer::from_pixel(4, 4, image::Rgb([40u8, 80u8, 120u8]))
+        .write_to(&mut Cursor::new(&mut png_bytes), ImageFormat::Png)
+        .expect("encode test png");
+    let server = TestServer::new(create_app(&test_config())).expect("test server");
+
+    for (name, value) in [
+        ("page_size", "a3"),
+        ("margin_mm", "wide"),
+        ("margin_mm", "inf"),
+        ("margin_mm", "-1"),
+        ("margin_mm", "150"),
+        ("image_encoding", "png"),
+        ("jpeg_quality", "0"),
+        ("jpeg_quality", "high"),
+    ] {
+        let form = MultipartForm::new()
+            .add_part("file", Part::bytes(png_bytes.clone()).file_name("a.png"))
+            .add_part(name, Part::text(value));
+        let response = server.post("/api/pdf").multipart(form).await;
+        assert_eq!(
+            response.status_code(),
+            StatusCode::BAD_REQUEST,
+            "{}={}",
+            name,
+            value
+        );
+    }
+}
+
+#[tokio::test]
+async fn compress_applies_transforms_and_rejects_invalid_ones() {
+    let mut png_bytes = Vec::new();
+    let img: ImageBuffer<image::Rgb<u8>, Vec<u8>> =
+        ImageBuffer::from_pixel(4, 2, image::Rgb([40u8, 80u8, 120u8]));
+    img.write_to(&mut Cursor::new(&mut png_bytes), ImageFormat::Png)
+        .expect("encode test png");
+
+    let config = test_config();
+    let server = TestServer::new(create_app(&config)).expect("test server");
+
+    let form = MultipartForm::new()
+        .add_part(
+            "file",
+            Part::bytes(png_bytes.clone()).file_name("sample.png"),
+        )
+        .add_part("output_format", Part::text("png"))
+        .add_part(
+            "transforms",
+            Part::text(r#"[{"op":"rotate","degrees":90}]"#),
+        );
+    let response = server.post("/api/compress").multipart(form).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let v: serde_json::Value = response.json();
+    let data: Vec<u8> = serde_json::from_value(v["data"].clone()).expect("data bytes");
+    let out = image::load_from_memory(&data).expect("decode output");
+    assert_eq!((out.width(), out.height()), (2, 4));
+
+    let form = MultipartForm::new()
+        .add_part("file", Part::bytes(png_bytes).file_name("sample.png"))
+        .add_part("transforms", Part::text("spin:3"));
+    let response = server.post("/api/compress").multipart(form).await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn compress_rejects_unknown_tiff_compression() {
+    let mut png_bytes = Vec::new();
+    ImageBuffer::from_pixel(4, 4, image::Rgb([40u8, 80u8, 120u8]))
+        .write_to(&mut Cursor::new(&mut png_bytes), ImageFormat::Png)
+        .expect("encode test png");
+    let server = TestServer::new(create_app(&test_config())).expect("test server");
+
+    let form = MultipartForm::new()
+        .add_part("file", Part::bytes(png_bytes).file_name("sample.png"))
+        .add_part("output_format", Part::text("tiff"))
+        .add_part("tiff_compression", Part::text("jpeg"));
+    let response = server.post("/api/compress").multipart(form).await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn batch_trim_reports_trimmed_rect() {
+    let mut png_bytes = Vec::new();
+    let mut img: ImageBuffer<image::Rgb<u8>, Vec<u8>> =
+        ImageBuffer::from_pixel(20, 20, image::Rgb([255u8, 255, 255]));
+    for y in 5..9 {
+        for x in 3..13 {
+            img.put_pixel(x, y, image::Rgb([0, 0, 0]));
+        }
+    }
+    img.write_to(&mut Cursor::new(&mut png_bytes), ImageFormat::Png)
+        .expect("encode test png");
+
+    let config = test_config();
+    let server = TestServer::new(create_app(&config)).expect("test server");
+
+    let form = MultipartForm::new()
+        .add_part("file", Part::bytes(png_bytes).file_name("sample.png"))
+        .add_part("output_format", Part::text("png"))
+        .add_part("transforms", Part::text("trim"));
+    let response = server.post("/api/compress/batch").multipart(form).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let v: serde_json::Value = response.json();
+    assert_eq!(
+        v["results"][0]["trimmed"],
+        serde_json::json!({ "x": 3, "y": 5, "width": 10, "height": 4 })
+    );
+}
+
+#[tokio::test]
+async fn compress_applies_watermark_part() {
+    let mut base_png = Vec::new();
+    ImageBuffer::from_pixel(40, 40, image::Rgb([255u8, 255, 255]))
+        .write_to(&mut Cursor::new(&mut base_png), ImageFormat::Png)
+        .expect("encode base png");
+    let mut logo_png = Vec::new();
+    ImageBuffer::from_pixel(4, 4, image::Rgba([0u8, 0, 0, 255]))
+        .write_to(&mut Cursor::new(&mut logo_png), ImageFormat::Png)
+        .expect("encode logo png");
+
+    let config = test_config();
+    let server = TestServer::new(create_app(&config)).expect("test server");
+
+    let form = MultipartForm::new()
+        .add_part("file", Part::bytes(base_png.clone()).file_name("base.png"))
+        .add_part("output_format", Part::text("png"))
+        .add_part("png_lossy", Part::text("false"))
+        .add_part("watermark", Part::bytes(logo_png).file_name("logo.png"))
+        .add_part("watermark_gravity", Part::text("northwest"))
+        .add_part("watermark_margin", Part::text("0"))
+        .add_part("watermark_opacity", Part::text("1"));
+    let response = server.post("/api/compress").multipart(form).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let v: serde_json::Value = response.json();
+    let data: Vec<u8> = serde_json::from_value(v["data"].clone()).expect("data bytes");
+    let out = image::load_from_memory(&data)
+        .expect("decode output")
+        .to_rgb8();
+    assert_eq!(out.get_pixel(2, 2), &image::Rgb([0, 0, 0]));
+    assert_eq!(out.get_pixel(30, 30), &image::Rgb([255, 255, 255]));
+
+    for (name, value) in [
+        ("watermark_color", "not-a-colour"),
+        ("watermark_margin", "-4"),
+        ("watermark_scale", "big"),
+        ("watermark_opacity", "half"),
+        ("watermark_tile", "sometimes"),
+        ("watermark_gravity", "upward"),
+    ] {
+        let form = MultipartForm::new()
+            .add_part("file", Part::bytes(base_png.clone()).file_name("base.png"))
+            .add_part("watermark_text", Part::text("ACME"))
+            .add_part(name, Part::text(value));
+        let response = server.post("/api/compress").multipart(form).await;
+        assert_eq!(response.status_code(), StatusCode::BAD_REQUEST, "{}", name);
+    }
+}
+
+#[tokio::test]
+async fn compress_applies_adjustment_fields() {
+    let mut png = Vec::new();
+    ImageBuffer::from_pixel(16, 16, image::Rgb([200u8, 40, 40]))
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .expect("encode png");
+
+    let config = test_config();
+    let server = TestServer::new(create_app(&config)).expect("test server");
+
+    let form = MultipartForm::new()
+        .add_part("file", Part::bytes(png.clone()).file_name("red.png"))
+        .add_part("output_format", Part::text("png"))
+        .add_part("png_lossy", Part::text("false"))
+        .add_part("saturation", Part::text("-1"));
+    let response = server.post("/api/compress").multipart(form).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let v: serde_json::Value = response.json();
+    let data: Vec<u8> = serde_json::from_value(v["data"].clone()).expect("data bytes");
+    let out = image::load_from_memory(&data)
+        .expect("decode output")
+        .to_rgb8();
+    let p = out.get_pixel(8, 8);
+    assert!(p[0] == p[1] && p[1] == p[2], "desaturated to gray: {:?}", p);
+
+    let form = MultipartForm::new()
+        .add_part("file", Part::bytes(png).file_name("red.png"))
+        .add_part("brightness", Part::text("lots"));
+    let response = server.post("/api/compress").multipart(form).await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn compress_converts_display_p3_to_srgb_unless_ignored() {
+    let p3 = std::fs::read(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../../tests/fixtures/display_p3.jpg"
+    ))
+    .expect("fixture");
+
+    let config = test_config();
+    let server = TestServer::new(create_app(&config)).expect("test server");
+
+    let mut orange = Vec::new();
+    for mode in ["srgb", "ignore"] {
+        let form = MultipartForm::new()
+            .add_part("file", Part::bytes(p3.clone()).file_name("p3.jpg"))
+            .add_part("output_format", Part::text("png"))
+            .add_part("png_lossy", Part::text("false"))
+            .add_part("color_profile", Part::text(mode));
+        let response = server.post("/api/compress").multipart(form).await;
+        assert_eq!(response.status_code(), StatusCode::OK);
+        let v: serde_json::Value = response.json();
+        let data: Vec<u8> = serde_json::from_value(v["data"].clone()).expect("data bytes");
+        let out = image::load_from_memory(&data)
+            .expect("decode output")
+            .to_rgb8();
+        orange.push(*out.get_pixel(4, 4));
+    }
+    assert!(
+        orange[0][0] > orange[1][0] + 8,
+        "sRGB conversion widens the P3 orange: {:?}",
+        orange
+    );
+
+    let form = MultipartForm::new()
+        .add_part("file", Part::bytes(p3).file_name("p3.jpg"))
+        .add_part("color_profile", Part::text("kepe"));
+    let response = server.post("/api/compress").multipart(form).await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn compress_reports_bit_depth_of_16bit_png() {
+    let img: ImageBuffer<image::Rgb<u16>, Vec<u16>> = ImageBuffer::from_fn(8, 8, |x, y| {
+        image::Rgb([x as u16 * 8000, y as u16 * 8000, 1234])
+    });
+    let mut png = Vec::new();
+    img.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .expect("encode png");
+
+    let config = test_config();
+    let server = TestServer::new(create_app(&config)).expect("test server");
+
+    for (format, expected) in [("png", 16), ("jpeg", 8)] {
+        let form = MultipartForm::new()
+            .add_part("file", Part::bytes(png.clone()).file_name("deep.png"))
+            .add_part("output_format", Part::text(format))
+            .add_part("png_lossy", Part::text("false"))
+            .add_part("dither", Part::text("true"));
+        let response = server.post("/api/compress").multipart(form).await;
+        assert_eq!(response.status_code(), StatusCode::OK);
+        let v: serde_json::Value = response.json();
+        assert_eq!(v["bit_depth"], expected, "{format}");
+    }
+}
+
+#[tokio::test]
+async fn job_api_queues_polls_downloads_and_deletes() {
+    let mut png = Vec::new();
+    ImageBuffer::from_pixel(8, 8, image::Rgb([40u8, 80u8, 120u8]))
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .expect("encode png");
+
+    let config = test_config();
+    let server = TestServer::new(create_app(&config)).expect("test server");
+
+    let form = MultipartForm::new()
+        .add_part("file", Part::bytes(png.clone()).file_name("a.png"))
+        .add_part("file1", Part::bytes(b"broken".to_vec()).file_name("b.png"))
+        .add_part("output_format", Part::text("webp"));
+    let response = server.post("/api/jobs").multipart(form).await;
+    assert_eq!(response.status_code(), StatusCode::ACCEPTED);
+    let created: serde_json::Value = response.json();
+    let id = created["id"].as_str().expect("job id").to_string();
+    assert_eq!(created["total"], 2);
+
+    let mut status = serde_json::Value::Null;
+    for _ in 0..200 {
+        status = server.get(&format!("/api/jobs/{id}")).await.json();
+        if status["status"] == "done" {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    assert_eq!(status["status"], "done", "{status}");
+    assert_eq!(status["progress"], 100.0);
+    assert_eq!(status["files"][0]["status"], "done");
+    assert_eq!(status["files"][0]["outputs"][0]["mime_type"], "image/webp");
+    assert_eq!(status["files"][1]["status"], "failed");
+
+    let file = server.get(&format!("/api/jobs/{id}/files/0")).await;
+    assert_eq!(file.status_code(), StatusCode::OK);
+    assert_eq!(file.header("content-type"), "image/webp");
+    assert!(file.as_bytes().starts_with(b"RIFF"));
+    let failed = server.get(&format!("/api/jobs/{id}/files/1")).await;
+    assert_eq!(failed.status_code(), StatusCode::CONFLICT);
+
+    let deleted = server.delete(&format!("/api/jobs/{id}")).await;
+    assert_eq!(deleted.status_code(), StatusCode::NO_CONTENT);
+    let gone = server.get(&format!("/api/jobs/{id}")).await;
+    assert_eq!(gone.status_code(), StatusCode::NOT_FOUND);
+}
+
+/// Submit `png` as a one-file job and return its id.
+async fn submit_job(server: &TestServer, png: &[u8], format: &str) -> String {
+    let form = MultipartForm::new()
+        .add_part("file", Part::bytes(png.to_vec()).file_name("a.png"))
+        .add_part("output_format", Part::text(format.to_string()));
+    let response = server.post("/api/jobs").multipart(form).await;
+    assert_eq!(response.status_code(), StatusCode::ACCEPTED);
+    let created: serde_json::Value = response.json();
+    created["id"].as_str().expect("job id").to_string()
+}
+
+/// Poll job `id` until `done(status code, body)` holds, for up to two seconds.
+async fn poll_job(
+    server: &TestServer,
+    id: &str,
+    done: impl Fn(StatusCode, &serde_json::Value) -> bool,
+) -> bool {
+    for _ in 0..200 {
+        let response = server.get(&format!("/api/jobs/{id}")).await;
+        let body = if response.status_code() == StatusCode::OK {
+            response.json()
+        } else {
+            serde_json::Value::Null
+        };
+        if done(response.status_code(), &body) {
+            return true;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    false
+}
+
+#[tokio::test]
+async fn job_queue_limit_rejects_and_evicts_finished_jobs() {
+    let mut png = Vec::new();
+    ImageBuffer::from_pixel(8, 8, image::Rgb([40u8, 80u8, 120u8]))
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .expect("encode png");
+    // Noise takes the AVIF encoder long enough to keep a job pending.
+    let mut noisy = Vec::new();
+    ImageBuffer::from_fn(512, 512, |x, y| {
+        let v = (x.wrapping_mul(7919) ^ y.wrapping_mul(104_729)).wrapping_mul(2_654_435_761);
+        image::Rgb([(v >> 8) as u8, (v >> 16) as u8, (v >> 24) as u8])
+    })
+    .write_to(&mut Cursor::new(&mut noisy), ImageFormat::Png)
+    .expect("encode png");
+
+    let config = Config {
+        job_queue_limit: 1,
+        ..test_config()
+    };
+    let server = TestServer::new(create_app(&config)).expect("test server");
+
+    let slow = submit_job(&server, &noisy, "avif").await;
+    let form = MultipartForm::new().add_part("file", Part::bytes(png.clone()).file_name("b.png"));
+    let full = server.post("/api/jobs").multipart(form).await;
+    assert_eq!(full.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+    let deleted = server.delete(&format!("/api/jobs/{slow}")).await;
+    assert_eq!(deleted.status_code(), StatusCode::NO_CONTENT);
+
+    // Only one finished job is kept: the second one to finish drops the first.
+    let first = submit_job(&server, &png, "webp").await;
+    assert!(poll_job(&server, &first, |_, body| body["status"] == "done").await);
+    let second = submit_job(&server, &png, "webp").await;
+    assert!(poll_job(&server, &second, |_, body| body["status"] == "done").await);
+    let evicted = server.get(&format!("/api/jobs/{first}")).await;
+    assert_eq!(evicted.status_code(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn finished_jobs_expire_after_ttl() {
+    let mut png = Vec::new();
+    ImageBuffer::from_pixel(8, 8, image::Rgb([40u8, 80u8, 120u8]))
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .expect("encode png");
+
+    let config = Config {
+        job_ttl_secs: 0,
+        ..test_config()
+    };
+    let server = TestServer::new(create_app(&config)).expect("test server");
+
+    let id = submit_job(&server, &png, "webp").await;
+    assert!(poll_job(&server, &id, |status, _| status == StatusCode::NOT_FOUND).await);
+}
+
+#[tokio::test]
+async fn binary_responses_carry_metadata_in_headers() {
+    let mut png = Vec::new();
+    ImageBuffer::from_pixel(8, 8, image::Rgb([40u8, 80u8, 120u8]))
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .expect("encode png");
+
+    let config = test_config();
+    let server = TestServer::new(create_app(&config)).expect("test server");
+
+    let form = MultipartForm::new()
+        .add_part("file", Part::bytes(png.clone()).file_name("a.png"))
+        .add_part("output_format", Part::text("webp"));
+    let single = server
+        .post("/api/compress")
+        .add_header("accept", "application/octet-stream")
+        .multipart(form)
+        .await;
+    assert_eq!(single.status_code(), StatusCode::OK);
+    assert_eq!(single.header("content-type"), "image/webp");
+    assert_eq!(single.header("x-original-size"), png.len().to_string());
+    assert_eq!(
+        single.header("x-compressed-size"),
+        single.as_bytes().len().to_string()
+    );
+    assert_eq!(single.header("x-bit-depth"), "8");
+    assert!(single.as_bytes().starts_with(b"RIFF"));
+
+    let form = MultipartForm::new()
+        .add_part("file", Part::bytes(png.clone()).file_name("a.png"))
+        .add_part("file1", Part::bytes(b"broken".to_vec()).file_name("b.png"))
+        .add_part("output_format", Part::text("webp"));
+    let batch = server
+        .post("/api/compress/batch")
+        .add_header("accept", "multipart/mixed")
+        .multipart(form)
+        .await;
+    assert_eq!(batch.status_code(), StatusCode::OK);
+    let content_type = batch.header("content-type");
+    let boundary = content_type
+        .to_str()
+        .unwrap()
+        .strip_prefix("multipart/mixed; boundary=")
+        .expect("multipart/mixed content type")
+        .to_string();
+    let body = batch.as_bytes();
+    let delimiter = format!("--{boundary}");
+    let parts: Vec<&[u8]> = split_bytes(body, delimiter.as_bytes())
+        .into_iter()
+        .skip(1)
+        .filter(|part| !part.starts_with(b"--"))
+        .collect();
+    assert_eq!(parts.len(), 2);
+    let head = |part: &[u8]| {
+        let end = part.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+        String::from_utf8_lossy(&part[..end]).to_string()
+    };
+    assert!(head(parts[0]).contains("content-type: image/webp"));
+    assert!(head(parts[0]).contains("x-file-index: 0"));
+    assert!(parts[0].windows(4).any(|w| w == b"RIFF"));
+    assert!(head(parts[1]).contains("content-type: application/error"));
+    assert!(head(parts[1]).contains("x-file-index: 1"));
+}
+
+fn split_bytes<'a>(haystack: &'a [u8], needle: &[u8]) -> Vec<&'a [u8]> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i + needle.len() <= haystack.len() {
+        if &haystack[i..i + needle.len()] == needle {
+            parts.push(&haystack[start..i]);
+            i += needle.len();
+            start = i;
+        } else {
+            i += 1;
+        }
+    }
+    parts.push(&haystack[start..]);
+    parts
+}
+
+#[tokio::test]
+async fn zip_endpoints_return_archives_with_unique_entry_names() {
+    let mut png = Vec::new();
+    ImageBuffer::from_pixel(8, 8, image::Rgb([40u8, 80u8, 120u8]))
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .expect("encode png");
+
+    let config = test_config();
+    let server = TestServer::new(create_app(&config)).expect("test server");
+    let entry_names = |bytes: &[u8]| {
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes.to_vec())).expect("zip archive");
+        (0..archive.len())
+            .map(|i| archive.by_index(i).unwrap().name().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    let form = MultipartForm::new()
+        .add_part("file", Part::bytes(png.clone()).file_name("a.png"))
+        .add_part("file1", Part::bytes(png.clone()).file_name("a.jpg"))
+        .add_part("file2", Part::bytes(b"broken".to_vec()).file_name("b.png"))
+        .add_part("output_format", Part::text("webp"));
+    let response = server.post("/api/compress/zip").multipart(form).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert_eq!(response.header("content-type"), "application/zip");
+    assert_eq!(response.header("x-failed-count"), "1");
+    assert_eq!(
+        entry_names(response.as_bytes()),
+        vec!["a.webp", "a (1).webp"]
+    );
+
+    let form = MultipartForm::new()
+        .add_part("file", Part::bytes(png.clone()).file_name("x.png"))
+        .add_part("file1", Part::bytes(b"as-is".to_vec()).file_name("x.png"));
+    let response = server.post("/api/zip").multipart(form).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert_eq!(entry_names(response.as_bytes()), vec!["x.png", "x (1).png"]);
+    let mut archive = zip::ZipArchive::new(Cursor::new(response.as_bytes().to_vec())).unwrap();
+    let mut stored = Vec::new();
+    std::io::Read::read_to_end(&mut archive.by_index(1).unwrap(), &mut stored).unwrap();
+    assert_eq!(stored, b"as-is");
+
+    let form = MultipartForm::new()
+        .add_part("file", Part::bytes(png.clone()).file_name("CON.png"))
+        .add_part("file1", Part::bytes(png).file_name("bad:name.png."));
+    let response = server.post("/api/zip").multipart(form).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert_eq!(
+        entry_names(response.as_bytes()),
+        vec!["CON_.png", "bad_name.png"]
+    );
+}
+
+#[tokio::test]
+async fn filename_template_sets_suggested_filenames() {
+    let mut png = Vec::new();
+    ImageBuffer::from_pixel(12, 8, image::Rgb([40u8, 80u8, 120u8]))
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .expect("encode png");
+
+    let config = test_config();
+    let server = TestServer::new(create_app(&config)).expect("test server");
+    let form = |template: &str| {
+        MultipartForm::new()
+            .add_part("file", Part::bytes(png.clone()).file_name("photo.png"))
+            .add_part("file1", Part::bytes(png.clone()).file_name("café.png"))
+            .add_part("output_format", Part::text("jpeg"))
+            .add_part("filename_template", Part::text(template.to_string()))
+    };
+    let template = "{stem}-{width}x{height}-q{quality}-{index}.{ext}";
+
+    let response = server
+        .post("/api/compress/batch")
+        .multipart(form(template))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let v: serde_json::Value = response.json();
+    assert_eq!(
+        v["results"][0]["suggested_filename"],
+        "photo-12x8-q78-1.jpg"
+    );
+    assert_eq!(v["results"][1]["suggested_filename"], "café-12x8-q78-2.jpg");
+
+    let response = server
+        .post("/api/compress")
+        .add_header("accept", "application/octet-stream")
+        .multipart(form("{stem}-min.{ext}"))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert_eq!(response.header("x-suggested-filename"), "photo-min.jpg");
+
+    let response = server
+        .post("/api/compress/zip")
+        .multipart(form(template))
+        .await;
+    let mut archive = zip::ZipArchive::new(Cursor::new(response.as_bytes().to_vec())).unwrap();
+    assert_eq!(archive.by_index(1).unwrap().name(), "café-12x8-q78-2.jpg");
+
+    let created: serde_json::Value = server
+        .post("/api/jobs")
+        .multipart(form(template))
+        .await
+        .json();
+    let id = created["id"].as_str().expect("job id").to_string();
+    let mut status = serde_json::Value::Null;
+    for _ in 0..200 {
+        status = server.get(&format!("/api/jobs/{id}")).await.json();
+        if status["status"] == "done" {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    let output = &status["files"][1]["outputs"][0];
+    assert_eq!(output["suggested_filename"], "café-12x8-q78-2.jpg");
+    let file = server.get(&format!("/api/jobs/{id}/files/1")).await;
+    assert_eq!(
+        file.header("content-disposition"),
+        "attachment; filename=\"caf_-12x8-q78-2.jpg\"; filename*=UTF-8''caf%C3%A9-12x8-q78-2.jpg"
+    );
+
+    let response = server
+        .post("/api/compress/batch")
+        .multipart(form("{stem}-{size}.{ext}"))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn preset_field_applies_named_options_under_explicit_fields() {
+    let mut png_bytes = Vec::new();
+    let img: ImageBuffer<image::Rgb<u8>, Vec<u8>> =
+        ImageBuffer::from_pixel(40, 20, image::Rgb([40u8, 80u8, 120u8]));
+    img.write_to(&mut Cursor::new(&mut png_bytes), ImageFormat::Png)
+        .expect("encode test png");
+
+    let mut blog = presets::Preset::new("Blog", Default::default());
+    blog.options.set_output_format("jpeg");
+    blog.options.filename_template = Some("{stem}-blog.{ext}".to_string());
+    let config = Config {
+        presets: vec![blog],
+        ..test_config()
+    };
+    let server = TestServer::new(create_app(&config)).expect("test server");
+
+    let listed: serde_json::Value = server.get("/api/presets").await.json();
+    let names: Vec<&str> = listed["presets"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        names,
+        ["Web", "Email", "Archive (lossless)", "Thumbnail", "Blog"]
+    );
+
+    let form = |fields: &[(&str, &str)]| {
+        fields.iter().fold(
+            MultipartForm::new().add_part(
+                "file",
+                Part::bytes(png_bytes.clone()).file_name("photo.png"),
+            ),
+            |form, (name, value)| form.add_part(name.to_string(), Part::text(value.to_string())),
+        )
+    };
+
+    let v: serde_json::Value = server
+        .post("/api/compress/batch")
+        .multipart(form(&[("preset", "thumbnail")]))
+        .await
+        .json();
+    assert_eq!(v["results"][0]["mime_type"], "image/webp");
+    assert_eq!(v["results"][0]["suggested_filename"], "photo-thumb.webp");
+
+    // Explicit fields win, whichever side of `preset` they are on.
+    let v: serde_json::Value = server
+        .post("/api/compress/batch")
+        .multipart(form(&[("output_format", "png"), ("preset", "blog")]))
+        .await
+        .json();
+    assert_eq!(v["results"][0]["mime_type"], "image/png");
+    assert_eq!(v["results"][0]["suggested_filename"], "photo-blog.png");
+
+    let response = server
+        .post("/api/compress")
+        .multipart(form(&[("preset", "print")]))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn quality_fields_pick_encoder_quality() {
+    let mut png_bytes = Vec::new();
+    let img: ImageBuffer<image::Rgb<u8>, Vec<u8>> =
+        ImageBuffer::from_pixel(24, 16, image::Rgb([200u8, 60u8, 30u8]));
+    img.write_to(&mut Cursor::new(&mut png_bytes), ImageFormat::Png)
+        .expect("encode test png");
+
+    let config = Config {
+        quality_table: Some(serde_json::from_str(r#"{"low": {"webp": 91}}"#).unwrap()),
+        ..test_config()
+    };
+    let server = TestServer::new(create_app(&config)).expect("test server");
+    let quality_of = |fields: &[(&str, &str)]| {
+        let form = fields.iter().fold(
+            MultipartForm::new()
+                .add_part("file", Part::bytes(png_bytes.clone()).file_name("a.png"))
+                .add_part("filename_template", Part::text("q{quality}.{ext}")),
+            |form, (name, value)| form.add_part(name.to_string(), Part::text(value.to_string())),
+        );
+        let server = &server;
+        async move {
+            let v: serde_json::Value = server
+                .post("/api/compress/batch")
+                .multipart(form)
+                .await
+                .json();
+            v["results"][0]["suggested_filename"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string()
+        }
+    };
+    let defaults = domain::QualityTable::default();
+
+    // Level names work in `png_quality`, and JPEG and AVIF use their own scales.
+    assert_eq!(
+        quality_of(&[("output_format", "jpeg"), ("png_quality", "max")]).await,
+        format!("q{}.jpg", defaults.max.jpeg)
+    );
+    assert_eq!(
+        quality_of(&[("output_format", "avif"), ("png_quality", "max")]).await,
+        format!("q{}.avif", defaults.max.avif)
+    );
+    // The server's table, then the form's own table, then an explicit quality.
+    assert_eq!(
+        quality_of(&[("output_format", "webp"), ("png_quality", "low")]).await,
+        "q91.webp"
+    );
+    assert_eq!(
+        quality_of(&[
+            ("output_format", "webp"),
+            ("png_quality", "low"),
+            ("quality_table", r#"{"low": {"webp": 70}}"#),
+        ])
+        .await,
+        "q70.webp"
+    );
+    assert_eq!(
+        quality_of(&[("output_format", "webp"), ("quality", "33")]).await,
+        "q33.webp"
+    );
+
+    for (name, value) in [
+        ("quality", "101"),
+        ("quality_table", r#"{"low": {"gif": 1}}"#),
+    ] {
+        let response = server
+            .post("/api/compress")
+            .multipart(
+                MultipartForm::new()
+                    .add_part("file", Part::bytes(png_bytes.clone()).file_name("a.png"))
+                    .add_part(name, Part::text(value)),
+            )
+            .await;
+        assert_eq!(response.status_code(), StatusCode::BAD_REQUEST, "{}", name);
+    }
+}
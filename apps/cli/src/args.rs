@@ -0,0 +1,495 @@
+//! Command-line arguments shared by the subcommands, and their conversion into inputs and
+//! [`CompressionOptions`].
+
+use clap::builder::PossibleValuesParser;
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use domain::{
+    compression_level_to_range, parse_color_profile, parse_tiff_compression, parse_transforms,
+    CompressionOptions, Gravity, QualityTable, Transform, Watermark, WatermarkKind,
+};
+use scan::{Glob, ScanFilter, ScannedFile};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const FORMATS: [&str; 8] = ["webp", "avif", "jpeg", "png", "tiff", "bmp", "ico", "jxl"];
+
+/// Input paths, folders and globs, plus the filters applied while expanding them.
+pub fn input_args(cmd: Command) -> Command {
+    cmd.arg(
+        Arg::new("inputs")
+            .value_name("INPUT")
+            .num_args(1..)
+            .required(true)
+            .help(
+                "Image files, folders (with --recursive) or quoted globs such as 'shots/**/*.png'",
+            ),
+    )
+    .arg(
+        Arg::new("recursive")
+            .short('r')
+            .long("recursive")
+            .action(ArgAction::SetTrue)
+            .help("Include images in folders and below; lets '*.png' match in subfolders"),
+    )
+    .arg(
+        Arg::new("include")
+            .long("include")
+            .value_name("GLOB")
+            .action(ArgAction::Append)
+            .help("Only take matching files from folders and globs (repeatable)"),
+    )
+    .arg(
+        Arg::new("exclude")
+            .long("exclude")
+            .value_name("GLOB")
+            .action(ArgAction::Append)
+            .help("Skip matching files and folders (repeatable)"),
+    )
+}
+
+/// Where outputs go, how they are named and how many files run at once.
+pub fn output_args(cmd: Command) -> Command {
+    cmd.arg(
+        Arg::new("output")
+            .short('o')
+            .long("output")
+            .value_name("DIR")
+            .value_parser(value_parser!(PathBuf))
+            .help("Output folder; subfolders of folder and glob inputs are kept [default: next to each input]"),
+    )
+    .arg(
+        Arg::new("template")
+            .long("template")
+            .value_name("TEMPLATE")
+            .help(format!(
+                "Output name template, e.g. {{stem}}-min.{{ext}}; tokens: {}",
+                naming::TEMPLATE_TOKENS.join(", ")
+            )),
+    )
+    .arg(
+        Arg::new("overwrite")
+            .long("overwrite")
+            .action(ArgAction::SetTrue)
+            .help("Replace existing files (never other inputs) instead of adding ' (1)'"),
+    )
+    .arg(
+        Arg::new("jobs")
+            .short('j')
+            .long("jobs")
+            .value_name("N")
+            .value_parser(value_parser!(usize))
+            .default_value("0")
+            .help("Files compressed at once; 0 means one per CPU core"),
+    )
+    .arg(json_arg())
+}
+
+pub fn json_arg() -> Arg {
+    Arg::new("json")
+        .long("json")
+        .action(ArgAction::SetTrue)
+        .help("Print a JSON report on stdout instead of one line per file")
+}
+
+/// `--format`; `original` keeps each input's format and is only offered when not `required`.
+pub fn format_arg(required: bool) -> Arg {
+    let arg = Arg::new("format")
+        .short('f')
+        .long("format")
+        .value_name("FORMAT");
+    if required {
+        arg.required(true)
+            .value_parser(PossibleValuesParser::new(FORMATS))
+            .help("Output format")
+    } else {
+        let mut formats = FORMATS.to_vec();
+        formats.push("original");
+        arg.default_value("original")
+            .value_parser(PossibleValuesParser::new(formats))
+            .help("Output format")
+    }
+}
+
+/// Every other [`CompressionOptions`] setting.
+pub fn encoding_args(cmd: Command) -> Command {
+    cmd.arg(
+        Arg::new("level")
+            .short('l')
+            .long("level")
+            .value_parser(["low", "mid", "max"])
+            .default_value("mid")
+            .help("Compression level"),
+    )
+    .arg(
+        Arg::new("quality_range")
+            .long("quality-range")
+            .value_name("MIN-MAX")
+            .help("Quality range such as 50-80, instead of --level"),
+    )
+    .arg(
+        Arg::new("quality")
+            .short('q')
+            .long("quality")
+            .value_name("0-100")
+            .value_parser(value_parser!(u8).range(0..=100))
+            .help("Encoder quality for every format, instead of the per-level table"),
+    )
+    .arg(
+        Arg::new("quality_table")
+            .long("quality-table")
+            .value_name("JSON")
+            .help(r#"Per-level quality overrides such as '{"max": {"avif": 35}}'"#),
+    )
+    .arg(flag("lossless", "lossless", "Keep PNG output lossless (no palette quantization)"))
+    .arg(flag("no_oxipng", "no-oxipng", "Skip the oxipng pass on PNG output"))
+    .arg(flag("jxl_lossless_jpeg", "jxl-lossless-jpeg",
+        "JPEG XL: recompress JPEG input losslessly instead of re-encoding pixels",
+    ))
+    .arg(
+        Arg::new("tiff_compression")
+            .long("tiff-compression")
+            .value_parser(["lzw", "deflate", "packbits", "none"])
+            .default_value("lzw")
+            .help("TIFF output compression"),
+    )
+    .arg(flag("split_pages", "split-pages", "One output per page of a multi-page TIFF"))
+    .arg(flag("dither", "dither", "Dither 16-bit input reduced to 8 bits"))
+    .arg(
+        Arg::new("color_profile")
+            .long("color-profile")
+            .value_parser(["srgb", "keep", "ignore"])
+            .default_value("srgb")
+            .help("Embedded ICC profiles: convert to sRGB, keep, or ignore"),
+    )
+    .arg(
+        Arg::new("transform")
+            .long("transform")
+            .value_name("SPEC")
+            .action(ArgAction::Append)
+            .help("Transforms in order, e.g. rotate:90,flip:h,crop:0:0:800:600,aspect:16:9,trim,resize:1200 (repeatable)"),
+    )
+    .arg(
+        Arg::new("adjust")
+            .long("adjust")
+            .value_name("NAME=VALUE")
+            .action(ArgAction::Append)
+            .help(format!(
+                "Colour correction (repeatable): {}",
+                domain::Adjustments::FIELDS.join(", ")
+            )),
+    )
+    .arg(
+        Arg::new("watermark_image")
+            .long("watermark-image")
+            .value_name("PATH")
+            .value_parser(value_parser!(PathBuf))
+            .conflicts_with("watermark_text")
+            .help("Logo stamped on every output (PNG with alpha)"),
+    )
+    .arg(
+        Arg::new("watermark_text")
+            .long("watermark-text")
+            .value_name("TEXT")
+            .help("Text stamped on every output"),
+    )
+    .arg(
+        Arg::new("watermark_color")
+            .long("watermark-color")
+            .value_name("#RRGGBB[AA]")
+            .default_value("#ffffff")
+            .help("Watermark text colour"),
+    )
+    .arg(
+        Arg::new("watermark_gravity")
+            .long("watermark-gravity")
+            .value_name("GRAVITY")
+            .help("Watermark position: center, north, south-east, … [default: south-east]"),
+    )
+    .arg(
+        Arg::new("watermark_margin")
+            .long("watermark-margin")
+            .value_name("PX")
+            .value_parser(value_parser!(u32))
+            .help("Distance from the edges in pixels"),
+    )
+    .arg(
+        Arg::new("watermark_scale")
+            .long("watermark-scale")
+            .value_name("FRACTION")
+            .value_parser(value_parser!(f32))
+            .help("Watermark width relative to the image width"),
+    )
+    .arg(
+        Arg::new("watermark_opacity")
+            .long("watermark-opacity")
+            .value_name("0-1")
+            .value_parser(value_parser!(f32))
+            .help("Watermark opacity"),
+    )
+    .arg(flag("watermark_tile", "watermark-tile", "Repeat the watermark across the image"))
+}
+
+fn flag(id: &'static str, long: &'static str, help: &'static str) -> Arg {
+    Arg::new(id)
+        .long(long)
+        .action(ArgAction::SetTrue)
+        .help(help)
+}
+
+fn strings<'a>(matches: &'a ArgMatches, id: &str) -> impl Iterator<Item = &'a String> {
+    matches.get_many::<String>(id).into_iter().flatten()
+}
+
+/// [`CompressionOptions`] from [`format_arg`] and [`encoding_args`], with `extra` transforms
+/// appended to the ones given with `--transform`.
+pub fn compression_options(
+    matches: &ArgMatches,
+    extra: Vec<Transform>,
+) -> Result<CompressionOptions, String> {
+    let mut transforms = Vec::new();
+    for spec in strings(matches, "transform") {
+        transforms.extend(parse_transforms(spec)?);
+    }
+    transforms.extend(extra);
+
+    let mut adjustments = domain::Adjustments::default();
+    for setting in strings(matches, "adjust") {
+        let (name, value) = setting
+            .split_once('=')
+            .ok_or_else(|| format!("Expected NAME=VALUE, got '{}'", setting))?;
+        adjustments.set_field(name.trim(), value)?;
+    }
+
+    let filename_template = matches.get_one::<String>("template").cloned();
+    if let Some(template) = &filename_template {
+        naming::validate_template(template)?;
+    }
+
+    let flag = |id: &str| matches.get_flag(id);
+    let value = |id: &str| matches.get_one::<String>(id).cloned().unwrap_or_default();
+    let mut options = CompressionOptions {
+        png_quality: match matches.get_one::<String>("quality_range") {
+            Some(range) => parse_quality_range_arg(range)?,
+            None => compression_level_to_range(&value("level")),
+        },
+        png_lossy: !flag("lossless"),
+        oxipng: !flag("no_oxipng"),
+        jxl_lossless_jpeg: flag("jxl_lossless_jpeg"),
+        tiff_compression: parse_tiff_compression(&value("tiff_compression")),
+        split_pages: flag("split_pages"),
+        dither: flag("dither"),
+        color_profile: parse_color_profile(&value("color_profile")),
+        transforms,
+        adjustments,
+        watermark: watermark(matches)?,
+        filename_template,
+        quality: matches.get_one::<u8>("quality").copied(),
+        quality_table: match matches.get_one::<String>("quality_table") {
+            Some(json) => Some(parse_quality_table_arg(json)?),
+            None => None,
+        },
+        ..Default::default()
+    };
+    options.set_output_format(&value("format"));
+    Ok(options)
+}
+
+/// `--quality-range`: `MIN-MAX` with `0 <= MIN <= MAX <= 100`.
+fn parse_quality_range_arg(range: &str) -> Result<String, String> {
+    let invalid = || {
+        format!(
+            "Invalid quality range '{}' (expected MIN-MAX, e.g. 50-80)",
+            range
+        )
+    };
+    let (min, max) = range.split_once('-').ok_or_else(invalid)?;
+    let (min, max) = (
+        min.trim().parse::<u8>().map_err(|_| invalid())?,
+        max.trim().parse::<u8>().map_err(|_| invalid())?,
+    );
+    if min > max || max > 100 {
+        return Err(invalid());
+    }
+    Ok(format!("{}-{}", min, max))
+}
+
+/// `--quality-table`: JSON overriding some entries of [`QualityTable::default`].
+fn parse_quality_table_arg(json: &str) -> Result<QualityTable, String> {
+    serde_json::from_str(json).map_err(|e| format!("Invalid quality table: {}", e))
+}
+
+fn watermark(matches: &ArgMatches) -> Result<Option<Watermark>, String> {
+    let kind = if let Some(path) = matches.get_one::<PathBuf>("watermark_image") {
+        let data =
+            fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        WatermarkKind::Image { data }
+    } else if let Some(text) = matches.get_one::<String>("watermark_text") {
+        let color = matches
+            .get_one::<String>("watermark_color")
+            .cloned()
+            .unwrap_or_default();
+        if domain::parse_hex_color(&color).is_none() {
+            return Err(format!("Invalid watermark colour '{}'", color));
+        }
+        WatermarkKind::Text {
+            text: text.clone(),
+            color,
+        }
+    } else {
+        return Ok(None);
+    };
+
+    let mut watermark = Watermark::new(kind);
+    if let Some(gravity) = matches.get_one::<String>("watermark_gravity") {
+        watermark.gravity = Gravity::parse(gravity)
+            .ok_or_else(|| format!("Invalid watermark gravity '{}'", gravity))?;
+    }
+    if let Some(margin) = matches.get_one::<u32>("watermark_margin") {
+        watermark.margin = *margin;
+    }
+    if let Some(scale) = matches.get_one::<f32>("watermark_scale") {
+        watermark.scale = *scale;
+    }
+    if let Some(opacity) = matches.get_one::<f32>("watermark_opacity") {
+        watermark.opacity = *opacity;
+    }
+    watermark.tile = matches.get_flag("watermark_tile");
+    Ok(Some(watermark))
+}
+
+/// Whether `s` uses [`scan::Glob`] wildcards. It has no `[...]` classes, so brackets are
+/// literal.
+fn has_glob_chars(s: &str) -> bool {
+    s.contains(['*', '?'])
+}
+
+/// Expand the `inputs` of [`input_args`] into files. Files are taken as given; folders are
+/// scanned (with `--recursive`) like the desktop app's folder picker, so their files keep the
+/// folder name in `relative_path`; a glob is matched below its literal leading folders, which
+/// are left out of `relative_path`. A file reached twice is listed once.
+pub fn inputs(matches: &ArgMatches) -> Result<Vec<ScannedFile>, String> {
+    let recursive = matches.get_flag("recursive");
+    let filter = ScanFilter {
+        include: strings(matches, "include").cloned().collect(),
+        exclude: strings(matches, "exclude").cloned().collect(),
+        ..Default::default()
+    };
+    let extensions = compression::supported_input_extensions();
+
+    let mut found = Vec::new();
+    for input in strings(matches, "inputs") {
+        let path = Path::new(input);
+        if path.exists() {
+            if path.is_dir() && !recursive {
+                return Err(format!(
+                    "{} is a folder; pass --recursive to include the images in it",
+                    input
+                ));
+            }
+            found.extend(scan::scan_paths(
+                &[path.to_path_buf()],
+                &filter,
+                &extensions,
+            )?);
+        } else if has_glob_chars(input) {
+            let matched = expand_glob(input, recursive, &filter, &extensions)?;
+            if matched.is_empty() {
+                return Err(format!("No images match {}", input));
+            }
+            found.extend(matched);
+        } else {
+            return Err(format!("No such file or folder: {}", input));
+        }
+    }
+
+    let mut seen = HashSet::new();
+    found.retain(|file| seen.insert(file.path.clone()));
+    if found.is_empty() {
+        return Err("No images found".to_string());
+    }
+    Ok(found)
+}
+
+fn expand_glob(
+    pattern: &str,
+    recursive: bool,
+    filter: &ScanFilter,
+    extensions: &[&str],
+) -> Result<Vec<ScannedFile>, String> {
+    let normalized = pattern.replace('\\', "/");
+    let components: Vec<&str> = normalized.split('/').collect();
+    let literal = components.iter().take_while(|c| !has_glob_chars(c)).count();
+    let base = match components[..literal].join("/") {
+        base if base.is_empty() && normalized.starts_with('/') => "/".to_string(),
+        base if base.is_empty() => ".".to_string(),
+        base => base,
+    };
+    let rest = components[literal..].join("/");
+    let glob = Glob::new(&rest)?;
+    // Like a shell, `*.png` only looks in the folder itself unless `--recursive` is given.
+    let top_level_only = !recursive && !rest.contains('/');
+
+    let files = scan::scan_folder(Path::new(&base), filter, extensions)?;
+    Ok(files
+        .into_iter()
+        .filter(|file| !(top_level_only && file.relative_path.contains('/')))
+        .filter(|file| glob.matches(&file.relative_path))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quality_range_is_checked() {
+        assert_eq!(parse_quality_range_arg("40 - 90").unwrap(), "40-90");
+        assert!(parse_quality_range_arg("90-40").is_err());
+        assert!(parse_quality_range_arg("50-120").is_err());
+        assert!(parse_quality_range_arg("80").is_err());
+    }
+
+    #[test]
+    fn quality_table_keeps_defaults_it_does_not_override() {
+        let table = parse_quality_table_arg(r#"{"max": {"avif": 35}}"#).unwrap();
+        assert_eq!(table.max.avif, 35);
+        assert_eq!(table.max.jpeg, QualityTable::default().max.jpeg);
+        assert!(parse_quality_table_arg(r#"{"max": {"avif": 135}}"#).is_err());
+        assert!(parse_quality_table_arg(r#"{"best": {}}"#).is_err());
+    }
+
+    #[test]
+    fn globs_match_below_their_literal_folders() {
+        let dir = tempfile::tempdir().unwrap();
+        for path in [
+            "a.png",
+            "b.jpg",
+            "sub/c.png",
+            "sub/deep/d.png",
+            "x[1]/e.png",
+        ] {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, b"x").unwrap();
+        }
+        let root = dir.path().to_string_lossy().replace('\\', "/");
+        let relative = |pattern: &str, recursive| {
+            let pattern = format!("{}/{}", root, pattern);
+            expand_glob(&pattern, recursive, &ScanFilter::default(), &["png", "jpg"])
+                .unwrap()
+                .into_iter()
+                .map(|f| f.relative_path)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(relative("*.png", false), vec!["a.png"]);
+        assert_eq!(
+            relative("*.png", true),
+            vec!["a.png", "sub/c.png", "sub/deep/d.png", "x[1]/e.png"]
+        );
+        assert_eq!(relative("sub/**/*.png", false), vec!["c.png", "deep/d.png"]);
+        assert_eq!(relative("x[1]/*.png", false), vec!["e.png"]);
+        assert!(!has_glob_chars("img[12].png"));
+    }
+}
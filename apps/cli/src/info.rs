@@ -0,0 +1,136 @@
+//! `panda-pixel info`: what each input holds, read from its header where possible.
+
+use crate::run::Summary;
+use compression::{image_info, supported_input_formats, ImageInfo};
+use scan::ScannedFile;
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct InfoReport {
+    path: PathBuf,
+    size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mime_type: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    height: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bit_depth: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    has_alpha: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pages: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    non_srgb_profile: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Display name of a format (`PNG`, `JPEG XL`), falling back to the MIME subtype.
+fn format_name(mime_type: &str) -> String {
+    supported_input_formats()
+        .into_iter()
+        .find(|format| format.mime_type == mime_type)
+        .map(|format| format.name.to_string())
+        .unwrap_or_else(|| {
+            mime_type
+                .rsplit('/')
+                .next()
+                .unwrap_or_default()
+                .to_uppercase()
+        })
+}
+
+fn describe(file: &ScannedFile) -> InfoReport {
+    let info = fs::read(&file.path)
+        .map_err(|e| format!("Failed to read file: {}", e))
+        .and_then(|bytes| image_info(&bytes).map_err(|e| e.to_string()));
+    let mut report = InfoReport {
+        path: file.path.clone(),
+        size: file.size,
+        format: None,
+        mime_type: None,
+        width: None,
+        height: None,
+        bit_depth: None,
+        has_alpha: None,
+        pages: None,
+        non_srgb_profile: None,
+        error: None,
+    };
+    match info {
+        Ok(ImageInfo {
+            mime_type,
+            width,
+            height,
+            bit_depth,
+            has_alpha,
+            pages,
+            non_srgb_profile,
+        }) => {
+            report.format = Some(format_name(mime_type));
+            report.mime_type = Some(mime_type);
+            report.width = Some(width);
+            report.height = Some(height);
+            report.bit_depth = Some(bit_depth);
+            report.has_alpha = Some(has_alpha);
+            report.pages = Some(pages);
+            report.non_srgb_profile = Some(non_srgb_profile);
+        }
+        Err(e) => report.error = Some(e),
+    }
+    report
+}
+
+fn print_report(report: &InfoReport) {
+    if let Some(error) = &report.error {
+        eprintln!("error: {}: {}", report.path.display(), error);
+        return;
+    }
+    let mut details = vec![
+        report.format.clone().unwrap_or_default(),
+        format!(
+            "{}x{}",
+            report.width.unwrap_or_default(),
+            report.height.unwrap_or_default()
+        ),
+        format!("{}-bit", report.bit_depth.unwrap_or_default()),
+    ];
+    if report.has_alpha == Some(true) {
+        details.push("alpha".to_string());
+    }
+    if let Some(pages) = report.pages.filter(|&pages| pages > 1) {
+        details.push(format!("{} pages", pages));
+    }
+    if report.non_srgb_profile == Some(true) {
+        details.push("non-sRGB profile".to_string());
+    }
+    details.push(format!("{} bytes", report.size));
+    println!("{}: {}", report.path.display(), details.join(", "));
+}
+
+/// Describe `inputs`, one line each or as a JSON array.
+pub fn run(inputs: &[ScannedFile], json: bool) -> Summary {
+    let reports: Vec<InfoReport> = inputs.iter().map(describe).collect();
+    if json {
+        match serde_json::to_string_pretty(&reports) {
+            Ok(json) => println!("{}", json),
+            Err(e) => eprintln!("error: Failed to serialize report: {}", e),
+        }
+    } else {
+        reports.iter().for_each(print_report);
+    }
+
+    let failed = reports.iter().filter(|r| r.error.is_some()).count();
+    Summary {
+        succeeded: reports.len() - failed,
+        failed,
+        ..Default::default()
+    }
+}
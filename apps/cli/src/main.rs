@@ -0,0 +1,127 @@
+//! `panda-pixel`: compress, convert, resize and inspect images from the command line with the
+//! same pipeline as the desktop app and the API.
+
+mod args;
+mod info;
+mod run;
+
+use clap::{value_parser, Arg, ArgGroup, ArgMatches, Command};
+use domain::Transform;
+use std::process::ExitCode;
+
+/// Every input failed, or the command could not start.
+const EXIT_FAILURE: u8 = 1;
+/// Some inputs failed and others succeeded.
+const EXIT_PARTIAL: u8 = 3;
+
+const EXIT_CODES: &str = "\
+Exit codes:
+  0  every input succeeded
+  1  every input failed, or the command could not run
+  2  invalid arguments
+  3  some inputs failed";
+
+fn batch_command(name: &'static str, about: &'static str, format_required: bool) -> Command {
+    let cmd = Command::new(name)
+        .about(about)
+        .arg(args::format_arg(format_required));
+    args::encoding_args(args::output_args(args::input_args(cmd)))
+}
+
+fn command() -> Command {
+    Command::new("panda-pixel")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Compress, convert, resize and inspect images")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .after_help(EXIT_CODES)
+        .subcommand(batch_command(
+            "compress",
+            "Compress images, keeping their format unless --format is given",
+            false,
+        ))
+        .subcommand(batch_command(
+            "convert",
+            "Convert images to another format",
+            true,
+        ))
+        .subcommand(
+            batch_command(
+                "resize",
+                "Scale images down to fit a width and/or height (never enlarges)",
+                false,
+            )
+            .arg(
+                Arg::new("width")
+                    .long("width")
+                    .value_name("PX")
+                    .value_parser(value_parser!(u32).range(1..))
+                    .help("Largest output width"),
+            )
+            .arg(
+                Arg::new("height")
+                    .long("height")
+                    .value_name("PX")
+                    .value_parser(value_parser!(u32).range(1..))
+                    .help("Largest output height"),
+            )
+            .group(
+                ArgGroup::new("size")
+                    .args(["width", "height"])
+                    .multiple(true)
+                    .required(true),
+            ),
+        )
+        .subcommand(
+            args::input_args(Command::new("info").about("Show format, size and depth of images"))
+                .arg(args::json_arg()),
+        )
+}
+
+fn batch(matches: &ArgMatches, extra: Vec<Transform>) -> Result<run::Summary, String> {
+    let options = args::compression_options(matches, extra)?;
+    let inputs = args::inputs(matches)?;
+    Ok(run::run(
+        inputs,
+        &options,
+        &run::RunConfig::from_matches(matches),
+    ))
+}
+
+fn main() -> ExitCode {
+    let matches = command().get_matches();
+    let outcome = match matches.subcommand() {
+        Some(("compress" | "convert", sub)) => batch(sub, Vec::new()),
+        Some(("resize", sub)) => batch(
+            sub,
+            vec![Transform::Resize {
+                width: sub.get_one::<u32>("width").copied(),
+                height: sub.get_one::<u32>("height").copied(),
+            }],
+        ),
+        Some(("info", sub)) => {
+            args::inputs(sub).map(|inputs| info::run(&inputs, sub.get_flag("json")))
+        }
+        _ => unreachable!("clap requires a subcommand"),
+    };
+
+    match outcome {
+        Ok(summary) if summary.failed == 0 => ExitCode::SUCCESS,
+        Ok(summary) if summary.succeeded == 0 => ExitCode::from(EXIT_FAILURE),
+        Ok(_) => ExitCode::from(EXIT_PARTIAL),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(EXIT_FAILURE)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_is_well_formed() {
+        command().debug_assert();
+    }
+}
@@ -0,0 +1,291 @@
+//! Batch compression of the expanded inputs, and the report printed afterwards.
+
+use clap::ArgMatches;
+use compression::{compress_image_pages, run_parallel};
+use domain::CompressionOptions;
+use scan::ScannedFile;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Settings from [`crate::args::output_args`] that are not [`CompressionOptions`].
+pub struct RunConfig {
+    pub output: Option<PathBuf>,
+    pub overwrite: bool,
+    pub jobs: usize,
+    pub json: bool,
+}
+
+impl RunConfig {
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        Self {
+            output: matches.get_one::<PathBuf>("output").cloned(),
+            overwrite: matches.get_flag("overwrite"),
+            jobs: matches.get_one::<usize>("jobs").copied().unwrap_or(0),
+            json: matches.get_flag("json"),
+        }
+    }
+}
+
+/// One file written for an input; a split multi-page input has one per page.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputReport {
+    pub path: PathBuf,
+    pub size: u64,
+    pub mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    pub bit_depth: u8,
+    pub trimmed: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileReport {
+    pub input: PathBuf,
+    pub original_size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub outputs: Vec<OutputReport>,
+}
+
+/// Counts that decide the exit code.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Summary {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub original_bytes: u64,
+    pub output_bytes: u64,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    files: &'a [FileReport],
+    #[serde(flatten)]
+    summary: &'a Summary,
+}
+
+/// Output paths handed out so far, so parallel jobs never pick the same name.
+struct Claims {
+    claimed: Mutex<HashSet<PathBuf>>,
+    inputs: HashSet<PathBuf>,
+    overwrite: bool,
+}
+
+impl Claims {
+    /// Claim `name` in `dir` for `input`, adding ` (1)`, ` (2)`, … while the path is claimed,
+    /// is another input, or (without `--overwrite`) already exists. An input may replace itself.
+    fn claim(&self, dir: &Path, name: &str, input: &Path) -> Result<PathBuf, String> {
+        let mut claimed = self.claimed.lock().unwrap_or_else(|e| e.into_inner());
+        let unique = naming::uniquify_paths(&[name.to_string()], |candidate| {
+            let path = dir.join(candidate);
+            claimed.contains(&path)
+                || (path != input && self.inputs.contains(&path))
+                || (!self.overwrite && path.exists())
+        })?;
+        let path = dir.join(&unique[0]);
+        claimed.insert(path.clone());
+        Ok(path)
+    }
+}
+
+fn file_extension(path: &Path) -> String {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// Folder an input's outputs go to: the `--output` folder plus the input's subfolder from the
+/// scan, or the input's own folder.
+fn output_dir(file: &ScannedFile, output: Option<&Path>) -> PathBuf {
+    match output {
+        Some(output) => match file.relative_path.rsplit_once('/') {
+            Some((subfolder, _)) => output.join(subfolder),
+            None => output.to_path_buf(),
+        },
+        None => file
+            .path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default(),
+    }
+}
+
+fn process(
+    index: usize,
+    file: &ScannedFile,
+    options: &CompressionOptions,
+    config: &RunConfig,
+    claims: &Claims,
+) -> Result<Vec<OutputReport>, String> {
+    let bytes = fs::read(&file.path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let outputs = compress_image_pages(&bytes, &file_extension(&file.path), options)
+        .map_err(|e| format!("Compression failed: {}", e))?;
+
+    let dir = output_dir(file, config.output.as_deref());
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let source_name = file.path.to_string_lossy();
+    let split = outputs.len() > 1;
+    let mut reports = Vec::new();
+    for (page_index, output) in outputs.into_iter().enumerate() {
+        let page = split.then_some(page_index as u32 + 1);
+        let name = output.suggested_filename(&source_name, options, index + 1, page);
+        let path = claims.claim(&dir, &name, &file.path)?;
+        inplace::write_atomic(&path, &output.data, &Default::default())
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        reports.push(OutputReport {
+            path,
+            size: output.data.len() as u64,
+            mime_type: output.mime_type,
+            page,
+            width: output.dimensions.map(|(width, _)| width),
+            height: output.dimensions.map(|(_, height)| height),
+            bit_depth: output.bit_depth,
+            trimmed: output.trimmed.is_some(),
+        });
+    }
+    Ok(reports)
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1024 * 1024 => format!("{:.1} MB", b as f64 / (1024.0 * 1024.0)),
+        b if b >= 1024 => format!("{:.1} KB", b as f64 / 1024.0),
+        b => format!("{} B", b),
+    }
+}
+
+fn savings(original: u64, output: u64) -> String {
+    if original == 0 {
+        return String::new();
+    }
+    let percent = (1.0 - output as f64 / original as f64) * 100.0;
+    format!(" ({:+.1}%)", -percent)
+}
+
+fn print_file(report: &FileReport) {
+    if let Some(error) = &report.error {
+        eprintln!("error: {}: {}", report.input.display(), error);
+        return;
+    }
+    for output in &report.outputs {
+        println!(
+            "{} -> {}  {} -> {}{}",
+            report.input.display(),
+            output.path.display(),
+            format_size(report.original_size),
+            format_size(output.size),
+            savings(report.original_size, output.size)
+        );
+    }
+}
+
+/// Compress `inputs` with up to `config.jobs` at once and print a line per output (or the JSON
+/// report). Existing files are never replaced without `--overwrite`, and other inputs never.
+pub fn run(inputs: Vec<ScannedFile>, options: &CompressionOptions, config: &RunConfig) -> Summary {
+    let claims = Claims {
+        claimed: Mutex::default(),
+        inputs: inputs.iter().map(|file| file.path.clone()).collect(),
+        overwrite: config.overwrite,
+    };
+    let jobs: Vec<_> = inputs.into_iter().enumerate().collect();
+    let files = run_parallel(jobs, config.jobs, |(index, file)| {
+        let (outputs, error) = match process(index, &file, options, config, &claims) {
+            Ok(outputs) => (outputs, None),
+            Err(e) => (Vec::new(), Some(e)),
+        };
+        let report = FileReport {
+            input: file.path,
+            original_size: file.size,
+            error,
+            outputs,
+        };
+        if !config.json {
+            print_file(&report);
+        }
+        report
+    });
+
+    let mut summary = Summary::default();
+    for file in &files {
+        if file.error.is_some() {
+            summary.failed += 1;
+            continue;
+        }
+        summary.succeeded += 1;
+        summary.original_bytes += file.original_size;
+        summary.output_bytes += file.outputs.iter().map(|o| o.size).sum::<u64>();
+    }
+
+    if config.json {
+        let report = Report {
+            files: &files,
+            summary: &summary,
+        };
+        match serde_json::to_string_pretty(&report) {
+            Ok(json) => println!("{}", json),
+            Err(e) => eprintln!("error: Failed to serialize report: {}", e),
+        }
+    } else if files.len() > 1 {
+        println!(
+            "{} compressed, {} failed; {} -> {}{}",
+            summary.succeeded,
+            summary.failed,
+            format_size(summary.original_bytes),
+            format_size(summary.output_bytes),
+            savings(summary.original_bytes, summary.output_bytes)
+        );
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outputs_keep_the_scanned_subfolder() {
+        let file = ScannedFile {
+            path: PathBuf::from("/in/photos/2024/a.png"),
+            relative_path: "photos/2024/a.png".to_string(),
+            size: 1,
+        };
+        assert_eq!(
+            output_dir(&file, Some(Path::new("/out"))),
+            PathBuf::from("/out/photos/2024")
+        );
+        assert_eq!(output_dir(&file, None), PathBuf::from("/in/photos/2024"));
+    }
+
+    #[test]
+    fn claims_never_reuse_a_name_or_touch_other_inputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.png");
+        let b = dir.path().join("b.png");
+        fs::write(&a, b"a").unwrap();
+        fs::write(&b, b"b").unwrap();
+        let claims = Claims {
+            claimed: Mutex::default(),
+            inputs: [a.clone(), b.clone()].into(),
+            overwrite: true,
+        };
+
+        assert_eq!(claims.claim(dir.path(), "a.png", &a).unwrap(), a);
+        assert_eq!(
+            claims.claim(dir.path(), "b.png", &a).unwrap(),
+            dir.path().join("b (1).png")
+        );
+        assert_eq!(
+            claims.claim(dir.path(), "a.png", &b).unwrap(),
+            dir.path().join("a (1).png")
+        );
+    }
+}
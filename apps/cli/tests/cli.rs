@@ -0,0 +1,116 @@
+use image::{ImageBuffer, ImageFormat};
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn write_png(path: &Path, width: u32, height: u32) {
+    let img: ImageBuffer<image::Rgb<u8>, Vec<u8>> = ImageBuffer::from_fn(width, height, |x, y| {
+        image::Rgb([(x * 7) as u8, (y * 5) as u8, 120])
+    });
+    let mut bytes = Vec::new();
+    img.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .expect("encode test png");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, bytes).unwrap();
+}
+
+fn panda_pixel(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_panda-pixel"))
+        .args(args)
+        .output()
+        .expect("run panda-pixel")
+}
+
+fn json(output: &Output) -> serde_json::Value {
+    serde_json::from_slice(&output.stdout).expect("JSON on stdout")
+}
+
+#[test]
+fn convert_folder_recursively_keeps_subfolders_and_existing_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("shots");
+    let out = dir.path().join("out");
+    write_png(&input.join("a.png"), 16, 12);
+    write_png(&input.join("2024/b.png"), 8, 8);
+    fs::create_dir_all(out.join("shots")).unwrap();
+    fs::write(out.join("shots/a.webp"), b"keep me").unwrap();
+
+    let output = panda_pixel(&[
+        "convert",
+        input.to_str().unwrap(),
+        "-r",
+        "-f",
+        "webp",
+        "-o",
+        out.to_str().unwrap(),
+        "--json",
+    ]);
+    assert_eq!(output.status.code(), Some(0), "{:?}", output);
+
+    let report = json(&output);
+    assert_eq!(report["succeeded"], 2);
+    assert_eq!(report["failed"], 0);
+    assert_eq!(fs::read(out.join("shots/a.webp")).unwrap(), b"keep me");
+    assert!(out.join("shots/a (1).webp").is_file());
+    assert!(out.join("shots/2024/b.webp").is_file());
+    for file in report["files"].as_array().unwrap() {
+        assert_eq!(file["outputs"][0]["mimeType"], "image/webp");
+    }
+}
+
+#[test]
+fn folder_without_recursive_is_an_error() {
+    let dir = tempfile::tempdir().unwrap();
+    write_png(&dir.path().join("a.png"), 4, 4);
+
+    let output = panda_pixel(&["compress", dir.path().to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--recursive"));
+}
+
+#[test]
+fn partial_failure_has_its_own_exit_code() {
+    let dir = tempfile::tempdir().unwrap();
+    write_png(&dir.path().join("good.png"), 4, 4);
+    fs::write(dir.path().join("bad.png"), b"not an image").unwrap();
+    let pattern = format!("{}/*.png", dir.path().display());
+
+    let output = panda_pixel(&["compress", &pattern, "--json"]);
+    assert_eq!(output.status.code(), Some(3), "{:?}", output);
+    let report = json(&output);
+    assert_eq!(report["succeeded"], 1);
+    assert_eq!(report["failed"], 1);
+    assert!(dir.path().join("good (1).png").is_file());
+}
+
+#[test]
+fn resize_fits_the_box_and_info_reports_it() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("wide.png");
+    write_png(&input, 40, 20);
+
+    let output = panda_pixel(&[
+        "resize",
+        input.to_str().unwrap(),
+        "--width",
+        "10",
+        "--template",
+        "{stem}-small.{ext}",
+    ]);
+    assert_eq!(output.status.code(), Some(0), "{:?}", output);
+
+    let small = dir.path().join("wide-small.png");
+    let output = panda_pixel(&["info", small.to_str().unwrap(), "--json"]);
+    assert_eq!(output.status.code(), Some(0), "{:?}", output);
+    let info = &json(&output)[0];
+    assert_eq!(info["format"], "PNG");
+    assert_eq!(info["width"], 10);
+    assert_eq!(info["height"], 5);
+}
+
+#[test]
+fn resize_needs_a_size() {
+    let output = panda_pixel(&["resize", "a.png"]);
+    assert_eq!(output.status.code(), Some(2));
+}
@@ -0,0 +1,176 @@
+//! Colour corrections (denoise, white balance, levels, tone, sharpening) applied after the
+//! transforms and before the watermark.
+
+use domain::Adjustments;
+use image::{imageops, DynamicImage, RgbaImage};
+
+/// Fraction of pixels allowed to clip at each end when stretching levels.
+const LEVELS_CLIP: f64 = 0.005;
+
+/// Apply `adj` in the order documented on [`Adjustments`]. Alpha is never modified, and an
+/// image without alpha comes back without alpha.
+pub(crate) fn apply_adjustments(img: DynamicImage, adj: &Adjustments) -> DynamicImage {
+    if adj.is_identity() {
+        return img;
+    }
+    let had_alpha = img.color().has_alpha();
+    let mut rgba = img.to_rgba8();
+
+    if adj.denoise {
+        rgba = median3x3(&rgba);
+    }
+    if adj.white_balance {
+        gray_world(&mut rgba);
+    }
+    if adj.auto_levels {
+        auto_levels(&mut rgba);
+    }
+    tone(&mut rgba, adj);
+    if adj.sharpen > 0.0 {
+        rgba = unsharp_mask(
+            &rgba,
+            adj.sharpen.min(5.0),
+            adj.sharpen_radius.clamp(0.1, 20.0),
+        );
+    }
+
+    if had_alpha {
+        DynamicImage::ImageRgba8(rgba)
+    } else {
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(rgba).to_rgb8())
+    }
+}
+
+/// Per-channel median of each pixel's 3×3 neighbourhood (edges repeat).
+fn median3x3(src: &RgbaImage) -> RgbaImage {
+    let (w, h) = src.dimensions();
+    RgbaImage::from_fn(w, h, |x, y| {
+        let mut out = *src.get_pixel(x, y);
+        for c in 0..3 {
+            let mut window = [0u8; 9];
+            let mut i = 0;
+            for dy in -1i64..=1 {
+                for dx in -1i64..=1 {
+                    let sx = (x as i64 + dx).clamp(0, w as i64 - 1) as u32;
+                    let sy = (y as i64 + dy).clamp(0, h as i64 - 1) as u32;
+                    window[i] = src.get_pixel(sx, sy)[c];
+                    i += 1;
+                }
+            }
+            window.sort_unstable();
+            out[c] = window[4];
+        }
+        out
+    })
+}
+
+/// Scale each channel so the mean colour of the visible pixels becomes neutral gray.
+fn gray_world(img: &mut RgbaImage) {
+    let mut sums = [0f64; 3];
+    let mut count = 0u64;
+    for p in img.pixels().filter(|p| p[3] > 0) {
+        for c in 0..3 {
+            sums[c] += p[c] as f64;
+        }
+        count += 1;
+    }
+    if count == 0 || sums.contains(&0.0) {
+        return;
+    }
+    let gray = sums.iter().sum::<f64>() / 3.0;
+    // Limit the correction so a genuinely single-coloured image is not blown out.
+    let gains = sums.map(|s| (gray / s).clamp(0.5, 2.0) as f32);
+    let luts = gains.map(|gain| lut(|v| v * gain));
+    map_channels(img, &luts);
+}
+
+/// Stretch the shared RGB range so the darkest / brightest 0.5% become black / white.
+/// The same mapping is used for every channel so hues do not shift.
+fn auto_levels(img: &mut RgbaImage) {
+    let mut histogram = [0u64; 256];
+    for p in img.pixels().filter(|p| p[3] > 0) {
+        for c in 0..3 {
+            histogram[p[c] as usize] += 1;
+        }
+    }
+    let total: u64 = histogram.iter().sum();
+    if total == 0 {
+        return;
+    }
+    let clip = (total as f64 * LEVELS_CLIP) as u64;
+    let low = clipped_end(&histogram, clip, 0..256) as f32;
+    let high = clipped_end(&histogram, clip, (0..256).rev()) as f32;
+    if high <= low {
+        return;
+    }
+    let lut = lut(|v| (v - low) * 255.0 / (high - low));
+    map_channels(img, &[lut, lut, lut]);
+}
+
+/// First value in `order` past which more than `clip` samples have been seen.
+fn clipped_end(histogram: &[u64; 256], clip: u64, order: impl Iterator<Item = usize>) -> usize {
+    let mut seen = 0;
+    for v in order {
+        seen += histogram[v];
+        if seen > clip {
+            return v;
+        }
+    }
+    0
+}
+
+/// Brightness, contrast, gamma and saturation in one pass, in that order.
+fn tone(img: &mut RgbaImage, adj: &Adjustments) {
+    let brightness = adj.brightness.clamp(-1.0, 1.0);
+    let contrast = adj.contrast.clamp(-1.0, 0.99);
+    let gamma = adj.gamma.clamp(0.1, 10.0);
+    let saturation = adj.saturation.clamp(-1.0, 1.0);
+    if brightness == 0.0 && contrast == 0.0 && gamma == 1.0 && saturation == 0.0 {
+        return;
+    }
+    let contrast_factor = (1.0 + contrast) / (1.0 - contrast);
+
+    for p in img.pixels_mut() {
+        let mut rgb = [0f32; 3];
+        for c in 0..3 {
+            let mut v = p[c] as f32 / 255.0 + brightness;
+            v = (v - 0.5) * contrast_factor + 0.5;
+            v = v.clamp(0.0, 1.0).powf(1.0 / gamma);
+            rgb[c] = v;
+        }
+        if saturation != 0.0 {
+            let luma = 0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2];
+            for v in &mut rgb {
+                *v = luma + (*v - luma) * (1.0 + saturation);
+            }
+        }
+        for c in 0..3 {
+            p[c] = (rgb[c] * 255.0).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+/// Classic unsharp mask: add back `amount` × (image − Gaussian blur).
+fn unsharp_mask(src: &RgbaImage, amount: f32, sigma: f32) -> RgbaImage {
+    let blurred = imageops::blur(src, sigma);
+    let mut out = src.clone();
+    for (p, b) in out.pixels_mut().zip(blurred.pixels()) {
+        for c in 0..3 {
+            let v = p[c] as f32;
+            p[c] = (v + (v - b[c] as f32) * amount).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+    out
+}
+
+fn lut(f: impl Fn(f32) -> f32) -> [u8; 256] {
+    std::array::from_fn(|v| f(v as f32).round().clamp(0.0, 255.0) as u8)
+}
+
+fn map_channels(img: &mut RgbaImage, luts: &[[u8; 256]; 3]) {
+    for p in img.pixels_mut() {
+        for c in 0..3 {
+            p[c] = luts[c][p[c] as usize];
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! Parallel batch execution on a bounded pool of scoped worker threads.
+//!
+//! Each worker decodes and encodes one input at a time, so `concurrency` is also the cap on
+//! decoded images held in memory at once. Results come back in input order.
+
+use super::{compress_image_pages_with_cancel, CancelToken, CompressedImage};
+use anyhow::Result;
+use domain::CompressionOptions;
+use std::num::NonZeroUsize;
+use std::panic;
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+
+/// Worker count for a requested `concurrency`: `0` means one worker per available core.
+pub fn effective_concurrency(concurrency: usize) -> usize {
+    if concurrency > 0 {
+        return concurrency;
+    }
+    thread::available_parallelism()
+        .map(NonZeroUsize::get)
+        .unwrap_or(1)
+}
+
+/// Run `work` over `jobs` on at most `concurrency` threads (see [`effective_concurrency`]),
+/// returning the results in the order of `jobs`. A panic in `work` is re-raised here.
+///
+/// Blocking: async callers should wrap this in `spawn_blocking`.
+pub fn run_parallel<T, R, F>(jobs: Vec<T>, concurrency: usize, work: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> R + Sync,
+{
+    let workers = effective_concurrency(concurrency).min(jobs.len());
+    if workers <= 1 {
+        return jobs.into_iter().map(work).collect();
+    }
+
+    let total = jobs.len();
+    let queue = Mutex::new(jobs.into_iter().enumerate());
+    let mut slots: Vec<Option<R>> = (0..total).map(|_| None).collect();
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let next = queue.lock().unwrap_or_else(PoisonError::into_inner).next();
+                        let Some((index, job)) = next else { break };
+                        done.push((index, work(job)));
+                    }
+                    done
+                })
+            })
+            .collect();
+        for handle in handles {
+            let done = handle.join().unwrap_or_else(|e| panic::resume_unwind(e));
+            for (index, result) in done {
+                slots[index] = Some(result);
+            }
+        }
+    });
+    slots
+        .into_iter()
+        .map(|slot| slot.expect("every job produces a result"))
+        .collect()
+}
+
+/// [`compress_image_pages`](super::compress_image_pages) for every `(bytes, extension)` input,
+/// in parallel. One entry per input, in input order; a failed input does not stop the others.
+/// Once `cancel` is cancelled, inputs not yet finished come back as [`super::Cancelled`].
+pub fn compress_batch(
+    inputs: Vec<(Vec<u8>, String)>,
+    opts: &CompressionOptions,
+    concurrency: usize,
+    cancel: &CancelToken,
+) -> Vec<Result<Vec<CompressedImage>>> {
+    run_parallel(inputs, concurrency, |(bytes, ext)| {
+        compress_image_pages_with_cancel(&bytes, &ext, opts, cancel)
+    })
+}
@@ -0,0 +1,110 @@
+//! Bit depth: 16-bit samples are kept where the output format can store them and reduced to
+//! 8 bits (optionally with ordered dithering) where it cannot.
+
+use anyhow::Result;
+use image::{ColorType, DynamicImage, ImageBuffer, ImageDecoder, ImageReader};
+use rav1e::prelude::PixelRange;
+use ravif::{Encoder as AvifEncoder, MatrixCoefficients};
+use std::io::Cursor;
+
+/// 4×4 Bayer matrix; thresholds are `(value + 0.5) / 16`.
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// BT.601 luma weights, as ravif uses for its 8-bit path.
+const BT601: [f32; 3] = [0.299, 0.587, 0.114];
+
+pub(crate) fn is_high_depth(img: &DynamicImage) -> bool {
+    !matches!(
+        img.color(),
+        ColorType::L8 | ColorType::La8 | ColorType::Rgb8 | ColorType::Rgba8
+    )
+}
+
+/// Whether `input` stores more than 8 bits per channel (16-bit PNG / TIFF, float HDR / EXR).
+pub(crate) fn source_is_high_depth(input: &[u8]) -> bool {
+    bits_per_channel(input) > 8
+}
+
+/// Bits per channel of an encoded image, read from its header (8 when the header can't be read).
+pub(crate) fn bits_per_channel(bytes: &[u8]) -> u8 {
+    ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .ok()
+        .and_then(|reader| reader.into_decoder().ok())
+        .map(|decoder| {
+            let color = decoder.color_type();
+            (color.bits_per_pixel() / color.channel_count() as u16) as u8
+        })
+        .unwrap_or(8)
+}
+
+/// Reduce a high-depth image to 8 bits per channel. Alpha is rounded, never dithered.
+pub(crate) fn reduce_to_8bit(img: DynamicImage, dither: bool) -> DynamicImage {
+    if !is_high_depth(&img) {
+        return img;
+    }
+    let (w, h) = (img.width(), img.height());
+    match img.color() {
+        ColorType::L16 => {
+            let raw = reduce(img.to_luma16().as_raw(), w, 1, dither);
+            DynamicImage::ImageLuma8(ImageBuffer::from_raw(w, h, raw).expect("same size"))
+        }
+        ColorType::La16 => {
+            let raw = reduce(img.to_luma_alpha16().as_raw(), w, 2, dither);
+            DynamicImage::ImageLumaA8(ImageBuffer::from_raw(w, h, raw).expect("same size"))
+        }
+        color if color.has_alpha() => {
+            let raw = reduce(img.to_rgba16().as_raw(), w, 4, dither);
+            DynamicImage::ImageRgba8(ImageBuffer::from_raw(w, h, raw).expect("same size"))
+        }
+        _ => {
+            let raw = reduce(img.to_rgb16().as_raw(), w, 3, dither);
+            DynamicImage::ImageRgb8(ImageBuffer::from_raw(w, h, raw).expect("same size"))
+        }
+    }
+}
+
+fn reduce(samples: &[u16], width: u32, channels: usize, dither: bool) -> Vec<u8> {
+    let has_alpha = channels % 2 == 0;
+    samples
+        .iter()
+        .enumerate()
+        .map(|(i, &v)| {
+            let scaled = v as f32 * 255.0 / 65535.0;
+            let is_alpha = has_alpha && i % channels == channels - 1;
+            if !dither || is_alpha {
+                return scaled.round() as u8;
+            }
+            let pixel = (i / channels) as u32;
+            let (x, y) = ((pixel % width) as usize, (pixel / width) as usize);
+            let threshold = (BAYER[y % 4][x % 4] as f32 + 0.5) / 16.0;
+            (scaled + threshold).floor().min(255.0) as u8
+        })
+        .collect()
+}
+
+/// AVIF from 16-bit samples, encoded at 10 bits (BT.601 YCbCr, full range) so the extra
+/// precision is not first rounded to 8 bits.
+pub(crate) fn encode_avif_10bit(img: &DynamicImage, encoder: &AvifEncoder) -> Result<Vec<u8>> {
+    let rgba = img.to_rgba16();
+    let (w, h) = (rgba.width() as usize, rgba.height() as usize);
+    let to_ten = |v: u16| ((v as f32 / 65535.0) * 1023.0).round() as u16;
+    let planes = rgba.pixels().map(|p| {
+        let [r, g, b] = [p[0], p[1], p[2]].map(|v| v as f32 / 65535.0 * 1023.0);
+        let y = BT601[0] * r + BT601[1] * g + BT601[2] * b;
+        let cb = (b - y) * (0.5 / (1.0 - BT601[2])) + 512.0;
+        let cr = (r - y) * (0.5 / (1.0 - BT601[0])) + 512.0;
+        [y, cb, cr].map(|v| v.round().clamp(0.0, 1023.0) as u16)
+    });
+    let has_alpha = img.color().has_alpha() && rgba.pixels().any(|p| p[3] != u16::MAX);
+    let alpha = has_alpha.then(|| rgba.pixels().map(|p| to_ten(p[3])));
+    let encoded = encoder.encode_raw_planes_10_bit(
+        w,
+        h,
+        planes,
+        alpha,
+        PixelRange::Full,
+        MatrixCoefficients::BT601,
+    )?;
+    Ok(encoded.avif_file)
+}
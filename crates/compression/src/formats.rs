@@ -0,0 +1,64 @@
+//! Build-dependent input format capabilities.
+
+/// An input format this build can decode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputFormat {
+    /// Display name, e.g. `"PNG"`.
+    pub name: &'static str,
+    pub mime_type: &'static str,
+    /// Lowercase file extensions without the leading dot.
+    pub extensions: &'static [&'static str],
+}
+
+const fn format(
+    name: &'static str,
+    mime_type: &'static str,
+    extensions: &'static [&'static str],
+) -> InputFormat {
+    InputFormat {
+        name,
+        mime_type,
+        extensions,
+    }
+}
+
+/// Input formats accepted by [`crate::compress_image_inproc`] in this build.
+/// The list depends on the enabled cargo features (`jxl`, `qoi`, `pnm`, `tga`, `dds`, `hdr`, `exr`).
+pub fn supported_input_formats() -> Vec<InputFormat> {
+    #[allow(unused_mut)]
+    let mut formats = vec![
+        format("PNG", "image/png", &["png"]),
+        format("JPEG", "image/jpeg", &["jpg", "jpeg"]),
+        format("WebP", "image/webp", &["webp"]),
+        format("BMP", "image/bmp", &["bmp"]),
+        format("TIFF", "image/tiff", &["tiff", "tif"]),
+        format("ICO", "image/x-icon", &["ico"]),
+    ];
+    #[cfg(feature = "jxl")]
+    formats.push(format("JPEG XL", "image/jxl", &["jxl"]));
+    #[cfg(feature = "qoi")]
+    formats.push(format("QOI", "image/qoi", &["qoi"]));
+    #[cfg(feature = "pnm")]
+    formats.push(format(
+        "PNM",
+        "image/x-portable-anymap",
+        &["pnm", "pbm", "pgm", "ppm", "pam"],
+    ));
+    #[cfg(feature = "tga")]
+    formats.push(format("TGA", "image/x-tga", &["tga"]));
+    #[cfg(feature = "dds")]
+    formats.push(format("DDS", "image/vnd-ms.dds", &["dds"]));
+    #[cfg(feature = "hdr")]
+    formats.push(format("Radiance HDR", "image/vnd.radiance", &["hdr"]));
+    #[cfg(feature = "exr")]
+    formats.push(format("OpenEXR", "image/x-exr", &["exr"]));
+    formats
+}
+
+/// Flattened extension list of [`supported_input_formats`], for file picker filters.
+pub fn supported_input_extensions() -> Vec<&'static str> {
+    supported_input_formats()
+        .iter()
+        .flat_map(|f| f.extensions.iter().copied())
+        .collect()
+}
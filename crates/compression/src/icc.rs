@@ -0,0 +1,202 @@
+//! ICC colour management: find an embedded non-sRGB profile, convert pixels to sRGB, or carry
+//! the profile over into JPEG / PNG output.
+
+use super::OutputKind;
+use anyhow::{anyhow, Result};
+use domain::{ColorProfile, CompressionOptions};
+use flate2::{write::ZlibEncoder, Compression, Crc};
+use image::{DynamicImage, ImageBuffer, ImageDecoder, ImageReader};
+use moxcms::{ColorProfile as IccProfile, DataColorSpace, Layout, TransformOptions};
+use std::io::{Cursor, Write};
+
+/// Largest ICC payload in one JPEG APP2 segment (65535 − length − "ICC_PROFILE\0" − seq/count).
+const JPEG_ICC_CHUNK: usize = 65519;
+
+/// What [`super::compress_image_detailed`] should do about the input's colour profile.
+pub(crate) enum ProfileAction {
+    None,
+    /// Convert pixels from this profile to sRGB while decoding
+    ConvertToSrgb(Vec<u8>),
+    /// Keep the pixels and embed this profile in the output
+    Embed(Vec<u8>),
+}
+
+pub(crate) fn plan(input: &[u8], output: OutputKind, opts: &CompressionOptions) -> ProfileAction {
+    if opts.color_profile == ColorProfile::Ignore {
+        return ProfileAction::None;
+    }
+    // Lossless JPEG → JXL recompression keeps the JPEG, profile included, bit for bit.
+    if opts.to_jxl && opts.jxl_lossless_jpeg && super::is_jpeg_bytes(input) {
+        return ProfileAction::None;
+    }
+    let Some(profile) = non_srgb_profile(input) else {
+        return ProfileAction::None;
+    };
+    // Only JPEG and PNG output can be tagged by [`embed`].
+    let carries_profile = matches!(output, OutputKind::Jpeg | OutputKind::Png { .. });
+    if opts.color_profile == ColorProfile::Keep && carries_profile {
+        ProfileAction::Embed(profile)
+    } else {
+        ProfileAction::ConvertToSrgb(profile)
+    }
+}
+
+/// The embedded ICC profile, when it is an RGB profile that is not equivalent to sRGB.
+/// Unreadable profiles are ignored rather than failing the job.
+pub(crate) fn non_srgb_profile(input: &[u8]) -> Option<Vec<u8>> {
+    let mut decoder = ImageReader::new(Cursor::new(input))
+        .with_guessed_format()
+        .ok()?
+        .into_decoder()
+        .ok()?;
+    let icc = decoder.icc_profile().ok().flatten()?;
+    let profile = IccProfile::new_from_slice(&icc).ok()?;
+    if profile.color_space != DataColorSpace::Rgb || is_srgb_equivalent(&profile) {
+        return None;
+    }
+    Some(icc)
+}
+
+/// Compare a handful of probe colours after conversion; within ±1 everywhere counts as sRGB.
+fn is_srgb_equivalent(profile: &IccProfile) -> bool {
+    const PROBES: [u8; 18] = [
+        255, 0, 0, 0, 255, 0, 0, 0, 255, 128, 128, 128, 200, 120, 40, 30, 90, 160,
+    ];
+    let Ok(transform) = profile.create_transform_8bit(
+        Layout::Rgb,
+        &IccProfile::new_srgb(),
+        Layout::Rgb,
+        TransformOptions::default(),
+    ) else {
+        return true;
+    };
+    let mut out = [0u8; PROBES.len()];
+    if transform.transform(&PROBES, &mut out).is_err() {
+        return true;
+    }
+    PROBES.iter().zip(out).all(|(a, b)| a.abs_diff(b) <= 1)
+}
+
+/// Convert `img` from `icc` to sRGB. Alpha is carried through unchanged.
+pub(crate) fn convert_to_srgb(img: DynamicImage, icc: &[u8]) -> Result<DynamicImage> {
+    let source =
+        IccProfile::new_from_slice(icc).map_err(|e| anyhow!("Invalid ICC profile: {e}"))?;
+    let srgb = IccProfile::new_srgb();
+    let unsupported = |e| anyhow!("Unsupported ICC profile: {e}");
+    let failed = |e| anyhow!("ICC conversion failed: {e}");
+    let convert = |layout: Layout, pixels: &[u8]| -> Result<Vec<u8>> {
+        let transform = source
+            .create_transform_8bit(layout, &srgb, layout, TransformOptions::default())
+            .map_err(unsupported)?;
+        let mut out = vec![0u8; pixels.len()];
+        transform.transform(pixels, &mut out).map_err(failed)?;
+        Ok(out)
+    };
+    let convert16 = |layout: Layout, pixels: &[u16]| -> Result<Vec<u16>> {
+        let transform = source
+            .create_transform_16bit(layout, &srgb, layout, TransformOptions::default())
+            .map_err(unsupported)?;
+        let mut out = vec![0u16; pixels.len()];
+        transform.transform(pixels, &mut out).map_err(failed)?;
+        Ok(out)
+    };
+
+    let (w, h) = (img.width(), img.height());
+    let same_size = "same size as the source buffer";
+    if super::depth::is_high_depth(&img) {
+        return Ok(if img.color().has_alpha() {
+            let out = convert16(Layout::Rgba, img.to_rgba16().as_raw())?;
+            DynamicImage::ImageRgba16(ImageBuffer::from_raw(w, h, out).expect(same_size))
+        } else {
+            let out = convert16(Layout::Rgb, img.to_rgb16().as_raw())?;
+            DynamicImage::ImageRgb16(ImageBuffer::from_raw(w, h, out).expect(same_size))
+        });
+    }
+    if img.color().has_alpha() {
+        let rgba = img.to_rgba8();
+        let out = convert(Layout::Rgba, rgba.as_raw())?;
+        Ok(DynamicImage::ImageRgba8(
+            image::RgbaImage::from_raw(w, h, out).expect(same_size),
+        ))
+    } else {
+        let rgb = img.to_rgb8();
+        let out = convert(Layout::Rgb, rgb.as_raw())?;
+        Ok(DynamicImage::ImageRgb8(
+            image::RgbImage::from_raw(w, h, out).expect(same_size),
+        ))
+    }
+}
+
+/// Tag encoded JPEG or PNG `data` with `icc`; other formats are returned unchanged.
+pub(crate) fn embed(data: Vec<u8>, icc: &[u8]) -> Result<Vec<u8>> {
+    if data.starts_with(&[0xFF, 0xD8]) {
+        Ok(embed_jpeg(&data, icc))
+    } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        embed_png(&data, icc)
+    } else {
+        Ok(data)
+    }
+}
+
+/// Insert APP2 `ICC_PROFILE` segments after SOI and any APP0 (JFIF) segment.
+fn embed_jpeg(data: &[u8], icc: &[u8]) -> Vec<u8> {
+    let mut insert_at = 2;
+    if data.get(2..4) == Some(&[0xFF, 0xE0]) {
+        if let Some(len) = data.get(4..6) {
+            insert_at = 4 + u16::from_be_bytes([len[0], len[1]]) as usize;
+        }
+    }
+    let insert_at = insert_at.min(data.len());
+
+    let chunks: Vec<&[u8]> = icc.chunks(JPEG_ICC_CHUNK).collect();
+    let mut out = Vec::with_capacity(data.len() + icc.len() + chunks.len() * 18);
+    out.extend_from_slice(&data[..insert_at]);
+    for (index, chunk) in chunks.iter().enumerate() {
+        let length = (2 + 12 + 2 + chunk.len()) as u16;
+        out.extend_from_slice(&[0xFF, 0xE2]);
+        out.extend_from_slice(&length.to_be_bytes());
+        out.extend_from_slice(b"ICC_PROFILE\0");
+        out.push(index as u8 + 1);
+        out.push(chunks.len() as u8);
+        out.extend_from_slice(chunk);
+    }
+    out.extend_from_slice(&data[insert_at..]);
+    out
+}
+
+/// Insert an `iCCP` chunk after IHDR, dropping any `sRGB` / `iCCP` chunk already present.
+fn embed_png(data: &[u8], icc: &[u8]) -> Result<Vec<u8>> {
+    let mut compressed = ZlibEncoder::new(Vec::new(), Compression::default());
+    compressed.write_all(icc)?;
+    let mut payload = b"ICC Profile\0\0".to_vec();
+    payload.extend_from_slice(&compressed.finish()?);
+
+    let mut out = data[..8].to_vec();
+    let mut pos = 8;
+    while pos + 12 <= data.len() {
+        let len = u32::from_be_bytes(data[pos..pos + 4].try_into()?) as usize;
+        let end = pos + 12 + len;
+        let chunk = data
+            .get(pos..end)
+            .ok_or_else(|| anyhow!("Truncated PNG chunk"))?;
+        let kind = &chunk[4..8];
+        if kind != b"sRGB" && kind != b"iCCP" {
+            out.extend_from_slice(chunk);
+        }
+        if kind == b"IHDR" {
+            write_png_chunk(&mut out, b"iCCP", &payload);
+        }
+        pos = end;
+    }
+    Ok(out)
+}
+
+fn write_png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], payload: &[u8]) {
+    let mut crc = Crc::new();
+    crc.update(kind);
+    crc.update(payload);
+    out.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    out.extend_from_slice(kind);
+    out.extend_from_slice(payload);
+    out.extend_from_slice(&crc.sum().to_be_bytes());
+}
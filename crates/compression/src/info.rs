@@ -0,0 +1,72 @@
+//! Header-level facts about an input image, for listings such as `panda-pixel info`.
+
+use super::{decode_dynamic_image, depth, icc, is_jxl_bytes, multipage};
+use anyhow::Result;
+use image::{ImageDecoder, ImageReader};
+use std::io::Cursor;
+
+/// What an input image holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageInfo {
+    /// MIME type of the detected format, e.g. `image/png`.
+    pub mime_type: &'static str,
+    pub width: u32,
+    pub height: u32,
+    /// Bits per channel (8, 16, or 32 for float HDR/EXR).
+    pub bit_depth: u8,
+    pub has_alpha: bool,
+    /// Pages of a multi-page TIFF; 1 for everything else.
+    pub pages: u32,
+    /// Whether an embedded ICC profile describes a colour space other than sRGB.
+    pub non_srgb_profile: bool,
+}
+
+/// Describe `input` from its header where the `image` crate can read one, otherwise by
+/// decoding it (JPEG XL, and the WebP and TGA fallbacks of the compression pipeline).
+pub fn image_info(input: &[u8]) -> Result<ImageInfo> {
+    let pages = if multipage::is_tiff_bytes(input) {
+        multipage::page_count(input)?
+    } else {
+        1
+    };
+    let non_srgb_profile = icc::non_srgb_profile(input).is_some();
+
+    let header = ImageReader::new(Cursor::new(input))
+        .with_guessed_format()
+        .ok()
+        .and_then(|reader| {
+            let format = reader.format()?;
+            Some((format, reader.into_decoder().ok()?))
+        });
+    if let Some((format, decoder)) = header {
+        let (width, height) = decoder.dimensions();
+        let color = decoder.color_type();
+        return Ok(ImageInfo {
+            mime_type: format.to_mime_type(),
+            width,
+            height,
+            bit_depth: (color.bits_per_pixel() / color.channel_count() as u16) as u8,
+            has_alpha: color.has_alpha(),
+            pages,
+            non_srgb_profile,
+        });
+    }
+
+    let img = decode_dynamic_image(input)?;
+    let mime_type = if is_jxl_bytes(input) {
+        "image/jxl"
+    } else if input.get(8..12) == Some(b"WEBP") {
+        "image/webp"
+    } else {
+        "image/x-tga"
+    };
+    Ok(ImageInfo {
+        mime_type,
+        width: img.width(),
+        height: img.height(),
+        bit_depth: if depth::is_high_depth(&img) { 16 } else { 8 },
+        has_alpha: img.color().has_alpha(),
+        pages,
+        non_srgb_profile,
+    })
+}
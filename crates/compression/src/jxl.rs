@@ -0,0 +1,71 @@
+//! JPEG XL encode/decode via libjxl (`jxl` feature).
+
+use anyhow::{anyhow, Result};
+use image::DynamicImage;
+use jpegxl_rs::decode::Data;
+use jpegxl_rs::encode::{EncoderFrame, EncoderResult, EncoderSpeed};
+use jpegxl_rs::image::ToDynamic;
+use jpegxl_rs::{decoder_builder, encoder_builder};
+
+/// Decode a JPEG XL codestream or container to pixels.
+pub(crate) fn decode(input: &[u8]) -> Result<DynamicImage> {
+    let decoder = decoder_builder()
+        .build()
+        .map_err(|e| anyhow!("Failed to create JPEG XL decoder: {e}"))?;
+    decoder
+        .decode_to_image(input)
+        .map_err(|e| anyhow!("Failed to decode JPEG XL image: {e}"))?
+        .ok_or_else(|| anyhow!("Unsupported JPEG XL pixel format"))
+}
+
+/// Rebuild the original JPEG bytes when the file was produced by lossless JPEG recompression.
+/// Returns `None` for JPEG XL files without reconstruction data.
+pub(crate) fn reconstruct_jpeg(input: &[u8]) -> Result<Option<Vec<u8>>> {
+    let decoder = decoder_builder()
+        .build()
+        .map_err(|e| anyhow!("Failed to create JPEG XL decoder: {e}"))?;
+    let (_, data) = decoder
+        .reconstruct(input)
+        .map_err(|e| anyhow!("Failed to decode JPEG XL image: {e}"))?;
+    match data {
+        Data::Jpeg(jpeg) => Ok(Some(jpeg)),
+        Data::Pixels(_) => Ok(None),
+    }
+}
+
+/// Lossless JPEG → JPEG XL recompression. Keeps the DCT coefficients and stores
+/// reconstruction metadata so [`reconstruct_jpeg`] returns the original file byte for byte.
+pub(crate) fn transcode_jpeg(input: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = encoder_builder()
+        .use_container(true)
+        .speed(EncoderSpeed::Squirrel)
+        .build()
+        .map_err(|e| anyhow!("Failed to create JPEG XL encoder: {e}"))?;
+    let result = encoder
+        .encode_jpeg(input)
+        .map_err(|e| anyhow!("JPEG XL lossless JPEG recompression failed: {e}"))?;
+    Ok(result.data)
+}
+
+/// Lossy pixel encode; `quality` uses the JPEG-style 0–100 scale.
+pub(crate) fn encode(img: &DynamicImage, quality: f32) -> Result<Vec<u8>> {
+    let has_alpha = img.color().has_alpha();
+    let mut encoder = encoder_builder()
+        .has_alpha(has_alpha)
+        .speed(EncoderSpeed::Squirrel)
+        .jpeg_quality(quality)
+        .build()
+        .map_err(|e| anyhow!("Failed to create JPEG XL encoder: {e}"))?;
+
+    let (w, h) = (img.width(), img.height());
+    let result: EncoderResult<u8> = if has_alpha {
+        let rgba = img.to_rgba8();
+        encoder.encode_frame(&EncoderFrame::new(rgba.as_raw()).num_channels(4), w, h)
+    } else {
+        let rgb = img.to_rgb8();
+        encoder.encode(rgb.as_raw(), w, h)
+    }
+    .map_err(|e| anyhow!("JPEG XL encode failed: {e}"))?;
+
+    Ok(result.data)
+}
@@ -1,6 +1,6 @@
 use anyhow::{anyhow, Result};
-use domain::CompressionOptions;
-use image::{self, DynamicImage, ImageFormat};
+use domain::{CompressionOptions, LossyFormat, TiffCompression, TrimRect};
+use image::{self, ColorType, DynamicImage, ImageFormat};
 use imagequant::{Attributes, Image as LiqImage};
 use mozjpeg::{ColorSpace, Compress, ScanMode};
 use oxipng::{optimize_from_memory, Options as OxipngOptions};
@@ -9,6 +9,68 @@ use rgb::FromSlice;
 use std::io::Cursor;
 use webp::Encoder as WebpEncoder;
 
+mod adjust;
+mod batch;
+mod cancel;
+mod depth;
+mod formats;
+mod icc;
+mod info;
+mod multipage;
+mod pdf;
+mod tonemap;
+mod transform;
+mod watermark;
+
+pub use batch::{compress_batch, effective_concurrency, run_parallel};
+pub use cancel::{is_cancelled, CancelGuard, CancelToken, Cancelled};
+pub use formats::{supported_input_extensions, supported_input_formats, InputFormat};
+pub use info::{image_info, ImageInfo};
+pub use pdf::images_to_pdf;
+
+#[cfg(feature = "jxl")]
+mod jxl;
+
+#[cfg(not(feature = "jxl"))]
+mod jxl {
+    //! Stand-ins used when the crate is built without the `jxl` feature.
+
+    use anyhow::{anyhow, Result};
+    use image::DynamicImage;
+
+    fn disabled() -> anyhow::Error {
+        anyhow!("Unsupported image format: JPEG XL support is not enabled in this build (`jxl` feature)")
+    }
+
+    pub(crate) fn decode(_input: &[u8]) -> Result<DynamicImage> {
+        Err(disabled())
+    }
+
+    pub(crate) fn reconstruct_jpeg(_input: &[u8]) -> Result<Option<Vec<u8>>> {
+        Ok(None)
+    }
+
+    pub(crate) fn transcode_jpeg(_input: &[u8]) -> Result<Vec<u8>> {
+        Err(disabled())
+    }
+
+    pub(crate) fn encode(_img: &DynamicImage, _quality: f32) -> Result<Vec<u8>> {
+        Err(disabled())
+    }
+}
+
+/// JPEG XL bare codestream (`FF 0A`) or ISOBMFF container signature.
+pub fn is_jxl_bytes(input: &[u8]) -> bool {
+    const CONTAINER: [u8; 12] = [
+        0, 0, 0, 0x0C, b'J', b'X', b'L', b' ', 0x0D, 0x0A, 0x87, 0x0A,
+    ];
+    input.starts_with(&[0xFF, 0x0A]) || input.starts_with(&CONTAINER)
+}
+
+fn is_jpeg_bytes(input: &[u8]) -> bool {
+    input.starts_with(&[0xFF, 0xD8, 0xFF])
+}
+
 fn decode_webp_dynamic(input: &[u8]) -> Result<DynamicImage> {
     let decoded = webp::Decoder::new(input)
         .decode()
@@ -28,12 +90,38 @@ fn decode_webp_dynamic(input: &[u8]) -> Result<DynamicImage> {
     Ok(DynamicImage::ImageRgba8(rgba_img))
 }
 
+/// TGA has no magic bytes, so it is only tried once signature-based detection has failed.
+#[cfg(feature = "tga")]
+fn decode_tga_dynamic(input: &[u8]) -> Result<DynamicImage> {
+    Ok(image::load_from_memory_with_format(
+        input,
+        ImageFormat::Tga,
+    )?)
+}
+
+#[cfg(not(feature = "tga"))]
+fn decode_tga_dynamic(_input: &[u8]) -> Result<DynamicImage> {
+    Err(anyhow!(
+        "TGA support is not enabled in this build (`tga` feature)"
+    ))
+}
+
 fn decode_dynamic_image(input: &[u8]) -> Result<DynamicImage> {
-    match image::load_from_memory(input) {
-        Ok(img) => Ok(img),
-        Err(primary_err) => decode_webp_dynamic(input)
-            .map_err(|_| anyhow!("Failed to decode image bytes: {primary_err}")),
-    }
+    let img = if is_jxl_bytes(input) {
+        jxl::decode(input)?
+    } else {
+        match image::load_from_memory(input) {
+            Ok(img) => img,
+            Err(primary_err) => decode_webp_dynamic(input)
+                .or_else(|_| decode_tga_dynamic(input))
+                .map_err(|_| anyhow!("Failed to decode image bytes: {primary_err}"))?,
+        }
+    };
+    // HDR / EXR decode to linear float; the encoders downstream expect sRGB integers.
+    Ok(match img.color() {
+        ColorType::Rgb32F | ColorType::Rgba32F => tonemap::tone_map(&img),
+        _ => img,
+    })
 }
 
 /// PNG: quantize via libimagequant + optional oxipng (lossless)
@@ -113,9 +201,10 @@ pub fn compress_jpeg_bytes(input: &[u8], quality: u8) -> Result<Vec<u8>> {
 
     let mut comp = Compress::new(ColorSpace::JCS_RGB);
     comp.set_size(w, h);
+    // Resets the encoder to its defaults, so it has to come before the quality.
+    comp.set_scan_optimization_mode(ScanMode::AllComponentsTogether);
     comp.set_quality(quality as f32);
     comp.set_progressive_mode();
-    comp.set_scan_optimization_mode(ScanMode::AllComponentsTogether);
 
     // For max compression, enable additional optimization
     if quality <= 60 {
@@ -186,12 +275,20 @@ pub fn to_png_bytes(
     Ok(png_buf)
 }
 
-/// Convert to TIFF
-pub fn to_tiff_bytes(input: &[u8]) -> Result<Vec<u8>> {
-    let img = decode_dynamic_image(input)?;
-    let mut cursor = Cursor::new(Vec::new());
-    img.write_to(&mut cursor, ImageFormat::Tiff)?;
-    Ok(cursor.into_inner())
+/// All pages of a multi-page TIFF, or the single decoded image for any other input.
+fn decode_all_pages(input: &[u8]) -> Result<Vec<DynamicImage>> {
+    if multipage::is_tiff_bytes(input) {
+        if let Ok(pages) = multipage::decode_pages(input) {
+            return Ok(pages);
+        }
+    }
+    Ok(vec![decode_dynamic_image(input)?])
+}
+
+/// Convert to TIFF; every page of a multi-page TIFF input is kept
+pub fn to_tiff_bytes(input: &[u8], compression: TiffCompression) -> Result<Vec<u8>> {
+    let pages = decode_all_pages(input)?;
+    multipage::encode_pages(&pages, compression)
 }
 
 /// Convert to BMP
@@ -228,10 +325,13 @@ pub fn to_ico_bytes(input: &[u8]) -> Result<Vec<u8>> {
 /// AVIF via ravif crate (lossy)
 pub fn to_avif_bytes(input: &[u8], quality: f32) -> Result<Vec<u8>> {
     let img = decode_dynamic_image(input)?;
-    let rgba = img.to_rgba8();
-    let (w, h) = (img.width(), img.height());
     let speed = 6u8; // 0 best / slowest, 10 fastest
     let enc = AvifEncoder::new().with_quality(quality).with_speed(speed);
+    if depth::is_high_depth(&img) {
+        return depth::encode_avif_10bit(&img, &enc);
+    }
+    let rgba = img.to_rgba8();
+    let (w, h) = (img.width(), img.height());
 
     // Convert to proper RGBA format
     let rgba_pixels: Vec<rgb::RGBA<u8>> = rgba
@@ -244,23 +344,184 @@ pub fn to_avif_bytes(input: &[u8], quality: f32) -> Result<Vec<u8>> {
     Ok(avif.avif_file)
 }
 
+/// JPEG XL via libjxl (`jxl` feature). With `lossless_jpeg`, JPEG input is recompressed
+/// without re-encoding pixels (~20% smaller, and the original file can be rebuilt byte for byte).
+pub fn to_jxl_bytes(input: &[u8], quality: f32, lossless_jpeg: bool) -> Result<Vec<u8>> {
+    if lossless_jpeg && is_jpeg_bytes(input) {
+        return jxl::transcode_jpeg(input);
+    }
+    let img = decode_dynamic_image(input)?;
+    jxl::encode(&img, quality)
+}
+
+/// Encoded output plus what the pipeline did to the pixels on the way.
+#[derive(Debug, Clone)]
+pub struct CompressedImage {
+    pub data: Vec<u8>,
+    pub mime_type: String,
+    /// Content rectangle kept by an auto-trim transform, if it trimmed anything.
+    pub trimmed: Option<TrimRect>,
+    /// Bits per channel stored in `data` (8, 10 for AVIF, or 16).
+    pub bit_depth: u8,
+    /// Output size in pixels, when it could be determined.
+    pub dimensions: Option<(u32, u32)>,
+}
+
+impl CompressedImage {
+    /// Suggested filename for this output of the input `source_name`, rendered from
+    /// `opts.filename_template` ([`naming::render_filename`]). `index` is the input's 1-based
+    /// position in its batch and `page` the page of a split input.
+    pub fn suggested_filename(
+        &self,
+        source_name: &str,
+        opts: &CompressionOptions,
+        index: usize,
+        page: Option<u32>,
+    ) -> String {
+        let format = match self.mime_type.as_str() {
+            "image/x-icon" => "ico",
+            mime => mime.rsplit('/').next().unwrap_or_default(),
+        };
+        let quality = match format {
+            "jpeg" => Some(opts.encoder_quality(LossyFormat::Jpeg)),
+            "webp" => Some(opts.encoder_quality(LossyFormat::Webp)),
+            "avif" => Some(opts.encoder_quality(LossyFormat::Avif)),
+            "jxl" => Some(opts.encoder_quality(LossyFormat::Jxl)),
+            _ => {
+                let (min_q, max_q) = domain::parse_quality_range(&opts.png_quality_range());
+                Some(((min_q as u16 + max_q as u16) / 2) as u8)
+            }
+        };
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .ok();
+        let values = naming::TemplateValues {
+            stem: naming::file_stem(source_name),
+            ext: naming::extension_for_mime(&self.mime_type).unwrap_or("bin"),
+            page,
+            width: self.dimensions.map(|(width, _)| width),
+            height: self.dimensions.map(|(_, height)| height),
+            quality,
+            format,
+            index: Some(index),
+            timestamp,
+            data: &self.data,
+        };
+        let template = opts
+            .filename_template
+            .as_deref()
+            .unwrap_or(naming::DEFAULT_TEMPLATE);
+        naming::render_filename(template, &values)
+    }
+}
+
+/// Pixel size of an encoded image, read from its header.
+fn image_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    image::ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()
+}
+
 /// In-process compress dispatcher
 pub fn compress_image_inproc(
     input_bytes: &[u8],
     ext_lower: &str,
     opts: &CompressionOptions,
 ) -> Result<(Vec<u8>, String)> {
+    compress_image_detailed(input_bytes, ext_lower, opts).map(|out| (out.data, out.mime_type))
+}
+
+/// Like [`compress_image_inproc`], also reporting the trimmed rectangle.
+pub fn compress_image_detailed(
+    input_bytes: &[u8],
+    ext_lower: &str,
+    opts: &CompressionOptions,
+) -> Result<CompressedImage> {
+    compress_image_with_cancel(input_bytes, ext_lower, opts, &CancelToken::new())
+}
+
+/// [`compress_image_detailed`] that stops with [`Cancelled`] at the next stage boundary once
+/// `cancel` is cancelled.
+pub fn compress_image_with_cancel(
+    input_bytes: &[u8],
+    ext_lower: &str,
+    opts: &CompressionOptions,
+    cancel: &CancelToken,
+) -> Result<CompressedImage> {
+    cancel.check()?;
+    let output = OutputKind::for_options(ext_lower, opts);
+    let (convert_profile, embed_profile) = match icc::plan(input_bytes, output, opts) {
+        icc::ProfileAction::None => (None, None),
+        icc::ProfileAction::ConvertToSrgb(profile) => (Some(profile), None),
+        icc::ProfileAction::Embed(profile) => (None, Some(profile)),
+    };
+    // Dithered reduction to 8 bits happens once here rather than inside each encoder.
+    let dither =
+        opts.dither && output.max_bit_depth() == 8 && depth::source_is_high_depth(input_bytes);
+
+    // Pixel operations run once up front; the encoders then see the edited image.
+    let (data, mime_type, trimmed, source_dimensions) =
+        if convert_profile.is_some() || dither || has_pixel_ops(opts) {
+            let (prepared, trimmed) = apply_pixel_ops(
+                input_bytes,
+                opts,
+                convert_profile.as_deref(),
+                output,
+                cancel,
+            )?;
+            let opts = CompressionOptions {
+                transforms: Vec::new(),
+                adjustments: Default::default(),
+                watermark: None,
+                ..opts.clone()
+            };
+            cancel.check()?;
+            let (data, mime_type) = encode_image(&prepared, ext_lower, &opts)?;
+            (data, mime_type, trimmed, image_dimensions(&prepared))
+        } else {
+            let (data, mime_type) = encode_image(input_bytes, ext_lower, opts)?;
+            (data, mime_type, None, image_dimensions(input_bytes))
+        };
+    let data = match embed_profile {
+        Some(profile) => icc::embed(data, &profile)?,
+        None => data,
+    };
+    let bit_depth = match output {
+        OutputKind::Avif => 10,
+        _ => depth::bits_per_channel(&data),
+    };
+    // Formats the `image` crate can't read back (AVIF, JPEG XL) keep the size they were fed.
+    let dimensions = image_dimensions(&data).or(source_dimensions);
+    Ok(CompressedImage {
+        data,
+        mime_type,
+        trimmed,
+        bit_depth,
+        dimensions,
+    })
+}
+
+/// Encode to the requested output format (or keep/compress the input format).
+fn encode_image(
+    input_bytes: &[u8],
+    ext_lower: &str,
+    opts: &CompressionOptions,
+) -> Result<(Vec<u8>, String)> {
+    let webp_quality = opts.encoder_quality(LossyFormat::Webp) as f32;
+    let jpeg_quality = opts.encoder_quality(LossyFormat::Jpeg);
+
     // Handle HEIC files first (convert to JPEG like TinyPNG)
     if ext_lower == "heic" || ext_lower == "heif" {
-        let bytes = heic_to_jpeg_bytes(input_bytes, 85)?; // High quality for HEIC conversion
+        let bytes = heic_to_jpeg_bytes(input_bytes, jpeg_quality)?;
         return Ok((bytes, "image/jpeg".to_string()));
     }
-
-    // Parse quality range to determine compression level
-    let (min_q, max_q) = domain::parse_quality_range(&opts.png_quality);
-    let webp_quality = ((min_q + max_q) / 2) as f32;
-    let jpeg_quality = (min_q + max_q) / 2;
-    let avif_quality = ((min_q + max_q) / 2) as f32;
+    // ravif rejects 0
+    let avif_quality = opts.encoder_quality(LossyFormat::Avif).max(1) as f32;
+    let jxl_quality = opts.encoder_quality(LossyFormat::Jxl) as f32;
+    let png_quality = opts.png_quality_range();
 
     // If conversion requested, honor it next
     if opts.to_webp {
@@ -272,15 +533,21 @@ pub fn compress_image_inproc(
         return Ok((bytes, "image/avif".to_string()));
     }
     if opts.to_jpeg {
+        // JPEG XL produced by lossless recompression converts back to the original JPEG.
+        if is_jxl_bytes(input_bytes) {
+            if let Some(bytes) = jxl::reconstruct_jpeg(input_bytes)? {
+                return Ok((bytes, "image/jpeg".to_string()));
+            }
+        }
         let bytes = compress_jpeg_bytes(input_bytes, jpeg_quality)?;
         return Ok((bytes, "image/jpeg".to_string()));
     }
     if opts.to_png {
-        let bytes = to_png_bytes(input_bytes, &opts.png_quality, opts.oxipng, opts.png_lossy)?;
+        let bytes = to_png_bytes(input_bytes, &png_quality, opts.oxipng, opts.png_lossy)?;
         return Ok((bytes, "image/png".to_string()));
     }
     if opts.to_tiff {
-        let bytes = to_tiff_bytes(input_bytes)?;
+        let bytes = to_tiff_bytes(input_bytes, opts.tiff_compression)?;
         return Ok((bytes, "image/tiff".to_string()));
     }
     if opts.to_bmp {
@@ -291,11 +558,15 @@ pub fn compress_image_inproc(
         let bytes = to_ico_bytes(input_bytes)?;
         return Ok((bytes, "image/x-icon".to_string()));
     }
+    if opts.to_jxl {
+        let bytes = to_jxl_bytes(input_bytes, jxl_quality, opts.jxl_lossless_jpeg)?;
+        return Ok((bytes, "image/jxl".to_string()));
+    }
 
     match ext_lower {
         "png" => {
             if opts.png_lossy {
-                let bytes = compress_png_bytes(input_bytes, &opts.png_quality, opts.oxipng)?;
+                let bytes = compress_png_bytes(input_bytes, &png_quality, opts.oxipng)?;
                 Ok((bytes, "image/png".into()))
             } else {
                 // lossless re-encode
@@ -307,17 +578,188 @@ pub fn compress_image_inproc(
             }
         }
         "jpg" | "jpeg" => {
-            let bytes = compress_jpeg_bytes(input_bytes, 75)?;
+            let bytes = compress_jpeg_bytes(input_bytes, jpeg_quality)?;
             Ok((bytes, "image/jpeg".into()))
         }
+        "jxl" => {
+            let bytes = to_jxl_bytes(input_bytes, jxl_quality, false)?;
+            Ok((bytes, "image/jxl".into()))
+        }
+        "tif" | "tiff" => {
+            let bytes = to_tiff_bytes(input_bytes, opts.tiff_compression)?;
+            Ok((bytes, "image/tiff".into()))
+        }
         // Other formats → PNG by default
         _ => {
-            let bytes = compress_png_bytes(input_bytes, &opts.png_quality, opts.oxipng)?;
+            let bytes = compress_png_bytes(input_bytes, &png_quality, opts.oxipng)?;
             Ok((bytes, "image/png".into()))
         }
     }
 }
 
+fn has_pixel_ops(opts: &CompressionOptions) -> bool {
+    !opts.transforms.is_empty() || !opts.adjustments.is_identity() || opts.watermark.is_some()
+}
+
+/// Decode (converting from `source_profile` to sRGB when given), apply the requested pixel
+/// operations and hand back a lossless intermediate: PNG for single images, uncompressed TIFF
+/// when a multi-page TIFF keeps all its pages. 16-bit pages are reduced to 8 bits only when
+/// `output` cannot store them. The trim rectangle reported is the first page's.
+fn apply_pixel_ops(
+    input: &[u8],
+    opts: &CompressionOptions,
+    source_profile: Option<&[u8]>,
+    output: OutputKind,
+    cancel: &CancelToken,
+) -> Result<(Vec<u8>, Option<TrimRect>)> {
+    let mut trimmed = None;
+    let mut pages = Vec::new();
+    let eight_bit_stages = !opts.adjustments.is_identity() || opts.watermark.is_some();
+    for (index, page) in decode_all_pages(input)?.into_iter().enumerate() {
+        cancel.check()?;
+        let page = match source_profile {
+            Some(profile) => icc::convert_to_srgb(page, profile)?,
+            None => page,
+        };
+        let (mut page, page_trim) = transform::apply_transforms(page, &opts.transforms)?;
+        cancel.check()?;
+        // Corrections and watermarks work on 8-bit pixels, so reduce (dithered if asked)
+        // before them instead of letting them truncate.
+        if output.max_bit_depth() == 8 || eight_bit_stages {
+            page = depth::reduce_to_8bit(page, opts.dither);
+        }
+        let mut page = adjust::apply_adjustments(page, &opts.adjustments);
+        if let Some(mark) = &opts.watermark {
+            cancel.check()?;
+            page = watermark::apply_watermark(page, mark)?;
+        }
+        if index == 0 {
+            trimmed = page_trim;
+        }
+        pages.push(page);
+    }
+
+    if pages.len() > 1 {
+        let bytes = multipage::encode_pages(&pages, TiffCompression::None)?;
+        return Ok((bytes, trimmed));
+    }
+    let mut cursor = Cursor::new(Vec::new());
+    pages[0].write_to(&mut cursor, ImageFormat::Png)?;
+    Ok((cursor.into_inner(), trimmed))
+}
+
+/// Like [`compress_image_inproc`], but with `opts.split_pages` a multi-page TIFF converted to a
+/// single-page format yields one output per page (in page order). Otherwise returns one output.
+pub fn compress_image_pages(
+    input_bytes: &[u8],
+    ext_lower: &str,
+    opts: &CompressionOptions,
+) -> Result<Vec<CompressedImage>> {
+    compress_image_pages_with_cancel(input_bytes, ext_lower, opts, &CancelToken::new())
+}
+
+/// [`compress_image_pages`] that checks `cancel` between pages and pipeline stages.
+pub fn compress_image_pages_with_cancel(
+    input_bytes: &[u8],
+    ext_lower: &str,
+    opts: &CompressionOptions,
+    cancel: &CancelToken,
+) -> Result<Vec<CompressedImage>> {
+    cancel.check()?;
+    if opts.split_pages
+        && OutputKind::for_options(ext_lower, opts) != OutputKind::Tiff
+        && multipage::is_tiff_bytes(input_bytes)
+    {
+        let pages = multipage::decode_pages(input_bytes)?;
+        if pages.len() > 1 {
+            // Each page inherits the document's profile so it gets the same colour handling.
+            let profile = icc::non_srgb_profile(input_bytes);
+            return pages
+                .iter()
+                .map(|page| {
+                    let mut cursor = Cursor::new(Vec::new());
+                    page.write_to(&mut cursor, ImageFormat::Png)?;
+                    let page_bytes = match &profile {
+                        Some(profile) => icc::embed(cursor.into_inner(), profile)?,
+                        None => cursor.into_inner(),
+                    };
+                    compress_image_with_cancel(&page_bytes, ext_lower, opts, cancel)
+                })
+                .collect();
+        }
+    }
+    Ok(vec![compress_image_with_cancel(
+        input_bytes,
+        ext_lower,
+        opts,
+        cancel,
+    )?])
+}
+
+/// Container [`encode_image`] writes for a given extension and options.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputKind {
+    Webp,
+    Avif,
+    Jpeg,
+    /// `quantized`: palette PNG from libimagequant rather than a lossless re-encode
+    Png {
+        quantized: bool,
+    },
+    Tiff,
+    Bmp,
+    Ico,
+    Jxl,
+}
+
+impl OutputKind {
+    /// Mirrors the precedence in [`encode_image`]: the first requested conversion wins,
+    /// otherwise the input format decides.
+    fn for_options(ext_lower: &str, opts: &CompressionOptions) -> Self {
+        if matches!(ext_lower, "heic" | "heif") {
+            return Self::Jpeg;
+        }
+        if opts.to_webp {
+            Self::Webp
+        } else if opts.to_avif {
+            Self::Avif
+        } else if opts.to_jpeg {
+            Self::Jpeg
+        } else if opts.to_png {
+            Self::Png {
+                quantized: opts.png_lossy,
+            }
+        } else if opts.to_tiff {
+            Self::Tiff
+        } else if opts.to_bmp {
+            Self::Bmp
+        } else if opts.to_ico {
+            Self::Ico
+        } else if opts.to_jxl {
+            Self::Jxl
+        } else {
+            match ext_lower {
+                "png" => Self::Png {
+                    quantized: opts.png_lossy,
+                },
+                "jpg" | "jpeg" => Self::Jpeg,
+                "jxl" => Self::Jxl,
+                "tif" | "tiff" => Self::Tiff,
+                _ => Self::Png { quantized: true },
+            }
+        }
+    }
+
+    /// Most bits per channel this container is written with.
+    fn max_bit_depth(self) -> u8 {
+        match self {
+            Self::Png { quantized: false } | Self::Tiff => 16,
+            Self::Avif => 10,
+            _ => 8,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -381,6 +823,37 @@ mod tests {
         assert!(!compressed.is_empty());
     }
 
+    #[test]
+    fn test_jpeg_recompression_follows_level_and_quality() {
+        let img = image::ImageBuffer::from_fn(128, 128, |x, y| {
+            image::Rgb([(x * 2) as u8, (y * 2) as u8, ((x * y) % 256) as u8])
+        });
+        let mut jpeg_data = Vec::new();
+        DynamicImage::ImageRgb8(img)
+            .write_to(&mut Cursor::new(&mut jpeg_data), ImageFormat::Jpeg)
+            .unwrap();
+        let size_as = |ext: &str, opts: CompressionOptions| {
+            compress_image_inproc(&jpeg_data, ext, &opts)
+                .unwrap()
+                .0
+                .len()
+        };
+        let size = |opts: CompressionOptions| size_as("jpeg", opts);
+        let level = |level: &str| CompressionOptions {
+            png_quality: domain::compression_level_to_range(level),
+            ..Default::default()
+        };
+
+        assert!(size(level("max")) < size(level("low")));
+        let explicit = CompressionOptions {
+            quality: Some(95),
+            ..level("max")
+        };
+        assert!(size(explicit) > size(level("low")));
+        // HEIC input becomes JPEG at the same quality (the decoder sniffs the bytes).
+        assert!(size_as("heic", level("max")) < size_as("heic", level("low")));
+    }
+
     #[test]
     fn test_webp_input_to_png_conversion_lossless() {
         let webp_data = create_test_webp();
@@ -413,6 +886,992 @@ mod tests {
         assert!(!converted.is_empty());
     }
 
+    #[test]
+    fn test_jxl_signature_detection() {
+        assert!(is_jxl_bytes(&[0xFF, 0x0A, 0x00]));
+        assert!(is_jxl_bytes(&[
+            0, 0, 0, 0x0C, b'J', b'X', b'L', b' ', 0x0D, 0x0A, 0x87, 0x0A, 0, 0
+        ]));
+        assert!(!is_jxl_bytes(&create_test_jpeg()));
+        assert!(!is_jxl_bytes(&create_test_png()));
+    }
+
+    #[cfg(not(feature = "jxl"))]
+    #[test]
+    fn test_jxl_output_without_feature_returns_error() {
+        let opts = CompressionOptions {
+            to_jxl: true,
+            ..Default::default()
+        };
+        let err = compress_image_inproc(&create_test_png(), "png", &opts).unwrap_err();
+        assert!(err.to_string().contains("jxl"));
+    }
+
+    #[cfg(feature = "jxl")]
+    #[test]
+    fn test_png_to_jxl_conversion() {
+        let opts = CompressionOptions {
+            to_jxl: true,
+            ..Default::default()
+        };
+        let (converted, mime_type) = compress_image_inproc(&create_test_png(), "png", &opts)
+            .expect("png to jxl conversion should succeed");
+        assert_eq!(mime_type, "image/jxl");
+        assert!(is_jxl_bytes(&converted));
+    }
+
+    #[cfg(feature = "jxl")]
+    #[test]
+    fn test_jpeg_to_jxl_lossless_roundtrip_is_byte_exact() {
+        let jpeg_data = create_test_jpeg();
+        let to_jxl = CompressionOptions {
+            to_jxl: true,
+            jxl_lossless_jpeg: true,
+            ..Default::default()
+        };
+        let (jxl_data, mime_type) = compress_image_inproc(&jpeg_data, "jpg", &to_jxl)
+            .expect("lossless jpeg recompression should succeed");
+        assert_eq!(mime_type, "image/jxl");
+
+        let to_jpeg = CompressionOptions {
+            to_jpeg: true,
+            ..Default::default()
+        };
+        let (restored, mime_type) = compress_image_inproc(&jxl_data, "jxl", &to_jpeg)
+            .expect("jxl to jpeg reconstruction should succeed");
+        assert_eq!(mime_type, "image/jpeg");
+        assert_eq!(restored, jpeg_data);
+    }
+
+    #[test]
+    fn test_supported_input_formats_follow_features() {
+        let exts = supported_input_extensions();
+        for ext in ["png", "jpg", "jpeg", "webp", "bmp", "tiff", "tif", "ico"] {
+            assert!(exts.contains(&ext), "missing {ext}");
+        }
+        assert_eq!(exts.contains(&"jxl"), cfg!(feature = "jxl"));
+        assert_eq!(exts.contains(&"qoi"), cfg!(feature = "qoi"));
+        assert_eq!(exts.contains(&"tga"), cfg!(feature = "tga"));
+        assert_eq!(exts.contains(&"exr"), cfg!(feature = "exr"));
+    }
+
+    #[cfg(feature = "qoi")]
+    #[test]
+    fn test_qoi_input_to_png_conversion() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            16,
+            16,
+            image::Rgba([10, 200, 30, 255]),
+        ));
+        let mut cursor = Cursor::new(Vec::new());
+        img.write_to(&mut cursor, ImageFormat::Qoi).unwrap();
+        let opts = CompressionOptions {
+            to_png: true,
+            png_lossy: false,
+            ..Default::default()
+        };
+        let (png_data, mime_type) =
+            compress_image_inproc(&cursor.into_inner(), "qoi", &opts).unwrap();
+        assert_eq!(mime_type, "image/png");
+        let decoded = image::load_from_memory(&png_data).unwrap().to_rgba8();
+        assert_eq!(decoded.get_pixel(3, 3).0, [10, 200, 30, 255]);
+    }
+
+    #[cfg(feature = "tga")]
+    #[test]
+    fn test_tga_input_without_signature_decodes() {
+        let img =
+            DynamicImage::ImageRgb8(image::RgbImage::from_pixel(8, 8, image::Rgb([40, 80, 120])));
+        let mut cursor = Cursor::new(Vec::new());
+        img.write_to(&mut cursor, ImageFormat::Tga).unwrap();
+        let opts = CompressionOptions {
+            to_png: true,
+            png_lossy: false,
+            ..Default::default()
+        };
+        let (png_data, _) = compress_image_inproc(&cursor.into_inner(), "tga", &opts).unwrap();
+        let decoded = image::load_from_memory(&png_data).unwrap().to_rgb8();
+        assert_eq!(decoded.get_pixel(0, 0).0, [40, 80, 120]);
+    }
+
+    #[cfg(feature = "hdr")]
+    #[test]
+    fn test_hdr_input_is_tone_mapped_not_clipped() {
+        // Dim, mid and very bright pixels; a plain clamp would collapse the last two to white.
+        let values = [0.05f32, 1.0, 50.0];
+        let hdr = image::Rgb32FImage::from_fn(3, 1, |x, _| {
+            let v = values[x as usize];
+            image::Rgb([v, v, v])
+        });
+        let mut cursor = Cursor::new(Vec::new());
+        DynamicImage::ImageRgb32F(hdr)
+            .write_to(&mut cursor, ImageFormat::Hdr)
+            .unwrap();
+        let opts = CompressionOptions {
+            to_png: true,
+            png_lossy: false,
+            ..Default::default()
+        };
+        let (png_data, _) = compress_image_inproc(&cursor.into_inner(), "hdr", &opts).unwrap();
+        let decoded = image::load_from_memory(&png_data).unwrap().to_rgb8();
+        let (dim, mid, bright) = (
+            decoded.get_pixel(0, 0)[0],
+            decoded.get_pixel(1, 0)[0],
+            decoded.get_pixel(2, 0)[0],
+        );
+        assert!(dim < mid && mid < bright, "{dim} {mid} {bright}");
+        assert!(mid < 255);
+    }
+
+    fn create_test_multipage_tiff(colors: &[[u8; 3]]) -> Vec<u8> {
+        let pages: Vec<DynamicImage> = colors
+            .iter()
+            .map(|c| DynamicImage::ImageRgb8(image::RgbImage::from_pixel(64, 64, image::Rgb(*c))))
+            .collect();
+        multipage::encode_pages(&pages, TiffCompression::None).unwrap()
+    }
+
+    #[test]
+    fn test_tiff_to_tiff_keeps_pages_and_compresses() {
+        let colors = [[255, 0, 0], [0, 255, 0], [0, 0, 255]];
+        let input = create_test_multipage_tiff(&colors);
+
+        for compression in [
+            TiffCompression::Lzw,
+            TiffCompression::Deflate,
+            TiffCompression::PackBits,
+        ] {
+            let opts = CompressionOptions {
+                to_tiff: true,
+                tiff_compression: compression,
+                ..Default::default()
+            };
+            let (tiff_data, mime_type) = compress_image_inproc(&input, "tiff", &opts).unwrap();
+            assert_eq!(mime_type, "image/tiff");
+            // PackBits only collapses byte runs, which interleaved RGB doesn't have.
+            if compression != TiffCompression::PackBits {
+                assert!(
+                    tiff_data.len() < input.len() / 4,
+                    "{compression:?} produced {} bytes",
+                    tiff_data.len()
+                );
+            }
+
+            let pages = multipage::decode_pages(&tiff_data).unwrap();
+            assert_eq!(pages.len(), colors.len());
+            for (page, color) in pages.iter().zip(colors) {
+                assert_eq!(page.to_rgb8().get_pixel(10, 10).0, color);
+            }
+        }
+    }
+
+    #[test]
+    fn test_split_pages_yields_one_output_per_page() {
+        let colors = [[10, 20, 30], [200, 100, 50]];
+        let input = create_test_multipage_tiff(&colors);
+        let opts = CompressionOptions {
+            to_png: true,
+            png_lossy: false,
+            split_pages: true,
+            ..Default::default()
+        };
+
+        let outputs = compress_image_pages(&input, "tiff", &opts).unwrap();
+        assert_eq!(outputs.len(), colors.len());
+        for (output, color) in outputs.iter().zip(colors) {
+            assert_eq!(output.mime_type, "image/png");
+            let decoded = image::load_from_memory(&output.data).unwrap().to_rgb8();
+            assert_eq!(decoded.get_pixel(0, 0).0, color);
+        }
+
+        // Without the flag, only one output is produced.
+        let single = CompressionOptions {
+            split_pages: false,
+            ..opts
+        };
+        assert_eq!(
+            compress_image_pages(&input, "tiff", &single).unwrap().len(),
+            1
+        );
+    }
+
+    /// Every xref entry must point at the start of its object.
+    fn assert_pdf_xref_consistent(pdf: &[u8]) {
+        let text = String::from_utf8_lossy(pdf);
+        let startxref = text.rfind("startxref\n").unwrap() + "startxref\n".len();
+        let xref_offset: usize = text[startxref..].lines().next().unwrap().parse().unwrap();
+        assert!(pdf[xref_offset..].starts_with(b"xref\n"));
+
+        let entries: Vec<&str> = text[xref_offset..].lines().skip(3).collect();
+        for (i, entry) in entries.iter().take_while(|l| l.ends_with(" n")).enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            let header = format!("{} 0 obj", i + 1);
+            assert!(
+                pdf[offset..].starts_with(header.as_bytes()),
+                "object {}",
+                i + 1
+            );
+        }
+    }
+
+    #[test]
+    fn test_images_to_pdf_embeds_jpeg_as_dct_and_png_as_flate() {
+        let jpeg = create_test_jpeg();
+        let rgba = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            40,
+            20,
+            image::Rgba([0, 128, 255, 100]),
+        ));
+        let mut png = Cursor::new(Vec::new());
+        rgba.write_to(&mut png, ImageFormat::Png).unwrap();
+
+        let opts = domain::PdfOptions {
+            page_size: domain::PdfPageSize::A4,
+            margin_mm: 10.0,
+            ..Default::default()
+        };
+        let pdf = images_to_pdf(&[jpeg.clone(), png.into_inner()], &opts).unwrap();
+
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(pdf.starts_with(b"%PDF-1.4"));
+        assert!(text.trim_end().ends_with("%%EOF"));
+        assert!(text.contains("/Count 2"));
+        assert!(text.contains("/DCTDecode"));
+        assert!(text.contains("/FlateDecode"));
+        assert!(text.contains("/SMask"));
+        // A4 portrait for the square JPEG, landscape for the wide PNG.
+        assert!(text.contains("/MediaBox [0 0 595.28 841.89]"));
+        assert!(text.contains("/MediaBox [0 0 841.89 595.28]"));
+        // JPEG input is embedded untouched.
+        assert!(pdf.windows(jpeg.len()).any(|w| w == jpeg.as_slice()));
+        assert_pdf_xref_consistent(&pdf);
+    }
+
+    #[test]
+    fn test_images_to_pdf_fit_page_and_flate_only() {
+        let opts = domain::PdfOptions {
+            image_encoding: domain::PdfImageEncoding::Flate,
+            ..Default::default()
+        };
+        let pdf = images_to_pdf(&[create_test_jpeg()], &opts).unwrap();
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.contains("/MediaBox [0 0 100.00 100.00]"));
+        assert!(!text.contains("/DCTDecode"));
+        assert_pdf_xref_consistent(&pdf);
+
+        assert!(images_to_pdf(&[], &opts).is_err());
+
+        // Margins are capped at 100 mm (283.46 pt) on each side, and a non-finite one is none.
+        for (margin_mm, media_box) in [
+            (1e30, "/MediaBox [0 0 666.93 666.93]"),
+            (f32::INFINITY, "/MediaBox [0 0 100.00 100.00]"),
+            (f32::NAN, "/MediaBox [0 0 100.00 100.00]"),
+        ] {
+            let opts = domain::PdfOptions {
+                margin_mm,
+                ..opts.clone()
+            };
+            let pdf = images_to_pdf(&[create_test_jpeg()], &opts).unwrap();
+            assert!(
+                String::from_utf8_lossy(&pdf).contains(media_box),
+                "{margin_mm}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_transforms_apply_in_order_before_encoding() {
+        // 40x20, left half red and right half blue.
+        let img = image::ImageBuffer::from_fn(40, 20, |x, _| {
+            if x < 20 {
+                image::Rgb([255u8, 0, 0])
+            } else {
+                image::Rgb([0, 0, 255])
+            }
+        });
+        let mut input = Vec::new();
+        DynamicImage::ImageRgb8(img)
+            .write_to(&mut Cursor::new(&mut input), ImageFormat::Png)
+            .unwrap();
+
+        let opts = CompressionOptions {
+            to_png: true,
+            transforms: domain::parse_transforms("rotate:90,flip:v,crop:0:0:20:30").unwrap(),
+            ..Default::default()
+        };
+        let (bytes, mime) = compress_image_inproc(&input, "png", &opts).unwrap();
+        assert_eq!(mime, "image/png");
+        let out = image::load_from_memory(&bytes).unwrap().to_rgb8();
+        // Rotating clockwise puts the red half on top; the vertical flip moves it to the bottom.
+        assert_eq!(out.dimensions(), (20, 30));
+        assert_eq!(out.get_pixel(10, 5), &image::Rgb([0, 0, 255]));
+        assert_eq!(out.get_pixel(10, 25), &image::Rgb([255, 0, 0]));
+
+        let aspect = CompressionOptions {
+            to_png: true,
+            transforms: domain::parse_transforms("aspect:1:1:east").unwrap(),
+            ..Default::default()
+        };
+        let (bytes, _) = compress_image_inproc(&input, "png", &aspect).unwrap();
+        let out = image::load_from_memory(&bytes).unwrap().to_rgb8();
+        assert_eq!(out.dimensions(), (20, 20));
+        assert_eq!(out.get_pixel(0, 0), &image::Rgb([0, 0, 255]));
+
+        let outside = CompressionOptions {
+            transforms: domain::parse_transforms("crop:100:0:10:10").unwrap(),
+            ..Default::default()
+        };
+        assert!(compress_image_inproc(&input, "png", &outside).is_err());
+    }
+
+    #[test]
+    fn test_arbitrary_rotation_expands_canvas_with_fill() {
+        let opts = CompressionOptions {
+            to_png: true,
+            transforms: vec![domain::Transform::Rotate {
+                degrees: 45.0,
+                fill: Some("#00ff00".to_string()),
+            }],
+            ..Default::default()
+        };
+        let (bytes, _) = compress_image_inproc(&create_test_png(), "png", &opts).unwrap();
+        let out = image::load_from_memory(&bytes).unwrap().to_rgba8();
+        // 100x100 rotated 45° needs a ~141px square canvas.
+        assert_eq!(out.dimensions(), (141, 141));
+        assert_eq!(out.get_pixel(0, 0), &image::Rgba([0, 255, 0, 255]));
+        assert_eq!(out.get_pixel(70, 70), &image::Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_trim_removes_uniform_and_transparent_borders() {
+        // 60x40 white canvas with a near-white (JPEG-ish noise) border pixel and a
+        // 20x10 black block at (15, 12).
+        let mut img = image::RgbImage::from_pixel(60, 40, image::Rgb([255, 255, 255]));
+        img.put_pixel(2, 2, image::Rgb([250, 252, 251]));
+        for y in 12..22 {
+            for x in 15..35 {
+                img.put_pixel(x, y, image::Rgb([0, 0, 0]));
+            }
+        }
+        let mut input = Vec::new();
+        DynamicImage::ImageRgb8(img)
+            .write_to(&mut Cursor::new(&mut input), ImageFormat::Png)
+            .unwrap();
+
+        let opts = CompressionOptions {
+            to_png: true,
+            png_lossy: false,
+            transforms: domain::parse_transforms("trim").unwrap(),
+            ..Default::default()
+        };
+        let out = compress_image_detailed(&input, "png", &opts).unwrap();
+        assert_eq!(
+            out.trimmed,
+            Some(TrimRect {
+                x: 15,
+                y: 12,
+                width: 20,
+                height: 10
+            })
+        );
+        let decoded = image::load_from_memory(&out.data).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (20, 10));
+
+        // Padding is added back evenly, even past the original edge.
+        let padded = CompressionOptions {
+            transforms: domain::parse_transforms("trim:8:14").unwrap(),
+            ..opts.clone()
+        };
+        let out = compress_image_detailed(&input, "png", &padded).unwrap();
+        let decoded = image::load_from_memory(&out.data).unwrap().to_rgb8();
+        assert_eq!(decoded.dimensions(), (48, 38));
+        assert_eq!(decoded.get_pixel(0, 37), &image::Rgb([255, 255, 255]));
+        assert_eq!(decoded.get_pixel(14, 14), &image::Rgb([0, 0, 0]));
+
+        // Huge padding (from JSON or a stored preset) is capped at the larger side.
+        let huge = CompressionOptions {
+            transforms: vec![domain::Transform::Trim {
+                tolerance: 8,
+                padding: u32::MAX,
+            }],
+            ..opts.clone()
+        };
+        let out = compress_image_detailed(&input, "png", &huge).unwrap();
+        let decoded = image::load_from_memory(&out.data).unwrap();
+        assert_eq!(
+            (decoded.width(), decoded.height()),
+            (20 + 2 * 60, 10 + 2 * 60)
+        );
+
+        // Transparent margins are trimmed regardless of the colour stored under them.
+        let mut rgba = image::RgbaImage::from_pixel(30, 30, image::Rgba([9, 9, 9, 0]));
+        rgba.put_pixel(5, 20, image::Rgba([255, 0, 0, 255]));
+        rgba.put_pixel(24, 7, image::Rgba([255, 0, 0, 255]));
+        let mut input = Vec::new();
+        DynamicImage::ImageRgba8(rgba)
+            .write_to(&mut Cursor::new(&mut input), ImageFormat::Png)
+            .unwrap();
+        let out = compress_image_detailed(&input, "png", &opts).unwrap();
+        assert_eq!(
+            out.trimmed,
+            Some(TrimRect {
+                x: 5,
+                y: 7,
+                width: 20,
+                height: 14
+            })
+        );
+
+        // A uniform image is left alone.
+        let out = compress_image_detailed(&create_test_png(), "png", &opts).unwrap();
+        assert_eq!(out.trimmed, None);
+        assert_eq!(image::load_from_memory(&out.data).unwrap().width(), 100);
+    }
+
+    #[test]
+    fn test_watermark_image_placed_by_gravity_with_opacity() {
+        let logo = image::RgbaImage::from_pixel(10, 10, image::Rgba([0, 0, 255, 255]));
+        let mut logo_png = Vec::new();
+        DynamicImage::ImageRgba8(logo)
+            .write_to(&mut Cursor::new(&mut logo_png), ImageFormat::Png)
+            .unwrap();
+
+        let opts = CompressionOptions {
+            to_png: true,
+            png_lossy: false,
+            watermark: Some(domain::Watermark {
+                scale: 0.2,
+                opacity: 1.0,
+                margin: 5,
+                ..domain::Watermark::new(domain::WatermarkKind::Image { data: logo_png })
+            }),
+            ..Default::default()
+        };
+        let (bytes, _) = compress_image_inproc(&create_test_png(), "png", &opts).unwrap();
+        let out = image::load_from_memory(&bytes).unwrap().to_rgb8();
+        // 20px-wide logo in the bottom-right corner, 5px from the edges.
+        assert_eq!(out.get_pixel(85, 85), &image::Rgb([0, 0, 255]));
+        assert_eq!(out.get_pixel(76, 76), &image::Rgb([0, 0, 255]));
+        assert_eq!(out.get_pixel(96, 96), &image::Rgb([255, 0, 0]));
+        assert_eq!(out.get_pixel(10, 10), &image::Rgb([255, 0, 0]));
+
+        // Half opacity blends with the base.
+        let mut half = opts.clone();
+        half.watermark.as_mut().unwrap().opacity = 0.5;
+        let (bytes, _) = compress_image_inproc(&create_test_png(), "png", &half).unwrap();
+        let out = image::load_from_memory(&bytes).unwrap().to_rgb8();
+        let p = out.get_pixel(85, 85);
+        assert!((120..=135).contains(&p[0]) && (120..=135).contains(&p[2]));
+
+        // A margin far beyond the image neither overflows nor draws the mark.
+        for tile in [false, true] {
+            let mut far = opts.clone();
+            let mark = far.watermark.as_mut().unwrap();
+            mark.margin = 3_000_000_000;
+            mark.tile = tile;
+            let (bytes, _) = compress_image_inproc(&create_test_png(), "png", &far).unwrap();
+            let out = image::load_from_memory(&bytes).unwrap().to_rgb8();
+            assert!(out.pixels().all(|p| p == &image::Rgb([255, 0, 0])));
+        }
+    }
+
+    #[test]
+    fn test_watermark_text_and_tiling() {
+        let white = image::RgbImage::from_pixel(200, 100, image::Rgb([255, 255, 255]));
+        let mut input = Vec::new();
+        DynamicImage::ImageRgb8(white)
+            .write_to(&mut Cursor::new(&mut input), ImageFormat::Png)
+            .unwrap();
+
+        let text = |tile: bool| CompressionOptions {
+            to_png: true,
+            png_lossy: false,
+            watermark: Some(domain::Watermark {
+                opacity: 1.0,
+                tile,
+                ..domain::Watermark::new(domain::WatermarkKind::Text {
+                    text: "ACME".to_string(),
+                    color: "#000000".to_string(),
+                })
+            }),
+            ..Default::default()
+        };
+        let inked = |bytes: &[u8]| {
+            let out = image::load_from_memory(bytes).unwrap().to_rgb8();
+            let left = (0..100)
+                .flat_map(|x| (0..100).map(move |y| (x, y)))
+                .filter(|&(x, y)| out.get_pixel(x, y)[0] < 128)
+                .count();
+            let right = (100..200)
+                .flat_map(|x| (0..100).map(move |y| (x, y)))
+                .filter(|&(x, y)| out.get_pixel(x, y)[0] < 128)
+                .count();
+            (left, right)
+        };
+
+        let (bytes, _) = compress_image_inproc(&input, "png", &text(false)).unwrap();
+        let (left, right) = inked(&bytes);
+        assert_eq!(left, 0, "single watermark sits bottom-right");
+        assert!(right > 0);
+
+        let (bytes, _) = compress_image_inproc(&input, "png", &text(true)).unwrap();
+        let (left, right) = inked(&bytes);
+        assert!(left > 0 && right > 0, "tiled watermark covers the image");
+    }
+
+    fn lossless_png(opts: CompressionOptions) -> CompressionOptions {
+        CompressionOptions {
+            to_png: true,
+            png_lossy: false,
+            ..opts
+        }
+    }
+
+    #[test]
+    fn test_adjustments_levels_white_balance_and_tone() {
+        // Low-contrast, blue-tinted gradient.
+        let img = image::RgbImage::from_fn(64, 8, |x, _| {
+            let v = 80 + x as u8;
+            image::Rgb([v, v, v + 40])
+        });
+        let mut input = Vec::new();
+        DynamicImage::ImageRgb8(img)
+            .write_to(&mut Cursor::new(&mut input), ImageFormat::Png)
+            .unwrap();
+        let run = |adjustments: domain::Adjustments| {
+            let opts = lossless_png(CompressionOptions {
+                adjustments,
+                ..Default::default()
+            });
+            let (bytes, _) = compress_image_inproc(&input, "png", &opts).unwrap();
+            image::load_from_memory(&bytes).unwrap().to_rgb8()
+        };
+
+        let balanced = run(domain::Adjustments {
+            white_balance: true,
+            ..Default::default()
+        });
+        let p = balanced.get_pixel(32, 4);
+        assert!(
+            p[2].abs_diff(p[0]) <= 3,
+            "gray-world removes the cast: {p:?}"
+        );
+
+        let levelled = run(domain::Adjustments {
+            white_balance: true,
+            auto_levels: true,
+            ..Default::default()
+        });
+        assert!(levelled.get_pixel(0, 0)[1] < 10);
+        assert!(levelled.get_pixel(63, 0)[1] > 245);
+
+        let gray = run(domain::Adjustments {
+            saturation: -1.0,
+            brightness: 0.1,
+            ..Default::default()
+        });
+        let p = gray.get_pixel(10, 0);
+        assert_eq!(p[0], p[2], "saturation -1 is grayscale");
+        assert!(p[0] > 90 + 25, "brightness lifts the tone");
+    }
+
+    #[test]
+    fn test_adjustments_sharpen_and_denoise() {
+        // Vertical edge with one speck of noise on the dark side.
+        let mut img = image::RgbImage::from_fn(32, 32, |x, _| {
+            if x < 16 {
+                image::Rgb([60, 60, 60])
+            } else {
+                image::Rgb([190, 190, 190])
+            }
+        });
+        img.put_pixel(5, 5, image::Rgb([255, 255, 255]));
+        let mut input = Vec::new();
+        DynamicImage::ImageRgb8(img)
+            .write_to(&mut Cursor::new(&mut input), ImageFormat::Png)
+            .unwrap();
+        let run = |adjustments: domain::Adjustments| {
+            let opts = lossless_png(CompressionOptions {
+                adjustments,
+                ..Default::default()
+            });
+            let (bytes, _) = compress_image_inproc(&input, "png", &opts).unwrap();
+            image::load_from_memory(&bytes).unwrap().to_rgb8()
+        };
+
+        let denoised = run(domain::Adjustments {
+            denoise: true,
+            ..Default::default()
+        });
+        assert_eq!(denoised.get_pixel(5, 5)[0], 60);
+        assert_eq!(
+            denoised.get_pixel(16, 10)[0],
+            190,
+            "edges survive the median"
+        );
+
+        let sharpened = run(domain::Adjustments {
+            sharpen: 1.0,
+            ..Default::default()
+        });
+        assert!(
+            sharpened.get_pixel(15, 10)[0] < 60,
+            "dark side of the edge darkens"
+        );
+        assert!(
+            sharpened.get_pixel(16, 10)[0] > 190,
+            "light side of the edge brightens"
+        );
+    }
+
+    fn read_fixture(name: &str) -> Vec<u8> {
+        std::fs::read(format!(
+            "{}/../../tests/fixtures/{name}",
+            env!("CARGO_MANIFEST_DIR")
+        ))
+        .expect("fixture missing or unreadable")
+    }
+
+    fn embedded_icc(bytes: &[u8]) -> Option<Vec<u8>> {
+        use image::ImageDecoder;
+        let mut decoder = image::ImageReader::new(Cursor::new(bytes))
+            .with_guessed_format()
+            .unwrap()
+            .into_decoder()
+            .unwrap();
+        decoder.icc_profile().unwrap()
+    }
+
+    #[test]
+    fn test_display_p3_fixture_converted_to_srgb() {
+        // 16×16 JPEG tagged Display P3: orange, green, gray and blue 8×8 blocks.
+        let p3 = read_fixture("display_p3.jpg");
+        let png = |color_profile| {
+            let opts = lossless_png(CompressionOptions {
+                color_profile,
+                ..Default::default()
+            });
+            let (bytes, _) = compress_image_inproc(&p3, "jpg", &opts).unwrap();
+            let pixels = image::load_from_memory(&bytes).unwrap().to_rgb8();
+            (embedded_icc(&bytes), pixels)
+        };
+
+        let (icc, srgb) = png(domain::ColorProfile::Srgb);
+        let (_, raw) = png(domain::ColorProfile::Ignore);
+        assert!(icc.is_none(), "converted output is plain sRGB");
+        let (orange, raw_orange) = (srgb.get_pixel(4, 4), raw.get_pixel(4, 4));
+        assert!(
+            orange[0] > raw_orange[0] + 8,
+            "P3 orange is more saturated in sRGB"
+        );
+        assert!(orange[2] + 8 < raw_orange[2]);
+        let gray = srgb.get_pixel(4, 12);
+        assert!(gray[0].abs_diff(gray[1]) <= 2 && gray[1].abs_diff(gray[2]) <= 2);
+    }
+
+    #[test]
+    fn test_keep_color_profile_embeds_or_falls_back_to_srgb() {
+        let p3 = read_fixture("display_p3.jpg");
+        let source_icc = embedded_icc(&p3).expect("fixture carries a profile");
+        let keep = |to_webp: bool| {
+            let opts = lossless_png(CompressionOptions {
+                color_profile: domain::ColorProfile::Keep,
+                ..Default::default()
+            });
+            let opts = CompressionOptions {
+                to_webp,
+                to_png: !to_webp,
+                ..opts
+            };
+            compress_image_inproc(&p3, "jpg", &opts).unwrap().0
+        };
+
+        let png = keep(false);
+        assert_eq!(embedded_icc(&png), Some(source_icc.clone()));
+        let kept = image::load_from_memory(&png).unwrap().to_rgb8();
+        let original = image::load_from_memory(&p3).unwrap().to_rgb8();
+        assert_eq!(kept.get_pixel(4, 4), original.get_pixel(4, 4));
+
+        let jpeg = compress_image_inproc(
+            &p3,
+            "jpg",
+            &CompressionOptions {
+                color_profile: domain::ColorProfile::Keep,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .0;
+        assert_eq!(embedded_icc(&jpeg), Some(source_icc));
+
+        // WebP output cannot carry the profile here, so the pixels are converted instead.
+        let webp = keep(true);
+        let converted = image::load_from_memory(&webp).unwrap().to_rgb8();
+        assert!(converted.get_pixel(4, 4)[0] > original.get_pixel(4, 4)[0] + 8);
+    }
+
+    fn create_test_png16(value: impl Fn(u32, u32) -> u16) -> Vec<u8> {
+        let img = image::ImageBuffer::from_fn(16, 16, |x, y| {
+            let v = value(x, y);
+            image::Rgb([v, v / 2, 65535 - v])
+        });
+        let mut bytes = Vec::new();
+        DynamicImage::ImageRgb16(img)
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_16bit_png_kept_where_the_output_can_store_it() {
+        let input = create_test_png16(|x, y| (x * 4096 + y * 7) as u16 + 3);
+        let lossless = CompressionOptions {
+            png_lossy: false,
+            oxipng: false,
+            transforms: domain::parse_transforms("flip:h").unwrap(),
+            ..Default::default()
+        };
+        let png = compress_image_detailed(&input, "png", &lossless).unwrap();
+        assert_eq!(png.bit_depth, 16);
+        let out = image::load_from_memory(&png.data).unwrap().to_rgb16();
+        assert_eq!(
+            out.get_pixel(15, 2)[0],
+            2 * 7 + 3,
+            "16-bit samples survive the flip"
+        );
+
+        let tiff = CompressionOptions {
+            to_tiff: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            compress_image_detailed(&input, "png", &tiff)
+                .unwrap()
+                .bit_depth,
+            16
+        );
+
+        let avif = CompressionOptions {
+            to_avif: true,
+            ..Default::default()
+        };
+        let avif = compress_image_detailed(&input, "png", &avif).unwrap();
+        assert_eq!(avif.bit_depth, 10);
+        assert!(!avif.data.is_empty());
+
+        let webp = CompressionOptions {
+            to_webp: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            compress_image_detailed(&input, "png", &webp)
+                .unwrap()
+                .bit_depth,
+            8
+        );
+    }
+
+    #[test]
+    fn test_dithered_reduction_to_8bit() {
+        // Halfway between 8-bit levels 128 and 129 everywhere.
+        let input = create_test_png16(|_, _| 128 * 257 + 128);
+        let bmp = |dither: bool| {
+            let opts = CompressionOptions {
+                to_bmp: true,
+                dither,
+                ..Default::default()
+            };
+            let out = compress_image_detailed(&input, "png", &opts).unwrap();
+            assert_eq!(out.bit_depth, 8);
+            image::load_from_memory(&out.data).unwrap().to_rgb8()
+        };
+
+        let plain = bmp(false);
+        assert!(plain.pixels().all(|p| p[0] == plain.get_pixel(0, 0)[0]));
+        let dithered = bmp(true);
+        let low = dithered.pixels().filter(|p| p[0] == 128).count();
+        let high = dithered.pixels().filter(|p| p[0] == 129).count();
+        assert_eq!(low + high, 256);
+        assert!(
+            (96..=160).contains(&low),
+            "about half of each level: {low}/{high}"
+        );
+    }
+
+    #[test]
+    fn test_dither_survives_adjustments_on_16bit_input() {
+        let input = create_test_png16(|_, _| 128 * 257 + 128);
+        let opts = CompressionOptions {
+            to_png: true,
+            png_lossy: false,
+            oxipng: false,
+            dither: true,
+            // Too small to move an 8-bit level, so only the reduction changes pixels.
+            adjustments: domain::Adjustments {
+                brightness: 0.0001,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let out = compress_image_detailed(&input, "png", &opts).unwrap();
+        assert_eq!(out.bit_depth, 8);
+        let decoded = image::load_from_memory(&out.data).unwrap().to_rgb8();
+        let low = decoded.pixels().filter(|p| p[0] == 128).count();
+        let high = decoded.pixels().filter(|p| p[0] == 129).count();
+        assert_eq!(low + high, 256);
+        assert!((96..=160).contains(&low), "dithered: {low}/{high}");
+    }
+
+    #[test]
+    fn test_parallel_batch_keeps_order_and_bounds_workers() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::time::Duration;
+
+        let in_flight = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let squares = run_parallel((0..40u64).collect(), 3, |n| {
+            let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(40 - n));
+            in_flight.fetch_sub(1, Ordering::SeqCst);
+            n * n
+        });
+        assert_eq!(squares, (0..40u64).map(|n| n * n).collect::<Vec<_>>());
+        assert!(peak.load(Ordering::SeqCst) <= 3);
+
+        let opts = CompressionOptions {
+            to_png: true,
+            ..Default::default()
+        };
+        let inputs = vec![
+            (create_test_png(), "png".to_string()),
+            (b"broken".to_vec(), "png".to_string()),
+            (create_test_jpeg(), "jpg".to_string()),
+        ];
+        let results = compress_batch(inputs, &opts, 0, &CancelToken::new());
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        assert_eq!(results[2].as_ref().unwrap()[0].mime_type, "image/png");
+    }
+
+    #[test]
+    fn test_cancelled_batch_keeps_finished_results() {
+        let opts = CompressionOptions {
+            to_png: true,
+            transforms: vec![domain::Transform::FlipHorizontal],
+            ..Default::default()
+        };
+        let cancel = CancelToken::new();
+        // One worker: the second input cancels the batch once it has been compressed.
+        let results = run_parallel((0..4).collect(), 1, |n: usize| {
+            let out = compress_image_pages_with_cancel(&create_test_png(), "png", &opts, &cancel);
+            if n == 1 {
+                cancel.cancel();
+            }
+            out
+        });
+        assert!(results[0].is_ok() && results[1].is_ok());
+        for result in &results[2..] {
+            assert!(is_cancelled(result.as_ref().unwrap_err()));
+        }
+
+        let err = compress_image_with_cancel(&create_test_png(), "png", &opts, &cancel)
+            .expect_err("cancelled token");
+        assert!(is_cancelled(&err));
+
+        let token = CancelToken::new();
+        let guard = token.drop_guard();
+        assert!(!token.is_cancelled());
+        drop(guard);
+        assert!(token.is_cancelled());
+    }
+
+    #[test]
+    fn test_resize_fits_inside_the_box_and_never_enlarges() {
+        let resized = |width, height| {
+            let opts = CompressionOptions {
+                to_png: true,
+                png_lossy: false,
+                transforms: vec![
+                    domain::Transform::Crop {
+                        x: 0,
+                        y: 0,
+                        width: 100,
+                        height: 50,
+                    },
+                    domain::Transform::Resize { width, height },
+                ],
+                ..Default::default()
+            };
+            compress_image_detailed(&create_test_png(), "png", &opts)
+                .unwrap()
+                .dimensions
+        };
+        assert_eq!(resized(Some(40), None), Some((40, 20)));
+        assert_eq!(resized(Some(40), Some(10)), Some((20, 10)));
+        assert_eq!(resized(None, Some(500)), Some((100, 50)));
+    }
+
+    #[test]
+    fn test_image_info_reads_headers() {
+        let info = image_info(&create_test_png16(|x, _| x as u16 * 4000)).unwrap();
+        assert_eq!(
+            info,
+            ImageInfo {
+                mime_type: "image/png",
+                width: 16,
+                height: 16,
+                bit_depth: 16,
+                has_alpha: false,
+                pages: 1,
+                non_srgb_profile: false,
+            }
+        );
+
+        let tiff = create_test_multipage_tiff(&[[255, 0, 0], [0, 255, 0], [0, 0, 255]]);
+        let info = image_info(&tiff).unwrap();
+        assert_eq!((info.mime_type, info.pages), ("image/tiff", 3));
+
+        assert_eq!(
+            image_info(&create_test_webp()).unwrap().mime_type,
+            "image/webp"
+        );
+        assert!(image_info(b"not an image").is_err());
+    }
+
+    #[test]
+    fn test_suggested_filename_uses_output_size_and_template() {
+        let opts = CompressionOptions {
+            to_avif: true,
+            transforms: vec![domain::Transform::Crop {
+                x: 0,
+                y: 0,
+                width: 40,
+                height: 30,
+            }],
+            filename_template: Some("{stem}-{width}x{height}-q{quality}-{format}.{ext}".into()),
+            ..Default::default()
+        };
+        let avif = compress_image_detailed(&create_test_png(), "png", &opts).unwrap();
+        assert_eq!(avif.dimensions, Some((40, 30)));
+        assert_eq!(
+            avif.suggested_filename("shots/red.png", &opts, 1, Some(2)),
+            "red-page2-40x30-q60-avif.avif"
+        );
+
+        let opts = CompressionOptions {
+            to_jpeg: true,
+            ..Default::default()
+        };
+        let jpeg = compress_image_detailed(&create_test_png(), "png", &opts).unwrap();
+        assert_eq!(jpeg.dimensions, Some((100, 100)));
+        assert_eq!(
+            jpeg.suggested_filename("red.png", &opts, 1, None),
+            "red.jpg"
+        );
+    }
+
     #[test]
     fn test_invalid_webp_input_returns_error() {
         let opts = CompressionOptions {
@@ -0,0 +1,122 @@
+//! Multi-page TIFF decode/encode with selectable compression.
+
+use anyhow::{anyhow, Result};
+use domain::TiffCompression;
+use image::{DynamicImage, ImageBuffer};
+use std::io::Cursor;
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::encoder::{colortype, Compression, DeflateLevel, TiffEncoder};
+use tiff::tags::Predictor;
+use tiff::ColorType;
+
+pub(crate) fn is_tiff_bytes(input: &[u8]) -> bool {
+    input.starts_with(b"II*\0") || input.starts_with(b"MM\0*")
+}
+
+/// Decode every page (IFD) of a TIFF file, in file order.
+pub(crate) fn decode_pages(input: &[u8]) -> Result<Vec<DynamicImage>> {
+    let mut decoder =
+        Decoder::new(Cursor::new(input)).map_err(|e| anyhow!("Failed to decode TIFF: {e}"))?;
+    let mut pages = vec![decode_page(&mut decoder)?];
+    while decoder.more_images() {
+        decoder
+            .next_image()
+            .map_err(|e| anyhow!("Failed to decode TIFF page {}: {e}", pages.len() + 1))?;
+        pages.push(decode_page(&mut decoder)?);
+    }
+    Ok(pages)
+}
+
+/// Number of pages (IFDs) in a TIFF file, without decoding their pixels.
+pub(crate) fn page_count(input: &[u8]) -> Result<u32> {
+    let mut decoder =
+        Decoder::new(Cursor::new(input)).map_err(|e| anyhow!("Failed to decode TIFF: {e}"))?;
+    let mut pages = 1;
+    while decoder.more_images() {
+        decoder
+            .next_image()
+            .map_err(|e| anyhow!("Failed to read TIFF page {}: {e}", pages + 1))?;
+        pages += 1;
+    }
+    Ok(pages)
+}
+
+fn decode_page(decoder: &mut Decoder<Cursor<&[u8]>>) -> Result<DynamicImage> {
+    let (w, h) = decoder
+        .dimensions()
+        .map_err(|e| anyhow!("Failed to decode TIFF: {e}"))?;
+    let color = decoder
+        .colortype()
+        .map_err(|e| anyhow!("Failed to decode TIFF: {e}"))?;
+    let data = decoder
+        .read_image()
+        .map_err(|e| anyhow!("Failed to decode TIFF: {e}"))?;
+
+    let img = match (color, data) {
+        (ColorType::Gray(8), DecodingResult::U8(buf)) => {
+            ImageBuffer::from_raw(w, h, buf).map(DynamicImage::ImageLuma8)
+        }
+        (ColorType::GrayA(8), DecodingResult::U8(buf)) => {
+            ImageBuffer::from_raw(w, h, buf).map(DynamicImage::ImageLumaA8)
+        }
+        (ColorType::RGB(8), DecodingResult::U8(buf)) => {
+            ImageBuffer::from_raw(w, h, buf).map(DynamicImage::ImageRgb8)
+        }
+        (ColorType::RGBA(8), DecodingResult::U8(buf)) => {
+            ImageBuffer::from_raw(w, h, buf).map(DynamicImage::ImageRgba8)
+        }
+        (ColorType::Gray(16), DecodingResult::U16(buf)) => {
+            ImageBuffer::from_raw(w, h, buf).map(DynamicImage::ImageLuma16)
+        }
+        (ColorType::GrayA(16), DecodingResult::U16(buf)) => {
+            ImageBuffer::from_raw(w, h, buf).map(DynamicImage::ImageLumaA16)
+        }
+        (ColorType::RGB(16), DecodingResult::U16(buf)) => {
+            ImageBuffer::from_raw(w, h, buf).map(DynamicImage::ImageRgb16)
+        }
+        (ColorType::RGBA(16), DecodingResult::U16(buf)) => {
+            ImageBuffer::from_raw(w, h, buf).map(DynamicImage::ImageRgba16)
+        }
+        (other, _) => return Err(anyhow!("Unsupported TIFF page format: {other:?}")),
+    };
+    img.ok_or_else(|| anyhow!("Failed to decode TIFF: page buffer does not match its dimensions"))
+}
+
+/// Write `pages` as one TIFF file (one IFD per page). 16-bit pages stay 16-bit.
+pub(crate) fn encode_pages(
+    pages: &[DynamicImage],
+    compression: TiffCompression,
+) -> Result<Vec<u8>> {
+    let (compression, predictor) = match compression {
+        TiffCompression::None => (Compression::Uncompressed, Predictor::None),
+        TiffCompression::Lzw => (Compression::Lzw, Predictor::Horizontal),
+        TiffCompression::Deflate => (
+            Compression::Deflate(DeflateLevel::Balanced),
+            Predictor::Horizontal,
+        ),
+        TiffCompression::PackBits => (Compression::Packbits, Predictor::None),
+    };
+
+    let mut cursor = Cursor::new(Vec::new());
+    let mut encoder = TiffEncoder::new(&mut cursor)
+        .map_err(|e| anyhow!("TIFF encode failed: {e}"))?
+        .with_compression(compression)
+        .with_predictor(predictor);
+
+    for page in pages {
+        let (w, h) = (page.width(), page.height());
+        let written = match page {
+            DynamicImage::ImageLuma8(buf) => encoder.write_image::<colortype::Gray8>(w, h, buf),
+            DynamicImage::ImageLuma16(buf) => encoder.write_image::<colortype::Gray16>(w, h, buf),
+            DynamicImage::ImageRgb16(buf) => encoder.write_image::<colortype::RGB16>(w, h, buf),
+            DynamicImage::ImageRgba16(buf) => encoder.write_image::<colortype::RGBA16>(w, h, buf),
+            other if other.color().has_alpha() => {
+                encoder.write_image::<colortype::RGBA8>(w, h, &other.to_rgba8())
+            }
+            other => encoder.write_image::<colortype::RGB8>(w, h, &other.to_rgb8()),
+        };
+        written.map_err(|e| anyhow!("TIFF encode failed: {e}"))?;
+    }
+
+    Ok(cursor.into_inner())
+}
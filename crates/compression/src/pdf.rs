@@ -0,0 +1,291 @@
+//! Multi-page PDF export: one image per page, embedded as DCT (JPEG) or Flate streams.
+
+use anyhow::{anyhow, Result};
+use domain::{PdfImageEncoding, PdfOptions, PdfPageSize};
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use image::codecs::jpeg::JpegEncoder;
+use image::DynamicImage;
+use std::io::Write;
+
+const MM_TO_PT: f32 = 72.0 / 25.4;
+const A4_PT: (f32, f32) = (595.28, 841.89);
+const LETTER_PT: (f32, f32) = (612.0, 792.0);
+
+/// An image XObject ready to be written.
+struct PdfImage {
+    width: u32,
+    height: u32,
+    color_space: &'static str,
+    filter: &'static str,
+    data: Vec<u8>,
+    /// Flate-compressed 8-bit alpha channel, if the image has transparency.
+    smask: Option<Vec<u8>>,
+}
+
+/// Bundle encoded images into a PDF, one page per image, in order.
+pub fn images_to_pdf(images: &[Vec<u8>], opts: &PdfOptions) -> Result<Vec<u8>> {
+    if images.is_empty() {
+        return Err(anyhow!("No images to bundle into PDF"));
+    }
+
+    let mut pdf = PdfWriter::new();
+    let mut page_ids = Vec::with_capacity(images.len());
+
+    for (index, bytes) in images.iter().enumerate() {
+        let image = prepare_image(bytes, opts)
+            .map_err(|e| anyhow!("Failed to decode image {} for PDF: {e}", index + 1))?;
+        let (page_w, page_h, x, y, draw_w, draw_h) = page_layout(image.width, image.height, opts);
+
+        let smask_id = match &image.smask {
+            Some(alpha) => {
+                let id = pdf.alloc();
+                let dict = format!(
+                    "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceGray /BitsPerComponent 8 /Filter /FlateDecode",
+                    image.width, image.height
+                );
+                pdf.write_stream(id, &dict, alpha);
+                Some(id)
+            }
+            None => None,
+        };
+
+        let image_id = pdf.alloc();
+        let mut dict = format!(
+            "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /{} /BitsPerComponent 8 /Filter /{}",
+            image.width, image.height, image.color_space, image.filter
+        );
+        if let Some(id) = smask_id {
+            dict.push_str(&format!(" /SMask {id} 0 R"));
+        }
+        pdf.write_stream(image_id, &dict, &image.data);
+
+        let content = format!("q\n{draw_w:.2} 0 0 {draw_h:.2} {x:.2} {y:.2} cm\n/Im0 Do\nQ\n");
+        let content_id = pdf.alloc();
+        pdf.write_stream(content_id, "", content.as_bytes());
+
+        let page_id = pdf.alloc();
+        pdf.write_object(
+            page_id,
+            &format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {page_w:.2} {page_h:.2}] /Resources << /XObject << /Im0 {image_id} 0 R >> >> /Contents {content_id} 0 R >>"
+            ),
+        );
+        page_ids.push(page_id);
+    }
+
+    let kids: Vec<String> = page_ids.iter().map(|id| format!("{id} 0 R")).collect();
+    pdf.write_object(
+        2,
+        &format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            page_ids.len()
+        ),
+    );
+    pdf.write_object(1, "<< /Type /Catalog /Pages 2 0 R >>");
+    Ok(pdf.finish())
+}
+
+fn prepare_image(bytes: &[u8], opts: &PdfOptions) -> Result<PdfImage> {
+    if opts.image_encoding != PdfImageEncoding::Flate {
+        if let Some((width, height, components)) = jpeg_info(bytes) {
+            let color_space = match components {
+                1 => Some("DeviceGray"),
+                3 => Some("DeviceRGB"),
+                _ => None,
+            };
+            if let Some(color_space) = color_space {
+                return Ok(PdfImage {
+                    width,
+                    height,
+                    color_space,
+                    filter: "DCTDecode",
+                    data: bytes.to_vec(),
+                    smask: None,
+                });
+            }
+        }
+    }
+
+    let img = super::decode_dynamic_image(bytes)?;
+    let (width, height) = (img.width(), img.height());
+    let gray = matches!(
+        img,
+        DynamicImage::ImageLuma8(_)
+            | DynamicImage::ImageLumaA8(_)
+            | DynamicImage::ImageLuma16(_)
+            | DynamicImage::ImageLumaA16(_)
+    );
+    let smask = if img.color().has_alpha() {
+        let alpha: Vec<u8> = img.to_rgba8().pixels().map(|p| p[3]).collect();
+        Some(deflate(&alpha)?)
+    } else {
+        None
+    };
+    let (color_space, pixels) = if gray {
+        ("DeviceGray", img.to_luma8().into_raw())
+    } else {
+        ("DeviceRGB", img.to_rgb8().into_raw())
+    };
+
+    let (filter, data) = if opts.image_encoding == PdfImageEncoding::Jpeg {
+        let mut buf = Vec::new();
+        let color = if gray {
+            image::ExtendedColorType::L8
+        } else {
+            image::ExtendedColorType::Rgb8
+        };
+        JpegEncoder::new_with_quality(&mut buf, opts.jpeg_quality.clamp(1, 100))
+            .encode(&pixels, width, height, color)?;
+        ("DCTDecode", buf)
+    } else {
+        ("FlateDecode", deflate(&pixels)?)
+    };
+
+    Ok(PdfImage {
+        width,
+        height,
+        color_space,
+        filter,
+        data,
+        smask,
+    })
+}
+
+/// Page size and image placement in points: `(page_w, page_h, x, y, draw_w, draw_h)`.
+fn page_layout(width: u32, height: u32, opts: &PdfOptions) -> (f32, f32, f32, f32, f32, f32) {
+    let margin = opts.clamped_margin_mm() * MM_TO_PT;
+    let (w, h) = (width as f32, height as f32);
+    let (page_w, page_h) = match opts.page_size {
+        PdfPageSize::Fit => return (w + 2.0 * margin, h + 2.0 * margin, margin, margin, w, h),
+        PdfPageSize::A4 => A4_PT,
+        PdfPageSize::Letter => LETTER_PT,
+    };
+    // Landscape images get a landscape page.
+    let (page_w, page_h) = if w > h {
+        (page_h, page_w)
+    } else {
+        (page_w, page_h)
+    };
+    let avail_w = (page_w - 2.0 * margin).max(1.0);
+    let avail_h = (page_h - 2.0 * margin).max(1.0);
+    let scale = (avail_w / w).min(avail_h / h);
+    let (draw_w, draw_h) = (w * scale, h * scale);
+    (
+        page_w,
+        page_h,
+        (page_w - draw_w) / 2.0,
+        (page_h - draw_h) / 2.0,
+        draw_w,
+        draw_h,
+    )
+}
+
+/// `(width, height, components)` from the frame header of a baseline or progressive JPEG.
+/// Other JPEG flavours (arithmetic, lossless) return `None` and are re-encoded instead.
+fn jpeg_info(data: &[u8]) -> Option<(u32, u32, u8)> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut i = 2;
+    while i + 4 <= data.len() {
+        if data[i] != 0xFF {
+            return None;
+        }
+        let marker = data[i + 1];
+        if marker == 0xFF {
+            i += 1;
+            continue;
+        }
+        let len = u16::from_be_bytes([data[i + 2], data[i + 3]]) as usize;
+        match marker {
+            0xC0..=0xC2 => {
+                let seg = data.get(i + 4..i + 2 + len)?;
+                if seg.len() < 6 {
+                    return None;
+                }
+                let height = u16::from_be_bytes([seg[1], seg[2]]) as u32;
+                let width = u16::from_be_bytes([seg[3], seg[4]]) as u32;
+                return Some((width, height, seg[5]));
+            }
+            0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF | 0xDA => return None,
+            _ => i += 2 + len,
+        }
+    }
+    None
+}
+
+fn deflate(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+/// Minimal PDF 1.4 object writer with a classic cross-reference table.
+struct PdfWriter {
+    buf: Vec<u8>,
+    /// Byte offset of each object, indexed by object id - 1.
+    offsets: Vec<usize>,
+    next_id: usize,
+}
+
+impl PdfWriter {
+    fn new() -> Self {
+        Self {
+            buf: b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec(),
+            offsets: Vec::new(),
+            // Objects 1 and 2 are reserved for the catalog and the page tree, written last.
+            next_id: 3,
+        }
+    }
+
+    fn alloc(&mut self) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    fn record_offset(&mut self, id: usize) {
+        if self.offsets.len() < id {
+            self.offsets.resize(id, 0);
+        }
+        self.offsets[id - 1] = self.buf.len();
+    }
+
+    fn write_object(&mut self, id: usize, body: &str) {
+        self.record_offset(id);
+        self.buf
+            .extend_from_slice(format!("{id} 0 obj\n{body}\nendobj\n").as_bytes());
+    }
+
+    fn write_stream(&mut self, id: usize, dict: &str, data: &[u8]) {
+        self.record_offset(id);
+        let sep = if dict.is_empty() { "" } else { " " };
+        self.buf.extend_from_slice(
+            format!(
+                "{id} 0 obj\n<< {dict}{sep}/Length {} >>\nstream\n",
+                data.len()
+            )
+            .as_bytes(),
+        );
+        self.buf.extend_from_slice(data);
+        self.buf.extend_from_slice(b"\nendstream\nendobj\n");
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        let xref_offset = self.buf.len();
+        let count = self.offsets.len() + 1;
+        self.buf
+            .extend_from_slice(format!("xref\n0 {count}\n0000000000 65535 f \n").as_bytes());
+        for offset in &self.offsets {
+            self.buf
+                .extend_from_slice(format!("{offset:010} 00000 n \n").as_bytes());
+        }
+        self.buf.extend_from_slice(
+            format!("trailer\n<< /Size {count} /Root 1 0 R >>\nstartxref\n{xref_offset}\n%%EOF\n")
+                .as_bytes(),
+        );
+        self.buf
+    }
+}
@@ -0,0 +1,60 @@
+//! HDR → 16-bit sRGB tone mapping for floating-point input (Radiance HDR, OpenEXR).
+
+use image::{DynamicImage, ImageBuffer, Rgba};
+
+/// Reinhard "key" value: the log-average luminance is mapped to middle grey.
+const KEY: f32 = 0.18;
+
+fn luminance(p: &[f32; 4]) -> f32 {
+    0.2126 * p[0] + 0.7152 * p[1] + 0.0722 * p[2]
+}
+
+fn srgb_encode(linear: f32) -> u16 {
+    let v = linear.clamp(0.0, 1.0);
+    let s = if v <= 0.003_130_8 {
+        12.92 * v
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    };
+    (s * 65535.0).round() as u16
+}
+
+/// Map linear scene-referred pixels to 16-bit sRGB using Reinhard's global operator,
+/// with the brightest pixel as the white point. Alpha is clamped to 0..1 and kept.
+pub(crate) fn tone_map(img: &DynamicImage) -> DynamicImage {
+    let src = img.to_rgba32f();
+
+    let finite_lum = || {
+        src.pixels()
+            .map(|p| luminance(&p.0).max(0.0))
+            .filter(|l| l.is_finite())
+    };
+    let count = finite_lum().count().max(1) as f64;
+    let log_avg = (finite_lum().map(|l| (1e-4 + l as f64).ln()).sum::<f64>() / count).exp() as f32;
+    let scale = KEY / log_avg;
+    let white = finite_lum().fold(0.0f32, f32::max) * scale;
+    let white_sq = (white * white).max(f32::EPSILON);
+
+    let out = ImageBuffer::from_fn(src.width(), src.height(), |x, y| {
+        let p = src.get_pixel(x, y).0;
+        let l = luminance(&p).max(0.0);
+        let ratio = if l > 0.0 && l.is_finite() {
+            let ls = l * scale;
+            ls * (1.0 + ls / white_sq) / (1.0 + ls) / l
+        } else {
+            0.0
+        };
+        Rgba([
+            srgb_encode(p[0] * ratio),
+            srgb_encode(p[1] * ratio),
+            srgb_encode(p[2] * ratio),
+            (p[3].clamp(0.0, 1.0) * 65535.0).round() as u16,
+        ])
+    });
+
+    if img.color().has_alpha() {
+        DynamicImage::ImageRgba16(out)
+    } else {
+        DynamicImage::ImageRgb16(DynamicImage::ImageRgba16(out).to_rgb16())
+    }
+}
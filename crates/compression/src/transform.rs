@@ -0,0 +1,237 @@
+//! Geometric transforms (rotate, flip, crop, trim, resize) applied to decoded pixels before
+//! encoding.
+
+use anyhow::{anyhow, Result};
+use domain::{Transform, TrimRect};
+use image::{imageops, DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+/// Apply `transforms` in list order. Also returns the content bounds found by the last
+/// [`Transform::Trim`] that trimmed something.
+pub(crate) fn apply_transforms(
+    img: DynamicImage,
+    transforms: &[Transform],
+) -> Result<(DynamicImage, Option<TrimRect>)> {
+    let mut img = img;
+    let mut trimmed = None;
+    for transform in transforms {
+        img = match transform {
+            Transform::Trim { tolerance, padding } => match content_bounds(&img, *tolerance) {
+                Some(rect) => {
+                    trimmed = Some(rect);
+                    trim(img, rect, *padding)?
+                }
+                None => img,
+            },
+            other => apply_transform(img, other).map_err(|e| anyhow!("Invalid transform: {e}"))?,
+        };
+    }
+    Ok((img, trimmed))
+}
+
+fn apply_transform(img: DynamicImage, transform: &Transform) -> Result<DynamicImage> {
+    match transform {
+        Transform::Rotate { degrees, fill } => {
+            let fill = match fill.as_deref() {
+                Some(hex) => domain::parse_hex_color(hex)
+                    .ok_or_else(|| anyhow!("Invalid rotate fill colour '{hex}'"))?,
+                None => [0, 0, 0, 0],
+            };
+            Ok(rotate(img, *degrees, Rgba(fill)))
+        }
+        Transform::FlipHorizontal => Ok(img.fliph()),
+        Transform::FlipVertical => Ok(img.flipv()),
+        Transform::Crop {
+            x,
+            y,
+            width,
+            height,
+        } => {
+            let (w, h) = (img.width(), img.height());
+            if *x >= w || *y >= h {
+                return Err(anyhow!(
+                    "Crop origin ({x}, {y}) lies outside the {w}x{h} image"
+                ));
+            }
+            let width = (*width).min(w - x);
+            let height = (*height).min(h - y);
+            if width == 0 || height == 0 {
+                return Err(anyhow!("Crop rectangle is empty"));
+            }
+            Ok(img.crop_imm(*x, *y, width, height))
+        }
+        Transform::AspectCrop {
+            width: ratio_w,
+            height: ratio_h,
+            gravity,
+        } => {
+            if *ratio_w == 0 || *ratio_h == 0 {
+                return Err(anyhow!("Aspect ratio must be non-zero"));
+            }
+            let (w, h) = (img.width() as u64, img.height() as u64);
+            let (rw, rh) = (*ratio_w as u64, *ratio_h as u64);
+            // Largest box of ratio rw:rh that fits: limited by height when the image is wider.
+            let (crop_w, crop_h) = if w * rh > h * rw {
+                ((h * rw / rh).max(1), h)
+            } else {
+                (w, (w * rh / rw).max(1))
+            };
+            let (crop_w, crop_h) = (crop_w as u32, crop_h as u32);
+            let (x, y) = gravity.offset((img.width(), img.height()), (crop_w, crop_h));
+            Ok(img.crop_imm(x, y, crop_w, crop_h))
+        }
+        Transform::Resize { width, height } => {
+            let (w, h) = (img.width(), img.height());
+            let max_w = width.unwrap_or(w);
+            let max_h = height.unwrap_or(h);
+            if max_w == 0 || max_h == 0 {
+                return Err(anyhow!("Resize bounds must be non-zero"));
+            }
+            if w <= max_w && h <= max_h {
+                return Ok(img);
+            }
+            // Fit inside the box: `resize` keeps the aspect ratio.
+            Ok(img.resize(max_w, max_h, imageops::FilterType::Lanczos3))
+        }
+        Transform::Trim { .. } => Ok(img),
+    }
+}
+
+/// Bounds of everything that is not border. Border is judged against the top-left pixel: when
+/// that pixel is fully transparent, any pixel with alpha ≤ `tolerance` is border; otherwise
+/// each channel must be within `tolerance` of it. Returns `None` when nothing would be trimmed
+/// (no border, or the whole image is border).
+fn content_bounds(img: &DynamicImage, tolerance: u8) -> Option<TrimRect> {
+    let rgba = img.to_rgba8();
+    let (w, h) = rgba.dimensions();
+    let background = *rgba.get_pixel(0, 0);
+    let is_border = |x: u32, y: u32| {
+        let p = rgba.get_pixel(x, y);
+        if background[3] == 0 {
+            p[3] <= tolerance
+        } else {
+            p.0.iter()
+                .zip(background.0)
+                .all(|(a, b)| a.abs_diff(b) <= tolerance)
+        }
+    };
+
+    let top = (0..h).find(|&y| (0..w).any(|x| !is_border(x, y)))?;
+    let bottom = (top..h).rev().find(|&y| (0..w).any(|x| !is_border(x, y)))?;
+    let column_has_content = |x: u32| (top..=bottom).any(|y| !is_border(x, y));
+    let left = (0..w).find(|&x| column_has_content(x))?;
+    let right = (left..w).rev().find(|&x| column_has_content(x))?;
+
+    let rect = TrimRect {
+        x: left,
+        y: top,
+        width: right - left + 1,
+        height: bottom - top + 1,
+    };
+    (rect.width < w || rect.height < h).then_some(rect)
+}
+
+/// Crop to `rect` plus `padding` on every side. Padding that reaches past the original edges
+/// is painted with the top-left (border) colour so it stays even. Padding is capped at the
+/// image's larger side.
+fn trim(img: DynamicImage, rect: TrimRect, padding: u32) -> Result<DynamicImage> {
+    let (w, h) = img.dimensions();
+    let padding = padding.min(w.max(h));
+    let padded = |start: u32, len: u32| {
+        Some((
+            start.checked_add(len)?.checked_add(padding)?,
+            padding.checked_mul(2)?.checked_add(len)?,
+        ))
+    };
+    let ((right, width), (bottom, height)) = padded(rect.x, rect.width)
+        .zip(padded(rect.y, rect.height))
+        .ok_or_else(|| anyhow!("Trim padding {} is too large", padding))?;
+    if rect.x >= padding && rect.y >= padding && right <= w && bottom <= h {
+        return Ok(img.crop_imm(rect.x - padding, rect.y - padding, width, height));
+    }
+
+    let background = img.get_pixel(0, 0);
+    let content = img
+        .crop_imm(rect.x, rect.y, rect.width, rect.height)
+        .to_rgba8();
+    let mut canvas = RgbaImage::from_pixel(width, height, background);
+    imageops::replace(&mut canvas, &content, padding as i64, padding as i64);
+    Ok(DynamicImage::ImageRgba8(canvas))
+}
+
+/// Clockwise rotation. Right angles are exact; other angles resample bilinearly onto a
+/// canvas large enough for the rotated image, painting uncovered area with `fill`.
+fn rotate(img: DynamicImage, degrees: f32, fill: Rgba<u8>) -> DynamicImage {
+    let degrees = degrees.rem_euclid(360.0);
+    let nearest_right_angle = (degrees / 90.0).round() * 90.0;
+    if (degrees - nearest_right_angle).abs() < 0.01 {
+        return match nearest_right_angle as u32 % 360 {
+            90 => img.rotate90(),
+            180 => img.rotate180(),
+            270 => img.rotate270(),
+            _ => img,
+        };
+    }
+
+    let src = img.to_rgba8();
+    let (w, h) = (src.width() as f32, src.height() as f32);
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let out_w = (w * cos.abs() + h * sin.abs()).round().max(1.0) as u32;
+    let out_h = (w * sin.abs() + h * cos.abs()).round().max(1.0) as u32;
+    let (src_cx, src_cy) = (w / 2.0, h / 2.0);
+    let (dst_cx, dst_cy) = (out_w as f32 / 2.0, out_h as f32 / 2.0);
+
+    let out = RgbaImage::from_fn(out_w, out_h, |x, y| {
+        // Inverse-rotate the destination pixel centre back into source space.
+        let dx = x as f32 + 0.5 - dst_cx;
+        let dy = y as f32 + 0.5 - dst_cy;
+        let sx = dx * cos + dy * sin + src_cx - 0.5;
+        let sy = -dx * sin + dy * cos + src_cy - 0.5;
+        sample_bilinear(&src, sx, sy, fill)
+    });
+    DynamicImage::ImageRgba8(out)
+}
+
+/// Bilinear sample at `(x, y)` in pixel-centre coordinates; texels outside the image read as
+/// `fill` so rotated edges blend into the background instead of aliasing.
+fn sample_bilinear(src: &RgbaImage, x: f32, y: f32, fill: Rgba<u8>) -> Rgba<u8> {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    // Interpolate premultiplied colour so transparent fill does not darken the edges.
+    let texel = |tx: f32, ty: f32| -> [f32; 4] {
+        let p = if tx < 0.0 || ty < 0.0 || tx >= src.width() as f32 || ty >= src.height() as f32 {
+            fill
+        } else {
+            *src.get_pixel(tx as u32, ty as u32)
+        };
+        let a = p[3] as f32 / 255.0;
+        [
+            p[0] as f32 * a,
+            p[1] as f32 * a,
+            p[2] as f32 * a,
+            p[3] as f32,
+        ]
+    };
+    let (p00, p10) = (texel(x0, y0), texel(x0 + 1.0, y0));
+    let (p01, p11) = (texel(x0, y0 + 1.0), texel(x0 + 1.0, y0 + 1.0));
+
+    let mut mixed = [0f32; 4];
+    for c in 0..4 {
+        let top = p00[c] + (p10[c] - p00[c]) * fx;
+        let bottom = p01[c] + (p11[c] - p01[c]) * fx;
+        mixed[c] = top + (bottom - top) * fy;
+    }
+    let alpha = mixed[3] / 255.0;
+    let unpremultiply = |v: f32| {
+        if alpha > 0.0 {
+            (v / alpha).round().clamp(0.0, 255.0) as u8
+        } else {
+            0
+        }
+    };
+    Rgba([
+        unpremultiply(mixed[0]),
+        unpremultiply(mixed[1]),
+        unpremultiply(mixed[2]),
+        mixed[3].round().clamp(0.0, 255.0) as u8,
+    ])
+}
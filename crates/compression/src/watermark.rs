@@ -0,0 +1,144 @@
+//! Logo / text watermark compositing, applied after the transforms and before encoding.
+
+use ab_glyph::{point, Font, FontRef, Glyph, PxScale, ScaleFont};
+use anyhow::{anyhow, Result};
+use domain::{Watermark, WatermarkKind};
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, Rgba, RgbaImage};
+
+/// Font used for text watermarks (Ubuntu Light, Ubuntu Font Licence).
+const TEXT_FONT: &[u8] = epaint_default_fonts::UBUNTU_LIGHT;
+/// Size at which text is measured before being rendered at its final size.
+const MEASURE_PX: f32 = 100.0;
+
+pub(crate) fn apply_watermark(base: DynamicImage, watermark: &Watermark) -> Result<DynamicImage> {
+    let (base_w, base_h) = (base.width(), base.height());
+    let target_w = ((base_w as f32 * watermark.scale.clamp(0.01, 1.0)).round() as u32).max(1);
+
+    let mut overlay = match &watermark.kind {
+        WatermarkKind::Image { data } => {
+            let logo = super::decode_dynamic_image(data)
+                .map_err(|e| anyhow!("Failed to decode watermark image: {e}"))?
+                .to_rgba8();
+            let target_h = scaled_height(logo.width(), logo.height(), target_w);
+            imageops::resize(&logo, target_w, target_h, FilterType::Lanczos3)
+        }
+        WatermarkKind::Text { text, color } => {
+            let color = domain::parse_hex_color(color)
+                .ok_or_else(|| anyhow!("Invalid watermark colour '{color}'"))?;
+            render_text(text, Rgba(color), target_w)?
+        }
+    };
+
+    // Never larger than the base image.
+    if overlay.width() > base_w || overlay.height() > base_h {
+        let factor =
+            (base_w as f32 / overlay.width() as f32).min(base_h as f32 / overlay.height() as f32);
+        let w = ((overlay.width() as f32 * factor) as u32).max(1);
+        let h = ((overlay.height() as f32 * factor) as u32).max(1);
+        overlay = imageops::resize(&overlay, w, h, FilterType::Lanczos3);
+    }
+
+    let opacity = watermark.opacity.clamp(0.0, 1.0);
+    for pixel in overlay.pixels_mut() {
+        pixel[3] = (pixel[3] as f32 * opacity).round() as u8;
+    }
+
+    let had_alpha = base.color().has_alpha();
+    let mut canvas = base.to_rgba8();
+    let (overlay_w, overlay_h) = overlay.dimensions();
+    // More margin than the image only pushes the mark off the canvas.
+    let margin = watermark.margin.min(base_w.max(base_h));
+
+    if watermark.tile {
+        let mut y = margin;
+        while y < base_h {
+            let mut x = margin;
+            while x < base_w {
+                imageops::overlay(&mut canvas, &overlay, x as i64, y as i64);
+                x = x.saturating_add(overlay_w).saturating_add(margin);
+            }
+            y = y.saturating_add(overlay_h).saturating_add(margin);
+        }
+    } else {
+        let inner = (
+            base_w.saturating_sub(margin.saturating_mul(2)),
+            base_h.saturating_sub(margin.saturating_mul(2)),
+        );
+        let (x, y) = watermark.gravity.offset(inner, (overlay_w, overlay_h));
+        imageops::overlay(
+            &mut canvas,
+            &overlay,
+            x as i64 + margin as i64,
+            y as i64 + margin as i64,
+        );
+    }
+
+    Ok(if had_alpha {
+        DynamicImage::ImageRgba8(canvas)
+    } else {
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(canvas).to_rgb8())
+    })
+}
+
+fn scaled_height(width: u32, height: u32, target_w: u32) -> u32 {
+    ((height as f32 * target_w as f32 / width as f32).round() as u32).max(1)
+}
+
+/// Render one line of text so that it is about `target_w` pixels wide.
+fn render_text(text: &str, color: Rgba<u8>, target_w: u32) -> Result<RgbaImage> {
+    let font = FontRef::try_from_slice(TEXT_FONT)
+        .map_err(|e| anyhow!("Failed to load watermark font: {e}"))?;
+
+    let (measured_w, _) = layout(&font, text, PxScale::from(MEASURE_PX));
+    if measured_w <= 0.0 {
+        return Err(anyhow!("Watermark text is empty"));
+    }
+    let px = (MEASURE_PX * target_w as f32 / measured_w).max(4.0);
+    let scale = PxScale::from(px);
+    let (width, glyphs) = layout(&font, text, scale);
+
+    let scaled = font.as_scaled(scale);
+    let height = (scaled.ascent() - scaled.descent()).ceil().max(1.0) as u32;
+    let mut img = RgbaImage::from_pixel(
+        width.ceil().max(1.0) as u32,
+        height,
+        Rgba([color[0], color[1], color[2], 0]),
+    );
+
+    for glyph in glyphs {
+        let Some(outlined) = font.outline_glyph(glyph) else {
+            continue;
+        };
+        let bounds = outlined.px_bounds();
+        outlined.draw(|gx, gy, coverage| {
+            let x = bounds.min.x as i32 + gx as i32;
+            let y = bounds.min.y as i32 + gy as i32;
+            if x < 0 || y < 0 || x >= img.width() as i32 || y >= img.height() as i32 {
+                return;
+            }
+            let pixel = img.get_pixel_mut(x as u32, y as u32);
+            let alpha = (coverage.clamp(0.0, 1.0) * color[3] as f32).round() as u8;
+            pixel[3] = pixel[3].max(alpha);
+        });
+    }
+    Ok(img)
+}
+
+/// Position glyphs on a single baseline; returns the advance width and the glyphs.
+fn layout(font: &FontRef<'_>, text: &str, scale: PxScale) -> (f32, Vec<Glyph>) {
+    let scaled = font.as_scaled(scale);
+    let mut caret = 0.0;
+    let mut previous = None;
+    let mut glyphs = Vec::new();
+    for c in text.chars().filter(|c| !c.is_control()) {
+        let id = scaled.glyph_id(c);
+        if let Some(prev) = previous {
+            caret += scaled.kern(prev, id);
+        }
+        glyphs.push(id.with_scale_and_position(scale, point(caret, scaled.ascent())));
+        caret += scaled.h_advance(id);
+        previous = Some(id);
+    }
+    (caret, glyphs)
+}
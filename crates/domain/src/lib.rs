@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
 
-/// Compression options for image processing
+/// Compression options for image processing. Missing fields take their [`Default`] values, so
+/// presets only need to list what they change.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct CompressionOptions {
     pub png_lossy: bool,
     pub png_quality: String,
@@ -13,6 +15,458 @@ pub struct CompressionOptions {
     pub to_tiff: bool,
     pub to_bmp: bool,
     pub to_ico: bool,
+    #[serde(default)]
+    pub to_jxl: bool,
+    /// JPEG XL only: recompress JPEG input losslessly (bit-exact reversible) instead of re-encoding pixels.
+    #[serde(default)]
+    pub jxl_lossless_jpeg: bool,
+    /// Compression used when writing TIFF output.
+    #[serde(default)]
+    pub tiff_compression: TiffCompression,
+    /// Multi-page input converted to a single-page format yields one output per page.
+    #[serde(default)]
+    pub split_pages: bool,
+    /// Geometric operations applied in order before encoding.
+    #[serde(default)]
+    pub transforms: Vec<Transform>,
+    /// Ordered dithering when 16-bit input has to be reduced to 8 bits for the output format.
+    #[serde(default)]
+    pub dither: bool,
+    /// What to do with an embedded ICC profile when decoding.
+    #[serde(default)]
+    pub color_profile: ColorProfile,
+    /// Colour corrections applied after the transforms, before the watermark.
+    #[serde(default)]
+    pub adjustments: Adjustments,
+    /// Logo or text composited onto the image after the transforms.
+    #[serde(default)]
+    pub watermark: Option<Watermark>,
+    /// Output name template such as `{stem}-{width}w.{ext}` (see `naming::TEMPLATE_TOKENS`);
+    /// `None` means `{stem}.{ext}`. Only affects suggested filenames, not the encoded bytes.
+    #[serde(default)]
+    pub filename_template: Option<String>,
+    /// 0–100 encoder quality for every lossy format, instead of the level's row of the quality
+    /// table. PNG quantizes within the 30 points below it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quality: Option<u8>,
+    /// Per-format quality for each level; `None` uses [`QualityTable::default`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quality_table: Option<QualityTable>,
+}
+
+/// Anchor used to position a crop window (or overlay) inside the image
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Gravity {
+    #[default]
+    Center,
+    North,
+    South,
+    East,
+    West,
+    NorthEast,
+    NorthWest,
+    SouthEast,
+    SouthWest,
+}
+
+impl Gravity {
+    /// Parse "center", "north", "ne", "south-west", …; unknown values are `None`
+    pub fn parse(s: &str) -> Option<Self> {
+        let key: String = s
+            .trim()
+            .to_lowercase()
+            .chars()
+            .filter(|c| c.is_ascii_alphabetic())
+            .collect();
+        Some(match key.as_str() {
+            "center" | "centre" | "c" => Gravity::Center,
+            "north" | "top" | "n" => Gravity::North,
+            "south" | "bottom" | "s" => Gravity::South,
+            "east" | "right" | "e" => Gravity::East,
+            "west" | "left" | "w" => Gravity::West,
+            "northeast" | "topright" | "ne" => Gravity::NorthEast,
+            "northwest" | "topleft" | "nw" => Gravity::NorthWest,
+            "southeast" | "bottomright" | "se" => Gravity::SouthEast,
+            "southwest" | "bottomleft" | "sw" => Gravity::SouthWest,
+            _ => return None,
+        })
+    }
+
+    /// Top-left offset of an `inner` box placed inside an `outer` box
+    pub fn offset(self, outer: (u32, u32), inner: (u32, u32)) -> (u32, u32) {
+        let free_w = outer.0.saturating_sub(inner.0);
+        let free_h = outer.1.saturating_sub(inner.1);
+        let x = match self {
+            Gravity::West | Gravity::NorthWest | Gravity::SouthWest => 0,
+            Gravity::East | Gravity::NorthEast | Gravity::SouthEast => free_w,
+            _ => free_w / 2,
+        };
+        let y = match self {
+            Gravity::North | Gravity::NorthEast | Gravity::NorthWest => 0,
+            Gravity::South | Gravity::SouthEast | Gravity::SouthWest => free_h,
+            _ => free_h / 2,
+        };
+        (x, y)
+    }
+}
+
+/// Geometric transform applied before encoding
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Transform {
+    /// Clockwise rotation. Multiples of 90° are lossless; other angles enlarge the canvas and
+    /// paint uncovered corners with `fill` (`#RRGGBB[AA]`, transparent when unset).
+    Rotate {
+        degrees: f32,
+        #[serde(default)]
+        fill: Option<String>,
+    },
+    FlipHorizontal,
+    FlipVertical,
+    /// Rectangle crop in pixels; a rectangle reaching past the edge is clipped to the image
+    Crop {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
+    /// Largest crop with the given aspect ratio, positioned by `gravity`
+    AspectCrop {
+        width: u32,
+        height: u32,
+        #[serde(default)]
+        gravity: Gravity,
+    },
+    /// Crop away uniform-colour or fully transparent borders. A pixel counts as border when
+    /// every channel is within `tolerance` of the top-left pixel; `padding` pixels of border
+    /// are kept around the content.
+    Trim {
+        #[serde(default = "default_trim_tolerance")]
+        tolerance: u8,
+        #[serde(default)]
+        padding: u32,
+    },
+    /// Scale down to fit inside `width`×`height`, keeping the aspect ratio; a missing side is
+    /// unconstrained. Images that already fit are left alone.
+    Resize {
+        #[serde(default)]
+        width: Option<u32>,
+        #[serde(default)]
+        height: Option<u32>,
+    },
+}
+
+fn default_trim_tolerance() -> u8 {
+    8
+}
+
+/// What a [`Watermark`] draws
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum WatermarkKind {
+    /// Encoded overlay image, typically a PNG with alpha
+    Image { data: Vec<u8> },
+    /// Single line of text in `color` (`#RRGGBB[AA]`)
+    Text {
+        text: String,
+        #[serde(default = "default_watermark_color")]
+        color: String,
+    },
+}
+
+/// Overlay composited onto every output before encoding
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Watermark {
+    pub kind: WatermarkKind,
+    #[serde(default = "default_watermark_gravity")]
+    pub gravity: Gravity,
+    /// Distance from the anchored edges (and between tiles), in pixels
+    #[serde(default = "default_watermark_margin")]
+    pub margin: u32,
+    /// Overlay width as a fraction of the base image width
+    #[serde(default = "default_watermark_scale")]
+    pub scale: f32,
+    /// 0.0 (invisible) to 1.0 (as drawn)
+    #[serde(default = "default_watermark_opacity")]
+    pub opacity: f32,
+    /// Repeat the overlay across the whole image instead of placing it once
+    #[serde(default)]
+    pub tile: bool,
+}
+
+impl Watermark {
+    /// Watermark with the default placement: bottom-right, 16px margin, 25% wide, 50% opacity.
+    pub fn new(kind: WatermarkKind) -> Self {
+        Self {
+            kind,
+            gravity: default_watermark_gravity(),
+            margin: default_watermark_margin(),
+            scale: default_watermark_scale(),
+            opacity: default_watermark_opacity(),
+            tile: false,
+        }
+    }
+}
+
+fn default_watermark_color() -> String {
+    "#ffffff".to_string()
+}
+
+fn default_watermark_gravity() -> Gravity {
+    Gravity::SouthEast
+}
+
+fn default_watermark_margin() -> u32 {
+    16
+}
+
+fn default_watermark_scale() -> f32 {
+    0.25
+}
+
+fn default_watermark_opacity() -> f32 {
+    0.5
+}
+
+/// Colour corrections. They run in field order: denoise, white balance, auto-levels, then
+/// brightness/contrast/gamma/saturation in one pass, then sharpening.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Adjustments {
+    /// Light 3×3 median filter
+    pub denoise: bool,
+    /// Gray-world white balance
+    pub white_balance: bool,
+    /// Stretch the tonal range to full black/white, clipping 0.5% at each end
+    pub auto_levels: bool,
+    /// -1.0 to 1.0; 0 leaves the image unchanged
+    pub brightness: f32,
+    /// -1.0 to 1.0; 0 leaves the image unchanged
+    pub contrast: f32,
+    /// 0.1 to 10.0; above 1.0 brightens the midtones
+    pub gamma: f32,
+    /// -1.0 (grayscale) to 1.0; 0 leaves the image unchanged
+    pub saturation: f32,
+    /// Unsharp mask amount, 0.0 to 5.0; 0 disables sharpening
+    pub sharpen: f32,
+    /// Unsharp mask blur radius (Gaussian sigma) in pixels
+    pub sharpen_radius: f32,
+}
+
+impl Default for Adjustments {
+    fn default() -> Self {
+        Self {
+            denoise: false,
+            white_balance: false,
+            auto_levels: false,
+            brightness: 0.0,
+            contrast: 0.0,
+            gamma: 1.0,
+            saturation: 0.0,
+            sharpen: 0.0,
+            sharpen_radius: 1.0,
+        }
+    }
+}
+
+impl Adjustments {
+    /// Form / query field names accepted by [`Adjustments::set_field`]
+    pub const FIELDS: [&'static str; 9] = [
+        "denoise",
+        "white_balance",
+        "auto_levels",
+        "brightness",
+        "contrast",
+        "gamma",
+        "saturation",
+        "sharpen",
+        "sharpen_radius",
+    ];
+
+    /// True when no correction would change any pixel
+    pub fn is_identity(&self) -> bool {
+        !self.denoise
+            && !self.white_balance
+            && !self.auto_levels
+            && self.brightness == 0.0
+            && self.contrast == 0.0
+            && self.gamma == 1.0
+            && self.saturation == 0.0
+            && self.sharpen <= 0.0
+    }
+
+    /// Set one field from its string form (see [`Adjustments::FIELDS`])
+    pub fn set_field(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let value = value.trim();
+        let invalid = || format!("Invalid {} '{}'", name, value);
+        let flag = || value.parse::<bool>().map_err(|_| invalid());
+        let number = || {
+            value
+                .parse::<f32>()
+                .ok()
+                .filter(|v| v.is_finite())
+                .ok_or_else(invalid)
+        };
+        match name {
+            "denoise" => self.denoise = flag()?,
+            "white_balance" => self.white_balance = flag()?,
+            "auto_levels" => self.auto_levels = flag()?,
+            "brightness" => self.brightness = number()?,
+            "contrast" => self.contrast = number()?,
+            "gamma" => self.gamma = number()?,
+            "saturation" => self.saturation = number()?,
+            "sharpen" => self.sharpen = number()?,
+            "sharpen_radius" => self.sharpen_radius = number()?,
+            _ => return Err(format!("Unknown adjustment '{}'", name)),
+        }
+        Ok(())
+    }
+}
+
+/// Content bounds found by [`Transform::Trim`], in pixels of the image entering the trim step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrimRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Largest `trim` padding [`parse_transforms`] accepts. Trimming also caps the padding at the
+/// image's larger side, since more only adds border.
+pub const MAX_TRIM_PADDING: u32 = 10_000;
+
+/// Parse a transform list: either a JSON array of [`Transform`] objects or the compact form
+/// `rotate:90,flip:h,crop:x:y:w:h,aspect:16:9:north,trim:8:4,resize:800:600` (`rotate:12.5:#ffffff`
+/// sets the fill; `trim` takes an optional tolerance and padding; `resize:800` and `resize::600`
+/// bound one side only).
+pub fn parse_transforms(spec: &str) -> Result<Vec<Transform>, String> {
+    let spec = spec.trim();
+    if spec.is_empty() {
+        return Ok(Vec::new());
+    }
+    let transforms: Vec<Transform> = if spec.starts_with('[') {
+        serde_json::from_str(spec).map_err(|e| format!("Invalid transforms JSON: {}", e))?
+    } else {
+        spec.split(',')
+            .map(str::trim)
+            .filter(|op| !op.is_empty())
+            .map(parse_transform)
+            .collect::<Result<_, _>>()?
+    };
+    for transform in &transforms {
+        if let Transform::Trim { padding, .. } = transform {
+            if *padding > MAX_TRIM_PADDING {
+                return Err(format!(
+                    "Trim padding {} is above {}",
+                    padding, MAX_TRIM_PADDING
+                ));
+            }
+        }
+    }
+    Ok(transforms)
+}
+
+fn parse_transform(op: &str) -> Result<Transform, String> {
+    let parts: Vec<&str> = op.split(':').map(str::trim).collect();
+    let invalid = || format!("Invalid transform '{}'", op);
+    let num = |i: usize| -> Result<u32, String> {
+        parts
+            .get(i)
+            .and_then(|p| p.parse().ok())
+            .ok_or_else(invalid)
+    };
+
+    match parts[0].to_lowercase().as_str() {
+        "rotate" => Ok(Transform::Rotate {
+            degrees: parts
+                .get(1)
+                .and_then(|p| p.parse().ok())
+                .ok_or_else(invalid)?,
+            fill: parts.get(2).map(|f| f.to_string()),
+        }),
+        "fliph" => Ok(Transform::FlipHorizontal),
+        "flipv" => Ok(Transform::FlipVertical),
+        "flip" => match parts.get(1).map(|p| p.to_lowercase()).as_deref() {
+            Some("h") | Some("horizontal") => Ok(Transform::FlipHorizontal),
+            Some("v") | Some("vertical") => Ok(Transform::FlipVertical),
+            _ => Err(invalid()),
+        },
+        "crop" => Ok(Transform::Crop {
+            x: num(1)?,
+            y: num(2)?,
+            width: num(3)?,
+            height: num(4)?,
+        }),
+        "aspect" => Ok(Transform::AspectCrop {
+            width: num(1)?,
+            height: num(2)?,
+            gravity: match parts.get(3) {
+                Some(g) => Gravity::parse(g).ok_or_else(invalid)?,
+                None => Gravity::Center,
+            },
+        }),
+        "trim" => Ok(Transform::Trim {
+            tolerance: match parts.get(1) {
+                Some(t) => t.parse().map_err(|_| invalid())?,
+                None => default_trim_tolerance(),
+            },
+            padding: match parts.get(2) {
+                Some(p) => p.parse().map_err(|_| invalid())?,
+                None => 0,
+            },
+        }),
+        "resize" => {
+            let side = |i: usize| -> Result<Option<u32>, String> {
+                match parts.get(i).filter(|p| !p.is_empty()) {
+                    Some(p) => p
+                        .parse()
+                        .ok()
+                        .filter(|v| *v > 0)
+                        .map(Some)
+                        .ok_or_else(invalid),
+                    None => Ok(None),
+                }
+            };
+            let (width, height) = (side(1)?, side(2)?);
+            if width.is_none() && height.is_none() {
+                return Err(invalid());
+            }
+            Ok(Transform::Resize { width, height })
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Parse `#RGB`, `#RRGGBB` or `#RRGGBBAA` (leading `#` optional) into RGBA
+pub fn parse_hex_color(s: &str) -> Option<[u8; 4]> {
+    let hex = s.trim().trim_start_matches('#');
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    match hex.len() {
+        3 => {
+            let mut rgba = [255u8; 4];
+            for (i, c) in hex.chars().enumerate() {
+                let v = c.to_digit(16)? as u8;
+                rgba[i] = v * 17;
+            }
+            Some(rgba)
+        }
+        6 => Some([channel(0)?, channel(2)?, channel(4)?, 255]),
+        8 => Some([channel(0)?, channel(2)?, channel(4)?, channel(6)?]),
+        _ => None,
+    }
+}
+
+/// TIFF output compression scheme (all lossless)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TiffCompression {
+    None,
+    #[default]
+    Lzw,
+    Deflate,
+    PackBits,
 }
 
 impl Default for CompressionOptions {
@@ -28,6 +482,80 @@ impl Default for CompressionOptions {
             to_tiff: false,
             to_bmp: false,
             to_ico: false,
+            to_jxl: false,
+            jxl_lossless_jpeg: false,
+            tiff_compression: TiffCompression::default(),
+            split_pages: false,
+            dither: false,
+            color_profile: ColorProfile::default(),
+            transforms: Vec::new(),
+            adjustments: Adjustments::default(),
+            watermark: None,
+            filename_template: None,
+            quality: None,
+            quality_table: None,
+        }
+    }
+}
+
+impl CompressionOptions {
+    /// Encoder quality for `format`: `quality` when set, otherwise the quality table's entry
+    /// for the level `png_quality` names. A custom `png_quality` range gives its midpoint.
+    pub fn encoder_quality(&self, format: LossyFormat) -> u8 {
+        if let Some(quality) = self.quality {
+            return quality.min(100);
+        }
+        match CompressionLevel::from_range(&self.png_quality) {
+            Some(level) => self
+                .quality_table
+                .unwrap_or_default()
+                .row(level)
+                .get(format),
+            None => {
+                let (min, max) = parse_quality_range(&self.png_quality);
+                ((min as u16 + max as u16) / 2) as u8
+            }
+        }
+    }
+
+    /// Quality range for PNG quantization: `png_quality`, or the 30 points up to `quality`.
+    pub fn png_quality_range(&self) -> String {
+        match self.quality {
+            Some(quality) => {
+                let max = quality.min(100);
+                format!("{}-{}", max.saturating_sub(30), max)
+            }
+            None => self.png_quality.clone(),
+        }
+    }
+
+    /// `output_format` strings [`CompressionOptions::set_output_format`] knows
+    pub const OUTPUT_FORMATS: [&'static str; 9] = [
+        "original", "webp", "avif", "jpeg", "png", "tiff", "bmp", "ico", "jxl",
+    ];
+
+    /// Set the output container from a UI / desktop `output_format` string (`webp`, `jpeg`,
+    /// `original`, …), turning every other `to_*` flag off. Unknown formats mean WebP.
+    pub fn set_output_format(&mut self, output_format: &str) {
+        self.to_webp = false;
+        self.to_avif = false;
+        self.to_jpeg = false;
+        self.to_png = false;
+        self.to_tiff = false;
+        self.to_bmp = false;
+        self.to_ico = false;
+        self.to_jxl = false;
+        match output_format.trim().to_lowercase().as_str() {
+            "webp" => self.to_webp = true,
+            "avif" => self.to_avif = true,
+            "jpeg" => self.to_jpeg = true,
+            "png" => self.to_png = true,
+            "tiff" => self.to_tiff = true,
+            "bmp" => self.to_bmp = true,
+            "ico" => self.to_ico = true,
+            "jxl" => self.to_jxl = true,
+            "original" => {}
+            _ => self.to_webp = true,
         }
     }
 }
@@ -46,13 +574,327 @@ pub fn parse_quality_range(s: &str) -> (u8, u8) {
     (min, max)
 }
 
+/// Page size for PDF export
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PdfPageSize {
+    /// Page matches the image (1 px = 1 pt), plus margins
+    #[default]
+    Fit,
+    A4,
+    Letter,
+}
+
+/// How images are stored inside an exported PDF
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PdfImageEncoding {
+    /// JPEG images are embedded as-is (DCT); everything else is stored losslessly (Flate)
+    #[default]
+    Auto,
+    /// Re-encode non-JPEG images as JPEG (DCT) at `jpeg_quality`
+    Jpeg,
+    /// Store every image losslessly (Flate)
+    Flate,
+}
+
+/// PDF export options (one image per page)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PdfOptions {
+    #[serde(default)]
+    pub page_size: PdfPageSize,
+    /// Margin on every side, in millimetres (0 to [`MAX_PDF_MARGIN_MM`])
+    #[serde(default)]
+    pub margin_mm: f32,
+    #[serde(default)]
+    pub image_encoding: PdfImageEncoding,
+    #[serde(default = "default_pdf_jpeg_quality")]
+    pub jpeg_quality: u8,
+}
+
+fn default_pdf_jpeg_quality() -> u8 {
+    85
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self {
+            page_size: PdfPageSize::default(),
+            margin_mm: 0.0,
+            image_encoding: PdfImageEncoding::default(),
+            jpeg_quality: default_pdf_jpeg_quality(),
+        }
+    }
+}
+
+/// Largest PDF page margin, in millimetres
+pub const MAX_PDF_MARGIN_MM: f32 = 100.0;
+
+impl PdfOptions {
+    /// `margin_mm` limited to 0–[`MAX_PDF_MARGIN_MM`]; a non-finite margin means none
+    pub fn clamped_margin_mm(&self) -> f32 {
+        if self.margin_mm.is_finite() {
+            self.margin_mm.clamp(0.0, MAX_PDF_MARGIN_MM)
+        } else {
+            0.0
+        }
+    }
+}
+
+impl PdfPageSize {
+    /// Parse a page size name ("fit", "a4", "letter")
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "fit" => Some(Self::Fit),
+            "a4" => Some(Self::A4),
+            "letter" => Some(Self::Letter),
+            _ => None,
+        }
+    }
+}
+
+impl PdfImageEncoding {
+    /// Parse an image encoding name ("auto", "jpeg", "flate")
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "auto" => Some(Self::Auto),
+            "jpeg" | "jpg" | "dct" => Some(Self::Jpeg),
+            "flate" | "lossless" => Some(Self::Flate),
+            _ => None,
+        }
+    }
+}
+
+/// Parse a PDF page size name ("fit", "a4", "letter"); unknown values fit the page to the image
+pub fn parse_pdf_page_size(s: &str) -> PdfPageSize {
+    PdfPageSize::parse(s).unwrap_or_default()
+}
+
+/// Parse a PDF image encoding name ("auto", "jpeg", "flate"); unknown values use auto
+pub fn parse_pdf_image_encoding(s: &str) -> PdfImageEncoding {
+    PdfImageEncoding::parse(s).unwrap_or_default()
+}
+
+/// Handling of an embedded ICC profile (Display P3, Adobe RGB, …)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorProfile {
+    /// Convert the pixels to sRGB; the output carries no profile
+    #[default]
+    Srgb,
+    /// Keep the pixel values and embed the source profile. Only JPEG and PNG output can carry
+    /// it; other formats are converted to sRGB instead.
+    Keep,
+    /// Drop the profile and keep the numbers unchanged
+    Ignore,
+}
+
+impl ColorProfile {
+    /// Parse a colour profile mode ("srgb", "keep", "ignore")
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "srgb" => Some(Self::Srgb),
+            "keep" | "embed" | "preserve" => Some(Self::Keep),
+            "ignore" | "none" | "strip" => Some(Self::Ignore),
+            _ => None,
+        }
+    }
+}
+
+/// Parse a colour profile mode ("srgb", "keep", "ignore"); unknown values convert to sRGB
+pub fn parse_color_profile(s: &str) -> ColorProfile {
+    ColorProfile::parse(s).unwrap_or_default()
+}
+
+impl TiffCompression {
+    /// Parse a TIFF compression name ("none", "lzw", "deflate", "packbits")
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "none" | "uncompressed" => Some(Self::None),
+            "lzw" => Some(Self::Lzw),
+            "deflate" | "zip" => Some(Self::Deflate),
+            "packbits" => Some(Self::PackBits),
+            _ => None,
+        }
+    }
+}
+
+/// Parse a TIFF compression name ("none", "lzw", "deflate", "packbits"); unknown values use LZW
+pub fn parse_tiff_compression(s: &str) -> TiffCompression {
+    TiffCompression::parse(s).unwrap_or_default()
+}
+
 /// Map compression level (low/mid/max) to quality range
 pub fn compression_level_to_range(level: &str) -> String {
-    match level.to_lowercase().as_str() {
-        "low" => "70-90".to_string(),
-        "mid" => "50-80".to_string(),
-        "max" => "20-60".to_string(),
-        _ => "50-80".to_string(), // Default to mid
+    CompressionLevel::parse(level)
+        .unwrap_or_default()
+        .png_range()
+        .to_string()
+}
+
+/// Compression level picked in the UI. `png_quality` carries it as the level's PNG range.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionLevel {
+    /// Best quality, largest files
+    Low,
+    #[default]
+    Mid,
+    /// Smallest files
+    Max,
+}
+
+impl CompressionLevel {
+    pub const ALL: [CompressionLevel; 3] = [Self::Low, Self::Mid, Self::Max];
+
+    /// Parse a level name ("low", "mid", "max")
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "low" => Some(Self::Low),
+            "mid" => Some(Self::Mid),
+            "max" => Some(Self::Max),
+            _ => None,
+        }
+    }
+
+    /// PNG quantization range for the level
+    pub fn png_range(self) -> &'static str {
+        match self {
+            Self::Low => "70-90",
+            Self::Mid => "50-80",
+            Self::Max => "20-60",
+        }
+    }
+
+    /// The level whose PNG range is `range`; `None` for a custom range
+    pub fn from_range(range: &str) -> Option<Self> {
+        let range = parse_quality_range(range);
+        Self::ALL
+            .into_iter()
+            .find(|level| parse_quality_range(level.png_range()) == range)
+    }
+}
+
+/// Output formats whose encoder takes a 0–100 quality
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LossyFormat {
+    Jpeg,
+    Webp,
+    Avif,
+    Jxl,
+}
+
+/// Encoder quality of each lossy format at one compression level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FormatQuality {
+    pub jpeg: u8,
+    pub webp: u8,
+    pub avif: u8,
+    pub jxl: u8,
+}
+
+impl FormatQuality {
+    pub fn get(&self, format: LossyFormat) -> u8 {
+        match format {
+            LossyFormat::Jpeg => self.jpeg,
+            LossyFormat::Webp => self.webp,
+            LossyFormat::Avif => self.avif,
+            LossyFormat::Jxl => self.jxl,
+        }
+    }
+}
+
+/// Encoder quality per compression level and format. The scales differ because the encoders
+/// do: AVIF at 60 looks about like JPEG at 80. Written as JSON or TOML, a table only needs the
+/// entries it changes (`{"max": {"avif": 35}}`); the rest keep the defaults.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "QualityTableOverrides")]
+pub struct QualityTable {
+    pub low: FormatQuality,
+    pub mid: FormatQuality,
+    pub max: FormatQuality,
+}
+
+impl Default for QualityTable {
+    fn default() -> Self {
+        Self {
+            low: FormatQuality {
+                jpeg: 88,
+                webp: 85,
+                avif: 75,
+                jxl: 90,
+            },
+            mid: FormatQuality {
+                jpeg: 78,
+                webp: 75,
+                avif: 60,
+                jxl: 80,
+            },
+            max: FormatQuality {
+                jpeg: 62,
+                webp: 55,
+                avif: 42,
+                jxl: 65,
+            },
+        }
+    }
+}
+
+impl QualityTable {
+    pub fn row(&self, level: CompressionLevel) -> &FormatQuality {
+        match level {
+            CompressionLevel::Low => &self.low,
+            CompressionLevel::Mid => &self.mid,
+            CompressionLevel::Max => &self.max,
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct QualityTableOverrides {
+    low: FormatQualityOverrides,
+    mid: FormatQualityOverrides,
+    max: FormatQualityOverrides,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FormatQualityOverrides {
+    jpeg: Option<u8>,
+    webp: Option<u8>,
+    avif: Option<u8>,
+    jxl: Option<u8>,
+}
+
+impl FormatQualityOverrides {
+    fn apply(self, row: &mut FormatQuality) -> Result<(), String> {
+        for (value, target) in [
+            (self.jpeg, &mut row.jpeg),
+            (self.webp, &mut row.webp),
+            (self.avif, &mut row.avif),
+            (self.jxl, &mut row.jxl),
+        ] {
+            if let Some(value) = value {
+                if value > 100 {
+                    return Err(format!("Quality {} is above 100", value));
+                }
+                *target = value;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<QualityTableOverrides> for QualityTable {
+    type Error = String;
+
+    fn try_from(overrides: QualityTableOverrides) -> Result<Self, String> {
+        let mut table = Self::default();
+        overrides.low.apply(&mut table.low)?;
+        overrides.mid.apply(&mut table.mid)?;
+        overrides.max.apply(&mut table.max)?;
+        Ok(table)
     }
 }
 
@@ -74,4 +916,204 @@ mod tests {
         assert_eq!(compression_level_to_range("max"), "20-60");
         assert_eq!(compression_level_to_range("unknown"), "50-80");
     }
+
+    #[test]
+    fn encoder_quality_follows_level_table_and_override() {
+        let mut options = CompressionOptions::default();
+        let mid = *QualityTable::default().row(CompressionLevel::Mid);
+        assert_eq!(options.encoder_quality(LossyFormat::Jpeg), mid.jpeg);
+        assert_ne!(mid.jpeg, mid.avif);
+
+        options.png_quality = compression_level_to_range("max");
+        options.quality_table = Some(serde_json::from_str(r#"{"max": {"avif": 30}}"#).unwrap());
+        assert_eq!(options.encoder_quality(LossyFormat::Avif), 30);
+        assert_eq!(
+            options.encoder_quality(LossyFormat::Webp),
+            QualityTable::default().max.webp
+        );
+
+        // A custom range keeps using its midpoint; an explicit quality beats both.
+        options.png_quality = "40-90".to_string();
+        assert_eq!(options.encoder_quality(LossyFormat::Webp), 65);
+        options.quality = Some(93);
+        assert_eq!(options.encoder_quality(LossyFormat::Jpeg), 93);
+        assert_eq!(options.png_quality_range(), "63-93");
+
+        assert!(serde_json::from_str::<QualityTable>(r#"{"low": {"jpeg": 101}}"#).is_err());
+        assert!(serde_json::from_str::<QualityTable>(r#"{"mid": {"jpg": 80}}"#).is_err());
+    }
+
+    #[test]
+    fn test_set_output_format() {
+        let mut options = CompressionOptions::default();
+        options.set_output_format("AVIF");
+        assert!(options.to_avif && !options.to_webp);
+        options.set_output_format("original");
+        assert!(!options.to_avif && !options.to_webp);
+        options.set_output_format("bogus");
+        assert!(options.to_webp);
+    }
+
+    #[test]
+    fn test_tiff_compression_parsing() {
+        assert_eq!(parse_tiff_compression("deflate"), TiffCompression::Deflate);
+        assert_eq!(
+            parse_tiff_compression("PackBits"),
+            TiffCompression::PackBits
+        );
+        assert_eq!(parse_tiff_compression("none"), TiffCompression::None);
+        assert_eq!(parse_tiff_compression("bogus"), TiffCompression::Lzw);
+        assert_eq!(TiffCompression::parse("LZW"), Some(TiffCompression::Lzw));
+        assert_eq!(TiffCompression::parse("jpeg"), None);
+    }
+
+    #[test]
+    fn test_color_profile_parsing() {
+        assert_eq!(parse_color_profile("Keep"), ColorProfile::Keep);
+        assert_eq!(parse_color_profile("ignore"), ColorProfile::Ignore);
+        assert_eq!(parse_color_profile(""), ColorProfile::Srgb);
+        assert_eq!(ColorProfile::parse("sRGB"), Some(ColorProfile::Srgb));
+        assert_eq!(ColorProfile::parse("kepe"), None);
+        assert_eq!(
+            CompressionOptions::default().color_profile,
+            ColorProfile::Srgb
+        );
+    }
+
+    #[test]
+    fn test_pdf_option_parsing() {
+        assert_eq!(parse_pdf_page_size("A4"), PdfPageSize::A4);
+        assert_eq!(parse_pdf_page_size("letter"), PdfPageSize::Letter);
+        assert_eq!(parse_pdf_page_size(""), PdfPageSize::Fit);
+        assert_eq!(parse_pdf_image_encoding("flate"), PdfImageEncoding::Flate);
+        assert_eq!(parse_pdf_image_encoding("jpeg"), PdfImageEncoding::Jpeg);
+        assert_eq!(parse_pdf_image_encoding("other"), PdfImageEncoding::Auto);
+        assert_eq!(PdfPageSize::parse("a3"), None);
+        assert_eq!(PdfImageEncoding::parse("png"), None);
+    }
+
+    #[test]
+    fn test_parse_transforms_compact_and_json() {
+        let ops = parse_transforms("rotate:90, flip:h, crop:1:2:30:40, aspect:16:9:ne").unwrap();
+        assert_eq!(
+            ops,
+            vec![
+                Transform::Rotate {
+                    degrees: 90.0,
+                    fill: None
+                },
+                Transform::FlipHorizontal,
+                Transform::Crop {
+                    x: 1,
+                    y: 2,
+                    width: 30,
+                    height: 40
+                },
+                Transform::AspectCrop {
+                    width: 16,
+                    height: 9,
+                    gravity: Gravity::NorthEast
+                },
+            ]
+        );
+
+        let json = r##"[{"op":"rotate","degrees":12.5,"fill":"#fff"},{"op":"flip_vertical"}]"##;
+        assert_eq!(
+            parse_transforms(json).unwrap(),
+            vec![
+                Transform::Rotate {
+                    degrees: 12.5,
+                    fill: Some("#fff".to_string())
+                },
+                Transform::FlipVertical,
+            ]
+        );
+
+        assert_eq!(
+            parse_transforms("trim, trim:20:4").unwrap(),
+            vec![
+                Transform::Trim {
+                    tolerance: 8,
+                    padding: 0
+                },
+                Transform::Trim {
+                    tolerance: 20,
+                    padding: 4
+                },
+            ]
+        );
+        assert!(parse_transforms("trim:8:100000").is_err());
+        assert!(parse_transforms(r#"[{"op":"trim","padding":100000}]"#).is_err());
+        assert_eq!(
+            parse_transforms(r#"[{"op":"trim"}]"#).unwrap(),
+            vec![Transform::Trim {
+                tolerance: 8,
+                padding: 0
+            }]
+        );
+
+        assert_eq!(
+            parse_transforms("resize:800, resize::600").unwrap(),
+            vec![
+                Transform::Resize {
+                    width: Some(800),
+                    height: None
+                },
+                Transform::Resize {
+                    width: None,
+                    height: Some(600)
+                },
+            ]
+        );
+
+        assert!(parse_transforms("").unwrap().is_empty());
+        assert!(parse_transforms("crop:1:2").is_err());
+        assert!(parse_transforms("resize").is_err());
+        assert!(parse_transforms("resize:0:10").is_err());
+        assert!(parse_transforms("explode").is_err());
+    }
+
+    #[test]
+    fn test_watermark_defaults_from_json() {
+        let wm: Watermark =
+            serde_json::from_str(r#"{"kind":{"type":"text","text":"ACME"},"tile":true}"#).unwrap();
+        assert_eq!(
+            wm,
+            Watermark {
+                tile: true,
+                ..Watermark::new(WatermarkKind::Text {
+                    text: "ACME".to_string(),
+                    color: "#ffffff".to_string(),
+                })
+            }
+        );
+        assert_eq!(wm.gravity, Gravity::SouthEast);
+    }
+
+    #[test]
+    fn test_adjustments_set_field_and_identity() {
+        let mut adj = Adjustments::default();
+        assert!(adj.is_identity());
+        adj.set_field("brightness", " 0.2 ").unwrap();
+        adj.set_field("auto_levels", "true").unwrap();
+        assert_eq!(adj.brightness, 0.2);
+        assert!(adj.auto_levels && !adj.is_identity());
+        assert!(adj.set_field("gamma", "bright").is_err());
+        assert!(adj.set_field("vibrance", "1").is_err());
+
+        let parsed: Adjustments = serde_json::from_str(r#"{"sharpen":0.5}"#).unwrap();
+        assert_eq!(parsed.gamma, 1.0);
+        assert_eq!(parsed.sharpen_radius, 1.0);
+    }
+
+    #[test]
+    fn test_gravity_offset_and_hex_colors() {
+        assert_eq!(Gravity::Center.offset((100, 50), (20, 10)), (40, 20));
+        assert_eq!(Gravity::SouthEast.offset((100, 50), (20, 10)), (80, 40));
+        assert_eq!(Gravity::NorthWest.offset((100, 50), (20, 10)), (0, 0));
+        assert_eq!(parse_hex_color("#ff8000"), Some([255, 128, 0, 255]));
+        assert_eq!(parse_hex_color("00000080"), Some([0, 0, 0, 128]));
+        assert_eq!(parse_hex_color("#fff"), Some([255, 255, 255, 255]));
+        assert_eq!(parse_hex_color("nope"), None);
+    }
 }
@@ -0,0 +1,117 @@
+//! Atomic file replacement.
+
+use std::fs::{self, File, Permissions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::SystemTime;
+
+/// Metadata [`write_atomic`] sets on the new file before it replaces the old one.
+#[derive(Debug, Clone, Default)]
+pub struct KeepMetadata {
+    pub modified: Option<SystemTime>,
+    pub permissions: Option<Permissions>,
+}
+
+impl KeepMetadata {
+    /// The modification time and/or permissions of the file at `path`, as selected.
+    pub fn of(path: &Path, times: bool, permissions: bool) -> io::Result<Self> {
+        if !times && !permissions {
+            return Ok(Self::default());
+        }
+        let metadata = fs::metadata(path)?;
+        Ok(Self {
+            modified: if times {
+                metadata.modified().ok()
+            } else {
+                None
+            },
+            permissions: permissions.then(|| metadata.permissions()),
+        })
+    }
+}
+
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Write `data` to `path` through a temporary file in the same folder that is synced and then
+/// renamed over `path`, so readers see either the old or the new file, never a partial one.
+pub fn write_atomic(path: &Path, data: &[u8], keep: &KeepMetadata) -> io::Result<()> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+    let temp = path.with_file_name(format!(
+        ".{}.{}-{}.tmp",
+        name.to_string_lossy(),
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let result = (|| {
+        let mut file = File::create(&temp)?;
+        file.write_all(data)?;
+        if let Some(modified) = keep.modified {
+            file.set_modified(modified)?;
+        }
+        if let Some(permissions) = &keep.permissions {
+            file.set_permissions(permissions.clone())?;
+        }
+        file.sync_all()?;
+        drop(file);
+        fs::rename(&temp, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn replaces_the_file_and_keeps_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.png");
+        fs::write(&path, b"old").unwrap();
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, Permissions::from_mode(0o640)).unwrap();
+        }
+
+        let keep = KeepMetadata::of(&path, true, true).unwrap();
+        write_atomic(&path, b"new", &keep).unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        let metadata = fs::metadata(&path).unwrap();
+        assert_eq!(metadata.modified().unwrap(), modified);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(metadata.permissions().mode() & 0o777, 0o640);
+        }
+        // Only the target is left behind.
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn failed_writes_leave_no_temp_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("folder");
+        fs::create_dir(&target).unwrap();
+        fs::write(target.join("inside"), b"x").unwrap();
+
+        // Renaming a file over a non-empty directory fails.
+        assert!(write_atomic(&target, b"data", &KeepMetadata::default()).is_err());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
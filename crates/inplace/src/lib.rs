@@ -0,0 +1,8 @@
+//! Writing compressed outputs back where their inputs are: a timestamped backup of every
+//! original, atomic temp-file-plus-rename writes and undo of the last run.
+
+mod atomic;
+mod run;
+
+pub use atomic::{write_atomic, KeepMetadata};
+pub use run::{optimize_in_place, undo_last_run, InPlaceFile, InPlaceOptions, RunSummary};
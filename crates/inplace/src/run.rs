@@ -0,0 +1,499 @@
+//! In-place runs: back up, write, and undo from the backup.
+
+use crate::{write_atomic, KeepMetadata};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Name of the file in each backup folder that records what a run wrote.
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// How outputs are written back.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct InPlaceOptions {
+    /// Appended to the stem of a copy written next to the original (`-min` writes
+    /// `photo-min.webp`); empty replaces the original.
+    pub suffix: String,
+    /// Give each output the modification time of its original.
+    pub preserve_times: bool,
+    /// Give each output the permissions of its original.
+    pub preserve_permissions: bool,
+}
+
+/// One compressed output and the input it came from.
+#[derive(Debug, Clone, Copy)]
+pub struct InPlaceFile<'a> {
+    pub original: &'a Path,
+    /// Extension of the output (`webp`); when it differs from the original's, replacing
+    /// writes `photo.webp` and removes `photo.png`.
+    pub extension: &'a str,
+    pub data: &'a [u8],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunSummary {
+    /// Backup folder of the run.
+    pub backup_dir: PathBuf,
+    /// Files written by [`optimize_in_place`], or restored and removed by [`undo_last_run`].
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    /// Seconds since the Unix epoch.
+    created: u64,
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    original: PathBuf,
+    /// Copy of the original, relative to the backup folder.
+    backup: String,
+    /// Where the output was written.
+    written: PathBuf,
+    /// Copy of a different file that was at `written` before the run; `None` when the run
+    /// created it.
+    written_backup: Option<String>,
+    /// Whether the run overwrote or removed the original.
+    replaced: bool,
+}
+
+/// Same format for the purpose of keeping a file's name (`photo.jpeg` stays `photo.jpeg`).
+fn same_extension(a: &str, b: &str) -> bool {
+    let normalize = |ext: &str| match ext.to_ascii_lowercase().as_str() {
+        "jpeg" | "jpe" => "jpg".to_string(),
+        "tif" => "tiff".to_string(),
+        other => other.to_string(),
+    };
+    normalize(a) == normalize(b)
+}
+
+fn output_path(original: &Path, extension: &str, suffix: &str) -> PathBuf {
+    let original_ext = original
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    if suffix.is_empty() && same_extension(original_ext, extension) {
+        return original.to_path_buf();
+    }
+    let original_str = original.to_string_lossy();
+    let stem = naming::file_stem(&original_str);
+    let name = if same_extension(original_ext, extension) {
+        format!("{}{}.{}", stem, suffix, original_ext)
+    } else {
+        format!("{}{}.{}", stem, suffix, extension)
+    };
+    original.with_file_name(naming::sanitize_filename(&name))
+}
+
+/// `YYYY-MM-DD_HH-MM-SS` (UTC) for seconds since the Unix epoch.
+fn folder_timestamp(secs: u64) -> String {
+    let time = secs % 86_400;
+    format!(
+        "{}_{:02}-{:02}-{:02}",
+        naming::format_date(secs),
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+fn new_run_dir(backup_root: &Path, created: u64) -> Result<PathBuf, String> {
+    fs::create_dir_all(backup_root)
+        .map_err(|e| format!("Failed to create backup folder: {}", e))?;
+    let base = folder_timestamp(created);
+    for n in 1.. {
+        let name = if n == 1 {
+            base.clone()
+        } else {
+            format!("{}-{}", base, n)
+        };
+        match fs::create_dir(backup_root.join(&name)) {
+            Ok(()) => return Ok(backup_root.join(name)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(format!("Failed to create backup folder: {}", e)),
+        }
+    }
+    unreachable!()
+}
+
+/// Copy `from` into the run folder as `name`, keeping its modification time.
+fn back_up(from: &Path, run_dir: &Path, name: String) -> Result<String, String> {
+    let to = run_dir.join(&name);
+    fs::copy(from, &to).map_err(|e| format!("Failed to back up {}: {}", from.display(), e))?;
+    if let Ok(modified) = fs::metadata(from).and_then(|m| m.modified()) {
+        let _ = fs::File::options()
+            .write(true)
+            .open(&to)
+            .and_then(|f| f.set_modified(modified));
+    }
+    Ok(name)
+}
+
+fn write_manifest(run_dir: &Path, manifest: &Manifest) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(manifest)
+        .map_err(|e| format!("Failed to serialize backup manifest: {}", e))?;
+    write_atomic(
+        &run_dir.join(MANIFEST_FILE),
+        &json,
+        &KeepMetadata::default(),
+    )
+    .map_err(|e| format!("Failed to write backup manifest: {}", e))
+}
+
+/// Write each output back next to or over its original. Every original, and any other file an
+/// output replaces, is first copied into a new timestamped folder below `backup_root`, which
+/// [`undo_last_run`] restores from. Outputs are written with [`write_atomic`].
+pub fn optimize_in_place(
+    backup_root: &Path,
+    files: &[InPlaceFile],
+    options: &InPlaceOptions,
+) -> Result<RunSummary, String> {
+    let suffix = options.suffix.trim();
+    let targets: Vec<PathBuf> = files
+        .iter()
+        .map(|f| output_path(f.original, f.extension, suffix))
+        .collect();
+    let mut seen = HashSet::new();
+    for target in &targets {
+        if !seen.insert(target) {
+            return Err(format!(
+                "Two outputs would be written to {}",
+                target.display()
+            ));
+        }
+    }
+    for file in files {
+        if !file.original.is_file() {
+            return Err(format!("Not a file: {}", file.original.display()));
+        }
+    }
+
+    let created = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let run_dir = new_run_dir(backup_root, created)?;
+    let mut entries = Vec::with_capacity(files.len());
+    for (i, (file, target)) in files.iter().zip(&targets).enumerate() {
+        let file_name = |path: &Path| {
+            naming::sanitize_filename(&path.file_name().unwrap_or_default().to_string_lossy())
+        };
+        let backup = back_up(
+            file.original,
+            &run_dir,
+            format!("{:04}-{}", i + 1, file_name(file.original)),
+        )?;
+        let written_backup = if target != file.original && target.exists() {
+            Some(back_up(
+                target,
+                &run_dir,
+                format!("{:04}-replaced-{}", i + 1, file_name(target)),
+            )?)
+        } else {
+            None
+        };
+        entries.push(Entry {
+            original: file.original.to_path_buf(),
+            backup,
+            written: target.clone(),
+            written_backup,
+            replaced: suffix.is_empty(),
+        });
+    }
+    // The manifest goes down before any output, so a run that fails halfway can be undone.
+    write_manifest(&run_dir, &Manifest { created, entries })?;
+
+    for (file, target) in files.iter().zip(&targets) {
+        let keep = KeepMetadata::of(
+            file.original,
+            options.preserve_times,
+            options.preserve_permissions,
+        )
+        .map_err(|e| format!("Failed to read {}: {}", file.original.display(), e))?;
+        write_atomic(target, file.data, &keep)
+            .map_err(|e| format!("Failed to write {}: {}", target.display(), e))?;
+        if suffix.is_empty() && target != file.original {
+            fs::remove_file(file.original)
+                .map_err(|e| format!("Failed to remove {}: {}", file.original.display(), e))?;
+        }
+    }
+
+    Ok(RunSummary {
+        backup_dir: run_dir,
+        paths: targets,
+    })
+}
+
+fn restore(run_dir: &Path, backup: &str, to: &Path) -> Result<(), String> {
+    let from = run_dir.join(backup);
+    let data = fs::read(&from).map_err(|e| format!("Failed to read backup {}: {}", backup, e))?;
+    let keep = KeepMetadata::of(&from, true, true)
+        .map_err(|e| format!("Failed to read backup {}: {}", backup, e))?;
+    write_atomic(to, &data, &keep).map_err(|e| format!("Failed to restore {}: {}", to.display(), e))
+}
+
+fn read_manifest(run_dir: &Path) -> Result<Manifest, String> {
+    let json = fs::read(run_dir.join(MANIFEST_FILE))
+        .map_err(|e| format!("Failed to read backup manifest: {}", e))?;
+    serde_json::from_slice(&json).map_err(|e| format!("Failed to parse backup manifest: {}", e))
+}
+
+/// Sort key of a run folder: when its manifest says it was created, then the `-n` that
+/// [`new_run_dir`] appends to later runs in the same second. `None` sorts unreadable runs first.
+fn run_order(run_dir: &Path) -> Option<(u64, u32)> {
+    let created = read_manifest(run_dir).ok()?.created;
+    let name = run_dir.file_name()?.to_str()?;
+    let n = match name.strip_prefix(&folder_timestamp(created))? {
+        "" => 1,
+        rest => rest.strip_prefix('-')?.parse().ok()?,
+    };
+    Some((created, n))
+}
+
+/// Undo the newest run below `backup_root`: restore the originals and any files outputs
+/// replaced, remove outputs written beside them, then delete the run's backup folder. Calling
+/// it again undoes the run before that.
+pub fn undo_last_run(backup_root: &Path) -> Result<RunSummary, String> {
+    let mut runs: Vec<PathBuf> = fs::read_dir(backup_root)
+        .map_err(|_| "No in-place run to undo".to_string())?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.join(MANIFEST_FILE).is_file())
+        .collect();
+    runs.sort_by_cached_key(|run_dir| (run_order(run_dir), run_dir.clone()));
+    let run_dir = runs
+        .pop()
+        .ok_or_else(|| "No in-place run to undo".to_string())?;
+
+    let manifest = read_manifest(&run_dir)?;
+
+    let mut paths = Vec::new();
+    for entry in manifest.entries.iter().rev() {
+        if entry.written != entry.original {
+            match &entry.written_backup {
+                Some(backup) => restore(&run_dir, backup, &entry.written)?,
+                None => match fs::remove_file(&entry.written) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                    Err(e) => {
+                        return Err(format!(
+                            "Failed to remove {}: {}",
+                            entry.written.display(),
+                            e
+                        ))
+                    }
+                },
+            }
+            paths.push(entry.written.clone());
+        }
+        if entry.replaced {
+            restore(&run_dir, &entry.backup, &entry.original)?;
+            paths.push(entry.original.clone());
+        }
+    }
+    paths.reverse();
+
+    fs::remove_dir_all(&run_dir).map_err(|e| format!("Failed to remove backup folder: {}", e))?;
+    Ok(RunSummary {
+        backup_dir: run_dir,
+        paths,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    struct Fixture {
+        dir: tempfile::TempDir,
+    }
+
+    impl Fixture {
+        fn new(files: &[(&str, &[u8])]) -> Self {
+            let dir = tempfile::tempdir().unwrap();
+            fs::create_dir(dir.path().join("assets")).unwrap();
+            for (name, data) in files {
+                fs::write(dir.path().join("assets").join(name), data).unwrap();
+            }
+            Self { dir }
+        }
+
+        fn path(&self, name: &str) -> PathBuf {
+            self.dir.path().join("assets").join(name)
+        }
+
+        fn backups(&self) -> PathBuf {
+            self.dir.path().join("backups")
+        }
+
+        fn names(&self) -> Vec<String> {
+            let mut names: Vec<String> = fs::read_dir(self.dir.path().join("assets"))
+                .unwrap()
+                .flatten()
+                .map(|e| e.file_name().to_string_lossy().into_owned())
+                .collect();
+            names.sort();
+            names
+        }
+    }
+
+    #[test]
+    fn replaces_originals_and_undoes_the_run() {
+        let fx = Fixture::new(&[("a.png", b"png a"), ("b.JPEG", b"jpeg b")]);
+        let (a, b) = (fx.path("a.png"), fx.path("b.JPEG"));
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        fs::File::options()
+            .write(true)
+            .open(&a)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+
+        let files = [
+            InPlaceFile {
+                original: &a,
+                extension: "png",
+                data: b"small a",
+            },
+            InPlaceFile {
+                original: &b,
+                extension: "jpg",
+                data: b"small b",
+            },
+        ];
+        let options = InPlaceOptions {
+            preserve_times: true,
+            ..Default::default()
+        };
+        let summary = optimize_in_place(&fx.backups(), &files, &options).unwrap();
+        assert_eq!(summary.paths, vec![a.clone(), b.clone()]);
+        assert_eq!(fs::read(&a).unwrap(), b"small a");
+        assert_eq!(fs::read(&b).unwrap(), b"small b");
+        assert_eq!(fs::metadata(&a).unwrap().modified().unwrap(), modified);
+        assert!(summary.backup_dir.join("0001-a.png").is_file());
+
+        let undone = undo_last_run(&fx.backups()).unwrap();
+        assert_eq!(undone.backup_dir, summary.backup_dir);
+        assert_eq!(fs::read(&a).unwrap(), b"png a");
+        assert_eq!(fs::read(&b).unwrap(), b"jpeg b");
+        assert_eq!(fs::metadata(&a).unwrap().modified().unwrap(), modified);
+        assert!(!summary.backup_dir.exists());
+        assert!(undo_last_run(&fx.backups()).is_err());
+    }
+
+    #[test]
+    fn format_changes_replace_the_file_under_its_new_extension() {
+        let fx = Fixture::new(&[("a.png", b"png a")]);
+        let a = fx.path("a.png");
+        let files = [InPlaceFile {
+            original: &a,
+            extension: "webp",
+            data: b"webp a",
+        }];
+        optimize_in_place(&fx.backups(), &files, &InPlaceOptions::default()).unwrap();
+        assert_eq!(fx.names(), vec!["a.webp"]);
+
+        undo_last_run(&fx.backups()).unwrap();
+        assert_eq!(fx.names(), vec!["a.png"]);
+        assert_eq!(fs::read(&a).unwrap(), b"png a");
+    }
+
+    #[test]
+    fn suffix_writes_beside_the_original_and_backs_up_what_it_replaces() {
+        let fx = Fixture::new(&[("a.png", b"png a"), ("a-min.webp", b"older output")]);
+        let a = fx.path("a.png");
+        let files = [InPlaceFile {
+            original: &a,
+            extension: "webp",
+            data: b"webp a",
+        }];
+        let options = InPlaceOptions {
+            suffix: "-min".to_string(),
+            ..Default::default()
+        };
+        let summary = optimize_in_place(&fx.backups(), &files, &options).unwrap();
+        assert_eq!(summary.paths, vec![fx.path("a-min.webp")]);
+        assert_eq!(fs::read(&a).unwrap(), b"png a");
+        assert_eq!(fs::read(fx.path("a-min.webp")).unwrap(), b"webp a");
+
+        undo_last_run(&fx.backups()).unwrap();
+        assert_eq!(fx.names(), vec!["a-min.webp", "a.png"]);
+        assert_eq!(fs::read(fx.path("a-min.webp")).unwrap(), b"older output");
+    }
+
+    #[test]
+    fn undo_goes_back_one_run_at_a_time() {
+        let fx = Fixture::new(&[("a.png", b"v1")]);
+        let a = fx.path("a.png");
+        for data in [b"v2", b"v3"] {
+            let files = [InPlaceFile {
+                original: &a,
+                extension: "png",
+                data,
+            }];
+            optimize_in_place(&fx.backups(), &files, &InPlaceOptions::default()).unwrap();
+        }
+        assert_eq!(fs::read(&a).unwrap(), b"v3");
+        undo_last_run(&fx.backups()).unwrap();
+        assert_eq!(fs::read(&a).unwrap(), b"v2");
+        undo_last_run(&fx.backups()).unwrap();
+        assert_eq!(fs::read(&a).unwrap(), b"v1");
+    }
+
+    #[test]
+    fn clashing_outputs_are_rejected_before_anything_is_written() {
+        let fx = Fixture::new(&[("a.png", b"png"), ("a.jpg", b"jpg")]);
+        let (png, jpg) = (fx.path("a.png"), fx.path("a.jpg"));
+        let files = [
+            InPlaceFile {
+                original: &png,
+                extension: "webp",
+                data: b"1",
+            },
+            InPlaceFile {
+                original: &jpg,
+                extension: "webp",
+                data: b"2",
+            },
+        ];
+        assert!(optimize_in_place(&fx.backups(), &files, &InPlaceOptions::default()).is_err());
+        assert_eq!(fx.names(), vec!["a.jpg", "a.png"]);
+        assert!(!fx.backups().exists());
+    }
+
+    #[test]
+    fn runs_in_the_same_second_are_undone_newest_first() {
+        let fx = Fixture::new(&[]);
+        let created = 1_792_326_185;
+        let runs: Vec<PathBuf> = (0..12)
+            .map(|_| {
+                let run_dir = new_run_dir(&fx.backups(), created).unwrap();
+                let manifest = Manifest {
+                    created,
+                    entries: Vec::new(),
+                };
+                write_manifest(&run_dir, &manifest).unwrap();
+                run_dir
+            })
+            .collect();
+        assert!(runs[11].ends_with("2026-10-18_12-23-05-12"));
+        for run_dir in runs.iter().rev() {
+            assert_eq!(&undo_last_run(&fx.backups()).unwrap().backup_dir, run_dir);
+        }
+    }
+
+    #[test]
+    fn backup_folders_are_named_by_time() {
+        assert_eq!(folder_timestamp(1_792_326_185), "2026-10-18_12-23-05");
+    }
+}
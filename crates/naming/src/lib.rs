@@ -0,0 +1,18 @@
+//! Filename rules shared by the desktop app, the API and the browser build: sanitizing
+//! user-supplied names, ` (n)` copy-index allocation and output naming from templates.
+
+mod output;
+mod sanitize;
+mod template;
+mod unique;
+
+pub use output::{extension_for_mime, file_stem, output_filename};
+pub use sanitize::{sanitize_filename, sanitize_relative_path, MAX_FILENAME_BYTES};
+pub use template::{
+    content_hash, format_date, render_filename, validate_template, TemplateValues,
+    DEFAULT_TEMPLATE, TEMPLATE_TOKENS,
+};
+pub use unique::{
+    parse_trailing_copy_index, resolve_unique_names_for_disk, uniquify_names, uniquify_paths,
+    uniquify_zip_entry_names,
+};
@@ -0,0 +1,62 @@
+//! Default names for compressed outputs.
+
+use crate::template::{render_filename, TemplateValues, DEFAULT_TEMPLATE};
+
+/// File extension for an output MIME type (`image/jpeg` → `jpg`).
+pub fn extension_for_mime(mime: &str) -> Option<&'static str> {
+    match mime {
+        "image/webp" => Some("webp"),
+        "image/avif" => Some("avif"),
+        "image/jpeg" => Some("jpg"),
+        "image/png" => Some("png"),
+        "image/tiff" => Some("tiff"),
+        "image/bmp" => Some("bmp"),
+        "image/x-icon" => Some("ico"),
+        "image/jxl" => Some("jxl"),
+        _ => None,
+    }
+}
+
+/// Stem of the last component of `path` (either separator; `browser://a.png` works too).
+pub fn file_stem(path: &str) -> &str {
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    match name.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem,
+        _ => name,
+    }
+}
+
+/// Output name `{stem}.{ext}`, or `{stem}-page{page}.{ext}` for a page of a split input,
+/// sanitized with [`sanitize_filename`](crate::sanitize_filename). An empty stem becomes
+/// `compressed`.
+pub fn output_filename(stem: &str, ext: &str, page: Option<u32>) -> String {
+    let values = TemplateValues {
+        stem,
+        ext,
+        page,
+        ..Default::default()
+    };
+    render_filename(DEFAULT_TEMPLATE, &values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_stem_handles_paths_and_dotfiles() {
+        assert_eq!(file_stem("/photos/trip/a.b.png"), "a.b");
+        assert_eq!(file_stem("C:\\scans\\page.tiff"), "page");
+        assert_eq!(file_stem("browser://shot.jpeg"), "shot");
+        assert_eq!(file_stem(".hidden"), ".hidden");
+        assert_eq!(file_stem("noext"), "noext");
+    }
+
+    #[test]
+    fn output_filename_adds_page_and_sanitizes() {
+        assert_eq!(output_filename("a", "webp", None), "a.webp");
+        assert_eq!(output_filename("scan", "png", Some(2)), "scan-page2.png");
+        assert_eq!(output_filename("CON", "jpg", None), "CON_.jpg");
+        assert_eq!(output_filename("", "png", None), "compressed.png");
+    }
+}
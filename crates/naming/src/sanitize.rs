@@ -0,0 +1,125 @@
+//! Turning user-supplied names into a single portable path component.
+
+use crate::unique::split_display_name;
+
+/// Longest filename accepted, in bytes (the common limit of ext4, APFS and NTFS).
+pub const MAX_FILENAME_BYTES: usize = 255;
+
+/// Device names Windows reserves regardless of extension (`CON.png` cannot be created).
+const WINDOWS_RESERVED: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Make `name` safe to use as one filename on Windows, macOS and Linux: path separators,
+/// characters Windows forbids and control characters become `_`, trailing dots and spaces are
+/// dropped, reserved device names get a `_` suffix (`CON.png` → `CON_.png`) and the stem is
+/// shortened to fit [`MAX_FILENAME_BYTES`]. Returns an empty string when nothing usable is
+/// left, so callers can reject the name.
+pub fn sanitize_filename(name: &str) -> String {
+    let replaced: String = name
+        .chars()
+        .map(|c| match c {
+            '\\' | '/' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let trimmed = replaced.trim().trim_end_matches(['.', ' ']);
+    if trimmed.is_empty() {
+        return String::new();
+    }
+
+    let (stem, ext) = split_display_name(trimmed);
+    let mut stem = stem.to_string();
+    // Windows matches the device name up to the first dot (`NUL.tar.gz` is reserved too).
+    let device_len = stem.find('.').unwrap_or(stem.len());
+    if WINDOWS_RESERVED
+        .iter()
+        .any(|reserved| stem[..device_len].trim_end().eq_ignore_ascii_case(reserved))
+    {
+        stem.insert(device_len, '_');
+    }
+
+    let ext_len = ext.map_or(0, |ext| ext.len() + 1);
+    let budget = MAX_FILENAME_BYTES.saturating_sub(ext_len);
+    if stem.len() > budget {
+        let mut cut = budget;
+        while !stem.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        stem.truncate(cut);
+    }
+    match ext {
+        Some(ext) if ext_len <= MAX_FILENAME_BYTES => format!("{stem}.{ext}"),
+        _ => stem,
+    }
+}
+
+/// Make `path` safe as a relative path below an output folder: components split on either
+/// separator are sanitized with [`sanitize_filename`] and joined with `/`; empty and `.`
+/// components are dropped. Returns an empty string for a `..` component or when no name is left.
+pub fn sanitize_relative_path(path: &str) -> String {
+    let mut components = Vec::new();
+    for component in path.split(['/', '\\']) {
+        match component.trim() {
+            "" | "." => {}
+            ".." => return String::new(),
+            component => {
+                let safe = sanitize_filename(component);
+                if !safe.is_empty() {
+                    components.push(safe);
+                }
+            }
+        }
+    }
+    components.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_separators_and_forbidden_characters() {
+        assert_eq!(sanitize_filename("a/b\\c:d*e?.png"), "a_b_c_d_e_.png");
+        assert_eq!(sanitize_filename("  <x>|\"y\"\t.webp "), "_x___y__.webp");
+    }
+
+    #[test]
+    fn drops_trailing_dots_and_spaces() {
+        assert_eq!(sanitize_filename("photo.png. . "), "photo.png");
+        assert_eq!(sanitize_filename("..."), "");
+        assert_eq!(sanitize_filename("   "), "");
+    }
+
+    #[test]
+    fn suffixes_windows_reserved_names() {
+        assert_eq!(sanitize_filename("CON.png"), "CON_.png");
+        assert_eq!(sanitize_filename("lpt1"), "lpt1_");
+        assert_eq!(sanitize_filename("nul.tar.gz"), "nul_.tar.gz");
+        assert_eq!(sanitize_filename("console.png"), "console.png");
+    }
+
+    #[test]
+    fn shortens_long_stems_and_keeps_the_extension() {
+        let long = format!("{}.webp", "é".repeat(200));
+        let got = sanitize_filename(&long);
+        assert!(got.len() <= MAX_FILENAME_BYTES);
+        assert!(got.ends_with("é.webp"));
+    }
+
+    #[test]
+    fn relative_paths_keep_their_folders() {
+        assert_eq!(
+            sanitize_relative_path("photos/2024\\a.webp"),
+            "photos/2024/a.webp"
+        );
+        assert_eq!(
+            sanitize_relative_path("/./photos//CON/a?.png"),
+            "photos/CON_/a_.png"
+        );
+        assert_eq!(sanitize_relative_path("photos/../../etc/passwd"), "");
+        assert_eq!(sanitize_relative_path("a.png"), "a.png");
+    }
+}
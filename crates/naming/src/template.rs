@@ -0,0 +1,248 @@
+//! Output filename templates such as `{stem}-{width}w-q{quality}.{ext}`.
+
+use crate::sanitize_filename;
+
+/// Template that produces the plain `{stem}.{ext}` names.
+pub const DEFAULT_TEMPLATE: &str = "{stem}.{ext}";
+
+/// Tokens a template may use, written `{token}`; `{{` and `}}` stand for literal braces.
+pub const TEMPLATE_TOKENS: [&str; 9] = [
+    "stem", "ext", "width", "height", "quality", "format", "index", "date", "hash",
+];
+
+/// Values substituted into a template. A `None` value renders as empty text.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TemplateValues<'a> {
+    /// Input file stem. A page of a split input renders `{stem}` as `{stem}-page{n}`.
+    pub stem: &'a str,
+    /// Output extension (`jpg`, `webp`, …).
+    pub ext: &'a str,
+    /// 1-based page number of a split input.
+    pub page: Option<u32>,
+    /// Output size in pixels.
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Encoder quality, 0–100.
+    pub quality: Option<u8>,
+    /// Output format name (`jpeg`, `webp`, …).
+    pub format: &'a str,
+    /// 1-based position of the input in its batch.
+    pub index: Option<usize>,
+    /// Seconds since the Unix epoch; `{date}` renders it as `YYYY-MM-DD` (UTC).
+    pub timestamp: Option<u64>,
+    /// Output bytes; `{hash}` renders [`content_hash`] of them.
+    pub data: &'a [u8],
+}
+
+enum Piece<'t> {
+    Text(&'t str),
+    Token(&'t str),
+}
+
+fn parse(template: &str) -> Result<Vec<Piece<'_>>, String> {
+    let mut pieces = Vec::new();
+    let mut rest = template;
+    while let Some(pos) = rest.find(['{', '}']) {
+        if pos > 0 {
+            pieces.push(Piece::Text(&rest[..pos]));
+        }
+        let tail = &rest[pos..];
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            pieces.push(Piece::Text(&tail[..1]));
+            rest = &tail[2..];
+            continue;
+        }
+        if tail.starts_with('}') {
+            return Err(format!(
+                "Unmatched '}}' in filename template {:?}",
+                template
+            ));
+        }
+        let end = tail
+            .find('}')
+            .ok_or_else(|| format!("Unclosed '{{' in filename template {:?}", template))?;
+        let token = &tail[1..end];
+        if !TEMPLATE_TOKENS.contains(&token) {
+            return Err(format!(
+                "Unknown token {{{}}} in filename template (expected one of {})",
+                token,
+                TEMPLATE_TOKENS.join(", ")
+            ));
+        }
+        pieces.push(Piece::Token(token));
+        rest = &tail[end + 1..];
+    }
+    if !rest.is_empty() {
+        pieces.push(Piece::Text(rest));
+    }
+    Ok(pieces)
+}
+
+/// Check that `template` only uses [`TEMPLATE_TOKENS`] and has balanced braces.
+pub fn validate_template(template: &str) -> Result<(), String> {
+    if template.trim().is_empty() {
+        return Err("Filename template is empty".to_string());
+    }
+    parse(template).map(|_| ())
+}
+
+/// Filename for `values` rendered from `template`, sanitized with [`sanitize_filename`].
+/// An invalid template, or one that renders to nothing usable, falls back to
+/// [`DEFAULT_TEMPLATE`]; an empty stem becomes `compressed`.
+pub fn render_filename(template: &str, values: &TemplateValues) -> String {
+    let pieces = match validate_template(template) {
+        Ok(()) => parse(template),
+        Err(_) => parse(DEFAULT_TEMPLATE),
+    }
+    .unwrap_or_default();
+
+    let stem = if values.stem.trim().is_empty() {
+        "compressed"
+    } else {
+        values.stem
+    };
+    let mut name = String::new();
+    for piece in pieces {
+        match piece {
+            Piece::Text(text) => name.push_str(text),
+            Piece::Token(token) => name.push_str(&match token {
+                "stem" => match values.page {
+                    Some(page) => format!("{stem}-page{page}"),
+                    None => stem.to_string(),
+                },
+                "ext" => values.ext.to_string(),
+                "width" => values.width.map(|v| v.to_string()).unwrap_or_default(),
+                "height" => values.height.map(|v| v.to_string()).unwrap_or_default(),
+                "quality" => values.quality.map(|v| v.to_string()).unwrap_or_default(),
+                "format" => values.format.to_string(),
+                "index" => values.index.map(|v| v.to_string()).unwrap_or_default(),
+                "date" => values.timestamp.map(format_date).unwrap_or_default(),
+                _ => content_hash(values.data),
+            }),
+        }
+    }
+
+    let name = sanitize_filename(&name);
+    if name.is_empty() && template != DEFAULT_TEMPLATE {
+        return render_filename(DEFAULT_TEMPLATE, values);
+    }
+    name
+}
+
+/// Short content hash for `{hash}`: 8 hex digits of the 64-bit FNV-1a hash of `data`. Stable
+/// across platforms, so every build names the same output the same way.
+pub fn content_hash(data: &[u8]) -> String {
+    let hash = data.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    format!("{:016x}", hash)[..8].to_string()
+}
+
+/// `YYYY-MM-DD` (UTC) for seconds since the Unix epoch.
+pub fn format_date(timestamp: u64) -> String {
+    // Days-to-civil conversion from Howard Hinnant's date algorithms.
+    let days = (timestamp / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values() -> TemplateValues<'static> {
+        TemplateValues {
+            stem: "photo",
+            ext: "jpg",
+            width: Some(1200),
+            height: Some(800),
+            quality: Some(65),
+            format: "jpeg",
+            index: Some(3),
+            timestamp: Some(1_792_281_600),
+            data: b"bytes",
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn renders_every_token() {
+        assert_eq!(render_filename(DEFAULT_TEMPLATE, &values()), "photo.jpg");
+        assert_eq!(
+            render_filename("{stem}-{width}w-q{quality}.{ext}", &values()),
+            "photo-1200w-q65.jpg"
+        );
+        assert_eq!(
+            render_filename("{index}_{format}_{width}x{height}_{date}.{ext}", &values()),
+            "3_jpeg_1200x800_2026-10-18.jpg"
+        );
+        assert_eq!(
+            render_filename("{hash}.{ext}", &values()),
+            format!("{}.jpg", content_hash(b"bytes"))
+        );
+        assert_eq!(
+            render_filename("{{{stem}}}.{ext}", &values()),
+            "{photo}.jpg"
+        );
+    }
+
+    #[test]
+    fn pages_missing_values_and_unsafe_output() {
+        let page = TemplateValues {
+            page: Some(2),
+            ..values()
+        };
+        assert_eq!(
+            render_filename("{stem}-min.{ext}", &page),
+            "photo-page2-min.jpg"
+        );
+        let unknown = TemplateValues {
+            width: None,
+            ..values()
+        };
+        assert_eq!(
+            render_filename("{stem}-{width}w.{ext}", &unknown),
+            "photo-w.jpg"
+        );
+        assert_eq!(
+            render_filename("{format}/{stem}.{ext}", &values()),
+            "jpeg_photo.jpg"
+        );
+        assert_eq!(
+            render_filename("{width}", &TemplateValues::default()),
+            "compressed"
+        );
+    }
+
+    #[test]
+    fn invalid_templates_are_rejected_and_fall_back() {
+        assert!(validate_template("{stem}.{ext}").is_ok());
+        assert!(validate_template("{size}.{ext}").is_err());
+        assert!(validate_template("{stem.{ext}").is_err());
+        assert!(validate_template("stem}.png").is_err());
+        assert!(validate_template("  ").is_err());
+        assert_eq!(render_filename("{size}.{ext}", &values()), "photo.jpg");
+    }
+
+    #[test]
+    fn content_hash_is_stable() {
+        assert_eq!(content_hash(b""), "cbf29ce4");
+        assert_eq!(content_hash(b"a"), "af63dc4c");
+        assert_eq!(content_hash(b"bytes").len(), 8);
+    }
+
+    #[test]
+    fn dates_are_utc_calendar_days() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(951_782_400), "2000-02-29");
+        assert_eq!(format_date(1_792_281_599), "2026-10-17");
+    }
+}
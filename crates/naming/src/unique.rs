@@ -1,13 +1,14 @@
 //! Display filename disambiguation: trailing ` (n)` copy suffix and batch uniqueness.
 //!
-//! Used for on-disk saves (with `std::fs`) and ZIP entry names (in-memory set only).
+//! Used for on-disk saves (with `std::fs`), ZIP entry names (in-memory set only) and names the
+//! browser build checks against a list it already has.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
 /// If `stem` ends with ` (digits)`, returns `(prefix, Some(digits))`. Otherwise `(stem, None)`.
 /// Only a **numeric** parenthetical at the end matches (e.g. `report (draft)` does not).
-pub(crate) fn parse_trailing_copy_index(stem: &str) -> (&str, Option<u32>) {
+pub fn parse_trailing_copy_index(stem: &str) -> (&str, Option<u32>) {
     let Some(open) = stem.rfind(" (") else {
         return (stem, None);
     };
@@ -29,7 +30,7 @@ pub(crate) fn parse_trailing_copy_index(stem: &str) -> (&str, Option<u32>) {
     (base, Some(n))
 }
 
-fn split_display_name(filename: &str) -> (&str, Option<&str>) {
+pub(crate) fn split_display_name(filename: &str) -> (&str, Option<&str>) {
     let Some(dot) = filename.rfind('.') else {
         return (filename, None);
     };
@@ -65,36 +66,16 @@ fn validate_leaf_filename(name: &str) -> Result<(), String> {
     Ok(())
 }
 
-fn is_taken(
-    folder: Option<&Path>,
-    name: &str,
-    reserved: &HashSet<String>,
-    check_disk: bool,
-) -> bool {
-    if reserved.contains(name) {
-        return true;
-    }
-    if check_disk {
-        if let Some(dir) = folder {
-            if dir.join(name).exists() {
-                return true;
-            }
-        }
-    }
-    false
-}
-
 /// Pick the first non-colliding name for `proposed`, updating `reserved`.
 fn allocate_unique(
-    folder: Option<&Path>,
     proposed: &str,
     reserved: &mut HashSet<String>,
-    check_disk: bool,
+    is_taken: &dyn Fn(&str) -> bool,
 ) -> Result<String, String> {
     validate_leaf_filename(proposed)?;
     let proposed = proposed.trim();
 
-    if !is_taken(folder, proposed, reserved, check_disk) {
+    if !reserved.contains(proposed) && !is_taken(proposed) {
         reserved.insert(proposed.to_string());
         return Ok(proposed.to_string());
     }
@@ -110,7 +91,7 @@ fn allocate_unique(
     for n in start_n..10_000 {
         let candidate = format_with_copy_index(logical_base, n, ext);
         validate_leaf_filename(&candidate)?;
-        if !is_taken(folder, &candidate, reserved, check_disk) {
+        if !reserved.contains(&candidate) && !is_taken(&candidate) {
             reserved.insert(candidate.clone());
             return Ok(candidate);
         }
@@ -122,27 +103,62 @@ fn allocate_unique(
     ))
 }
 
-/// Resolve each desired filename so the batch is unique vs disk (when `folder` is `Some`) and vs earlier slots.
+/// Make each desired filename unique vs earlier slots and vs names `is_taken` reports as used,
+/// continuing an existing ` (n)` suffix (`a (2).png` → `a (3).png`).
+pub fn uniquify_names(
+    desired: &[String],
+    is_taken: impl Fn(&str) -> bool,
+) -> Result<Vec<String>, String> {
+    let mut reserved = HashSet::new();
+    desired
+        .iter()
+        .map(|name| allocate_unique(name, &mut reserved, &is_taken))
+        .collect()
+}
+
+/// [`uniquify_names`] for `/`-separated relative paths (`photos/2024/a.png`): the file name is
+/// made unique within its folder, the folders are kept. Paths should already be sanitized
+/// ([`sanitize_relative_path`](crate::sanitize_relative_path)); `..` components are rejected.
+pub fn uniquify_paths(
+    desired: &[String],
+    is_taken: impl Fn(&str) -> bool,
+) -> Result<Vec<String>, String> {
+    let mut reserved: HashMap<&str, HashSet<String>> = HashMap::new();
+    desired
+        .iter()
+        .map(|path| {
+            let (dir, name) = match path.rsplit_once('/') {
+                Some((dir, name)) => (dir, name),
+                None => ("", path.as_str()),
+            };
+            if dir.split('/').any(|c| c.trim() == "..") {
+                return Err("Path cannot leave the output folder".to_string());
+            }
+            let join = |name: &str| match dir {
+                "" => name.to_string(),
+                dir => format!("{dir}/{name}"),
+            };
+            let name = allocate_unique(name, reserved.entry(dir).or_default(), &|name| {
+                is_taken(&join(name))
+            })?;
+            Ok(join(&name))
+        })
+        .collect()
+}
+
+/// Resolve each desired relative path so the batch is unique vs files in `folder` and vs
+/// earlier slots.
 pub fn resolve_unique_names_for_disk(
     folder: &Path,
     desired: &[String],
 ) -> Result<Vec<String>, String> {
-    let mut reserved = HashSet::new();
-    let mut out = Vec::with_capacity(desired.len());
-    for name in desired {
-        out.push(allocate_unique(Some(folder), name, &mut reserved, true)?);
-    }
-    Ok(out)
+    uniquify_paths(desired, |name| folder.join(name).exists())
 }
 
 /// ZIP entry names: unique within the archive using the same ` (n)` convention (no disk).
+/// Entries may sit in folders (`photos/a.png`).
 pub fn uniquify_zip_entry_names(desired: &[String]) -> Result<Vec<String>, String> {
-    let mut reserved = HashSet::new();
-    let mut out = Vec::with_capacity(desired.len());
-    for name in desired {
-        out.push(allocate_unique(None, name, &mut reserved, false)?);
-    }
-    Ok(out)
+    uniquify_paths(desired, |_| false)
 }
 
 #[cfg(test)]
@@ -187,6 +203,43 @@ mod tests {
         assert_eq!(got, vec!["a (1).png", "a (2).png"]);
     }
 
+    #[test]
+    fn uniquify_names_continues_existing_copy_index() {
+        let existing = ["a.png".to_string(), "a (1).png".to_string()];
+        let got = uniquify_names(
+            &[String::from("a.png"), String::from("a (1).png")],
+            |name| existing.iter().any(|e| e == name),
+        )
+        .unwrap();
+        assert_eq!(got, vec!["a (2).png", "a (3).png"]);
+    }
+
+    #[test]
+    fn uniquify_names_rejects_path_separators() {
+        assert!(uniquify_names(&[String::from("../a.png")], |_| false).is_err());
+    }
+
+    #[test]
+    fn uniquify_paths_is_per_folder() {
+        let desired = vec![
+            "photos/a.png".to_string(),
+            "photos/2024/a.png".to_string(),
+            "photos/a.png".to_string(),
+            "a.png".to_string(),
+        ];
+        let got = uniquify_paths(&desired, |p| p == "photos/2024/a.png").unwrap();
+        assert_eq!(
+            got,
+            vec![
+                "photos/a.png",
+                "photos/2024/a (1).png",
+                "photos/a (1).png",
+                "a.png"
+            ]
+        );
+        assert!(uniquify_paths(&["../a.png".to_string()], |_| false).is_err());
+    }
+
     #[test]
     fn uniquify_zip_two_same_names() {
         let got =
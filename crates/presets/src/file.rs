@@ -0,0 +1,161 @@
+//! Preset files: `{"presets": [...]}` in JSON, or `[[presets]]` tables in TOML.
+
+use crate::Preset;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Text format of a preset file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PresetFormat {
+    Json,
+    Toml,
+}
+
+impl PresetFormat {
+    /// Format for a file name: `.toml` is TOML, anything else JSON.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => Self::Toml,
+            _ => Self::Json,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Toml => "toml",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct PresetFileRef<'a> {
+    presets: &'a [Preset],
+}
+
+#[derive(Deserialize)]
+struct PresetFile {
+    #[serde(default)]
+    presets: Vec<Preset>,
+}
+
+/// Presets in `text`. Every preset is validated, and a name may only appear once.
+pub fn parse_presets(text: &str, format: PresetFormat) -> Result<Vec<Preset>, String> {
+    let file: PresetFile = match format {
+        PresetFormat::Json => {
+            serde_json::from_str(text).map_err(|e| format!("Failed to parse presets: {}", e))?
+        }
+        PresetFormat::Toml => {
+            toml::from_str(text).map_err(|e| format!("Failed to parse presets: {}", e))?
+        }
+    };
+    for (index, preset) in file.presets.iter().enumerate() {
+        preset.validate()?;
+        if crate::find_preset(&file.presets[..index], &preset.name).is_some() {
+            return Err(format!("Preset '{}' is listed twice", preset.name));
+        }
+    }
+    Ok(file.presets)
+}
+
+/// `presets` as a preset file that [`parse_presets`] reads back.
+pub fn export_presets(presets: &[Preset], format: PresetFormat) -> Result<String, String> {
+    let file = PresetFileRef { presets };
+    match format {
+        PresetFormat::Json => serde_json::to_string_pretty(&file)
+            .map_err(|e| format!("Failed to serialize presets: {}", e)),
+        PresetFormat::Toml => {
+            toml::to_string(&file).map_err(|e| format!("Failed to serialize presets: {}", e))
+        }
+    }
+}
+
+/// Read a preset file, in the format its extension names.
+pub fn load_presets_file(path: &Path) -> Result<Vec<Preset>, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    parse_presets(&text, PresetFormat::from_path(path))
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builtin_presets;
+    use domain::{Watermark, WatermarkKind};
+
+    #[test]
+    fn round_trips_in_both_formats() {
+        let mut presets = builtin_presets();
+        presets[0].options.watermark = Some(Watermark::new(WatermarkKind::Text {
+            text: "© Panda".to_string(),
+            color: "#ffffff80".to_string(),
+        }));
+
+        for format in [PresetFormat::Json, PresetFormat::Toml] {
+            let text = export_presets(&presets, format).unwrap();
+            let parsed = parse_presets(&text, format).unwrap();
+            assert_eq!(parsed.len(), presets.len());
+            for (parsed, preset) in parsed.iter().zip(&presets) {
+                assert_eq!(parsed.name, preset.name);
+                assert_eq!(
+                    serde_json::to_value(&parsed.options).unwrap(),
+                    serde_json::to_value(&preset.options).unwrap(),
+                    "{:?}",
+                    format
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn hand_written_toml_only_lists_changes() {
+        let text = r#"
+            [[presets]]
+            name = "Blog"
+
+            [presets.options]
+            to_avif = true
+            png_quality = "60-85"
+            transforms = [{ op = "resize", width = 1200 }]
+        "#;
+        let presets = parse_presets(text, PresetFormat::Toml).unwrap();
+        let options = &presets[0].options;
+        assert!(options.to_avif && options.oxipng);
+        assert_eq!(options.png_quality, "60-85");
+        assert_eq!(
+            options.transforms,
+            vec![domain::Transform::Resize {
+                width: Some(1200),
+                height: None
+            }]
+        );
+    }
+
+    #[test]
+    fn toml_quality_table_overrides_single_entries() {
+        let text = r#"
+            [[presets]]
+            name = "Small"
+
+            [presets.options]
+            png_quality = "20-60"
+            quality_table.max.avif = 35
+        "#;
+        let presets = parse_presets(text, PresetFormat::Toml).unwrap();
+        let table = presets[0].options.quality_table.unwrap();
+        assert_eq!(table.max.avif, 35);
+        assert_eq!(table.max.webp, domain::QualityTable::default().max.webp);
+    }
+
+    #[test]
+    fn rejects_duplicates_and_bad_templates() {
+        let twice = r#"{"presets": [{"name": "Blog"}, {"name": "blog"}]}"#;
+        assert!(parse_presets(twice, PresetFormat::Json).is_err());
+        let template =
+            r#"{"presets": [{"name": "Blog", "options": {"filename_template": "{nope}"}}]}"#;
+        assert!(parse_presets(template, PresetFormat::Json).is_err());
+    }
+}
@@ -0,0 +1,10 @@
+//! Named compression presets: the built-in set, the user's own presets saved in one JSON file,
+//! and import/export as JSON or TOML. Used by the desktop app and the API.
+
+mod file;
+mod preset;
+mod store;
+
+pub use file::{export_presets, load_presets_file, parse_presets, PresetFormat};
+pub use preset::{builtin_presets, find_preset, is_builtin, preset_key, Preset};
+pub use store::{PresetStore, PRESETS_FILE};
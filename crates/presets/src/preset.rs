@@ -0,0 +1,151 @@
+//! The preset type and the built-in presets.
+
+use domain::{compression_level_to_range, CompressionOptions, Transform};
+use serde::{Deserialize, Serialize};
+
+/// Longest preset name, in characters.
+const MAX_NAME_CHARS: usize = 64;
+
+/// A named bundle of [`CompressionOptions`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Preset {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(default)]
+    pub options: CompressionOptions,
+}
+
+impl Preset {
+    pub fn new(name: &str, options: CompressionOptions) -> Self {
+        Self {
+            name: name.trim().to_string(),
+            description: String::new(),
+            options,
+        }
+    }
+
+    /// Check the name and the filename template before a preset is stored or used.
+    pub fn validate(&self) -> Result<(), String> {
+        let name = self.name.trim();
+        if preset_key(name).is_empty() {
+            return Err("Preset name must contain a letter or digit".to_string());
+        }
+        if name.chars().count() > MAX_NAME_CHARS {
+            return Err(format!(
+                "Preset name is longer than {} characters",
+                MAX_NAME_CHARS
+            ));
+        }
+        if let Some(template) = &self.options.filename_template {
+            naming::validate_template(template).map_err(|e| format!("Preset '{}': {}", name, e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Form of `name` used to compare presets: lowercase letters and digits only, so
+/// `Archive (lossless)`, `archive-lossless` and `ARCHIVE_LOSSLESS` are the same preset.
+pub fn preset_key(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// The preset in `presets` whose name matches `name` (see [`preset_key`]).
+pub fn find_preset<'a>(presets: &'a [Preset], name: &str) -> Option<&'a Preset> {
+    let key = preset_key(name);
+    presets
+        .iter()
+        .find(|preset| preset_key(&preset.name) == key)
+}
+
+fn builtin(name: &str, description: &str, output_format: &str, level: &str) -> Preset {
+    let mut options = CompressionOptions {
+        png_quality: compression_level_to_range(level),
+        ..Default::default()
+    };
+    options.set_output_format(output_format);
+    Preset {
+        name: name.to_string(),
+        description: description.to_string(),
+        options,
+    }
+}
+
+/// Presets every install has; they cannot be replaced or removed.
+pub fn builtin_presets() -> Vec<Preset> {
+    let web = builtin(
+        "Web",
+        "WebP at balanced quality for web pages",
+        "webp",
+        "mid",
+    );
+
+    let mut email = builtin(
+        "Email",
+        "JPEG no larger than 1600 px, small enough to attach",
+        "jpeg",
+        "max",
+    );
+    email.options.transforms = vec![Transform::Resize {
+        width: Some(1600),
+        height: Some(1600),
+    }];
+
+    let mut archive = builtin(
+        "Archive (lossless)",
+        "Lossless PNG keeping full bit depth and the colour profile",
+        "png",
+        "low",
+    );
+    archive.options.png_lossy = false;
+    archive.options.color_profile = domain::ColorProfile::Keep;
+
+    let mut thumbnail = builtin(
+        "Thumbnail",
+        "Small WebP fitting 320×320 px, named {stem}-thumb",
+        "webp",
+        "mid",
+    );
+    thumbnail.options.transforms = vec![Transform::Resize {
+        width: Some(320),
+        height: Some(320),
+    }];
+    thumbnail.options.filename_template = Some("{stem}-thumb.{ext}".to_string());
+
+    vec![web, email, archive, thumbnail]
+}
+
+/// Whether `name` matches a built-in preset.
+pub fn is_builtin(name: &str) -> bool {
+    find_preset(&builtin_presets(), name).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_match_loosely() {
+        let presets = builtin_presets();
+        assert_eq!(
+            find_preset(&presets, "archive-lossless").unwrap().name,
+            "Archive (lossless)"
+        );
+        assert_eq!(find_preset(&presets, " WEB ").unwrap().name, "Web");
+        assert!(find_preset(&presets, "print").is_none());
+        assert!(is_builtin("thumbnail"));
+    }
+
+    #[test]
+    fn builtins_are_valid() {
+        for preset in builtin_presets() {
+            preset.validate().unwrap();
+        }
+        assert!(Preset::new(" -- ", CompressionOptions::default())
+            .validate()
+            .is_err());
+    }
+}
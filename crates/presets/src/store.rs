@@ -0,0 +1,187 @@
+//! The user's presets, kept in one JSON file next to the built-ins.
+
+use crate::{builtin_presets, export_presets, find_preset, is_builtin, parse_presets, preset_key};
+use crate::{Preset, PresetFormat};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// File name of the store inside the app config folder.
+pub const PRESETS_FILE: &str = "presets.json";
+
+/// User presets saved at `path`; every change is written straight back.
+#[derive(Debug, Clone)]
+pub struct PresetStore {
+    path: PathBuf,
+    presets: Vec<Preset>,
+}
+
+impl PresetStore {
+    /// The store at `path`; empty when the file does not exist yet.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let presets = match fs::read_to_string(path) {
+            Ok(json) => parse_presets(&json, PresetFormat::Json)
+                .map_err(|e| format!("{}: {}", path.display(), e))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            presets,
+        })
+    }
+
+    /// Built-in presets followed by the user's, in the order they were added.
+    pub fn all(&self) -> Vec<Preset> {
+        let mut all = builtin_presets();
+        all.extend(self.presets.iter().cloned());
+        all
+    }
+
+    pub fn user_presets(&self) -> &[Preset] {
+        &self.presets
+    }
+
+    /// The built-in or user preset called `name`.
+    pub fn get(&self, name: &str) -> Option<Preset> {
+        find_preset(&self.all(), name).cloned()
+    }
+
+    /// Add `preset`, replacing a user preset of the same name. Built-in names are taken.
+    pub fn save(&mut self, preset: Preset) -> Result<(), String> {
+        self.insert(preset)?;
+        self.write()
+    }
+
+    /// Remove the user preset called `name`; `false` when there is none.
+    pub fn remove(&mut self, name: &str) -> Result<bool, String> {
+        if is_builtin(name) {
+            return Err(format!("'{}' is a built-in preset", name.trim()));
+        }
+        let key = preset_key(name);
+        let before = self.presets.len();
+        self.presets
+            .retain(|preset| preset_key(&preset.name) != key);
+        if self.presets.len() == before {
+            return Ok(false);
+        }
+        self.write()?;
+        Ok(true)
+    }
+
+    /// Add every preset in `text` as with [`PresetStore::save`]; nothing is added when one of
+    /// them is invalid. Returns the imported presets.
+    pub fn import(&mut self, text: &str, format: PresetFormat) -> Result<Vec<Preset>, String> {
+        let imported = parse_presets(text, format)?;
+        let mut updated = self.clone();
+        for preset in &imported {
+            updated.insert(preset.clone())?;
+        }
+        updated.write()?;
+        *self = updated;
+        Ok(imported)
+    }
+
+    /// The presets called `names` (built-in or user) as a preset file.
+    pub fn export(&self, names: &[String], format: PresetFormat) -> Result<String, String> {
+        let all = self.all();
+        let presets = names
+            .iter()
+            .map(|name| {
+                find_preset(&all, name)
+                    .cloned()
+                    .ok_or_else(|| format!("No preset named '{}'", name))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        export_presets(&presets, format)
+    }
+
+    fn insert(&mut self, mut preset: Preset) -> Result<(), String> {
+        preset.name = preset.name.trim().to_string();
+        preset.validate()?;
+        if is_builtin(&preset.name) {
+            return Err(format!(
+                "'{}' is a built-in preset; choose another name",
+                preset.name
+            ));
+        }
+        let key = preset_key(&preset.name);
+        match self
+            .presets
+            .iter_mut()
+            .find(|existing| preset_key(&existing.name) == key)
+        {
+            Some(existing) => *existing = preset,
+            None => self.presets.push(preset),
+        }
+        Ok(())
+    }
+
+    fn write(&self) -> Result<(), String> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        let json = export_presets(&self.presets, PresetFormat::Json)?;
+        inplace::write_atomic(&self.path, json.as_bytes(), &Default::default())
+            .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain::CompressionOptions;
+
+    fn preset(name: &str, format: &str) -> Preset {
+        let mut options = CompressionOptions::default();
+        options.set_output_format(format);
+        Preset::new(name, options)
+    }
+
+    #[test]
+    fn saves_replaces_and_removes_user_presets() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config").join(PRESETS_FILE);
+        let mut store = PresetStore::load(&path).unwrap();
+        assert!(store.user_presets().is_empty());
+
+        store.save(preset("Blog", "webp")).unwrap();
+        store.save(preset("blog", "avif")).unwrap();
+        assert!(store.save(preset("web", "png")).is_err());
+
+        let reloaded = PresetStore::load(&path).unwrap();
+        assert_eq!(reloaded.user_presets().len(), 1);
+        assert!(reloaded.get("BLOG").unwrap().options.to_avif);
+        assert_eq!(reloaded.all().len(), builtin_presets().len() + 1);
+
+        assert!(store.remove("Blog").unwrap());
+        assert!(!store.remove("Blog").unwrap());
+        assert!(store.remove("Web").is_err());
+        assert!(PresetStore::load(&path).unwrap().user_presets().is_empty());
+    }
+
+    #[test]
+    fn import_is_all_or_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = PresetStore::load(&dir.path().join(PRESETS_FILE)).unwrap();
+        let exported = store
+            .export(&["Thumbnail".to_string()], PresetFormat::Toml)
+            .unwrap();
+        // A built-in cannot be imported under its own name.
+        assert!(store.import(&exported, PresetFormat::Toml).is_err());
+
+        let renamed = exported.replace("name = \"Thumbnail\"", "name = \"Small\"");
+        let imported = store.import(&renamed, PresetFormat::Toml).unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(
+            store
+                .get("small")
+                .unwrap()
+                .options
+                .filename_template
+                .as_deref(),
+            Some("{stem}-thumb.{ext}")
+        );
+    }
+}
@@ -0,0 +1,103 @@
+//! Glob patterns matched against `/`-separated relative paths.
+
+/// A compiled glob: `*` matches within one path component, `**` across components, `?` one
+/// character. A pattern without `/` matches the last component only (`*.png` matches
+/// `icons/a.png`); one with `/` matches the whole relative path. Matching ignores ASCII case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glob {
+    pattern: Vec<char>,
+    name_only: bool,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let pattern = pattern.trim().replace('\\', "/");
+        let pattern = pattern.trim_start_matches("./").trim_start_matches('/');
+        if pattern.is_empty() {
+            return Err("Glob pattern is empty".to_string());
+        }
+        Ok(Self {
+            name_only: !pattern.contains('/'),
+            pattern: pattern.to_ascii_lowercase().chars().collect(),
+        })
+    }
+
+    /// Whether `relative` (components separated by `/`) matches.
+    pub fn matches(&self, relative: &str) -> bool {
+        let relative = relative.to_ascii_lowercase();
+        let subject = if self.name_only {
+            relative.rsplit('/').next().unwrap_or_default()
+        } else {
+            relative.as_str()
+        };
+        let subject: Vec<char> = subject.chars().collect();
+        matches_from(&self.pattern, &subject)
+    }
+}
+
+fn matches_from(pattern: &[char], subject: &[char]) -> bool {
+    match pattern.first() {
+        None => subject.is_empty(),
+        Some('*') if pattern.get(1) == Some(&'*') => {
+            // `**/` also matches zero directories.
+            let rest = &pattern[2..];
+            if let Some(after_slash) = rest.strip_prefix(&['/']) {
+                if matches_from(after_slash, subject) {
+                    return true;
+                }
+            }
+            (0..=subject.len()).any(|skip| matches_from(rest, &subject[skip..]))
+        }
+        Some('*') => {
+            let rest = &pattern[1..];
+            for skip in 0..=subject.len() {
+                if matches_from(rest, &subject[skip..]) {
+                    return true;
+                }
+                if subject.get(skip) == Some(&'/') {
+                    break;
+                }
+            }
+            false
+        }
+        Some('?') => {
+            matches!(subject.first(), Some(c) if *c != '/')
+                && matches_from(&pattern[1..], &subject[1..])
+        }
+        Some(c) => subject.first() == Some(c) && matches_from(&pattern[1..], &subject[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glob(pattern: &str) -> Glob {
+        Glob::new(pattern).unwrap()
+    }
+
+    #[test]
+    fn name_patterns_match_the_last_component() {
+        assert!(glob("*.png").matches("a.png"));
+        assert!(glob("*.PNG").matches("icons/small/a.png"));
+        assert!(!glob("*.png").matches("a.png.bak"));
+        assert!(glob("img_??.jpg").matches("x/img_01.jpg"));
+        assert!(!glob("img_??.jpg").matches("img_1.jpg"));
+    }
+
+    #[test]
+    fn path_patterns_match_the_whole_relative_path() {
+        assert!(glob("raw/*.tif").matches("raw/a.tif"));
+        assert!(!glob("raw/*.tif").matches("raw/2024/a.tif"));
+        assert!(glob("raw/**/*.tif").matches("raw/a.tif"));
+        assert!(glob("raw/**/*.tif").matches("raw/2024/jan/a.tif"));
+        assert!(glob("**/thumbs/**").matches("site/thumbs/a.png"));
+        assert!(glob("./node_modules/**").matches("node_modules/pkg/logo.png"));
+        assert!(!glob("raw/*").matches("other/raw/a.tif"));
+    }
+
+    #[test]
+    fn empty_patterns_are_rejected() {
+        assert!(Glob::new("  ").is_err());
+    }
+}
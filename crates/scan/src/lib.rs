@@ -0,0 +1,8 @@
+//! Finding input images on disk: recursive folder walks filtered by include/exclude globs,
+//! file size and extension. Shared by the desktop app and headless tools.
+
+mod glob;
+mod walk;
+
+pub use glob::Glob;
+pub use walk::{scan_folder, scan_paths, ScanFilter, ScannedFile};
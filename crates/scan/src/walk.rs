@@ -0,0 +1,264 @@
+//! Recursive folder walk with include/exclude globs and size limits.
+
+use crate::Glob;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Which files a scan keeps. Globs follow [`Glob`] and are matched against the path relative
+/// to the scanned folder; an empty `include` keeps every file. `exclude` also prunes whole
+/// directories (`thumbs`, `**/node_modules`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ScanFilter {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    /// Smallest file kept, in bytes
+    pub min_size: Option<u64>,
+    /// Largest file kept, in bytes
+    pub max_size: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScannedFile {
+    pub path: PathBuf,
+    /// `/`-separated path relative to the scanned folder (`2024/jan/a.png`); just the file
+    /// name for a file passed directly to [`scan_paths`].
+    pub relative_path: String,
+    pub size: u64,
+}
+
+struct CompiledFilter {
+    include: Vec<Glob>,
+    exclude: Vec<Glob>,
+    min_size: u64,
+    max_size: u64,
+}
+
+impl CompiledFilter {
+    fn new(filter: &ScanFilter) -> Result<Self, String> {
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .filter(|p| !p.trim().is_empty())
+                .map(|p| Glob::new(p))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(Self {
+            include: compile(&filter.include)?,
+            exclude: compile(&filter.exclude)?,
+            min_size: filter.min_size.unwrap_or(0),
+            max_size: filter.max_size.unwrap_or(u64::MAX),
+        })
+    }
+
+    fn excluded(&self, relative: &str) -> bool {
+        self.exclude.iter().any(|glob| glob.matches(relative))
+    }
+
+    fn keeps_file(&self, relative: &str, size: u64) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|glob| glob.matches(relative)))
+            && !self.excluded(relative)
+            && (self.min_size..=self.max_size).contains(&size)
+    }
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    extensions.is_empty()
+        || path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|ext| extensions.iter().any(|e| e.eq_ignore_ascii_case(ext)))
+}
+
+/// Every file below `root` that passes `filter` and has one of `extensions` (any extension
+/// when empty), sorted by relative path. Symlinked directories are not followed and entries
+/// that can't be read below `root` are skipped.
+pub fn scan_folder(
+    root: &Path,
+    filter: &ScanFilter,
+    extensions: &[&str],
+) -> Result<Vec<ScannedFile>, String> {
+    let filter = CompiledFilter::new(filter)?;
+    if !root.is_dir() {
+        return Err(format!("Not a folder: {}", root.display()));
+    }
+    let mut found = Vec::new();
+    walk(root, "", &filter, extensions, &mut found);
+    Ok(found)
+}
+
+fn walk(
+    dir: &Path,
+    prefix: &str,
+    filter: &CompiledFilter,
+    extensions: &[&str],
+    found: &mut Vec<ScannedFile>,
+) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<_> = entries.flatten().collect();
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let relative = format!("{prefix}{name}");
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            if !filter.excluded(&relative) {
+                walk(&path, &format!("{relative}/"), filter, extensions, found);
+            }
+            continue;
+        }
+        // `metadata` follows symlinks to files.
+        let Ok(metadata) = fs::metadata(&path) else {
+            continue;
+        };
+        if metadata.is_file()
+            && has_extension(&path, extensions)
+            && filter.keeps_file(&relative, metadata.len())
+        {
+            found.push(ScannedFile {
+                path,
+                relative_path: relative,
+                size: metadata.len(),
+            });
+        }
+    }
+}
+
+/// Expand `paths` as a user picked or dropped them: folders are scanned with [`scan_folder`]
+/// and their results prefixed with the folder's name (`photos/2024/a.png`), so several folders
+/// keep apart; files are kept as given.
+pub fn scan_paths(
+    paths: &[PathBuf],
+    filter: &ScanFilter,
+    extensions: &[&str],
+) -> Result<Vec<ScannedFile>, String> {
+    let mut found = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let folder = path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            for mut file in scan_folder(path, filter, extensions)? {
+                if !folder.is_empty() {
+                    file.relative_path = format!("{}/{}", folder, file.relative_path);
+                }
+                found.push(file);
+            }
+        } else {
+            let metadata = fs::metadata(path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            found.push(ScannedFile {
+                path: path.clone(),
+                relative_path: path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                size: metadata.len(),
+            });
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for (path, size) in [
+            ("a.png", 10),
+            ("notes.txt", 10),
+            ("2024/b.JPG", 200),
+            ("2024/jan/c.webp", 30),
+            ("thumbs/t.png", 5),
+            ("2024/thumbs/u.png", 5),
+        ] {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, vec![0u8; size]).unwrap();
+        }
+        dir
+    }
+
+    fn relative(found: &[ScannedFile]) -> Vec<&str> {
+        found.iter().map(|f| f.relative_path.as_str()).collect()
+    }
+
+    const IMAGES: [&str; 3] = ["png", "jpg", "webp"];
+
+    #[test]
+    fn walks_recursively_keeping_supported_extensions() {
+        let dir = tree();
+        let found = scan_folder(dir.path(), &ScanFilter::default(), &IMAGES).unwrap();
+        assert_eq!(
+            relative(&found),
+            vec![
+                "2024/b.JPG",
+                "2024/jan/c.webp",
+                "2024/thumbs/u.png",
+                "a.png",
+                "thumbs/t.png"
+            ]
+        );
+        assert_eq!(found[0].size, 200);
+        assert_eq!(found[0].path, dir.path().join("2024").join("b.JPG"));
+    }
+
+    #[test]
+    fn applies_globs_and_size_limits() {
+        let dir = tree();
+        let filter = ScanFilter {
+            exclude: vec!["thumbs".into()],
+            ..Default::default()
+        };
+        let found = scan_folder(dir.path(), &filter, &IMAGES).unwrap();
+        assert_eq!(
+            relative(&found),
+            vec!["2024/b.JPG", "2024/jan/c.webp", "a.png"]
+        );
+
+        let filter = ScanFilter {
+            include: vec!["2024/**/*.webp".into(), "*.png".into()],
+            max_size: Some(20),
+            ..Default::default()
+        };
+        let found = scan_folder(dir.path(), &filter, &IMAGES).unwrap();
+        assert_eq!(
+            relative(&found),
+            vec!["2024/thumbs/u.png", "a.png", "thumbs/t.png"]
+        );
+
+        let filter = ScanFilter {
+            min_size: Some(100),
+            ..Default::default()
+        };
+        let found = scan_folder(dir.path(), &filter, &[]).unwrap();
+        assert_eq!(relative(&found), vec!["2024/b.JPG"]);
+    }
+
+    #[test]
+    fn scan_paths_prefixes_folders_and_keeps_files() {
+        let dir = tree();
+        let paths = vec![dir.path().join("2024"), dir.path().join("notes.txt")];
+        let found = scan_paths(&paths, &ScanFilter::default(), &IMAGES).unwrap();
+        assert_eq!(
+            relative(&found),
+            vec![
+                "2024/b.JPG",
+                "2024/jan/c.webp",
+                "2024/thumbs/u.png",
+                "notes.txt"
+            ]
+        );
+        assert!(scan_folder(&dir.path().join("a.png"), &ScanFilter::default(), &[]).is_err());
+    }
+}
@@ -0,0 +1,117 @@
+//! Headless hot folder: `panda-watch <input> <output> [options]`. Prints one JSON event per
+//! line as files are compressed; stop it with Ctrl-C (the ledger is saved after every file).
+
+use compression::CancelToken;
+use domain::{compression_level_to_range, CompressionOptions};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+use watch::{WatchConfig, Watcher};
+
+const USAGE: &str = "\
+Usage: panda-watch <input> <output> [options]
+
+Options:
+  --format <fmt>        webp (default), avif, jpeg, png, tiff, bmp, ico, jxl or original
+  --level <level>       low, mid (default) or max
+  --template <tmpl>     output name template, e.g. {stem}-min.{ext}
+  --include <glob>      only watch matching files (repeatable)
+  --exclude <glob>      skip matching files and folders (repeatable)
+  --settle-ms <ms>      how long a file must stay unchanged (default 2000)
+  --interval-ms <ms>    time between scans (default 1000)
+  --ledger <path>       processed-file ledger (default <output>/.panda-watch.json)
+  --once                scan twice, settle-ms apart, then exit";
+
+fn parse_args(args: &[String]) -> Result<(WatchConfig, bool), String> {
+    let mut positional = Vec::new();
+    let mut options = CompressionOptions::default();
+    options.set_output_format("webp");
+    let mut filter = scan::ScanFilter::default();
+    let mut settle = Duration::from_secs(2);
+    let mut interval = Duration::from_secs(1);
+    let mut ledger = None;
+    let mut once = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("{} needs a value", arg))
+        };
+        let millis = |v: String| {
+            v.parse::<u64>()
+                .map(Duration::from_millis)
+                .map_err(|_| format!("{} needs a number of milliseconds", arg))
+        };
+        match arg.as_str() {
+            "--format" => options.set_output_format(&value()?),
+            "--level" => options.png_quality = compression_level_to_range(&value()?),
+            "--template" => {
+                let template = value()?;
+                naming::validate_template(&template)?;
+                options.filename_template = Some(template);
+            }
+            "--include" => filter.include.push(value()?),
+            "--exclude" => filter.exclude.push(value()?),
+            "--settle-ms" => settle = millis(value()?)?,
+            "--interval-ms" => interval = millis(value()?)?,
+            "--ledger" => ledger = Some(PathBuf::from(value()?)),
+            "--once" => once = true,
+            flag if flag.starts_with("--") => return Err(format!("Unknown option {}", flag)),
+            path => positional.push(PathBuf::from(path)),
+        }
+    }
+    let [input, output] = <[PathBuf; 2]>::try_from(positional)
+        .map_err(|_| "Expected an input and an output folder".to_string())?;
+    let config = WatchConfig {
+        filter,
+        settle,
+        poll_interval: interval,
+        ledger,
+        ..WatchConfig::new(input, output, options)
+    };
+    Ok((config, once))
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    let (config, once) = match parse_args(&args) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let settle = config.settle;
+    let mut watcher = match Watcher::new(config) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let print = |event: watch::WatchEvent| match serde_json::to_string(&event) {
+        Ok(line) => println!("{}", line),
+        Err(e) => eprintln!("Failed to serialize event: {}", e),
+    };
+    let cancel = CancelToken::new();
+    if once {
+        watcher.poll(&cancel).into_iter().for_each(print);
+        std::thread::sleep(settle);
+        watcher.poll(&cancel).into_iter().for_each(print);
+    } else {
+        eprintln!(
+            "Watching {} -> {}",
+            watcher.config().input.display(),
+            watcher.config().output.display()
+        );
+        watcher.run(&cancel, print);
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,118 @@
+//! Record of processed inputs, kept on disk so a restarted watcher skips finished work.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// What a watcher last did with one input.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LedgerEntry {
+    /// Size and modification time (ms since the Unix epoch) of the input when processed; a
+    /// change to either processes the file again.
+    pub size: u64,
+    pub modified: u64,
+    /// Outputs written, relative to the output folder (`/`-separated).
+    #[serde(default)]
+    pub outputs: Vec<String>,
+    /// Why compression failed; failed inputs are not retried until they change.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Processed inputs by path relative to the input folder.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ledger {
+    #[serde(default)]
+    files: BTreeMap<String, LedgerEntry>,
+}
+
+impl Ledger {
+    /// The ledger at `path`; empty when the file does not exist yet.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match fs::read(path) {
+            Ok(json) => serde_json::from_slice(&json)
+                .map_err(|e| format!("Failed to parse {}: {}", path.display(), e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_vec_pretty(self)
+            .map_err(|e| format!("Failed to serialize ledger: {}", e))?;
+        inplace::write_atomic(path, &json, &Default::default())
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    pub fn get(&self, relative: &str) -> Option<&LedgerEntry> {
+        self.files.get(relative)
+    }
+
+    /// Whether `relative` was processed at exactly this size and modification time.
+    pub fn is_current(&self, relative: &str, size: u64, modified: u64) -> bool {
+        self.files
+            .get(relative)
+            .is_some_and(|e| e.size == size && e.modified == modified)
+    }
+
+    pub fn record(&mut self, relative: &str, entry: LedgerEntry) {
+        self.files.insert(relative.to_string(), entry);
+    }
+
+    /// Outputs of every input except `relative`.
+    pub fn outputs_except<'a>(&'a self, relative: &'a str) -> impl Iterator<Item = &'a str> {
+        self.files
+            .iter()
+            .filter(move |(input, _)| input.as_str() != relative)
+            .flat_map(|(_, entry)| entry.outputs.iter().map(String::as_str))
+    }
+
+    /// Every recorded output.
+    pub fn outputs(&self) -> impl Iterator<Item = &str> {
+        self.files
+            .values()
+            .flat_map(|entry| entry.outputs.iter().map(String::as_str))
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ledger.json");
+        assert!(Ledger::load(&path).unwrap().is_empty());
+
+        let mut ledger = Ledger::default();
+        ledger.record(
+            "2024/a.png",
+            LedgerEntry {
+                size: 10,
+                modified: 1_000,
+                outputs: vec!["2024/a.webp".into()],
+                error: None,
+            },
+        );
+        ledger.save(&path).unwrap();
+
+        let loaded = Ledger::load(&path).unwrap();
+        assert_eq!(loaded, ledger);
+        assert!(loaded.is_current("2024/a.png", 10, 1_000));
+        assert!(!loaded.is_current("2024/a.png", 10, 2_000));
+        assert_eq!(loaded.outputs().collect::<Vec<_>>(), vec!["2024/a.webp"]);
+        assert_eq!(loaded.outputs_except("2024/a.png").count(), 0);
+    }
+}
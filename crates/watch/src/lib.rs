@@ -0,0 +1,9 @@
+//! Hot folders: images saved into an input folder are compressed into an output folder.
+//! Polls rather than subscribing to OS file events, so it behaves the same on every platform
+//! and on network shares. Used by the desktop app and the headless `panda-watch` binary.
+
+mod ledger;
+mod watcher;
+
+pub use ledger::{Ledger, LedgerEntry};
+pub use watcher::{WatchConfig, WatchEvent, WatchOutput, Watcher, LEDGER_FILE};
@@ -0,0 +1,493 @@
+//! The polling watcher.
+
+use crate::{Ledger, LedgerEntry};
+use compression::CancelToken;
+use domain::{CompressionOptions, TrimRect};
+use scan::{ScanFilter, ScannedFile};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+/// Default ledger file name, kept in the output folder.
+pub const LEDGER_FILE: &str = ".panda-watch.json";
+
+#[derive(Debug, Clone)]
+pub struct WatchConfig {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub options: CompressionOptions,
+    /// Which files below `input` are watched; subfolders are watched too and kept in `output`.
+    pub filter: ScanFilter,
+    /// How long a file's size and modification time must stay unchanged before it is
+    /// compressed, so files that are still being written are left alone.
+    pub settle: Duration,
+    pub poll_interval: Duration,
+    /// Where processed files are recorded; `None` means [`LEDGER_FILE`] in `output`.
+    pub ledger: Option<PathBuf>,
+}
+
+impl WatchConfig {
+    pub fn new(input: PathBuf, output: PathBuf, options: CompressionOptions) -> Self {
+        Self {
+            input,
+            output,
+            options,
+            filter: ScanFilter::default(),
+            settle: Duration::from_secs(2),
+            poll_interval: Duration::from_secs(1),
+            ledger: None,
+        }
+    }
+}
+
+/// One file written for a watched input.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchOutput {
+    pub path: PathBuf,
+    pub size: u64,
+    pub mime_type: String,
+    /// 1-based page number when a multi-page input was split.
+    pub page: Option<u32>,
+    pub bit_depth: u8,
+    pub trimmed: Option<TrimRect>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum WatchEvent {
+    #[serde(rename_all = "camelCase")]
+    Compressed {
+        input: PathBuf,
+        original_size: u64,
+        outputs: Vec<WatchOutput>,
+    },
+    /// `input` is the watched folder itself when it could not be scanned.
+    #[serde(rename_all = "camelCase")]
+    Failed { input: PathBuf, error: String },
+}
+
+/// A file seen but not yet settled.
+struct Seen {
+    size: u64,
+    modified: u64,
+    since: Instant,
+}
+
+pub struct Watcher {
+    config: WatchConfig,
+    ledger: Ledger,
+    ledger_path: PathBuf,
+    /// `output` relative to `input` when it is a subfolder of it.
+    nested_output: Option<String>,
+    pending: HashMap<String, Seen>,
+}
+
+fn modified_millis(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_millis() as u64)
+}
+
+impl Watcher {
+    /// Check the folders and load the ledger. Creates `output` if needed.
+    pub fn new(mut config: WatchConfig) -> Result<Self, String> {
+        if !config.input.is_dir() {
+            return Err(format!("Not a folder: {}", config.input.display()));
+        }
+        fs::create_dir_all(&config.output).map_err(|e| {
+            format!(
+                "Failed to create output folder {}: {}",
+                config.output.display(),
+                e
+            )
+        })?;
+        let canonical = |path: &Path| {
+            path.canonicalize()
+                .map_err(|e| format!("Failed to resolve {}: {}", path.display(), e))
+        };
+        config.input = canonical(&config.input)?;
+        config.output = canonical(&config.output)?;
+
+        let ledger_path = config
+            .ledger
+            .clone()
+            .unwrap_or_else(|| config.output.join(LEDGER_FILE));
+        let ledger = Ledger::load(&ledger_path)?;
+        let nested_output = config
+            .output
+            .strip_prefix(&config.input)
+            .ok()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .map(|dir| dir.to_string_lossy().replace('\\', "/"));
+        Ok(Self {
+            config,
+            ledger,
+            ledger_path,
+            nested_output,
+            pending: HashMap::new(),
+        })
+    }
+
+    pub fn config(&self) -> &WatchConfig {
+        &self.config
+    }
+
+    pub fn ledger(&self) -> &Ledger {
+        &self.ledger
+    }
+
+    fn is_own_output(&self, file: &ScannedFile, own_outputs: &HashSet<PathBuf>) -> bool {
+        own_outputs.contains(&file.path)
+            || self.nested_output.as_deref().is_some_and(|dir| {
+                file.relative_path
+                    .strip_prefix(dir)
+                    .is_some_and(|rest| rest.starts_with('/'))
+            })
+    }
+
+    /// Scan the input folder once and compress every new or changed file that has settled.
+    /// Returns one event per file compressed or failed.
+    pub fn poll(&mut self, cancel: &CancelToken) -> Vec<WatchEvent> {
+        let extensions = compression::supported_input_extensions();
+        let found = match scan::scan_folder(&self.config.input, &self.config.filter, &extensions) {
+            Ok(found) => found,
+            Err(error) => {
+                return vec![WatchEvent::Failed {
+                    input: self.config.input.clone(),
+                    error,
+                }]
+            }
+        };
+        let own_outputs: HashSet<PathBuf> = self
+            .ledger
+            .outputs()
+            .map(|output| self.config.output.join(output))
+            .collect();
+
+        let now = Instant::now();
+        let mut present = HashSet::new();
+        let mut events = Vec::new();
+        for file in found {
+            if cancel.is_cancelled() {
+                break;
+            }
+            if self.is_own_output(&file, &own_outputs) {
+                continue;
+            }
+            let Some(modified) = modified_millis(&file.path) else {
+                continue;
+            };
+            let relative = file.relative_path.clone();
+            present.insert(relative.clone());
+            if self.ledger.is_current(&relative, file.size, modified) {
+                self.pending.remove(&relative);
+                continue;
+            }
+
+            let settled = match self.pending.get(&relative) {
+                Some(seen) if seen.size == file.size && seen.modified == modified => {
+                    now.duration_since(seen.since) >= self.config.settle
+                }
+                _ => {
+                    let seen = Seen {
+                        size: file.size,
+                        modified,
+                        since: now,
+                    };
+                    self.pending.insert(relative.clone(), seen);
+                    false
+                }
+            };
+            if settled {
+                self.pending.remove(&relative);
+                self.process(&file, modified, cancel, &mut events);
+            }
+        }
+        self.pending
+            .retain(|relative, _| present.contains(relative));
+        events
+    }
+
+    /// [`poll`](Self::poll) every `poll_interval` until `cancel` is cancelled, passing each
+    /// event to `on_event`.
+    pub fn run(&mut self, cancel: &CancelToken, mut on_event: impl FnMut(WatchEvent)) {
+        while !cancel.is_cancelled() {
+            for event in self.poll(cancel) {
+                on_event(event);
+            }
+            // Sleep in short steps so a cancel is noticed quickly.
+            let deadline = Instant::now() + self.config.poll_interval;
+            while !cancel.is_cancelled() {
+                let left = deadline.saturating_duration_since(Instant::now());
+                if left.is_zero() {
+                    break;
+                }
+                thread::sleep(left.min(Duration::from_millis(100)));
+            }
+        }
+    }
+
+    fn process(
+        &mut self,
+        file: &ScannedFile,
+        modified: u64,
+        cancel: &CancelToken,
+        events: &mut Vec<WatchEvent>,
+    ) {
+        let relative = &file.relative_path;
+        let outcome = self.compress(file, cancel);
+        if outcome.is_err() && cancel.is_cancelled() {
+            return;
+        }
+        let (entry, event) = match outcome {
+            Ok((written, outputs)) => (
+                LedgerEntry {
+                    size: file.size,
+                    modified,
+                    outputs: written,
+                    error: None,
+                },
+                WatchEvent::Compressed {
+                    input: file.path.clone(),
+                    original_size: file.size,
+                    outputs,
+                },
+            ),
+            Err(error) => (
+                LedgerEntry {
+                    size: file.size,
+                    modified,
+                    // Earlier outputs stay on disk and stay ours.
+                    outputs: self
+                        .ledger
+                        .get(relative)
+                        .map(|e| e.outputs.clone())
+                        .unwrap_or_default(),
+                    error: Some(error.clone()),
+                },
+                WatchEvent::Failed {
+                    input: file.path.clone(),
+                    error,
+                },
+            ),
+        };
+        self.ledger.record(relative, entry);
+        events.push(event);
+        if let Err(error) = self.ledger.save(&self.ledger_path) {
+            events.push(WatchEvent::Failed {
+                input: self.ledger_path.clone(),
+                error,
+            });
+        }
+    }
+
+    /// Compress `file` and write its outputs below `output`, in the same subfolder as the
+    /// input. Returns the outputs relative to `output` and their details.
+    fn compress(
+        &self,
+        file: &ScannedFile,
+        cancel: &CancelToken,
+    ) -> Result<(Vec<String>, Vec<WatchOutput>), String> {
+        let data = fs::read(&file.path).map_err(|e| format!("Failed to read file: {}", e))?;
+        let ext = file
+            .path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        let options = &self.config.options;
+        let images = compression::compress_image_pages_with_cancel(&data, &ext, options, cancel)
+            .map_err(|e| format!("Compression failed: {}", e))?;
+
+        let relative = file.relative_path.as_str();
+        let dir = relative.rsplit_once('/').map(|(dir, _)| dir);
+        let previous: HashSet<&str> = self
+            .ledger
+            .get(relative)
+            .map(|e| e.outputs.iter().map(String::as_str).collect())
+            .unwrap_or_default();
+        let others: HashSet<&str> = self.ledger.outputs_except(relative).collect();
+        // Never overwrite another input's output or a file the watcher didn't write (such as
+        // the input itself when both folders are the same); this input's own earlier outputs
+        // are replaced.
+        let is_taken = |name: &str| {
+            others.contains(name)
+                || (!previous.contains(name) && self.config.output.join(name).exists())
+        };
+
+        let split = images.len() > 1;
+        let index = self.ledger.len() + 1;
+        let mut written = Vec::with_capacity(images.len());
+        let mut outputs = Vec::with_capacity(images.len());
+        for (page_index, image) in images.into_iter().enumerate() {
+            let page = split.then_some(page_index as u32 + 1);
+            let name = image.suggested_filename(&file.path.to_string_lossy(), options, index, page);
+            let desired = match dir {
+                Some(dir) => format!("{}/{}", dir, name),
+                None => name,
+            };
+            let name = naming::uniquify_paths(&[desired], |n| {
+                is_taken(n) || written.iter().any(|w: &String| w == n)
+            })?
+            .remove(0);
+
+            let path = self.config.output.join(&name);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+            }
+            inplace::write_atomic(&path, &image.data, &Default::default())
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+            outputs.push(WatchOutput {
+                path,
+                size: image.data.len() as u64,
+                mime_type: image.mime_type,
+                page,
+                bit_depth: image.bit_depth,
+                trimmed: image.trimmed,
+            });
+            written.push(name);
+        }
+        Ok((written, outputs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(shade: u8) -> Vec<u8> {
+        let image = image::RgbImage::from_pixel(8, 8, image::Rgb([shade, 40, 200]));
+        let mut data = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut data, image::ImageFormat::Png).unwrap();
+        data.into_inner()
+    }
+
+    fn config(input: &Path, output: &Path, format: &str) -> WatchConfig {
+        let mut options = CompressionOptions::default();
+        options.set_output_format(format);
+        WatchConfig {
+            settle: Duration::ZERO,
+            ..WatchConfig::new(input.to_path_buf(), output.to_path_buf(), options)
+        }
+    }
+
+    fn compressed_inputs(events: &[WatchEvent]) -> Vec<String> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                WatchEvent::Compressed { input, .. } => Some(
+                    input
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .into_owned(),
+                ),
+                WatchEvent::Failed { .. } => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn compresses_settled_files_once_and_remembers_them_across_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let (input, output) = (dir.path().join("in"), dir.path().join("out"));
+        fs::create_dir_all(input.join("2024")).unwrap();
+        fs::write(input.join("a.png"), png(10)).unwrap();
+        fs::write(input.join("2024").join("b.png"), png(20)).unwrap();
+        let cancel = CancelToken::new();
+
+        let mut watcher = Watcher::new(config(&input, &output, "webp")).unwrap();
+        // First sight only starts the settle clock.
+        assert!(watcher.poll(&cancel).is_empty());
+        let events = watcher.poll(&cancel);
+        assert_eq!(compressed_inputs(&events), vec!["b.png", "a.png"]);
+        assert!(output.join("a.webp").is_file());
+        assert!(output.join("2024").join("b.webp").is_file());
+        let WatchEvent::Compressed { outputs, .. } = &events[0] else {
+            panic!("expected a compressed event");
+        };
+        assert_eq!(outputs[0].mime_type, "image/webp");
+        assert!(watcher.poll(&cancel).is_empty());
+
+        // A restarted watcher reads the ledger and only picks up the changed file.
+        let mut watcher = Watcher::new(config(&input, &output, "webp")).unwrap();
+        assert_eq!(watcher.ledger().len(), 2);
+        fs::write(input.join("a.png"), png(30)).unwrap();
+        assert!(watcher.poll(&cancel).is_empty());
+        assert_eq!(compressed_inputs(&watcher.poll(&cancel)), vec!["a.png"]);
+        assert!(watcher.poll(&cancel).is_empty());
+    }
+
+    #[test]
+    fn files_still_being_written_wait_until_they_settle() {
+        let dir = tempfile::tempdir().unwrap();
+        let (input, output) = (dir.path().join("in"), dir.path().join("out"));
+        fs::create_dir_all(&input).unwrap();
+        let cancel = CancelToken::new();
+        let mut watcher = Watcher::new(config(&input, &output, "webp")).unwrap();
+
+        let data = png(50);
+        fs::write(input.join("a.png"), &data[..data.len() / 2]).unwrap();
+        assert!(watcher.poll(&cancel).is_empty());
+        fs::write(input.join("a.png"), &data).unwrap();
+        // The size changed since the last poll, so the clock starts again.
+        assert!(watcher.poll(&cancel).is_empty());
+        assert_eq!(compressed_inputs(&watcher.poll(&cancel)), vec!["a.png"]);
+
+        let mut slow = Watcher::new(WatchConfig {
+            settle: Duration::from_secs(60),
+            ..config(&input, &dir.path().join("slow"), "webp")
+        })
+        .unwrap();
+        assert!(slow.poll(&cancel).is_empty());
+        assert!(slow.poll(&cancel).is_empty());
+    }
+
+    #[test]
+    fn skips_its_own_outputs_and_never_overwrites_inputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("in");
+        fs::create_dir_all(&input).unwrap();
+        fs::write(input.join("a.png"), png(60)).unwrap();
+        let cancel = CancelToken::new();
+
+        // Output folder inside the input folder.
+        let mut nested = Watcher::new(config(&input, &input.join("out"), "webp")).unwrap();
+        nested.poll(&cancel);
+        assert_eq!(compressed_inputs(&nested.poll(&cancel)), vec!["a.png"]);
+        assert!(nested.poll(&cancel).is_empty());
+        assert!(nested.poll(&cancel).is_empty());
+
+        // Output folder is the input folder and the format is kept.
+        let input = dir.path().join("same");
+        fs::create_dir_all(&input).unwrap();
+        fs::write(input.join("a.png"), png(60)).unwrap();
+        let mut same = Watcher::new(config(&input, &input, "original")).unwrap();
+        same.poll(&cancel);
+        assert_eq!(compressed_inputs(&same.poll(&cancel)), vec!["a.png"]);
+        assert_eq!(fs::read(input.join("a.png")).unwrap(), png(60));
+        assert!(input.join("a (1).png").is_file());
+        assert!(same.poll(&cancel).is_empty());
+        assert!(same.poll(&cancel).is_empty());
+    }
+
+    #[test]
+    fn failed_files_are_reported_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let (input, output) = (dir.path().join("in"), dir.path().join("out"));
+        fs::create_dir_all(&input).unwrap();
+        fs::write(input.join("broken.png"), b"not a png").unwrap();
+        let cancel = CancelToken::new();
+        let mut watcher = Watcher::new(config(&input, &output, "webp")).unwrap();
+
+        watcher.poll(&cancel);
+        let events = watcher.poll(&cancel);
+        assert!(matches!(&events[..], [WatchEvent::Failed { .. }]));
+        assert!(watcher.poll(&cancel).is_empty());
+        assert!(watcher.ledger().get("broken.png").unwrap().error.is_some());
+    }
+}
@@ -1,12 +1,26 @@
-use crate::compression::{compress_image_inproc, compression_level_to_range};
-use crate::compression_options::CompressionOptions;
+use crate::settings::{SettingsStore, UserSettings, SETTINGS_FILE};
 use anyhow::Result;
+use compression::{
+    compress_image_detailed, compress_image_pages_with_cancel, images_to_pdf, run_parallel,
+    CancelToken, CompressedImage,
+};
+use domain::{
+    compression_level_to_range, parse_color_profile, parse_tiff_compression, Adjustments,
+    CompressionOptions, Gravity, PdfOptions, Transform, TrimRect, Watermark, WatermarkKind,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
-use tauri::Window;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+use tauri::ipc::{InvokeBody, Request, Response};
+use tauri::{AppHandle, Emitter, Manager, State, Window};
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Event emitted by [`compress_batch_events`] as each file starts and finishes.
+pub const BATCH_PROGRESS_EVENT: &str = "compress-batch-progress";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompressionResult {
     pub original_path: String,
     pub compressed_path: Option<String>,
@@ -17,6 +31,22 @@ pub struct CompressionResult {
     pub data: Vec<u8>,
     #[serde(default)]
     pub requested_output_format: Option<String>,
+    /// 1-based page number when a multi-page input was split into separate results.
+    #[serde(default)]
+    pub page: Option<u32>,
+    /// Content rectangle kept by a `trim` transform, in input pixels.
+    #[serde(default)]
+    pub trimmed: Option<TrimRect>,
+    /// Bits per channel in `data` (8, 10 for AVIF, 16 for high-depth PNG/TIFF).
+    #[serde(default)]
+    pub bit_depth: Option<u8>,
+    /// Save name rendered from the `filenameTemplate` setting (default `{stem}.{ext}`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggested_filename: Option<String>,
+    /// Set instead of `data` in [`BatchProgress::Finished`] events: fetch the bytes with
+    /// [`take_batch_output`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -24,25 +54,51 @@ pub struct FileInfo {
     pub path: String,
     pub name: String,
     pub size: u64,
+    /// `/`-separated path below a picked or dropped folder (`photos/2024/a.png`); outputs are
+    /// saved at the same place below the output folder. `None` for files chosen directly.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relative_path: Option<String>,
+}
+
+impl From<scan::ScannedFile> for FileInfo {
+    fn from(file: scan::ScannedFile) -> Self {
+        let name = file
+            .path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown")
+            .to_string();
+        Self {
+            path: file.path.to_string_lossy().to_string(),
+            relative_path: file
+                .relative_path
+                .contains('/')
+                .then_some(file.relative_path),
+            name,
+            size: file.size,
+        }
+    }
 }
 
 /// Select files using Tauri file dialog
 #[tauri::command]
-pub async fn select_files(app: tauri::AppHandle) -> Result<Vec<FileInfo>, String> {
+pub async fn select_files(
+    app: tauri::AppHandle,
+    settings: State<'_, Settings>,
+) -> Result<Vec<FileInfo>, String> {
     use std::sync::mpsc;
     use tauri_plugin_dialog::DialogExt;
 
     let (tx, rx) = mpsc::channel();
+    let extensions = compression::supported_input_extensions();
 
-    app.dialog()
-        .file()
-        .add_filter(
-            "Images",
-            &["png", "jpg", "jpeg", "bmp", "tiff", "tif", "webp", "ico"],
-        )
-        .pick_files(move |paths| {
-            let _ = tx.send(paths);
-        });
+    let mut dialog = app.dialog().file().add_filter("Images", &extensions);
+    if let Some(folder) = settings.last_folder(&app, |s| &s.input_folder) {
+        dialog = dialog.set_directory(folder);
+    }
+    dialog.pick_files(move |paths| {
+        let _ = tx.send(paths);
+    });
 
     let file_paths = rx
         .recv()
@@ -66,130 +122,346 @@ pub async fn select_files(app: tauri::AppHandle) -> Result<Vec<FileInfo>, String
             path: path_buf.to_string_lossy().to_string(),
             name,
             size: metadata.len(),
+            relative_path: None,
         });
     }
+    if let Some(folder) = files.first().and_then(|f| Path::new(&f.path).parent()) {
+        settings.remember_folder(&app, "inputFolder", folder);
+    }
 
     Ok(files)
 }
 
-/// Handle files dropped onto the window (from drag & drop)
+/// Pick a folder and list the supported images below it, recursively, that pass `filter`.
+/// Each file's `relative_path` starts with the folder's name.
 #[tauri::command]
-pub async fn handle_dropped_files(file_paths: Vec<String>) -> Result<Vec<FileInfo>, String> {
-    let mut files = Vec::new();
+pub async fn select_folder(
+    app: tauri::AppHandle,
+    settings: State<'_, Settings>,
+    filter: Option<scan::ScanFilter>,
+) -> Result<Vec<FileInfo>, String> {
+    use std::sync::mpsc;
+    use tauri_plugin_dialog::DialogExt;
 
-    for path_str in file_paths {
-        let path_buf = Path::new(&path_str);
-        let metadata =
-            fs::metadata(path_buf).map_err(|e| format!("Failed to read file metadata: {}", e))?;
-        let name = path_buf
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown")
-            .to_string();
-        files.push(FileInfo {
-            path: path_str,
-            name,
-            size: metadata.len(),
-        });
+    let (tx, rx) = mpsc::channel();
+    let mut dialog = app.dialog().file();
+    if let Some(folder) = settings.last_folder(&app, |s| &s.input_folder) {
+        dialog = dialog.set_directory(folder);
     }
+    dialog.pick_folder(move |path| {
+        let _ = tx.send(path);
+    });
 
-    Ok(files)
+    let folder_path = rx
+        .recv()
+        .map_err(|_| "Dialog cancelled".to_string())?
+        .ok_or_else(|| "No folder selected".to_string())?;
+    let path_buf = folder_path
+        .as_path()
+        .ok_or_else(|| "Invalid path".to_string())?
+        .to_path_buf();
+    settings.remember_folder(&app, "inputFolder", &path_buf);
+
+    let found = scan::scan_paths(
+        &[path_buf],
+        &filter.unwrap_or_default(),
+        &compression::supported_input_extensions(),
+    )?;
+    if found.is_empty() {
+        return Err("No supported images found in the folder".to_string());
+    }
+    Ok(found.into_iter().map(FileInfo::from).collect())
 }
 
-/// Compress a single image file
+/// Handle files dropped onto the window (from drag & drop). Dropped folders are expanded
+/// like [`select_folder`]; dropped files are kept as they are.
 #[tauri::command]
-pub async fn compress_image(
-    file_path: String,
-    compression_level: String,
-    output_format: String,
-    oxipng: bool,
-    png_lossy: bool,
-) -> Result<CompressionResult, String> {
-    // Read file
-    let file_bytes = fs::read(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+pub async fn handle_dropped_files(
+    file_paths: Vec<String>,
+    filter: Option<scan::ScanFilter>,
+) -> Result<Vec<FileInfo>, String> {
+    let paths: Vec<PathBuf> = file_paths.iter().map(PathBuf::from).collect();
+    let found = scan::scan_paths(
+        &paths,
+        &filter.unwrap_or_default(),
+        &compression::supported_input_extensions(),
+    )
+    .map_err(|e| format!("Failed to read dropped files: {}", e))?;
+    Ok(found.into_iter().map(FileInfo::from).collect())
+}
 
-    // Get file extension
-    let ext = Path::new(&file_path)
-        .extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("")
-        .to_lowercase();
+/// Compression settings sent by the frontend with each compress command.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompressSettings {
+    pub compression_level: String,
+    pub output_format: String,
+    pub oxipng: bool,
+    pub png_lossy: bool,
+    /// Encoder quality (0-100) for every format; unset uses the level's quality table.
+    #[serde(default)]
+    pub quality: Option<u8>,
+    #[serde(default)]
+    pub jxl_lossless_jpeg: bool,
+    /// `none`, `lzw`, `deflate` or `packbits`; empty means the default (LZW).
+    #[serde(default)]
+    pub tiff_compression: String,
+    #[serde(default)]
+    pub split_pages: bool,
+    /// Dither when 16-bit input is reduced to 8 bits.
+    #[serde(default)]
+    pub dither: bool,
+    /// `srgb`, `keep` or `ignore`; empty means convert to sRGB.
+    #[serde(default)]
+    pub color_profile: String,
+    /// Rotate/flip/crop operations, applied in order before encoding.
+    #[serde(default)]
+    pub transforms: Vec<Transform>,
+    /// Colour corrections, applied after the transforms.
+    #[serde(default)]
+    pub adjustments: Adjustments,
+    /// Watermark preset to stamp on every output (see [`save_watermark_preset`]).
+    #[serde(default)]
+    pub watermark: Option<WatermarkPreset>,
+    /// Files compressed at once by `compress_batch`; `0` means one per CPU core.
+    #[serde(default)]
+    pub concurrency: usize,
+    /// Output name template such as `{stem}-{width}w.{ext}`; empty means `{stem}.{ext}`.
+    #[serde(default)]
+    pub filename_template: String,
+}
 
-    // Map compression level to quality range
-    let quality_range = compression_level_to_range(&compression_level);
+/// Desktop watermark preset. The logo is referenced by path and read when compressing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WatermarkPreset {
+    /// Logo image (PNG with alpha); takes precedence over `text`.
+    pub image_path: Option<String>,
+    pub text: String,
+    pub color: String,
+    pub gravity: Gravity,
+    pub margin: u32,
+    pub scale: f32,
+    pub opacity: f32,
+    pub tile: bool,
+}
 
-    // Build compression options
-    let mut opts = CompressionOptions {
-        png_quality: quality_range,
-        oxipng,
-        png_lossy,
-        ..Default::default()
-    };
+impl Default for WatermarkPreset {
+    fn default() -> Self {
+        let defaults = Watermark::new(WatermarkKind::Text {
+            text: String::new(),
+            color: "#ffffff".to_string(),
+        });
+        Self {
+            image_path: None,
+            text: String::new(),
+            color: "#ffffff".to_string(),
+            gravity: defaults.gravity,
+            margin: defaults.margin,
+            scale: defaults.scale,
+            opacity: defaults.opacity,
+            tile: defaults.tile,
+        }
+    }
+}
 
-    // Set output format
-    match output_format.as_str() {
-        "webp" => opts.to_webp = true,
-        "avif" => opts.to_avif = true,
-        "jpeg" => opts.to_jpeg = true,
-        "png" => opts.to_png = true,
-        "tiff" => opts.to_tiff = true,
-        "bmp" => opts.to_bmp = true,
-        "ico" => opts.to_ico = true,
-        "original" => {}          // Keep original format
-        _ => opts.to_webp = true, // Default to WebP
-    }
-
-    // Compress
-    let (compressed_bytes, mime_type) = compress_image_inproc(&file_bytes, &ext, &opts)
-        .map_err(|e| format!("Compression failed: {}", e))?;
+impl WatermarkPreset {
+    /// `None` when the preset has neither a logo nor text.
+    fn to_watermark(&self) -> Result<Option<Watermark>, String> {
+        let kind = match self.image_path.as_deref().filter(|p| !p.is_empty()) {
+            Some(path) => WatermarkKind::Image {
+                data: fs::read(path)
+                    .map_err(|e| format!("Failed to read watermark image: {}", e))?,
+            },
+            None if !self.text.trim().is_empty() => WatermarkKind::Text {
+                text: self.text.clone(),
+                color: self.color.clone(),
+            },
+            None => return Ok(None),
+        };
+        Ok(Some(Watermark {
+            kind,
+            gravity: self.gravity,
+            margin: self.margin,
+            scale: self.scale,
+            opacity: self.opacity,
+            tile: self.tile,
+        }))
+    }
+}
+
+impl CompressSettings {
+    fn filename_template(&self) -> Option<String> {
+        let template = self.filename_template.trim();
+        (!template.is_empty()).then(|| template.to_string())
+    }
+
+    fn to_options(&self) -> Result<CompressionOptions, String> {
+        if let Some(template) = self.filename_template() {
+            naming::validate_template(&template)?;
+        }
+        let watermark = match &self.watermark {
+            Some(preset) => preset.to_watermark()?,
+            None => None,
+        };
+        let mut opts = CompressionOptions {
+            png_quality: compression_level_to_range(&self.compression_level),
+            oxipng: self.oxipng,
+            png_lossy: self.png_lossy,
+            jxl_lossless_jpeg: self.jxl_lossless_jpeg,
+            tiff_compression: parse_tiff_compression(&self.tiff_compression),
+            split_pages: self.split_pages,
+            dither: self.dither,
+            color_profile: parse_color_profile(&self.color_profile),
+            transforms: self.transforms.clone(),
+            adjustments: self.adjustments.clone(),
+            watermark,
+            filename_template: self.filename_template(),
+            quality: self.quality.map(|quality| quality.min(100)),
+            ..Default::default()
+        };
+        opts.set_output_format(&self.output_format);
+        Ok(opts)
+    }
+}
+
+fn file_extension(file_path: &str) -> String {
+    Path::new(file_path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase()
+}
 
-    let original_size = file_bytes.len() as u64;
-    let compressed_size = compressed_bytes.len() as u64;
-    let savings_percent = if original_size > 0 {
+/// Size change in percent; negative when the output is smaller.
+fn savings_percent(original_size: u64, compressed_size: u64) -> f64 {
+    if original_size > 0 {
         ((compressed_size as f64 - original_size as f64) / original_size as f64) * 100.0
     } else {
         0.0
-    };
+    }
+}
 
-    Ok(CompressionResult {
-        original_path: file_path,
+fn build_result(
+    file_path: &str,
+    original_size: u64,
+    output: CompressedImage,
+    settings: &CompressSettings,
+    page: Option<u32>,
+    suggested_filename: String,
+) -> CompressionResult {
+    let compressed_size = output.data.len() as u64;
+    CompressionResult {
+        original_path: file_path.to_string(),
         compressed_path: None,
         original_size,
         compressed_size,
-        savings_percent,
-        mime_type,
-        data: compressed_bytes,
-        requested_output_format: Some(output_format),
+        savings_percent: savings_percent(original_size, compressed_size),
+        mime_type: output.mime_type,
+        data: output.data,
+        requested_output_format: Some(settings.output_format.clone()),
+        page,
+        trimmed: output.trimmed,
+        bit_depth: Some(output.bit_depth),
+        suggested_filename: Some(suggested_filename),
+        output_id: None,
+    }
+}
+
+/// Compress a single image file
+#[tauri::command]
+pub async fn compress_image(
+    file_path: String,
+    settings: CompressSettings,
+) -> Result<CompressionResult, String> {
+    let options = settings.to_options()?;
+
+    // Encoding is CPU-bound; keep it off the async runtime.
+    tauri::async_runtime::spawn_blocking(move || {
+        let file_bytes = fs::read(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+        let ext = file_extension(&file_path);
+
+        let output = compress_image_detailed(&file_bytes, &ext, &options)
+            .map_err(|e| format!("Compression failed: {}", e))?;
+        let suggested = output.suggested_filename(&file_path, &options, 1, None);
+
+        Ok(build_result(
+            &file_path,
+            file_bytes.len() as u64,
+            output,
+            &settings,
+            None,
+            suggested,
+        ))
     })
+    .await
+    .map_err(|e| format!("Compression worker failed: {}", e))?
+}
+
+/// Compress one file; a split multi-page input yields one result per page. `index` is the
+/// file's position in its batch.
+fn compress_file_pages(
+    file_path: &str,
+    index: usize,
+    settings: &CompressSettings,
+    options: &CompressionOptions,
+    cancel: &CancelToken,
+) -> Result<Vec<CompressionResult>, String> {
+    let file_bytes = fs::read(file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let ext = file_extension(file_path);
+
+    let outputs = compress_image_pages_with_cancel(&file_bytes, &ext, options, cancel)
+        .map_err(|e| format!("Compression failed: {}", e))?;
+
+    let split = outputs.len() > 1;
+    Ok(outputs
+        .into_iter()
+        .enumerate()
+        .map(|(page_index, output)| {
+            let page = split.then_some(page_index as u32 + 1);
+            let suggested = output.suggested_filename(file_path, options, index + 1, page);
+            build_result(
+                file_path,
+                file_bytes.len() as u64,
+                output,
+                settings,
+                page,
+                suggested,
+            )
+        })
+        .collect())
 }
 
 /// Compress multiple images in batch
 #[tauri::command]
 pub async fn compress_batch(
     file_paths: Vec<String>,
-    compression_level: String,
-    output_format: String,
-    oxipng: bool,
-    png_lossy: bool,
+    settings: CompressSettings,
 ) -> Result<Vec<CompressionResult>, String> {
-    let mut results = Vec::new();
+    let options = settings.to_options()?;
+    let concurrency = settings.concurrency;
+
+    // Files are read inside the workers, so at most `concurrency` are in memory at once.
+    let outcomes = tauri::async_runtime::spawn_blocking(move || {
+        let jobs: Vec<(usize, String)> = file_paths.into_iter().enumerate().collect();
+        run_parallel(jobs, concurrency, |(index, file_path)| {
+            let outcome =
+                compress_file_pages(&file_path, index, &settings, &options, &CancelToken::new());
+            (file_path, outcome)
+        })
+    })
+    .await
+    .map_err(|e| format!("Batch worker failed: {}", e))?;
 
-    for file_path in file_paths {
-        match compress_image(
-            file_path.clone(),
-            compression_level.clone(),
-            output_format.clone(),
-            oxipng,
-            png_lossy,
-        )
-        .await
-        {
-            Ok(result) => results.push(result),
+    let mut results = Vec::new();
+    for (file_path, outcome) in outcomes {
+        match outcome {
+            Ok(pages) => results.extend(pages),
             Err(e) => {
                 // Continue with other files even if one fails
-                let file_path_clone = file_path.clone();
+                log::error!("Failed to compress {}: {}", file_path, e);
                 results.push(CompressionResult {
-                    original_path: file_path_clone.clone(),
+                    original_path: file_path,
                     compressed_path: None,
                     original_size: 0,
                     compressed_size: 0,
@@ -197,8 +469,12 @@ pub async fn compress_batch(
                     mime_type: String::new(),
                     data: Vec::new(),
                     requested_output_format: None,
+                    page: None,
+                    trimmed: None,
+                    bit_depth: None,
+                    suggested_filename: None,
+                    output_id: None,
                 });
-                log::error!("Failed to compress {}: {}", file_path_clone, e);
             }
         }
     }
@@ -206,15 +482,487 @@ pub async fn compress_batch(
     Ok(results)
 }
 
+/// Per-file progress of a [`compress_batch_events`] run. `index` is the file's position in
+/// `file_paths`; files finish in any order when the batch runs in parallel.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum BatchProgress {
+    #[serde(rename_all = "camelCase")]
+    Started {
+        batch_id: String,
+        index: usize,
+        path: String,
+    },
+    /// One result per output (several for a split multi-page input).
+    #[serde(rename_all = "camelCase")]
+    Finished {
+        batch_id: String,
+        index: usize,
+        path: String,
+        results: Vec<CompressionResult>,
+        done: usize,
+        total: usize,
+    },
+    #[serde(rename_all = "camelCase")]
+    Failed {
+        batch_id: String,
+        index: usize,
+        path: String,
+        error: String,
+        done: usize,
+        total: usize,
+    },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchSummary {
+    pub total: usize,
+    pub failed: usize,
+    /// Files reported through a `finished` or `failed` event
+    pub completed: usize,
+    /// Whether [`cancel_batch`] stopped the batch before every file was compressed
+    pub cancelled: bool,
+}
+
+/// Cancel tokens of the running [`compress_batch_events`] calls, by batch id.
+#[derive(Default)]
+pub struct RunningBatches(Mutex<HashMap<String, CancelToken>>);
+
+impl RunningBatches {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, CancelToken>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Compressed bytes of [`compress_batch_events`] results, by output id, until the frontend
+/// fetches them with [`take_batch_output`]. Keeps image data out of the JSON events.
+#[derive(Default)]
+pub struct BatchOutputs(Mutex<HashMap<String, StoredOutput>>);
+
+struct StoredOutput {
+    batch_id: String,
+    data: Vec<u8>,
+}
+
+impl BatchOutputs {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, StoredOutput>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Move each result's `data` into the store, leaving an `output_id` in its place.
+    fn stash(
+        &self,
+        batch_id: &str,
+        index: usize,
+        results: Vec<CompressionResult>,
+    ) -> Vec<CompressionResult> {
+        let mut outputs = self.lock();
+        results
+            .into_iter()
+            .enumerate()
+            .map(|(n, mut result)| {
+                let id = format!("{}/{}/{}", batch_id, index, n);
+                let data = std::mem::take(&mut result.data);
+                let batch_id = batch_id.to_string();
+                outputs.insert(id.clone(), StoredOutput { batch_id, data });
+                result.output_id = Some(id);
+                result
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileOutcome {
+    Finished,
+    Failed,
+    Cancelled,
+}
+
+/// Compress multiple images, streaming results as [`BATCH_PROGRESS_EVENT`] events tagged with
+/// `batch_id` instead of returning them. Events carry an `output_id` per result in place of its
+/// bytes; see [`take_batch_output`]. Resolves once every file has finished, failed or been
+/// skipped by [`cancel_batch`]; files that finished before a cancel keep their events.
+#[tauri::command]
+pub async fn compress_batch_events(
+    app: AppHandle,
+    batches: State<'_, RunningBatches>,
+    outputs: State<'_, BatchOutputs>,
+    batch_id: String,
+    file_paths: Vec<String>,
+    settings: CompressSettings,
+) -> Result<BatchSummary, String> {
+    let options = settings.to_options()?;
+    let concurrency = settings.concurrency;
+    let total = file_paths.len();
+    let jobs: Vec<(usize, String)> = file_paths.into_iter().enumerate().collect();
+    let cancel = CancelToken::new();
+    {
+        let mut running = batches.lock();
+        running.insert(batch_id.clone(), cancel.clone());
+        // Outputs of earlier batches the frontend never fetched (e.g. its window reloaded).
+        outputs
+            .lock()
+            .retain(|_, output| running.contains_key(&output.batch_id));
+    }
+
+    let worker_batch_id = batch_id.clone();
+    let outcomes = tauri::async_runtime::spawn_blocking(move || {
+        let batch_id = worker_batch_id;
+        let outputs = app.state::<BatchOutputs>();
+        let done = AtomicUsize::new(0);
+        let emit = |event: BatchProgress| {
+            if let Err(e) = app.emit(BATCH_PROGRESS_EVENT, event) {
+                log::warn!("Failed to emit batch progress: {}", e);
+            }
+        };
+        run_parallel(jobs, concurrency, |(index, path)| {
+            if cancel.is_cancelled() {
+                return FileOutcome::Cancelled;
+            }
+            emit(BatchProgress::Started {
+                batch_id: batch_id.clone(),
+                index,
+                path: path.clone(),
+            });
+            let outcome = compress_file_pages(&path, index, &settings, &options, &cancel);
+            if outcome.is_err() && cancel.is_cancelled() {
+                return FileOutcome::Cancelled;
+            }
+            let done = done.fetch_add(1, Ordering::SeqCst) + 1;
+            match outcome {
+                Ok(results) => {
+                    emit(BatchProgress::Finished {
+                        batch_id: batch_id.clone(),
+                        index,
+                        path,
+                        results: outputs.stash(&batch_id, index, results),
+                        done,
+                        total,
+                    });
+                    FileOutcome::Finished
+                }
+                Err(error) => {
+                    log::error!("Failed to compress {}: {}", path, error);
+                    emit(BatchProgress::Failed {
+                        batch_id: batch_id.clone(),
+                        index,
+                        path,
+                        error,
+                        done,
+                        total,
+                    });
+                    FileOutcome::Failed
+                }
+            }
+        })
+    })
+    .await;
+    batches.lock().remove(&batch_id);
+    let outcomes = outcomes.map_err(|e| format!("Batch worker failed: {}", e))?;
+
+    let count = |kind: FileOutcome| outcomes.iter().filter(|o| **o == kind).count();
+    let failed = count(FileOutcome::Failed);
+    Ok(BatchSummary {
+        total,
+        failed,
+        completed: count(FileOutcome::Finished) + failed,
+        cancelled: count(FileOutcome::Cancelled) > 0,
+    })
+}
+
+/// Stop a running [`compress_batch_events`] call between files and pipeline stages. Returns
+/// whether a batch with that id was running.
+#[tauri::command]
+pub async fn cancel_batch(
+    batches: State<'_, RunningBatches>,
+    batch_id: String,
+) -> Result<bool, String> {
+    let token = batches.lock().get(&batch_id).cloned();
+    if let Some(token) = &token {
+        log::info!("Cancelling batch {}", batch_id);
+        token.cancel();
+    }
+    Ok(token.is_some())
+}
+
+/// The compressed bytes of a streamed batch result, as a raw IPC response. Each output can be
+/// taken once.
+#[tauri::command]
+pub async fn take_batch_output(
+    outputs: State<'_, BatchOutputs>,
+    output_id: String,
+) -> Result<Response, String> {
+    outputs
+        .lock()
+        .remove(&output_id)
+        .map(|output| Response::new(output.data))
+        .ok_or_else(|| format!("Unknown or already fetched output '{}'", output_id))
+}
+
+/// Event emitted by [`start_folder_watch`] for each processed file.
+pub const FOLDER_WATCH_EVENT: &str = "folder-watch";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum FolderWatchEvent {
+    /// The outputs are already saved: each result has `compressed_path` set and no `data`.
+    Compressed { results: Vec<CompressionResult> },
+    /// `path` is the watched folder itself when it could not be scanned.
+    Failed { path: String, error: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderWatchStatus {
+    pub input: String,
+    pub output: String,
+}
+
+/// The watcher started by [`start_folder_watch`], if any.
+#[derive(Default)]
+pub struct FolderWatch(Mutex<Option<RunningWatch>>);
+
+struct RunningWatch {
+    cancel: CancelToken,
+    status: FolderWatchStatus,
+}
+
+impl FolderWatch {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<RunningWatch>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn watch_results(
+    input: &Path,
+    original_size: u64,
+    outputs: Vec<watch::WatchOutput>,
+    settings: &CompressSettings,
+) -> Vec<CompressionResult> {
+    outputs
+        .into_iter()
+        .map(|output| CompressionResult {
+            original_path: input.to_string_lossy().to_string(),
+            suggested_filename: output
+                .path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string()),
+            compressed_path: Some(output.path.to_string_lossy().to_string()),
+            original_size,
+            compressed_size: output.size,
+            savings_percent: savings_percent(original_size, output.size),
+            mime_type: output.mime_type,
+            data: Vec::new(),
+            requested_output_format: Some(settings.output_format.clone()),
+            page: output.page,
+            trimmed: output.trimmed,
+            bit_depth: Some(output.bit_depth),
+            output_id: None,
+        })
+        .collect()
+}
+
+/// Compress images as they appear in `input` into `output`, reporting each file as a
+/// [`FOLDER_WATCH_EVENT`]. Files are picked up once they stop changing; a ledger in the
+/// output folder keeps restarts from redoing finished files. Replaces any running watch.
+#[tauri::command]
+pub async fn start_folder_watch(
+    app: AppHandle,
+    folder_watch: State<'_, FolderWatch>,
+    input: String,
+    output: String,
+    settings: CompressSettings,
+    filter: Option<scan::ScanFilter>,
+) -> Result<FolderWatchStatus, String> {
+    let config = watch::WatchConfig {
+        filter: filter.unwrap_or_default(),
+        ..watch::WatchConfig::new(input.into(), output.into(), settings.to_options()?)
+    };
+    let mut watcher = watch::Watcher::new(config)?;
+    let status = FolderWatchStatus {
+        input: watcher.config().input.to_string_lossy().to_string(),
+        output: watcher.config().output.to_string_lossy().to_string(),
+    };
+    let cancel = CancelToken::new();
+    let running = RunningWatch {
+        cancel: cancel.clone(),
+        status: status.clone(),
+    };
+    if let Some(previous) = folder_watch.lock().replace(running) {
+        previous.cancel.cancel();
+    }
+
+    log::info!("Watching {} -> {}", status.input, status.output);
+    tauri::async_runtime::spawn_blocking(move || {
+        watcher.run(&cancel, |event| {
+            let event = match event {
+                watch::WatchEvent::Compressed {
+                    input,
+                    original_size,
+                    outputs,
+                } => FolderWatchEvent::Compressed {
+                    results: watch_results(&input, original_size, outputs, &settings),
+                },
+                watch::WatchEvent::Failed { input, error } => {
+                    log::error!("Failed to compress {}: {}", input.display(), error);
+                    FolderWatchEvent::Failed {
+                        path: input.to_string_lossy().to_string(),
+                        error,
+                    }
+                }
+            };
+            if let Err(e) = app.emit(FOLDER_WATCH_EVENT, event) {
+                log::warn!("Failed to emit folder watch event: {}", e);
+            }
+        });
+    });
+    Ok(status)
+}
+
+/// Stop the [`start_folder_watch`] watcher. Returns whether one was running.
+#[tauri::command]
+pub async fn stop_folder_watch(folder_watch: State<'_, FolderWatch>) -> Result<bool, String> {
+    let running = folder_watch.lock().take();
+    if let Some(running) = &running {
+        log::info!("Stopped watching {}", running.status.input);
+        running.cancel.cancel();
+    }
+    Ok(running.is_some())
+}
+
+/// The folders of the running [`start_folder_watch`] watcher, e.g. after a window reload.
+#[tauri::command]
+pub async fn folder_watch_status(
+    folder_watch: State<'_, FolderWatch>,
+) -> Result<Option<FolderWatchStatus>, String> {
+    Ok(folder_watch
+        .lock()
+        .as_ref()
+        .map(|running| running.status.clone()))
+}
+
+/// A file listed in the header of a raw save request body.
+#[derive(Debug, Deserialize)]
+struct RawFile {
+    filename: String,
+    len: usize,
+}
+
+/// Split a raw IPC body into its JSON header and the bytes after it. The body is a
+/// little-endian `u32` header length, the header, then the data (for the save commands, every
+/// file of the header's `files` list back to back).
+fn read_raw_body<'r, H: serde::de::DeserializeOwned>(
+    request: &'r Request<'_>,
+) -> Result<(H, &'r [u8]), String> {
+    let InvokeBody::Raw(body) = request.body() else {
+        return Err("Expected a raw request body".to_string());
+    };
+    let header_len = body
+        .get(..4)
+        .and_then(|len| <[u8; 4]>::try_from(len).ok())
+        .map(|len| u32::from_le_bytes(len) as usize)
+        .ok_or_else(|| "Truncated request body".to_string())?;
+    let header = body
+        .get(4..4 + header_len)
+        .ok_or_else(|| "Truncated request header".to_string())?;
+    let header =
+        serde_json::from_slice(header).map_err(|e| format!("Invalid request header: {}", e))?;
+    Ok((header, &body[4 + header_len..]))
+}
+
+/// `name` as a single safe filename ([`naming::sanitize_filename`]); an error when nothing
+/// usable is left.
+fn safe_filename(name: &str) -> Result<String, String> {
+    let safe = naming::sanitize_filename(name);
+    if safe.is_empty() {
+        return Err(format!("Invalid filename {:?}", name));
+    }
+    Ok(safe)
+}
+
+/// `path` as a safe relative path below the output folder ([`naming::sanitize_relative_path`]),
+/// so folder structure from [`select_folder`] survives; an error for `..` or an empty name.
+fn safe_relative_path(path: &str) -> Result<String, String> {
+    let safe = naming::sanitize_relative_path(path);
+    if safe.is_empty() {
+        return Err(format!("Invalid filename {:?}", path));
+    }
+    Ok(safe)
+}
+
+/// Cut the data of a raw save body into one slice per listed file.
+fn split_raw_files<'d>(files: &[RawFile], mut data: &'d [u8]) -> Result<Vec<&'d [u8]>, String> {
+    let mut slices = Vec::with_capacity(files.len());
+    for file in files {
+        if file.len > data.len() {
+            return Err(format!("Missing data for {}", file.filename));
+        }
+        let (head, rest) = data.split_at(file.len);
+        slices.push(head);
+        data = rest;
+    }
+    Ok(slices)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SaveFilesHeader {
+    output_folder: String,
+    files: Vec<RawFile>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SaveZipHeader {
+    output_folder: String,
+    zip_filename: String,
+    files: Vec<RawFile>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SavePdfHeader {
+    output_folder: String,
+    pdf_filename: String,
+    files: Vec<RawFile>,
+    options: PdfOptions,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InPlaceHeader {
+    /// Input of each file, in the same order
+    original_paths: Vec<String>,
+    #[serde(flatten)]
+    options: inplace::InPlaceOptions,
+    files: Vec<RawFile>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SaveFileHeader {
+    original_path: String,
+    default_name: String,
+}
+
 /// Select output folder for saving files
 #[tauri::command]
-pub async fn select_output_folder(app: tauri::AppHandle) -> Result<String, String> {
+pub async fn select_output_folder(
+    app: tauri::AppHandle,
+    settings: State<'_, Settings>,
+) -> Result<String, String> {
     use std::sync::mpsc;
     use tauri_plugin_dialog::DialogExt;
 
     let (tx, rx) = mpsc::channel();
 
-    app.dialog().file().pick_folder(move |path| {
+    let mut dialog = app.dialog().file();
+    if let Some(folder) = settings.last_folder(&app, |s| &s.output_folder) {
+        dialog = dialog.set_directory(folder);
+    }
+    dialog.pick_folder(move |path| {
         let _ = tx.send(path);
     });
 
@@ -227,55 +975,40 @@ pub async fn select_output_folder(app: tauri::AppHandle) -> Result<String, Strin
         .as_path()
         .ok_or_else(|| "Invalid path".to_string())?
         .to_path_buf();
+    settings.remember_folder(&app, "outputFolder", &path_buf);
 
     Ok(path_buf.to_string_lossy().to_string())
 }
 
-/// Save multiple files to a folder (no per-file dialogs)
+/// Save multiple files to a folder (no per-file dialogs). Raw body: see [`read_raw_body`].
 #[tauri::command]
-pub async fn save_files_to_folder(
-    output_folder: String,
-    files: Vec<serde_json::Value>,
-) -> Result<Vec<String>, String> {
-    use serde_json;
-    use std::fs;
-    use std::path::Path;
-
-    let folder_path = Path::new(&output_folder);
+pub async fn save_files_to_folder(request: Request<'_>) -> Result<Vec<String>, String> {
+    let (header, data) = read_raw_body::<SaveFilesHeader>(&request)?;
+    let folder_path = Path::new(&header.output_folder);
 
     // Ensure folder exists
     fs::create_dir_all(folder_path).map_err(|e| format!("Failed to create folder: {}", e))?;
 
     let mut saved_paths = Vec::new();
 
-    for file in files {
-        let filename = file
-            .get("filename")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| "Missing filename".to_string())?;
-
-        // Parse data array from JSON - it comes as a JSON array of numbers
-        let data_array = file.get("data").ok_or_else(|| "Missing data".to_string())?;
-
-        // Convert JSON array to Vec<u8>
-        let data: Vec<u8> = serde_json::from_value(data_array.clone())
-            .map_err(|e| format!("Failed to parse data for {}: {}", filename, e))?;
-
+    for (file, data) in header
+        .files
+        .iter()
+        .zip(split_raw_files(&header.files, data)?)
+    {
+        let filename = &safe_relative_path(&file.filename)?;
         let file_path = folder_path.join(filename);
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create folder for {}: {}", filename, e))?;
+        }
 
         fs::write(&file_path, data).map_err(|e| format!("Failed to save {}: {}", filename, e))?;
 
         saved_paths.push(file_path.to_string_lossy().to_string());
 
         #[cfg(debug_assertions)]
-        {
-            let data_len = file
-                .get("data")
-                .and_then(|v| v.as_array())
-                .map(|arr| arr.len())
-                .unwrap_or_default();
-            log::debug!("Saved: {} ({} bytes)", filename, data_len);
-        }
+        log::debug!("Saved: {} ({} bytes)", filename, data.len());
     }
 
     Ok(saved_paths)
@@ -292,7 +1025,7 @@ pub async fn check_file_collisions(
     let mut collisions = Vec::new();
 
     for filename in filenames {
-        let file_path = folder_path.join(&filename);
+        let file_path = folder_path.join(safe_relative_path(&filename)?);
         if file_path.exists() {
             collisions.push(filename);
         }
@@ -301,22 +1034,24 @@ pub async fn check_file_collisions(
     Ok(collisions)
 }
 
-/// Returns display filenames that are unique in `output_folder` and within the batch (order-preserving).
+/// Returns display filenames that are unique in `output_folder` and within the batch
+/// (order-preserving). Names may be relative paths; each is made unique within its folder.
 #[tauri::command]
 pub async fn resolve_unique_filenames(
     output_folder: String,
     filenames: Vec<String>,
 ) -> Result<Vec<String>, String> {
     let folder_path = Path::new(&output_folder);
-    crate::filename_unique::resolve_unique_names_for_disk(folder_path, &filenames)
+    let filenames = filenames
+        .iter()
+        .map(|name| safe_relative_path(name))
+        .collect::<Result<Vec<_>, _>>()?;
+    naming::resolve_unique_names_for_disk(folder_path, &filenames)
 }
 
+/// Bundle files into one ZIP archive in the output folder. Raw body: see [`read_raw_body`].
 #[tauri::command]
-pub async fn save_files_as_zip(
-    output_folder: String,
-    zip_filename: String,
-    files: Vec<serde_json::Value>,
-) -> Result<String, String> {
+pub async fn save_files_as_zip(request: Request<'_>) -> Result<String, String> {
     use std::fs::File;
     use std::io::Write;
     use std::path::Path;
@@ -324,10 +1059,12 @@ pub async fn save_files_as_zip(
     use zip::CompressionMethod;
     use zip::ZipWriter;
 
-    let folder_path = Path::new(&output_folder);
+    let (header, data) = read_raw_body::<SaveZipHeader>(&request)?;
+    let contents = split_raw_files(&header.files, data)?;
+    let folder_path = Path::new(&header.output_folder);
     fs::create_dir_all(folder_path).map_err(|e| format!("Failed to create folder: {}", e))?;
 
-    let zip_path = folder_path.join(&zip_filename);
+    let zip_path = folder_path.join(safe_filename(&header.zip_filename)?);
     let zip_file =
         File::create(&zip_path).map_err(|e| format!("Failed to create ZIP file: {}", e))?;
 
@@ -336,30 +1073,18 @@ pub async fn save_files_as_zip(
         .compression_method(CompressionMethod::Deflated)
         .compression_level(Some(6));
 
-    let names: Vec<String> = files
+    let names = header
+        .files
         .iter()
-        .map(|file| {
-            file.get("filename")
-                .and_then(|v| v.as_str())
-                .map(String::from)
-                .ok_or_else(|| "Missing filename".to_string())
-        })
+        .map(|f| safe_relative_path(&f.filename))
         .collect::<Result<Vec<_>, _>>()?;
+    let unique_names = naming::uniquify_zip_entry_names(&names)?;
 
-    let unique_names = crate::filename_unique::uniquify_zip_entry_names(&names)?;
-
-    for (file, filename) in files.iter().zip(unique_names.iter()) {
-        let data_array = file
-            .get("data")
-            .ok_or_else(|| format!("Missing data for {}", filename))?;
-
-        let data: Vec<u8> = serde_json::from_value(data_array.clone())
-            .map_err(|e| format!("Failed to parse data for {}: {}", filename, e))?;
-
+    for (data, filename) in contents.into_iter().zip(unique_names.iter()) {
         zip.start_file(filename, file_options)
             .map_err(|e| format!("Failed to add {} to ZIP: {}", filename, e))?;
 
-        zip.write_all(&data)
+        zip.write_all(data)
             .map_err(|e| format!("Failed to write {} to ZIP: {}", filename, e))?;
     }
 
@@ -369,27 +1094,100 @@ pub async fn save_files_as_zip(
     Ok(zip_path.to_string_lossy().to_string())
 }
 
-/// Save compressed file to disk (saves in same directory as source by default)
+/// Bundle compressed results into one multi-page PDF (one image per page). Raw body: see
+/// [`read_raw_body`].
+#[tauri::command]
+pub async fn save_files_as_pdf(request: Request<'_>) -> Result<String, String> {
+    let (header, data) = read_raw_body::<SavePdfHeader>(&request)?;
+    let folder_path = Path::new(&header.output_folder);
+    fs::create_dir_all(folder_path).map_err(|e| format!("Failed to create folder: {}", e))?;
+
+    let images: Vec<Vec<u8>> = split_raw_files(&header.files, data)?
+        .into_iter()
+        .map(<[u8]>::to_vec)
+        .collect();
+
+    // Encoding is CPU-bound; keep it off the async runtime.
+    let options = header.options.clone();
+    let pdf = tauri::async_runtime::spawn_blocking(move || images_to_pdf(&images, &options))
+        .await
+        .map_err(|e| format!("PDF worker failed: {}", e))?
+        .map_err(|e| format!("Failed to build PDF: {}", e))?;
+
+    let pdf_path = folder_path.join(safe_filename(&header.pdf_filename)?);
+    fs::write(&pdf_path, pdf).map_err(|e| format!("Failed to write PDF file: {}", e))?;
+
+    Ok(pdf_path.to_string_lossy().to_string())
+}
+
+fn in_place_backup_root(app: &tauri::AppHandle) -> Result<std::path::PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve data directory: {}", e))?;
+    Ok(dir.join("backups"))
+}
+
+/// Write compressed results back over their originals, or next to them with `suffix`, after
+/// copying the originals into a timestamped folder below the app data dir. Raw body: see
+/// [`read_raw_body`]; the header adds `originalPaths` and the [`inplace::InPlaceOptions`]
+/// fields, and each file's name gives the output extension.
 #[tauri::command]
-pub async fn save_file(
+pub async fn optimize_in_place(
     app: tauri::AppHandle,
-    original_path: String,
-    default_name: String,
-    data: Vec<u8>,
-) -> Result<String, String> {
+    request: Request<'_>,
+) -> Result<inplace::RunSummary, String> {
+    let (header, data) = read_raw_body::<InPlaceHeader>(&request)?;
+    if header.original_paths.len() != header.files.len() {
+        return Err("Expected one original path per file".to_string());
+    }
+    let contents = split_raw_files(&header.files, data)?;
+    let files: Vec<inplace::InPlaceFile> = header
+        .original_paths
+        .iter()
+        .zip(&header.files)
+        .zip(contents)
+        .map(|((original, file), data)| inplace::InPlaceFile {
+            original: Path::new(original),
+            extension: Path::new(&file.filename)
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or_default(),
+            data,
+        })
+        .collect();
+    inplace::optimize_in_place(&in_place_backup_root(&app)?, &files, &header.options)
+}
+
+/// Restore the files changed by the last [`optimize_in_place`] run from its backup.
+#[tauri::command]
+pub async fn undo_last_in_place_run(app: tauri::AppHandle) -> Result<inplace::RunSummary, String> {
+    inplace::undo_last_run(&in_place_backup_root(&app)?)
+}
+
+/// Save compressed file to disk (saves in same directory as source by default). Raw body: a
+/// [`read_raw_body`] header with `originalPath` and `defaultName`, then the file's bytes.
+#[tauri::command]
+pub async fn save_file(app: tauri::AppHandle, request: Request<'_>) -> Result<String, String> {
     use std::path::Path;
     use std::sync::mpsc;
     use tauri_plugin_dialog::DialogExt;
 
+    let (header, data) = read_raw_body::<SaveFileHeader>(&request)?;
+    let SaveFileHeader {
+        original_path,
+        default_name,
+    } = header;
     let (tx, rx) = mpsc::channel();
 
     // Extract just the filename from default_name (in case it contains a path)
     // default_name should already be just the filename (e.g., "image.webp"), but be safe
-    let file_name_only = Path::new(&default_name)
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or(&default_name)
-        .to_string();
+    let file_name_only = naming::sanitize_filename(
+        Path::new(&default_name)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(&default_name),
+    );
 
     // Log summary only in debug mode
     #[cfg(debug_assertions)]
@@ -439,6 +1237,247 @@ pub async fn resize_window(window: Window, width: f64, height: f64) -> Result<()
     Ok(())
 }
 
+fn watermark_preset_path(app: &tauri::AppHandle) -> Result<std::path::PathBuf, String> {
+    use tauri::Manager;
+    let dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("Failed to resolve config directory: {}", e))?;
+    Ok(dir.join("watermark-preset.json"))
+}
+
+/// Load the saved watermark preset, if any
+#[tauri::command]
+pub async fn load_watermark_preset(
+    app: tauri::AppHandle,
+) -> Result<Option<WatermarkPreset>, String> {
+    let path = watermark_preset_path(&app)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let json =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read watermark preset: {}", e))?;
+    serde_json::from_str(&json)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse watermark preset: {}", e))
+}
+
+/// Save the watermark preset; `None` removes it
+#[tauri::command]
+pub async fn save_watermark_preset(
+    app: tauri::AppHandle,
+    preset: Option<WatermarkPreset>,
+) -> Result<(), String> {
+    let path = watermark_preset_path(&app)?;
+    match preset {
+        Some(preset) => {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)
+                    .map_err(|e| format!("Failed to create config directory: {}", e))?;
+            }
+            let json = serde_json::to_string_pretty(&preset)
+                .map_err(|e| format!("Failed to serialize watermark preset: {}", e))?;
+            fs::write(&path, json).map_err(|e| format!("Failed to save watermark preset: {}", e))
+        }
+        None if path.exists() => {
+            fs::remove_file(&path).map_err(|e| format!("Failed to remove watermark preset: {}", e))
+        }
+        None => Ok(()),
+    }
+}
+
+fn preset_store(app: &tauri::AppHandle) -> Result<presets::PresetStore, String> {
+    use tauri::Manager;
+    let dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("Failed to resolve config directory: {}", e))?;
+    presets::PresetStore::load(&dir.join(presets::PRESETS_FILE))
+}
+
+/// A preset as listed in the preset dropdown.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PresetEntry {
+    #[serde(flatten)]
+    pub preset: presets::Preset,
+    /// Built-in presets cannot be replaced or deleted.
+    pub builtin: bool,
+}
+
+fn preset_entries(store: &presets::PresetStore) -> Vec<PresetEntry> {
+    store
+        .all()
+        .into_iter()
+        .map(|preset| PresetEntry {
+            builtin: presets::is_builtin(&preset.name),
+            preset,
+        })
+        .collect()
+}
+
+/// Built-in presets followed by the user's
+#[tauri::command]
+pub async fn list_presets(app: tauri::AppHandle) -> Result<Vec<PresetEntry>, String> {
+    Ok(preset_entries(&preset_store(&app)?))
+}
+
+/// Save `settings` as the user preset `name`, replacing one of the same name. The watermark is
+/// left out; it has its own preset (see [`save_watermark_preset`]).
+#[tauri::command]
+pub async fn save_preset(
+    app: tauri::AppHandle,
+    name: String,
+    settings: CompressSettings,
+) -> Result<Vec<PresetEntry>, String> {
+    let settings = CompressSettings {
+        watermark: None,
+        ..settings
+    };
+    let mut store = preset_store(&app)?;
+    store.save(presets::Preset::new(&name, settings.to_options()?))?;
+    Ok(preset_entries(&store))
+}
+
+/// Delete the user preset `name`
+#[tauri::command]
+pub async fn delete_preset(
+    app: tauri::AppHandle,
+    name: String,
+) -> Result<Vec<PresetEntry>, String> {
+    let mut store = preset_store(&app)?;
+    if !store.remove(&name)? {
+        return Err(format!("No preset named '{}'", name));
+    }
+    Ok(preset_entries(&store))
+}
+
+/// Pick a `.json` or `.toml` preset file and add its presets, replacing user presets of the
+/// same names
+#[tauri::command]
+pub async fn import_presets(app: tauri::AppHandle) -> Result<Vec<PresetEntry>, String> {
+    use std::sync::mpsc;
+    use tauri_plugin_dialog::DialogExt;
+
+    let (tx, rx) = mpsc::channel();
+    app.dialog()
+        .file()
+        .add_filter("Presets", &["json", "toml"])
+        .pick_file(move |path| {
+            let _ = tx.send(path);
+        });
+    let path = rx
+        .recv()
+        .map_err(|_| "Dialog cancelled".to_string())?
+        .ok_or_else(|| "Import cancelled".to_string())?;
+    let path = path
+        .as_path()
+        .ok_or_else(|| "Invalid path".to_string())?
+        .to_path_buf();
+
+    let text = fs::read_to_string(&path).map_err(|e| format!("Failed to read presets: {}", e))?;
+    let mut store = preset_store(&app)?;
+    store.import(&text, presets::PresetFormat::from_path(&path))?;
+    Ok(preset_entries(&store))
+}
+
+/// Save the presets called `names` to a file; a `.toml` name writes TOML, anything else JSON.
+/// Returns the saved path.
+#[tauri::command]
+pub async fn export_presets(app: tauri::AppHandle, names: Vec<String>) -> Result<String, String> {
+    use std::sync::mpsc;
+    use tauri_plugin_dialog::DialogExt;
+
+    let default_name = match names.as_slice() {
+        [name] => format!("{}.json", naming::sanitize_filename(name.trim())),
+        _ => "presets.json".to_string(),
+    };
+    let (tx, rx) = mpsc::channel();
+    app.dialog()
+        .file()
+        .add_filter("Presets", &["json", "toml"])
+        .set_file_name(&default_name)
+        .save_file(move |path| {
+            let _ = tx.send(path);
+        });
+    let path = rx
+        .recv()
+        .map_err(|_| "Dialog cancelled".to_string())?
+        .ok_or_else(|| "Export cancelled".to_string())?;
+    let path = path
+        .as_path()
+        .ok_or_else(|| "Invalid path".to_string())?
+        .to_path_buf();
+
+    let text = preset_store(&app)?.export(&names, presets::PresetFormat::from_path(&path))?;
+    fs::write(&path, text).map_err(|e| format!("Failed to save presets: {}", e))?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// The user's settings, loaded from the app config folder on first use.
+#[derive(Default)]
+pub struct Settings(Mutex<Option<SettingsStore>>);
+
+impl Settings {
+    fn with<T>(
+        &self,
+        app: &tauri::AppHandle,
+        f: impl FnOnce(&mut SettingsStore) -> T,
+    ) -> Result<T, String> {
+        let dir = app
+            .path()
+            .app_config_dir()
+            .map_err(|e| format!("Failed to resolve config directory: {}", e))?;
+        let mut store = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        Ok(f(store.get_or_insert_with(|| {
+            SettingsStore::load(&dir.join(SETTINGS_FILE))
+        })))
+    }
+
+    /// The remembered folder `field` picks, if it still exists.
+    fn last_folder(
+        &self,
+        app: &tauri::AppHandle,
+        field: impl FnOnce(&UserSettings) -> &Option<String>,
+    ) -> Option<PathBuf> {
+        let folder = self.with(app, |store| field(store.get()).clone()).ok()??;
+        let folder = PathBuf::from(folder);
+        folder.is_dir().then_some(folder)
+    }
+
+    /// Save `folder` as the setting `key`. Failures are only logged; the pick still counts.
+    fn remember_folder(&self, app: &tauri::AppHandle, key: &str, folder: &Path) {
+        let mut changes = serde_json::Map::new();
+        changes.insert(key.to_string(), folder.to_string_lossy().into());
+        let saved = self
+            .with(app, |store| store.update(changes).map(|_| ()))
+            .and_then(|saved| saved);
+        if let Err(e) = saved {
+            log::warn!("Failed to remember {}: {}", key, e);
+        }
+    }
+}
+
+/// The settings saved in earlier sessions (defaults on first launch)
+#[tauri::command]
+pub async fn get_settings(
+    app: tauri::AppHandle,
+    settings: State<'_, Settings>,
+) -> Result<UserSettings, String> {
+    settings.with(&app, |store| store.get().clone())
+}
+
+/// Change the settings named in `changes` (camelCase fields of [`UserSettings`]), keep the
+/// rest, and save. Returns the updated settings.
+#[tauri::command]
+pub async fn update_settings(
+    app: tauri::AppHandle,
+    settings: State<'_, Settings>,
+    changes: serde_json::Map<String, serde_json::Value>,
+) -> Result<UserSettings, String> {
+    settings.with(&app, |store| store.update(changes).cloned())?
+}
+
 /// Open devtools (for debugging)
 #[tauri::command]
 pub async fn open_devtools(app: tauri::AppHandle) -> Result<(), String> {
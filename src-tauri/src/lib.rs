@@ -1,22 +1,43 @@
 mod commands;
-mod compression;
-mod compression_options;
-mod filename_unique;
+mod settings;
 
 /// Runs the Tauri desktop application.
 pub fn run() {
     let builder = tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
+        .manage(commands::RunningBatches::default())
+        .manage(commands::BatchOutputs::default())
+        .manage(commands::FolderWatch::default())
+        .manage(commands::Settings::default())
         .invoke_handler(tauri::generate_handler![
             commands::select_files,
+            commands::select_folder,
             commands::handle_dropped_files,
             commands::check_file_collisions,
             commands::resolve_unique_filenames,
             commands::compress_image,
             commands::compress_batch,
+            commands::compress_batch_events,
+            commands::cancel_batch,
+            commands::take_batch_output,
+            commands::start_folder_watch,
+            commands::stop_folder_watch,
+            commands::folder_watch_status,
             commands::select_output_folder,
             commands::save_files_to_folder,
             commands::save_files_as_zip,
+            commands::save_files_as_pdf,
+            commands::optimize_in_place,
+            commands::undo_last_in_place_run,
+            commands::load_watermark_preset,
+            commands::save_watermark_preset,
+            commands::list_presets,
+            commands::save_preset,
+            commands::delete_preset,
+            commands::import_presets,
+            commands::export_presets,
+            commands::get_settings,
+            commands::update_settings,
             commands::save_file,
             commands::resize_window,
             commands::open_devtools,
@@ -0,0 +1,267 @@
+//! User settings kept across sessions in `settings.json` in the app config folder.
+
+use domain::{CompressionLevel, CompressionOptions};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// File name of the settings inside the app config folder.
+pub const SETTINGS_FILE: &str = "settings.json";
+
+/// Schema version written to the file. Bump it and add a step to [`migrate`] when a field is
+/// renamed or changes meaning; new fields with a default need neither.
+pub const SETTINGS_VERSION: u64 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct UserSettings {
+    pub version: u64,
+    pub compression_level: CompressionLevel,
+    /// One of [`CompressionOptions::OUTPUT_FORMATS`]
+    #[serde(deserialize_with = "output_format")]
+    pub output_format: String,
+    pub oxipng: bool,
+    pub png_lossy: bool,
+    /// Encoder quality (0-100) set on the slider; `None` follows the compression level.
+    #[serde(deserialize_with = "quality")]
+    pub quality: Option<u8>,
+    /// Folder last chosen for outputs; the output folder dialog opens there.
+    pub output_folder: Option<String>,
+    /// Folder of the last files or folder picked as input; the input dialogs open there.
+    pub input_folder: Option<String>,
+    /// Fields this version does not know (written by a newer one), kept when saving.
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            compression_level: CompressionLevel::Mid,
+            output_format: "webp".to_string(),
+            oxipng: true,
+            png_lossy: true,
+            quality: None,
+            output_folder: None,
+            input_folder: None,
+            other: Map::new(),
+        }
+    }
+}
+
+fn output_format<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let format = String::deserialize(deserializer)?;
+    if CompressionOptions::OUTPUT_FORMATS.contains(&format.as_str()) {
+        Ok(format)
+    } else {
+        Err(D::Error::custom(format!(
+            "unknown output format '{}'",
+            format
+        )))
+    }
+}
+
+fn quality<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u8>, D::Error> {
+    match Option::<u8>::deserialize(deserializer)? {
+        Some(quality) if quality > 100 => Err(D::Error::custom(format!(
+            "quality {} is above 100",
+            quality
+        ))),
+        quality => Ok(quality),
+    }
+}
+
+/// Bring a settings object written by an older version up to [`SETTINGS_VERSION`]. Objects
+/// from a newer version are left as they are.
+fn migrate(mut settings: Map<String, Value>) -> Map<String, Value> {
+    let version = settings.get("version").and_then(Value::as_u64).unwrap_or(0);
+    if version >= SETTINGS_VERSION {
+        return settings;
+    }
+    // Version 0: files written before the schema was versioned; the fields are unchanged.
+    settings.insert("version".to_string(), SETTINGS_VERSION.into());
+    settings
+}
+
+/// Settings from `settings`, skipping fields whose values do not fit, so one bad field does
+/// not reset the rest.
+fn from_map_lenient(settings: Map<String, Value>) -> UserSettings {
+    let mut kept = match serde_json::to_value(UserSettings::default()) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    };
+    for (key, value) in settings {
+        let previous = kept.insert(key.clone(), value);
+        if serde_json::from_value::<UserSettings>(Value::Object(kept.clone())).is_err() {
+            log::warn!("Ignoring invalid setting '{}'", key);
+            match previous {
+                Some(previous) => kept.insert(key, previous),
+                None => kept.remove(&key),
+            };
+        }
+    }
+    serde_json::from_value(Value::Object(kept)).unwrap_or_default()
+}
+
+/// Settings saved at `path`; every update is written straight back.
+#[derive(Debug, Clone)]
+pub struct SettingsStore {
+    path: PathBuf,
+    settings: UserSettings,
+}
+
+impl SettingsStore {
+    /// The settings at `path`. A missing file gives the defaults, and so does an unreadable
+    /// one (it is replaced on the next update).
+    pub fn load(path: &Path) -> Self {
+        let settings = match fs::read_to_string(path) {
+            Ok(json) => match serde_json::from_str::<Value>(&json) {
+                Ok(Value::Object(map)) => from_map_lenient(migrate(map)),
+                Ok(_) | Err(_) => {
+                    log::warn!(
+                        "{} is not a settings object; using defaults",
+                        path.display()
+                    );
+                    UserSettings::default()
+                }
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => UserSettings::default(),
+            Err(e) => {
+                log::warn!("Failed to read {}: {}", path.display(), e);
+                UserSettings::default()
+            }
+        };
+        Self {
+            path: path.to_path_buf(),
+            settings,
+        }
+    }
+
+    pub fn get(&self) -> &UserSettings {
+        &self.settings
+    }
+
+    /// Set the fields in `changes` (camelCase, as returned by [`SettingsStore::get`]) and save.
+    /// Nothing changes when a value does not fit its field.
+    pub fn update(&mut self, changes: Map<String, Value>) -> Result<&UserSettings, String> {
+        let mut merged = match serde_json::to_value(&self.settings) {
+            Ok(Value::Object(map)) => map,
+            _ => return Err("Failed to serialize settings".to_string()),
+        };
+        // The version describes the file, not something the caller can set.
+        merged.extend(changes.into_iter().filter(|(key, _)| key != "version"));
+        let settings: UserSettings = serde_json::from_value(Value::Object(merged))
+            .map_err(|e| format!("Invalid settings: {}", e))?;
+        self.write(&settings)?;
+        self.settings = settings;
+        Ok(&self.settings)
+    }
+
+    fn write(&self, settings: &UserSettings) -> Result<(), String> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        let json = serde_json::to_string_pretty(settings)
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+        inplace::write_atomic(&self.path, json.as_bytes(), &Default::default())
+            .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn store_with(contents: Value) -> (tempfile::TempDir, SettingsStore) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(SETTINGS_FILE);
+        fs::write(&path, contents.to_string()).unwrap();
+        let store = SettingsStore::load(&path);
+        (dir, store)
+    }
+
+    fn saved(store: &SettingsStore) -> Value {
+        serde_json::from_str(&fs::read_to_string(&store.path).unwrap()).unwrap()
+    }
+
+    fn changes(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(map) => map,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn unversioned_file_is_migrated() {
+        let (_dir, store) = store_with(json!({"compressionLevel": "max", "pngLossy": false}));
+        let settings = store.get();
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.compression_level, CompressionLevel::Max);
+        assert!(!settings.png_lossy);
+        assert_eq!(settings.output_format, "webp");
+    }
+
+    #[test]
+    fn invalid_field_keeps_the_rest() {
+        let (_dir, store) = store_with(json!({
+            "version": 1,
+            "compressionLevel": "extreme",
+            "outputFormat": "avif",
+            "oxipng": "yes",
+            "quality": 60,
+        }));
+        let settings = store.get();
+        assert_eq!(settings.compression_level, CompressionLevel::Mid);
+        assert!(settings.oxipng);
+        assert_eq!(settings.output_format, "avif");
+        assert_eq!(settings.quality, Some(60));
+    }
+
+    #[test]
+    fn fields_from_a_newer_version_survive_an_update() {
+        let (_dir, mut store) = store_with(json!({"version": 2, "theme": "dark"}));
+        assert_eq!(store.get().other.get("theme"), Some(&json!("dark")));
+
+        store
+            .update(changes(json!({"outputFormat": "png"})))
+            .unwrap();
+        let file = saved(&store);
+        assert_eq!(file["theme"], "dark");
+        assert_eq!(file["version"], 2);
+        assert_eq!(file["outputFormat"], "png");
+    }
+
+    #[test]
+    fn update_rejects_bad_values_and_ignores_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = SettingsStore::load(&dir.path().join(SETTINGS_FILE));
+
+        for bad in [
+            json!({"outputFormat": "gif"}),
+            json!({"compressionLevel": "extreme"}),
+            json!({"quality": "high"}),
+            json!({"quality": 101}),
+        ] {
+            assert!(store.update(changes(bad)).is_err());
+        }
+        assert!(!store.path.exists(), "nothing written for rejected updates");
+        assert_eq!(store.get().output_format, "webp");
+
+        let settings = store
+            .update(changes(json!({"version": 7, "oxipng": false})))
+            .unwrap();
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert!(!settings.oxipng);
+        assert_eq!(saved(&store)["version"], SETTINGS_VERSION);
+        assert_eq!(
+            SettingsStore::load(&store.path).get().oxipng,
+            store.get().oxipng
+        );
+    }
+}
@@ -1,10 +1,45 @@
+use crate::backend::{AppBackend, BackendProvider};
 use crate::components::*;
 use crate::state::AppState;
+use crate::utils;
 use leptos::prelude::*;
+use wasm_bindgen_futures::spawn_local;
+
+/// Start from the settings of the last session and save them whenever they change.
+fn sync_user_settings(state: AppState) {
+    let backend = BackendProvider::new();
+    if !backend.is_tauri() {
+        return;
+    }
+    // Changes are only saved once the stored settings are applied, so the defaults never
+    // overwrite them.
+    let loaded = RwSignal::new(false);
+    spawn_local(async move {
+        match backend.get_settings().await {
+            Ok(Some(settings)) => state.apply_user_settings(settings),
+            Ok(None) => {}
+            Err(e) => utils::product_log(&format!("⚠️ Could not load settings: {}", e)),
+        }
+        loaded.set(true);
+    });
+
+    Effect::new(move |_| {
+        let settings = state.user_settings();
+        if !loaded.get() {
+            return;
+        }
+        spawn_local(async move {
+            if let Err(e) = BackendProvider::new().update_settings(settings).await {
+                utils::product_log(&format!("⚠️ Could not save settings: {}", e));
+            }
+        });
+    });
+}
 
 #[component]
 pub fn App() -> impl IntoView {
     let app_state = AppState::new();
+    sync_user_settings(app_state);
 
     view! {
         <div class="container">
@@ -23,9 +58,11 @@ pub fn App() -> impl IntoView {
             <div class="main-content">
                 <FileSelector state=app_state.clone() />
                 <FilesList state=app_state.clone() />
+                <PresetSelector state=app_state.clone() />
                 <QualitySlider state=app_state.clone() />
                 <FormatSelector state=app_state.clone() />
                 <AdvancedOptions state=app_state.clone() />
+                <WatchFolder state=app_state.clone() />
                 <ProgressBar state=app_state.clone() />
                 <StatusDisplay state=app_state.clone() />
                 <ErrorDisplay state=app_state.clone() />
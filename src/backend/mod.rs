@@ -1,11 +1,15 @@
-use crate::state::{CompressionResult, FileInfo};
+use crate::state::{AdjustmentSettings, CompressionResult, FileInfo, ScanFilterSettings, TrimRect, WatermarkSettings};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
 use std::fmt;
+use std::rc::Rc;
 use wasm_bindgen::JsCast;
 use web_sys::{window, Request, RequestInit, RequestMode, Response};
 use wasm_bindgen_futures::JsFuture;
 
+mod multipart;
+
 #[derive(Debug, Clone)]
 pub enum BackendError {
     Cancelled,
@@ -40,6 +44,207 @@ pub struct CompressionRequest {
     pub output_format: String,
     pub oxipng: bool,
     pub png_lossy: bool,
+    /// Encoder quality (0–100) for every format; `None` uses the level's quality table
+    pub quality: Option<u8>,
+    pub jxl_lossless_jpeg: bool,
+    pub tiff_compression: String,
+    pub split_pages: bool,
+    pub dither: bool,
+    pub color_profile: String,
+    /// Transform objects as accepted by the backend (`{"op": "rotate", "degrees": 90}`, ...)
+    pub transforms: Vec<serde_json::Value>,
+    pub adjustments: AdjustmentSettings,
+    pub watermark: Option<WatermarkSettings>,
+    /// Output name template; empty means `{stem}.{ext}`
+    pub filename_template: String,
+}
+
+/// Per-file progress of a streamed batch (the desktop `compress-batch-progress` event).
+/// `index` is the file's position in `CompressionRequest::file_paths`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum BatchProgress {
+    #[serde(rename_all = "camelCase")]
+    Started { batch_id: String, index: usize, path: String },
+    #[serde(rename_all = "camelCase")]
+    Finished {
+        batch_id: String,
+        index: usize,
+        path: String,
+        results: Vec<CompressionResult>,
+        done: usize,
+        total: usize,
+    },
+    #[serde(rename_all = "camelCase")]
+    Failed {
+        batch_id: String,
+        index: usize,
+        path: String,
+        error: String,
+        done: usize,
+        total: usize,
+    },
+}
+
+impl BatchProgress {
+    fn batch_id(&self) -> &str {
+        match self {
+            BatchProgress::Started { batch_id, .. }
+            | BatchProgress::Finished { batch_id, .. }
+            | BatchProgress::Failed { batch_id, .. } => batch_id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BatchSummary {
+    /// Files reported through a `finished` or `failed` event
+    completed: usize,
+    cancelled: bool,
+}
+
+/// The batch `cancel_batch` stops.
+enum InFlight {
+    /// Desktop batch id passed to `compress_batch_events`
+    Tauri(String),
+    /// Controller of the browser's `/api/compress/batch` fetch
+    Http(web_sys::AbortController),
+}
+
+thread_local! {
+    static IN_FLIGHT: RefCell<Option<InFlight>> = const { RefCell::new(None) };
+}
+
+/// A file processed by the running folder watch (the desktop `folder-watch` event).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum FolderWatchEvent {
+    /// Outputs already saved below the watch's output folder: each result has
+    /// `compressed_path` set and empty `data`
+    Compressed { results: Vec<CompressionResult> },
+    Failed { path: String, error: String },
+}
+
+/// Folders of a running folder watch, as resolved by the backend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FolderWatchStatus {
+    pub input: String,
+    pub output: String,
+}
+
+/// The part of a preset's backend `CompressionOptions` the settings panel can show; missing
+/// fields take the backend defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PresetOptions {
+    pub png_quality: String,
+    pub png_lossy: bool,
+    pub oxipng: bool,
+    pub quality: Option<u8>,
+    pub to_webp: bool,
+    pub to_avif: bool,
+    pub to_jpeg: bool,
+    pub to_png: bool,
+    pub to_tiff: bool,
+    pub to_bmp: bool,
+    pub to_ico: bool,
+    pub to_jxl: bool,
+    pub jxl_lossless_jpeg: bool,
+    pub tiff_compression: String,
+    pub split_pages: bool,
+    pub dither: bool,
+    pub color_profile: String,
+    pub transforms: Vec<serde_json::Value>,
+    pub adjustments: AdjustmentSettings,
+    pub filename_template: Option<String>,
+}
+
+impl Default for PresetOptions {
+    fn default() -> Self {
+        Self {
+            png_quality: "50-80".to_string(),
+            png_lossy: true,
+            oxipng: true,
+            quality: None,
+            to_webp: false,
+            to_avif: false,
+            to_jpeg: false,
+            to_png: false,
+            to_tiff: false,
+            to_bmp: false,
+            to_ico: false,
+            to_jxl: false,
+            jxl_lossless_jpeg: true,
+            tiff_compression: "lzw".to_string(),
+            split_pages: false,
+            dither: false,
+            color_profile: "srgb".to_string(),
+            transforms: Vec::new(),
+            adjustments: AdjustmentSettings::default(),
+            filename_template: None,
+        }
+    }
+}
+
+impl PresetOptions {
+    /// `output_format` value for the preset's target format; `original` when none is set
+    pub fn output_format(&self) -> &'static str {
+        [
+            (self.to_webp, "webp"),
+            (self.to_avif, "avif"),
+            (self.to_jpeg, "jpeg"),
+            (self.to_png, "png"),
+            (self.to_tiff, "tiff"),
+            (self.to_bmp, "bmp"),
+            (self.to_ico, "ico"),
+            (self.to_jxl, "jxl"),
+        ]
+        .into_iter()
+        .find_map(|(set, format)| set.then_some(format))
+        .unwrap_or("original")
+    }
+}
+
+/// A named compression preset; `builtin` ones cannot be replaced or deleted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresetEntry {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub options: PresetOptions,
+    #[serde(default)]
+    pub builtin: bool,
+}
+
+/// Settings the desktop keeps across sessions (`get_settings`); fields missing from an older
+/// settings file keep the defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct UserSettings {
+    pub compression_level: String,
+    pub output_format: String,
+    pub oxipng: bool,
+    pub png_lossy: bool,
+    pub quality: Option<u8>,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        Self {
+            compression_level: "mid".to_string(),
+            output_format: "webp".to_string(),
+            oxipng: true,
+            png_lossy: true,
+            quality: None,
+        }
+    }
+}
+
+thread_local! {
+    /// Delivers the running folder watch's events; dropped when the watch stops.
+    static WATCH_LISTENER: RefCell<Option<crate::tauri_helpers::TauriListener>> = const { RefCell::new(None) };
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -61,6 +266,36 @@ pub struct SaveZipRequest {
     pub files: Vec<FileSaveData>,
 }
 
+/// Compressed results written back where their inputs are (desktop).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InPlaceRequest {
+    /// Input of each file, in the same order; the file names only give the output extension
+    pub original_paths: Vec<String>,
+    pub files: Vec<FileSaveData>,
+    /// Appended to the stem of a copy written next to the original; empty replaces it
+    pub suffix: String,
+    pub preserve_times: bool,
+    pub preserve_permissions: bool,
+}
+
+/// Backup folder of an in-place run and the files it wrote (or an undo restored).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InPlaceSummary {
+    pub backup_dir: String,
+    pub paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavePdfRequest {
+    pub output_folder: String,
+    pub pdf_filename: String,
+    pub files: Vec<FileSaveData>,
+    /// `fit`, `a4` or `letter`
+    pub page_size: String,
+    pub margin_mm: f32,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CollisionCheckRequest {
     pub output_folder: String,
@@ -81,6 +316,14 @@ pub struct ApiCompressionResponse {
     pub savings_percent: f64,
     pub mime_type: String,
     pub data: Vec<u8>,
+    #[serde(default)]
+    pub page: Option<u32>,
+    #[serde(default)]
+    pub trimmed: Option<TrimRect>,
+    #[serde(default)]
+    pub bit_depth: Option<u8>,
+    #[serde(default)]
+    pub suggested_filename: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -106,6 +349,11 @@ fn sniff_mime_from_bytes(data: &[u8]) -> Option<&'static str> {
             return Some("image/tiff");
         }
     }
+    if data.starts_with(&[0xFF, 0x0A])
+        || data.starts_with(&[0, 0, 0, 0x0C, b'J', b'X', b'L', b' ', 0x0D, 0x0A, 0x87, 0x0A])
+    {
+        return Some("image/jxl");
+    }
     if data.len() >= 2 && data.get(0..2) == Some(b"BM") {
         return Some("image/bmp");
     }
@@ -136,6 +384,11 @@ pub struct BackendCapabilities {
     pub supports_collision_check: bool,
     pub supports_file_manager: bool,
     pub supports_drag_drop: bool,
+    /// `compress_batch_with_progress` reports each file as it finishes
+    pub supports_progress_events: bool,
+    pub supports_folder_watch: bool,
+    /// Presets can be saved, deleted, imported and exported (listing works everywhere)
+    pub supports_preset_editing: bool,
 }
 
 #[async_trait(?Send)]
@@ -143,8 +396,40 @@ pub trait AppBackend: fmt::Debug {
     fn capabilities(&self) -> BackendCapabilities;
     fn is_available(&self) -> bool;
     async fn select_files(&self) -> Result<Vec<FileInfo>, BackendError>;
-    async fn handle_dropped_files(&self, file_paths: Vec<String>) -> Result<Vec<FileInfo>, BackendError>;
+    /// Pick a folder and list the images below it that pass `filter` (see `supports_folder_picker`).
+    async fn select_folder(&self, filter: ScanFilterSettings) -> Result<Vec<FileInfo>, BackendError>;
+    async fn handle_dropped_files(
+        &self,
+        file_paths: Vec<String>,
+        filter: ScanFilterSettings,
+    ) -> Result<Vec<FileInfo>, BackendError>;
     async fn compress_batch(&self, request: CompressionRequest) -> Result<Vec<CompressionResult>, BackendError>;
+    /// Like `compress_batch`, calling `on_progress` as each file starts and finishes. Files that
+    /// fail are reported through `on_progress` and left out of the returned results. Backends
+    /// without per-file events (see `supports_progress_events`) just run `compress_batch`.
+    async fn compress_batch_with_progress(
+        &self,
+        request: CompressionRequest,
+        on_progress: Rc<dyn Fn(BatchProgress)>,
+    ) -> Result<Vec<CompressionResult>, BackendError> {
+        let _ = on_progress;
+        self.compress_batch(request).await
+    }
+    /// Stop the running `compress_batch` / `compress_batch_with_progress`, which then returns
+    /// `BackendError::Cancelled`. Results already reported through `on_progress` stay valid.
+    async fn cancel_batch(&self) -> Result<(), BackendError>;
+    /// Compress images as they appear in `input` into `output` with the request's settings
+    /// (its `file_paths` are ignored), calling `on_event` per file until `stop_folder_watch`.
+    /// Replaces a running watch.
+    async fn start_folder_watch(
+        &self,
+        input: String,
+        output: String,
+        request: CompressionRequest,
+        filter: ScanFilterSettings,
+        on_event: Rc<dyn Fn(FolderWatchEvent)>,
+    ) -> Result<FolderWatchStatus, BackendError>;
+    async fn stop_folder_watch(&self) -> Result<(), BackendError>;
     async fn select_output_folder(&self) -> Result<String, BackendError>;
     async fn save_files_to_folder(&self, request: SaveFilesRequest) -> Result<Vec<String>, BackendError>;
     async fn check_file_collisions(&self, request: CollisionCheckRequest) -> Result<Vec<String>, BackendError>;
@@ -153,11 +438,82 @@ pub trait AppBackend: fmt::Debug {
         request: ResolveUniqueFilenamesRequest,
     ) -> Result<Vec<String>, BackendError>;
     async fn save_files_as_zip(&self, request: SaveZipRequest) -> Result<String, BackendError>;
+    async fn save_files_as_pdf(&self, request: SavePdfRequest) -> Result<String, BackendError>;
+    /// Write results over (or next to) their inputs after backing the inputs up.
+    async fn optimize_in_place(&self, request: InPlaceRequest) -> Result<InPlaceSummary, BackendError>;
+    /// Restore the files changed by the last `optimize_in_place` run.
+    async fn undo_last_in_place_run(&self) -> Result<InPlaceSummary, BackendError>;
+    async fn load_watermark_preset(&self) -> Result<Option<WatermarkSettings>, BackendError>;
+    async fn save_watermark_preset(&self, preset: Option<WatermarkSettings>) -> Result<(), BackendError>;
+    /// Built-in presets followed by the user's (or the server's).
+    async fn list_presets(&self) -> Result<Vec<PresetEntry>, BackendError>;
+    /// Save the request's settings, without its watermark, as the preset `name`. Returns the
+    /// updated list.
+    async fn save_preset(&self, name: String, request: CompressionRequest) -> Result<Vec<PresetEntry>, BackendError>;
+    async fn delete_preset(&self, name: String) -> Result<Vec<PresetEntry>, BackendError>;
+    /// Pick a JSON or TOML preset file and add its presets. Returns the updated list.
+    async fn import_presets(&self) -> Result<Vec<PresetEntry>, BackendError>;
+    /// Save the presets called `names` to a file the user picks; returns its path.
+    async fn export_presets(&self, names: Vec<String>) -> Result<String, BackendError>;
+    /// Settings saved in an earlier session; `None` where nothing is kept between sessions.
+    async fn get_settings(&self) -> Result<Option<UserSettings>, BackendError>;
+    /// Save the settings in `changes`, keeping the others.
+    async fn update_settings(&self, changes: UserSettings) -> Result<(), BackendError>;
     async fn open_in_file_manager(&self, path: String) -> Result<(), BackendError>;
 }
 
 // Tauri Backend Implementation
 
+/// The desktop `CompressSettings` object (camelCase) for a compression request.
+fn tauri_compress_settings(request: &CompressionRequest) -> serde_json::Value {
+    serde_json::json!({
+        "compressionLevel": request.compression_level,
+        "outputFormat": request.output_format,
+        "oxipng": request.oxipng,
+        "pngLossy": request.png_lossy,
+        "quality": request.quality,
+        "jxlLosslessJpeg": request.jxl_lossless_jpeg,
+        "tiffCompression": request.tiff_compression,
+        "splitPages": request.split_pages,
+        "dither": request.dither,
+        "colorProfile": request.color_profile,
+        "transforms": request.transforms,
+        "adjustments": request.adjustments,
+        "watermark": request.watermark,
+        "filenameTemplate": request.filename_template,
+    })
+}
+
+/// Fill in `data` of results whose bytes the desktop still holds (`output_id`), fetched as raw
+/// IPC responses rather than JSON number arrays.
+async fn take_batch_outputs(mut results: Vec<CompressionResult>) -> Result<Vec<CompressionResult>, String> {
+    for result in &mut results {
+        if let Some(output_id) = result.output_id.take() {
+            let args = js_sys::JSON::parse(&serde_json::json!({ "outputId": output_id }).to_string())
+                .unwrap_or(wasm_bindgen::JsValue::NULL);
+            result.data = crate::tauri_helpers::invoke_tauri_bytes("take_batch_output", args).await?;
+        }
+    }
+    Ok(results)
+}
+
+/// Raw IPC body for the desktop save commands: little-endian `u32` length of a JSON header,
+/// the header (`header` plus a `files` list of names and byte lengths), then each file's bytes.
+fn raw_save_body(mut header: serde_json::Value, files: &[FileSaveData]) -> Vec<u8> {
+    header["files"] = files
+        .iter()
+        .map(|f| serde_json::json!({ "filename": f.filename, "len": f.data.len() }))
+        .collect();
+    let header = serde_json::to_vec(&header).unwrap_or_default();
+    let mut body = Vec::with_capacity(4 + header.len() + files.iter().map(|f| f.data.len()).sum::<usize>());
+    body.extend_from_slice(&(header.len() as u32).to_le_bytes());
+    body.extend_from_slice(&header);
+    for file in files {
+        body.extend_from_slice(&file.data);
+    }
+    body
+}
+
 #[derive(Debug, Clone)]
 pub struct TauriBackend;
 
@@ -182,6 +538,9 @@ impl AppBackend for TauriBackend {
             supports_collision_check: true,
             supports_file_manager: true,
             supports_drag_drop: true,
+            supports_progress_events: true,
+            supports_folder_watch: true,
+            supports_preset_editing: true,
         }
     }
 
@@ -206,12 +565,28 @@ impl AppBackend for TauriBackend {
         }
     }
 
-    async fn handle_dropped_files(&self, file_paths: Vec<String>) -> Result<Vec<FileInfo>, BackendError> {
+    async fn select_folder(&self, filter: ScanFilterSettings) -> Result<Vec<FileInfo>, BackendError> {
+        let args = js_sys::JSON::parse(&serde_json::json!({ "filter": filter }).to_string())
+            .unwrap_or(wasm_bindgen::JsValue::NULL);
+
+        match crate::tauri_helpers::invoke_tauri::<Vec<FileInfo>>("select_folder", args).await {
+            Ok(files) => Ok(files),
+            Err(e) if e.contains("cancelled") || e.contains("No folder selected") => Err(BackendError::Cancelled),
+            Err(e) => Err(BackendError::FileSelectionFailed(e)),
+        }
+    }
+
+    async fn handle_dropped_files(
+        &self,
+        file_paths: Vec<String>,
+        filter: ScanFilterSettings,
+    ) -> Result<Vec<FileInfo>, BackendError> {
         use wasm_bindgen::JsValue;
         
         let args = js_sys::JSON::parse(
             &serde_json::to_string(&serde_json::json!({
-                "filePaths": file_paths
+                "filePaths": file_paths,
+                "filter": filter,
             }))
             .unwrap_or_default(),
         )
@@ -226,25 +601,148 @@ impl AppBackend for TauriBackend {
     }
 
     async fn compress_batch(&self, request: CompressionRequest) -> Result<Vec<CompressionResult>, BackendError> {
+        // The streamed command hands back output bytes over raw IPC instead of JSON arrays.
+        self.compress_batch_with_progress(request, Rc::new(|_| {})).await
+    }
+
+    async fn compress_batch_with_progress(
+        &self,
+        request: CompressionRequest,
+        on_progress: Rc<dyn Fn(BatchProgress)>,
+    ) -> Result<Vec<CompressionResult>, BackendError> {
         use wasm_bindgen::JsValue;
-        
+
+        let batch_id = format!("batch-{}-{}", js_sys::Date::now() as u64, (js_sys::Math::random() * 1e9) as u64);
+        // One slot per input file so results keep input order whatever order files finish in.
+        let slots: Rc<RefCell<Vec<Option<Vec<CompressionResult>>>>> =
+            Rc::new(RefCell::new(vec![None; request.file_paths.len()]));
+        let finished = Rc::new(RefCell::new(0usize));
+
+        let listener = {
+            let batch_id = batch_id.clone();
+            let slots = slots.clone();
+            let finished = finished.clone();
+            crate::tauri_helpers::listen_tauri::<BatchProgress>("compress-batch-progress", move |event| {
+                if event.batch_id() != batch_id {
+                    return;
+                }
+                match event {
+                    BatchProgress::Finished { batch_id, index, path, results, done, total } => {
+                        // The event only carries metadata; fetch each output's bytes first.
+                        let slots = slots.clone();
+                        let finished = finished.clone();
+                        let on_progress = on_progress.clone();
+                        wasm_bindgen_futures::spawn_local(async move {
+                            let event = match take_batch_outputs(results).await {
+                                Ok(results) => {
+                                    if let Some(slot) = slots.borrow_mut().get_mut(index) {
+                                        *slot = Some(results.clone());
+                                    }
+                                    BatchProgress::Finished { batch_id, index, path, results, done, total }
+                                }
+                                Err(error) => BatchProgress::Failed { batch_id, index, path, error, done, total },
+                            };
+                            *finished.borrow_mut() += 1;
+                            on_progress(event);
+                        });
+                    }
+                    BatchProgress::Failed { .. } => {
+                        *finished.borrow_mut() += 1;
+                        on_progress(event);
+                    }
+                    BatchProgress::Started { .. } => on_progress(event),
+                }
+            })
+            .await
+            .map_err(BackendError::CompressionFailed)?
+        };
+
         let args_obj = serde_json::json!({
+            "batchId": batch_id,
             "filePaths": request.file_paths,
-            "compressionLevel": request.compression_level,
-            "outputFormat": request.output_format,
-            "oxipng": request.oxipng,
-            "pngLossy": request.png_lossy,
+            "settings": tauri_compress_settings(&request),
         });
+        let args = js_sys::JSON::parse(&serde_json::to_string(&args_obj).unwrap_or_default())
+            .unwrap_or_else(|_| JsValue::NULL);
+
+        IN_FLIGHT.with(|f| *f.borrow_mut() = Some(InFlight::Tauri(batch_id)));
+        let summary = crate::tauri_helpers::invoke_tauri::<BatchSummary>("compress_batch_events", args).await;
+        IN_FLIGHT.with(|f| *f.borrow_mut() = None);
+        let summary = summary.map_err(BackendError::CompressionFailed)?;
+
+        // Events and the command response travel separately; give late events a moment.
+        for _ in 0..100 {
+            if *finished.borrow() >= summary.completed {
+                break;
+            }
+            crate::utils::sleep_ms(20).await;
+        }
+        drop(listener);
+
+        if summary.cancelled {
+            return Err(BackendError::Cancelled);
+        }
+        let results = slots.borrow_mut().drain(..).flatten().flatten().collect();
+        Ok(results)
+    }
+
+    async fn cancel_batch(&self) -> Result<(), BackendError> {
+        use wasm_bindgen::JsValue;
+
+        let batch_id = IN_FLIGHT.with(|f| match &*f.borrow() {
+            Some(InFlight::Tauri(id)) => Some(id.clone()),
+            _ => None,
+        });
+        let Some(batch_id) = batch_id else {
+            return Ok(());
+        };
+        let args = js_sys::JSON::parse(
+            &serde_json::to_string(&serde_json::json!({ "batchId": batch_id })).unwrap_or_default(),
+        )
+        .unwrap_or_else(|_| JsValue::NULL);
 
+        crate::tauri_helpers::invoke_tauri::<bool>("cancel_batch", args)
+            .await
+            .map(|_| ())
+            .map_err(BackendError::Other)
+    }
+
+    async fn start_folder_watch(
+        &self,
+        input: String,
+        output: String,
+        request: CompressionRequest,
+        filter: ScanFilterSettings,
+        on_event: Rc<dyn Fn(FolderWatchEvent)>,
+    ) -> Result<FolderWatchStatus, BackendError> {
+        use wasm_bindgen::JsValue;
+
+        let listener = crate::tauri_helpers::listen_tauri::<FolderWatchEvent>("folder-watch", move |event| on_event(event))
+            .await
+            .map_err(BackendError::Other)?;
+        // Replacing the old listener first keeps a restarted watch from reporting files twice.
+        WATCH_LISTENER.with(|l| *l.borrow_mut() = Some(listener));
+
+        let args_obj = serde_json::json!({
+            "input": input,
+            "output": output,
+            "settings": tauri_compress_settings(&request),
+            "filter": filter,
+        });
         let args = js_sys::JSON::parse(&serde_json::to_string(&args_obj).unwrap_or_default())
             .unwrap_or_else(|_| JsValue::NULL);
 
-        let result = crate::tauri_helpers::invoke_tauri::<Vec<CompressionResult>>("compress_batch", args).await;
-        
-        match result {
-            Ok(results) => Ok(results),
-            Err(e) => Err(BackendError::CompressionFailed(e)),
+        let result = crate::tauri_helpers::invoke_tauri::<FolderWatchStatus>("start_folder_watch", args).await;
+        if result.is_err() {
+            WATCH_LISTENER.with(|l| *l.borrow_mut() = None);
         }
+        result.map_err(BackendError::Other)
+    }
+
+    async fn stop_folder_watch(&self) -> Result<(), BackendError> {
+        let result = crate::tauri_helpers::invoke_tauri::<bool>("stop_folder_watch", wasm_bindgen::JsValue::NULL).await;
+        WATCH_LISTENER.with(|l| *l.borrow_mut() = None);
+        result.map(|_| ()).map_err(BackendError::Other)
     }
 
     async fn select_output_folder(&self) -> Result<String, BackendError> {
@@ -265,24 +763,9 @@ impl AppBackend for TauriBackend {
     }
 
     async fn save_files_to_folder(&self, request: SaveFilesRequest) -> Result<Vec<String>, BackendError> {
-        use wasm_bindgen::JsValue;
-        
-        let files_json: Vec<serde_json::Value> = request.files.iter().map(|f| {
-            serde_json::json!({
-                "filename": f.filename,
-                "data": f.data,
-            })
-        }).collect();
+        let body = raw_save_body(serde_json::json!({ "outputFolder": request.output_folder }), &request.files);
 
-        let args_obj = serde_json::json!({
-            "outputFolder": request.output_folder,
-            "files": files_json,
-        });
-
-        let args = js_sys::JSON::parse(&serde_json::to_string(&args_obj).unwrap_or_default())
-            .unwrap_or_else(|_| JsValue::NULL);
-
-        let result = crate::tauri_helpers::invoke_tauri::<Vec<String>>("save_files_to_folder", args).await;
+        let result = crate::tauri_helpers::invoke_tauri_raw::<Vec<String>>("save_files_to_folder", &body).await;
         
         match result {
             Ok(paths) => Ok(paths),
@@ -332,32 +815,143 @@ impl AppBackend for TauriBackend {
     }
 
     async fn save_files_as_zip(&self, request: SaveZipRequest) -> Result<String, BackendError> {
-        use wasm_bindgen::JsValue;
+        let header = serde_json::json!({
+            "outputFolder": request.output_folder,
+            "zipFilename": request.zip_filename,
+        });
+        let body = raw_save_body(header, &request.files);
+
+        let result = crate::tauri_helpers::invoke_tauri_raw::<String>("save_files_as_zip", &body).await;
         
-        let files_json: Vec<serde_json::Value> = request.files.iter().map(|f| {
-            serde_json::json!({
-                "filename": f.filename,
-                "data": f.data,
-            })
-        }).collect();
+        match result {
+            Ok(path) => Ok(path),
+            Err(e) => Err(BackendError::SaveFailed(e)),
+        }
+    }
 
-        let args_obj = serde_json::json!({
+    async fn save_files_as_pdf(&self, request: SavePdfRequest) -> Result<String, BackendError> {
+        let header = serde_json::json!({
             "outputFolder": request.output_folder,
-            "zipFilename": request.zip_filename,
-            "files": files_json,
+            "pdfFilename": request.pdf_filename,
+            "options": {
+                "page_size": request.page_size,
+                "margin_mm": request.margin_mm,
+            },
         });
+        let body = raw_save_body(header, &request.files);
 
-        let args = js_sys::JSON::parse(&serde_json::to_string(&args_obj).unwrap_or_default())
-            .unwrap_or_else(|_| JsValue::NULL);
+        let result = crate::tauri_helpers::invoke_tauri_raw::<String>("save_files_as_pdf", &body).await;
 
-        let result = crate::tauri_helpers::invoke_tauri::<String>("save_files_as_zip", args).await;
-        
         match result {
             Ok(path) => Ok(path),
             Err(e) => Err(BackendError::SaveFailed(e)),
         }
     }
 
+    async fn optimize_in_place(&self, request: InPlaceRequest) -> Result<InPlaceSummary, BackendError> {
+        let header = serde_json::json!({
+            "originalPaths": request.original_paths,
+            "suffix": request.suffix,
+            "preserveTimes": request.preserve_times,
+            "preservePermissions": request.preserve_permissions,
+        });
+        let body = raw_save_body(header, &request.files);
+
+        crate::tauri_helpers::invoke_tauri_raw::<InPlaceSummary>("optimize_in_place", &body)
+            .await
+            .map_err(BackendError::SaveFailed)
+    }
+
+    async fn undo_last_in_place_run(&self) -> Result<InPlaceSummary, BackendError> {
+        crate::tauri_helpers::invoke_tauri::<InPlaceSummary>("undo_last_in_place_run", wasm_bindgen::JsValue::NULL)
+            .await
+            .map_err(BackendError::Other)
+    }
+
+    async fn load_watermark_preset(&self) -> Result<Option<WatermarkSettings>, BackendError> {
+        use wasm_bindgen::JsValue;
+
+        crate::tauri_helpers::invoke_tauri::<Option<WatermarkSettings>>("load_watermark_preset", JsValue::NULL)
+            .await
+            .map_err(BackendError::Other)
+    }
+
+    async fn save_watermark_preset(&self, preset: Option<WatermarkSettings>) -> Result<(), BackendError> {
+        use wasm_bindgen::JsValue;
+
+        let args = js_sys::JSON::parse(
+            &serde_json::to_string(&serde_json::json!({ "preset": preset })).unwrap_or_default(),
+        )
+        .unwrap_or_else(|_| JsValue::NULL);
+
+        crate::tauri_helpers::invoke_tauri::<()>("save_watermark_preset", args)
+            .await
+            .map_err(BackendError::SaveFailed)
+    }
+
+    async fn list_presets(&self) -> Result<Vec<PresetEntry>, BackendError> {
+        crate::tauri_helpers::invoke_tauri::<Vec<PresetEntry>>("list_presets", wasm_bindgen::JsValue::NULL)
+            .await
+            .map_err(BackendError::Other)
+    }
+
+    async fn save_preset(&self, name: String, request: CompressionRequest) -> Result<Vec<PresetEntry>, BackendError> {
+        let args = serde_json::json!({
+            "name": name,
+            "settings": tauri_compress_settings(&request),
+        });
+        let args = js_sys::JSON::parse(&args.to_string()).unwrap_or(wasm_bindgen::JsValue::NULL);
+
+        crate::tauri_helpers::invoke_tauri::<Vec<PresetEntry>>("save_preset", args)
+            .await
+            .map_err(BackendError::SaveFailed)
+    }
+
+    async fn delete_preset(&self, name: String) -> Result<Vec<PresetEntry>, BackendError> {
+        let args = js_sys::JSON::parse(&serde_json::json!({ "name": name }).to_string())
+            .unwrap_or(wasm_bindgen::JsValue::NULL);
+
+        crate::tauri_helpers::invoke_tauri::<Vec<PresetEntry>>("delete_preset", args)
+            .await
+            .map_err(BackendError::Other)
+    }
+
+    async fn import_presets(&self) -> Result<Vec<PresetEntry>, BackendError> {
+        match crate::tauri_helpers::invoke_tauri::<Vec<PresetEntry>>("import_presets", wasm_bindgen::JsValue::NULL).await {
+            Ok(presets) => Ok(presets),
+            Err(e) if e.contains("cancelled") => Err(BackendError::Cancelled),
+            Err(e) => Err(BackendError::Other(e)),
+        }
+    }
+
+    async fn export_presets(&self, names: Vec<String>) -> Result<String, BackendError> {
+        let args = js_sys::JSON::parse(&serde_json::json!({ "names": names }).to_string())
+            .unwrap_or(wasm_bindgen::JsValue::NULL);
+
+        match crate::tauri_helpers::invoke_tauri::<String>("export_presets", args).await {
+            Ok(path) => Ok(path),
+            Err(e) if e.contains("cancelled") => Err(BackendError::Cancelled),
+            Err(e) => Err(BackendError::SaveFailed(e)),
+        }
+    }
+
+    async fn get_settings(&self) -> Result<Option<UserSettings>, BackendError> {
+        crate::tauri_helpers::invoke_tauri::<UserSettings>("get_settings", wasm_bindgen::JsValue::NULL)
+            .await
+            .map(Some)
+            .map_err(BackendError::Other)
+    }
+
+    async fn update_settings(&self, changes: UserSettings) -> Result<(), BackendError> {
+        let args = js_sys::JSON::parse(&serde_json::json!({ "changes": changes }).to_string())
+            .unwrap_or(wasm_bindgen::JsValue::NULL);
+
+        crate::tauri_helpers::invoke_tauri::<serde_json::Value>("update_settings", args)
+            .await
+            .map(|_| ())
+            .map_err(BackendError::SaveFailed)
+    }
+
     async fn open_in_file_manager(&self, path: String) -> Result<(), BackendError> {
         use wasm_bindgen::JsValue;
         
@@ -466,6 +1060,75 @@ impl HttpBackend {
 
         Ok(json)
     }
+
+    /// POST `form_data` to `endpoint` and return the response body (a ZIP or PDF download).
+    async fn post_form_for_blob(&self, endpoint: &str, form_data: &web_sys::FormData) -> Result<web_sys::Blob, BackendError> {
+        let window = window().ok_or_else(|| BackendError::Other("No window object".to_string()))?;
+        let url = format!("{}{}", self.base_url, endpoint);
+        let opts = RequestInit::new();
+        opts.set_method("POST");
+        opts.set_mode(RequestMode::Cors);
+        opts.set_body(form_data);
+
+        let http_request = Request::new_with_str_and_init(&url, &opts)
+            .map_err(|e| BackendError::Other(format!("Failed to create request: {:?}", e)))?;
+        let resp_value = JsFuture::from(window.fetch_with_request(&http_request))
+            .await
+            .map_err(|e| BackendError::Other(format!("Fetch failed: {:?}", e)))?;
+        let resp: Response = resp_value.dyn_into()
+            .map_err(|e| BackendError::Other(format!("Invalid response: {:?}", e)))?;
+        if !resp.ok() {
+            return Err(BackendError::SaveFailed(format!("HTTP {}", resp.status())));
+        }
+
+        JsFuture::from(
+            resp.blob().map_err(|e| BackendError::SaveFailed(format!("Failed to read response: {:?}", e)))?,
+        )
+        .await
+        .map_err(|e| BackendError::SaveFailed(format!("Failed to read response: {:?}", e)))?
+        .dyn_into()
+        .map_err(|e| BackendError::SaveFailed(format!("Invalid response body: {:?}", e)))
+    }
+}
+
+/// Form with one `file`, `file1`, … part per file, named by its filename.
+fn files_form(files: &[FileSaveData]) -> Result<web_sys::FormData, BackendError> {
+    let form_data = web_sys::FormData::new()
+        .map_err(|e| BackendError::SaveFailed(format!("Failed to create form: {:?}", e)))?;
+    for (i, file_data) in files.iter().enumerate() {
+        let blob = web_sys::Blob::new_with_u8_array_sequence(
+            &js_sys::Array::of1(&js_sys::Uint8Array::from(&file_data.data[..])),
+        )
+        .map_err(|e| BackendError::SaveFailed(format!("Failed to create blob: {:?}", e)))?;
+        let field_name = if i == 0 { "file".to_string() } else { format!("file{}", i) };
+        form_data.append_with_blob_and_filename(&field_name, &blob, &file_data.filename)
+            .map_err(|e| BackendError::SaveFailed(format!("Failed to add file: {:?}", e)))?;
+    }
+    Ok(form_data)
+}
+
+/// Save `blob` through a temporary download link.
+fn download_blob(blob: &web_sys::Blob, filename: &str) -> Result<(), BackendError> {
+    let window = window().ok_or_else(|| BackendError::Other("No window object".to_string()))?;
+    let document = window.document().ok_or_else(|| BackendError::Other("No document".to_string()))?;
+    let body = document.body().ok_or_else(|| BackendError::Other("No body".to_string()))?;
+
+    let object_url = web_sys::Url::create_object_url_with_blob(blob)
+        .map_err(|e| BackendError::SaveFailed(format!("Failed to create URL: {:?}", e)))?;
+    let anchor = document.create_element("a")
+        .map_err(|e| BackendError::SaveFailed(format!("Failed to create anchor: {:?}", e)))?
+        .dyn_into::<web_sys::HtmlAnchorElement>()
+        .map_err(|e| BackendError::SaveFailed(format!("Failed to cast anchor: {:?}", e)))?;
+    anchor.set_href(&object_url);
+    anchor.set_download(filename);
+    anchor.style().set_property("display", "none").ok();
+    body.append_child(&anchor)
+        .map_err(|e| BackendError::SaveFailed(format!("Failed to append anchor: {:?}", e)))?;
+    anchor.click();
+    body.remove_child(&anchor)
+        .map_err(|e| BackendError::SaveFailed(format!("Failed to remove anchor: {:?}", e)))?;
+    let _ = web_sys::Url::revoke_object_url(&object_url);
+    Ok(())
 }
 
 impl Default for HttpBackend {
@@ -483,6 +1146,9 @@ impl AppBackend for HttpBackend {
             supports_collision_check: false,
             supports_file_manager: false,
             supports_drag_drop: false,
+            supports_progress_events: false,
+            supports_folder_watch: false,
+            supports_preset_editing: false,
         }
     }
 
@@ -494,7 +1160,15 @@ impl AppBackend for HttpBackend {
         Err(BackendError::NotAvailable)
     }
 
-    async fn handle_dropped_files(&self, _file_paths: Vec<String>) -> Result<Vec<FileInfo>, BackendError> {
+    async fn select_folder(&self, _filter: ScanFilterSettings) -> Result<Vec<FileInfo>, BackendError> {
+        Err(BackendError::NotAvailable)
+    }
+
+    async fn handle_dropped_files(
+        &self,
+        _file_paths: Vec<String>,
+        _filter: ScanFilterSettings,
+    ) -> Result<Vec<FileInfo>, BackendError> {
         Err(BackendError::NotAvailable)
     }
 
@@ -523,10 +1197,40 @@ impl AppBackend for HttpBackend {
             .map_err(|e| BackendError::CompressionFailed(format!("Failed to add field: {:?}", e)))?;
         form_data.append_with_str("oxipng", &request.oxipng.to_string())
             .map_err(|e| BackendError::CompressionFailed(format!("Failed to add field: {:?}", e)))?;
+        if let Some(quality) = request.quality {
+            form_data.append_with_str("quality", &quality.to_string())
+                .map_err(|e| BackendError::CompressionFailed(format!("Failed to add field: {:?}", e)))?;
+        }
+        form_data.append_with_str("jxl_lossless_jpeg", &request.jxl_lossless_jpeg.to_string())
+            .map_err(|e| BackendError::CompressionFailed(format!("Failed to add field: {:?}", e)))?;
+        form_data.append_with_str("tiff_compression", &request.tiff_compression)
+            .map_err(|e| BackendError::CompressionFailed(format!("Failed to add field: {:?}", e)))?;
+        form_data.append_with_str("split_pages", &request.split_pages.to_string())
+            .map_err(|e| BackendError::CompressionFailed(format!("Failed to add field: {:?}", e)))?;
+        form_data.append_with_str("dither", &request.dither.to_string())
+            .map_err(|e| BackendError::CompressionFailed(format!("Failed to add field: {:?}", e)))?;
+        form_data.append_with_str("color_profile", &request.color_profile)
+            .map_err(|e| BackendError::CompressionFailed(format!("Failed to add field: {:?}", e)))?;
+        if !request.transforms.is_empty() {
+            let transforms = serde_json::to_string(&request.transforms).unwrap_or_default();
+            form_data.append_with_str("transforms", &transforms)
+                .map_err(|e| BackendError::CompressionFailed(format!("Failed to add field: {:?}", e)))?;
+        }
+        if !request.adjustments.is_identity() {
+            append_adjustment_fields(&form_data, &request.adjustments)?;
+        }
+        if let Some(watermark) = &request.watermark {
+            append_watermark_fields(&form_data, &window, watermark)?;
+        }
         form_data.append_with_str("output_format", &request.output_format)
             .map_err(|e| BackendError::CompressionFailed(format!("Failed to add output_format: {:?}", e)))?;
+        if !request.filename_template.trim().is_empty() {
+            form_data.append_with_str("filename_template", &request.filename_template)
+                .map_err(|e| BackendError::CompressionFailed(format!("Failed to add field: {:?}", e)))?;
+        }
 
-        let mut file_count = 0;
+        // Paths in upload order; the API's `x-file-index` refers to this list.
+        let mut uploaded_paths: Vec<String> = Vec::new();
         for (i, path) in request.file_paths.iter().enumerate() {
             let file_obj = dropped_files.get(i as u32);
             let has_file = !file_obj.is_undefined() && !file_obj.is_null();
@@ -538,31 +1242,44 @@ impl AppBackend for HttpBackend {
                     let field_name = if i == 0 { "file".to_string() } else { format!("file{}", i) };
                     form_data.append_with_blob_and_filename(&field_name, &file.dyn_into().unwrap(), &path.replace("browser://", ""))
                         .map_err(|e| BackendError::CompressionFailed(format!("Failed to add file: {:?}", e)))?;
-                    file_count += 1;
+                    uploaded_paths.push(path.clone());
                 }
             }
         }
 
-        if file_count == 0 {
+        if uploaded_paths.is_empty() {
             return Err(BackendError::CompressionFailed("No files available to compress. Please select files using the file picker or drag & drop.".to_string()));
         }
 
-        // Save file paths before creating HTTP request (which would shadow the request parameter)
-        let file_paths = request.file_paths.clone();
-
-        // Send request to API
+        // Send request to API. Aborting it (`cancel_batch`) also stops the server-side batch.
+        // Results come back as raw bytes in a `multipart/mixed` body rather than JSON arrays.
         let url = format!("{}/api/compress/batch", self.base_url);
+        let abort = web_sys::AbortController::new()
+            .map_err(|e| BackendError::Other(format!("Failed to create AbortController: {:?}", e)))?;
+        let signal = abort.signal();
         let mut opts = RequestInit::new();
         opts.method("POST");
         opts.mode(RequestMode::Cors);
         opts.body(Some(&form_data));
+        opts.set_signal(Some(&signal));
 
         let http_request = Request::new_with_str_and_init(&url, &opts)
             .map_err(|e| BackendError::Other(format!("Failed to create request: {:?}", e)))?;
-
-        let resp_value = JsFuture::from(window.fetch_with_request(&http_request))
-            .await
-            .map_err(|e| BackendError::Other(format!("Fetch failed: {:?}", e)))?;
+        http_request
+            .headers()
+            .set("Accept", "multipart/mixed, application/json;q=0.5")
+            .map_err(|e| BackendError::Other(format!("Failed to set Accept header: {:?}", e)))?;
+
+        IN_FLIGHT.with(|f| *f.borrow_mut() = Some(InFlight::Http(abort)));
+        let resp_value = JsFuture::from(window.fetch_with_request(&http_request)).await;
+        IN_FLIGHT.with(|f| *f.borrow_mut() = None);
+        let resp_value = resp_value.map_err(|e| {
+            if signal.aborted() {
+                BackendError::Cancelled
+            } else {
+                BackendError::Other(format!("Fetch failed: {:?}", e))
+            }
+        })?;
         
         let resp: Response = resp_value.dyn_into()
             .map_err(|e| BackendError::Other(format!("Invalid response: {:?}", e)))?;
@@ -583,29 +1300,66 @@ impl AppBackend for HttpBackend {
             return Err(BackendError::CompressionFailed(format!("HTTP {}: {}", status, text)));
         }
 
-        // Parse response
-        let json = JsFuture::from(resp.json().map_err(|e| BackendError::Other(format!("JSON error: {:?}", e)))?)
+        let content_type = resp.headers().get("content-type").ok().flatten().unwrap_or_default();
+        let body = JsFuture::from(resp.array_buffer().map_err(|e| BackendError::Other(format!("Body error: {:?}", e)))?)
             .await
-            .map_err(|e| BackendError::Other(format!("Failed to parse JSON: {:?}", e)))?;
-
-        let response_str = js_sys::JSON::stringify(&json)
-            .map_err(|_| BackendError::Other("Failed to stringify response".to_string()))?
-            .as_string()
-            .ok_or_else(|| BackendError::Other("Invalid JSON response".to_string()))?;
-
-        let batch_response: ApiBatchCompressionResponse = serde_json::from_str(&response_str)
-            .map_err(|e| BackendError::Other(format!("Failed to parse response: {:?}", e)))?;
+            .map_err(|e| BackendError::Other(format!("Failed to read response: {:?}", e)))?;
+        let body = js_sys::Uint8Array::new(&body).to_vec();
+
+        // Servers without binary responses still answer with JSON.
+        let outputs: Vec<(usize, ApiCompressionResponse)> = if content_type.starts_with("multipart/mixed") {
+            multipart::parse(&content_type, &body)
+                .map_err(|e| BackendError::Other(format!("Failed to parse response: {}", e)))?
+                .into_iter()
+                .map(|part| {
+                    let file_index = part.parsed("x-file-index").unwrap_or(0);
+                    let trimmed = part.header("x-trimmed").and_then(|value| {
+                        let v: Vec<u32> = value.split(',').filter_map(|n| n.trim().parse().ok()).collect();
+                        (v.len() == 4).then(|| TrimRect { x: v[0], y: v[1], width: v[2], height: v[3] })
+                    });
+                    let response = ApiCompressionResponse {
+                        original_size: part.parsed("x-original-size").unwrap_or(0),
+                        compressed_size: part.parsed("x-compressed-size").unwrap_or(part.body.len()),
+                        savings_percent: part.parsed("x-savings-percent").unwrap_or(0.0),
+                        mime_type: part.header("content-type").unwrap_or("application/octet-stream").to_string(),
+                        page: part.parsed("x-page"),
+                        trimmed,
+                        bit_depth: part.parsed("x-bit-depth"),
+                        // Percent-encoded so non-ASCII names fit in a header.
+                        suggested_filename: part
+                            .header("x-suggested-filename")
+                            .and_then(|name| js_sys::decode_uri_component(name).ok())
+                            .map(String::from),
+                        data: part.body,
+                    };
+                    (file_index, response)
+                })
+                .collect()
+        } else {
+            let batch_response: ApiBatchCompressionResponse = serde_json::from_slice(&body)
+                .map_err(|e| BackendError::Other(format!("Failed to parse response: {:?}", e)))?;
+            // Split pages after the first belong to the same input file as the preceding result.
+            let mut file_index = 0usize;
+            batch_response
+                .results
+                .into_iter()
+                .enumerate()
+                .map(|(i, r)| {
+                    if i > 0 && !matches!(r.page, Some(p) if p > 1) {
+                        file_index += 1;
+                    }
+                    (file_index, r)
+                })
+                .collect()
+        };
 
-        // Convert API response to CompressionResult
         let req_fmt = request.output_format.clone();
-        let results = batch_response
-            .results
+        let results = outputs
             .into_iter()
-            .enumerate()
             .map(|(i, r)| {
                 let mime = mime_from_bytes_or_hint(&r.data, &r.mime_type);
                 CompressionResult {
-                    original_path: file_paths.get(i).cloned().unwrap_or_else(|| format!("file{}", i)),
+                    original_path: uploaded_paths.get(i).cloned().unwrap_or_else(|| format!("file{}", i)),
                     compressed_path: None,
                     original_size: r.original_size as u64,
                     compressed_size: r.compressed_size as u64,
@@ -613,6 +1367,12 @@ impl AppBackend for HttpBackend {
                     mime_type: mime,
                     data: r.data,
                     requested_output_format: Some(req_fmt.clone()),
+                    page: r.page,
+                    trimmed: r.trimmed,
+                    bit_depth: r.bit_depth,
+                    suggested_filename: r.suggested_filename,
+                    relative_dir: None,
+                    output_id: None,
                 }
             })
             .collect();
@@ -620,6 +1380,30 @@ impl AppBackend for HttpBackend {
         Ok(results)
     }
 
+    async fn cancel_batch(&self) -> Result<(), BackendError> {
+        IN_FLIGHT.with(|f| {
+            if let Some(InFlight::Http(abort)) = &*f.borrow() {
+                abort.abort();
+            }
+        });
+        Ok(())
+    }
+
+    async fn start_folder_watch(
+        &self,
+        _input: String,
+        _output: String,
+        _request: CompressionRequest,
+        _filter: ScanFilterSettings,
+        _on_event: Rc<dyn Fn(FolderWatchEvent)>,
+    ) -> Result<FolderWatchStatus, BackendError> {
+        Err(BackendError::NotAvailable)
+    }
+
+    async fn stop_folder_watch(&self) -> Result<(), BackendError> {
+        Ok(())
+    }
+
     async fn select_output_folder(&self) -> Result<String, BackendError> {
         // Browser mode doesn't have a folder picker, return a placeholder
         Ok("downloads".to_string())
@@ -684,92 +1468,84 @@ impl AppBackend for HttpBackend {
     }
 
     async fn save_files_as_zip(&self, request: SaveZipRequest) -> Result<String, BackendError> {
-        use wasm_bindgen::JsCast;
-        use wasm_bindgen::JsValue;
-        use wasm_bindgen_futures::JsFuture;
+        // The API builds the archive, naming entries with the desktop's ` (n)` collision rule.
+        let form_data = files_form(&request.files)?;
+        let blob = self.post_form_for_blob("/api/zip", &form_data).await?;
+        download_blob(&blob, &request.zip_filename)?;
 
-        let window = window().ok_or_else(|| BackendError::Other("No window object".to_string()))?;
-        let document = window.document().ok_or_else(|| BackendError::Other("No document".to_string()))?;
+        crate::utils::product_log(&format!("💾 Downloaded ZIP bundle: {}", request.zip_filename));
+        Ok(format!("downloads/{}", request.zip_filename))
+    }
 
-        let zip_cb = js_sys::Reflect::get(&window, &JsValue::from_str("__pandaZipBuildAndDownload")).ok();
-        if let Some(cb) = zip_cb {
-            if let Ok(func) = cb.dyn_into::<js_sys::Function>() {
-                let entries = js_sys::Array::new();
-                for file_data in &request.files {
-                    let o = js_sys::Object::new();
-                    let _ = js_sys::Reflect::set(
-                        &o,
-                        &JsValue::from_str("name"),
-                        &JsValue::from_str(&file_data.filename),
-                    );
-                    let _ = js_sys::Reflect::set(
-                        &o,
-                        &JsValue::from_str("data"),
-                        &JsValue::from(js_sys::Uint8Array::from(&file_data.data[..])),
-                    );
-                    entries.push(&o);
-                }
-                let zip_name = JsValue::from_str(&request.zip_filename);
-                if let Ok(ret) = func.call2(&JsValue::NULL, &zip_name, &entries) {
-                    let promise = js_sys::Promise::resolve(&ret);
-                    match JsFuture::from(promise).await {
-                        Ok(_) => {
-                            crate::utils::product_log(&format!(
-                                "💾 Downloaded ZIP bundle: {}",
-                                request.zip_filename
-                            ));
-                            return Ok(format!("downloads/{}", request.zip_filename));
-                        }
-                        Err(e) => {
-                            web_sys::console::warn_1(&JsValue::from_str(&format!(
-                                "ZIP helper failed (falling back to sequential downloads): {:?}",
-                                e
-                            )));
-                        }
-                    }
-                }
-            }
-        }
+    async fn save_files_as_pdf(&self, request: SavePdfRequest) -> Result<String, BackendError> {
+        let form_data = files_form(&request.files)?;
+        form_data.append_with_str("page_size", &request.page_size)
+            .map_err(|e| BackendError::SaveFailed(format!("Failed to add field: {:?}", e)))?;
+        form_data.append_with_str("margin_mm", &request.margin_mm.to_string())
+            .map_err(|e| BackendError::SaveFailed(format!("Failed to add field: {:?}", e)))?;
+        let blob = self.post_form_for_blob("/api/pdf", &form_data).await?;
+        download_blob(&blob, &request.pdf_filename)?;
+
+        crate::utils::product_log(&format!("💾 Downloaded PDF: {}", request.pdf_filename));
+        Ok(format!("downloads/{}", request.pdf_filename))
+    }
 
-        for file_data in &request.files {
-            let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(
-                &js_sys::Array::of1(&js_sys::Uint8Array::from(&file_data.data[..])),
-                web_sys::BlobPropertyBag::new().type_("application/octet-stream"),
-            )
-            .map_err(|e| BackendError::SaveFailed(format!("Failed to create blob: {:?}", e)))?;
+    async fn optimize_in_place(&self, _request: InPlaceRequest) -> Result<InPlaceSummary, BackendError> {
+        Err(BackendError::NotAvailable)
+    }
 
-            let url = web_sys::Url::create_object_url_with_blob(&blob)
-                .map_err(|e| BackendError::SaveFailed(format!("Failed to create URL: {:?}", e)))?;
+    async fn undo_last_in_place_run(&self) -> Result<InPlaceSummary, BackendError> {
+        Err(BackendError::NotAvailable)
+    }
 
-            let anchor = document
-                .create_element("a")
-                .map_err(|e| BackendError::SaveFailed(format!("Failed to create anchor: {:?}", e)))?
-                .dyn_into::<web_sys::HtmlAnchorElement>()
-                .map_err(|e| BackendError::SaveFailed(format!("Failed to cast anchor: {:?}", e)))?;
+    async fn load_watermark_preset(&self) -> Result<Option<WatermarkSettings>, BackendError> {
+        // Presets are a desktop feature; the browser keeps watermark settings for the session only
+        Ok(None)
+    }
 
-            anchor.set_href(&url);
-            anchor.set_download(&file_data.filename);
-            let html_el: &web_sys::HtmlElement = anchor.unchecked_ref();
-            let _ = html_el.style().set_property("display", "none");
+    async fn save_watermark_preset(&self, _preset: Option<WatermarkSettings>) -> Result<(), BackendError> {
+        Err(BackendError::NotAvailable)
+    }
 
-            document
-                .body()
-                .ok_or_else(|| BackendError::Other("No body".to_string()))?
-                .append_child(&anchor)
-                .map_err(|e| BackendError::SaveFailed(format!("Failed to append anchor: {:?}", e)))?;
+    async fn list_presets(&self) -> Result<Vec<PresetEntry>, BackendError> {
+        #[derive(Deserialize)]
+        struct PresetsResponse {
+            presets: Vec<PresetEntry>,
+        }
 
-            anchor.click();
+        let json = self.get("/api/presets").await?;
+        let text: String = js_sys::JSON::stringify(&json)
+            .map_err(|e| BackendError::Other(format!("Failed to read presets: {:?}", e)))?
+            .into();
+        let response: PresetsResponse = serde_json::from_str(&text)
+            .map_err(|e| BackendError::Other(format!("Failed to parse presets: {}", e)))?;
+        Ok(response.presets)
+    }
 
-            document
-                .body()
-                .unwrap()
-                .remove_child(&anchor)
-                .map_err(|e| BackendError::SaveFailed(format!("Failed to remove anchor: {:?}", e)))?;
+    async fn save_preset(&self, _name: String, _request: CompressionRequest) -> Result<Vec<PresetEntry>, BackendError> {
+        // Server presets come from its `PRESETS_FILE`
+        Err(BackendError::NotAvailable)
+    }
 
-            let _ = web_sys::Url::revoke_object_url(&url);
-        }
+    async fn delete_preset(&self, _name: String) -> Result<Vec<PresetEntry>, BackendError> {
+        Err(BackendError::NotAvailable)
+    }
 
-        Ok(format!("downloads/{}", request.zip_filename))
+    async fn import_presets(&self) -> Result<Vec<PresetEntry>, BackendError> {
+        Err(BackendError::NotAvailable)
+    }
+
+    async fn export_presets(&self, _names: Vec<String>) -> Result<String, BackendError> {
+        Err(BackendError::NotAvailable)
+    }
+
+    async fn get_settings(&self) -> Result<Option<UserSettings>, BackendError> {
+        // The browser starts every session from the defaults
+        Ok(None)
+    }
+
+    async fn update_settings(&self, _changes: UserSettings) -> Result<(), BackendError> {
+        Err(BackendError::NotAvailable)
     }
 
     async fn open_in_file_manager(&self, _path: String) -> Result<(), BackendError> {
@@ -778,6 +1554,47 @@ impl AppBackend for HttpBackend {
     }
 }
 
+/// Add the colour correction fields of `/api/compress/batch` (same names as `AdjustmentSettings`).
+fn append_adjustment_fields(form_data: &web_sys::FormData, adjustments: &AdjustmentSettings) -> Result<(), BackendError> {
+    let serde_json::Value::Object(fields) = serde_json::to_value(adjustments).unwrap_or_default() else {
+        return Ok(());
+    };
+    for (name, value) in fields {
+        form_data.append_with_str(&name, &value.to_string())
+            .map_err(|e| BackendError::CompressionFailed(format!("Failed to add {} field: {:?}", name, e)))?;
+    }
+    Ok(())
+}
+
+/// Add the watermark parts of `/api/compress/batch`: the logo picked in the browser (kept on
+/// `window.__WATERMARK_LOGO_FILE`) or the text, plus the placement settings.
+fn append_watermark_fields(
+    form_data: &web_sys::FormData,
+    window: &web_sys::Window,
+    watermark: &WatermarkSettings,
+) -> Result<(), BackendError> {
+    let field_err = |e: wasm_bindgen::JsValue| {
+        BackendError::CompressionFailed(format!("Failed to add watermark field: {:?}", e))
+    };
+
+    let logo = js_sys::Reflect::get(window, &wasm_bindgen::JsValue::from_str("__WATERMARK_LOGO_FILE"))
+        .ok()
+        .and_then(|file| file.dyn_into::<web_sys::Blob>().ok());
+    match (&watermark.image_path, logo) {
+        (Some(name), Some(blob)) => form_data.append_with_blob_and_filename("watermark", &blob, name).map_err(field_err)?,
+        _ => {
+            form_data.append_with_str("watermark_text", &watermark.text).map_err(field_err)?;
+            form_data.append_with_str("watermark_color", &watermark.color).map_err(field_err)?;
+        }
+    }
+    form_data.append_with_str("watermark_gravity", &watermark.gravity).map_err(field_err)?;
+    form_data.append_with_str("watermark_margin", &watermark.margin.to_string()).map_err(field_err)?;
+    form_data.append_with_str("watermark_scale", &watermark.scale.to_string()).map_err(field_err)?;
+    form_data.append_with_str("watermark_opacity", &watermark.opacity.to_string()).map_err(field_err)?;
+    form_data.append_with_str("watermark_tile", &watermark.tile.to_string()).map_err(field_err)?;
+    Ok(())
+}
+
 // Backend Provider
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -866,14 +1683,51 @@ impl AppBackend for BackendProvider {
         self.current_backend().select_files().await
     }
 
-    async fn handle_dropped_files(&self, file_paths: Vec<String>) -> Result<Vec<FileInfo>, BackendError> {
-        self.current_backend().handle_dropped_files(file_paths).await
+    async fn select_folder(&self, filter: ScanFilterSettings) -> Result<Vec<FileInfo>, BackendError> {
+        self.current_backend().select_folder(filter).await
+    }
+
+    async fn handle_dropped_files(
+        &self,
+        file_paths: Vec<String>,
+        filter: ScanFilterSettings,
+    ) -> Result<Vec<FileInfo>, BackendError> {
+        self.current_backend().handle_dropped_files(file_paths, filter).await
     }
 
     async fn compress_batch(&self, request: CompressionRequest) -> Result<Vec<CompressionResult>, BackendError> {
         self.current_backend().compress_batch(request).await
     }
 
+    async fn compress_batch_with_progress(
+        &self,
+        request: CompressionRequest,
+        on_progress: Rc<dyn Fn(BatchProgress)>,
+    ) -> Result<Vec<CompressionResult>, BackendError> {
+        self.current_backend().compress_batch_with_progress(request, on_progress).await
+    }
+
+    async fn cancel_batch(&self) -> Result<(), BackendError> {
+        self.current_backend().cancel_batch().await
+    }
+
+    async fn start_folder_watch(
+        &self,
+        input: String,
+        output: String,
+        request: CompressionRequest,
+        filter: ScanFilterSettings,
+        on_event: Rc<dyn Fn(FolderWatchEvent)>,
+    ) -> Result<FolderWatchStatus, BackendError> {
+        self.current_backend()
+            .start_folder_watch(input, output, request, filter, on_event)
+            .await
+    }
+
+    async fn stop_folder_watch(&self) -> Result<(), BackendError> {
+        self.current_backend().stop_folder_watch().await
+    }
+
     async fn select_output_folder(&self) -> Result<String, BackendError> {
         self.current_backend().select_output_folder().await
     }
@@ -897,6 +1751,54 @@ impl AppBackend for BackendProvider {
         self.current_backend().save_files_as_zip(request).await
     }
 
+    async fn save_files_as_pdf(&self, request: SavePdfRequest) -> Result<String, BackendError> {
+        self.current_backend().save_files_as_pdf(request).await
+    }
+
+    async fn optimize_in_place(&self, request: InPlaceRequest) -> Result<InPlaceSummary, BackendError> {
+        self.current_backend().optimize_in_place(request).await
+    }
+
+    async fn undo_last_in_place_run(&self) -> Result<InPlaceSummary, BackendError> {
+        self.current_backend().undo_last_in_place_run().await
+    }
+
+    async fn load_watermark_preset(&self) -> Result<Option<WatermarkSettings>, BackendError> {
+        self.current_backend().load_watermark_preset().await
+    }
+
+    async fn save_watermark_preset(&self, preset: Option<WatermarkSettings>) -> Result<(), BackendError> {
+        self.current_backend().save_watermark_preset(preset).await
+    }
+
+    async fn list_presets(&self) -> Result<Vec<PresetEntry>, BackendError> {
+        self.current_backend().list_presets().await
+    }
+
+    async fn save_preset(&self, name: String, request: CompressionRequest) -> Result<Vec<PresetEntry>, BackendError> {
+        self.current_backend().save_preset(name, request).await
+    }
+
+    async fn delete_preset(&self, name: String) -> Result<Vec<PresetEntry>, BackendError> {
+        self.current_backend().delete_preset(name).await
+    }
+
+    async fn import_presets(&self) -> Result<Vec<PresetEntry>, BackendError> {
+        self.current_backend().import_presets().await
+    }
+
+    async fn export_presets(&self, names: Vec<String>) -> Result<String, BackendError> {
+        self.current_backend().export_presets(names).await
+    }
+
+    async fn get_settings(&self) -> Result<Option<UserSettings>, BackendError> {
+        self.current_backend().get_settings().await
+    }
+
+    async fn update_settings(&self, changes: UserSettings) -> Result<(), BackendError> {
+        self.current_backend().update_settings(changes).await
+    }
+
     async fn open_in_file_manager(&self, path: String) -> Result<(), BackendError> {
         self.current_backend().open_in_file_manager(path).await
     }
@@ -0,0 +1,63 @@
+//! Reader for the `multipart/mixed` body `/api/compress/batch` returns for
+//! `Accept: multipart/mixed`: one part per output, metadata in `x-*` part headers.
+
+/// One body part: lowercase header names with their values, and the raw part body.
+pub struct Part {
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Part {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Header parsed as `T`, `None` when missing or malformed.
+    pub fn parsed<T: std::str::FromStr>(&self, name: &str) -> Option<T> {
+        self.header(name).and_then(|value| value.trim().parse().ok())
+    }
+}
+
+/// Split `body` into its parts using the `boundary` parameter of `content_type`.
+pub fn parse(content_type: &str, body: &[u8]) -> Result<Vec<Part>, String> {
+    let boundary = content_type
+        .split(';')
+        .filter_map(|param| param.trim().strip_prefix("boundary="))
+        .next()
+        .map(|b| b.trim_matches('"'))
+        .ok_or_else(|| format!("No boundary in content type '{}'", content_type))?;
+    let delimiter = format!("--{}", boundary);
+    let delimiter = delimiter.as_bytes();
+
+    let mut parts = Vec::new();
+    let mut rest = match find(body, delimiter) {
+        Some(start) => &body[start + delimiter.len()..],
+        None => return Err("Multipart body has no parts".to_string()),
+    };
+    // After each delimiter: `--` closes the body, otherwise CRLF, headers, blank line, body.
+    while !rest.starts_with(b"--") {
+        let rest_after_crlf = rest.strip_prefix(b"\r\n").unwrap_or(rest);
+        let end = find(rest_after_crlf, delimiter).ok_or("Unterminated multipart part")?;
+        let raw = &rest_after_crlf[..end];
+        let raw = raw.strip_suffix(b"\r\n").unwrap_or(raw);
+        let head_end = find(raw, b"\r\n\r\n").ok_or("Multipart part without headers")?;
+        let headers = String::from_utf8_lossy(&raw[..head_end])
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+            .collect();
+        parts.push(Part {
+            headers,
+            body: raw[head_end + 4..].to_vec(),
+        });
+        rest = &rest_after_crlf[end + delimiter.len()..];
+    }
+    Ok(parts)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
@@ -0,0 +1,91 @@
+use leptos::prelude::*;
+use crate::state::{AdjustmentSettings, AppState};
+
+/// Slider rows: id, label, min, max, how to read / write the setting as a whole number.
+type Slider = (
+    &'static str,
+    &'static str,
+    i32,
+    i32,
+    fn(&AdjustmentSettings) -> i32,
+    fn(&mut AdjustmentSettings, i32),
+);
+
+const SLIDERS: [Slider; 5] = [
+    ("adjBrightness", "Brightness", -100, 100,
+        |a| (a.brightness * 100.0).round() as i32, |a, v| a.brightness = v as f32 / 100.0),
+    ("adjContrast", "Contrast", -100, 100,
+        |a| (a.contrast * 100.0).round() as i32, |a, v| a.contrast = v as f32 / 100.0),
+    ("adjSaturation", "Saturation", -100, 100,
+        |a| (a.saturation * 100.0).round() as i32, |a, v| a.saturation = v as f32 / 100.0),
+    // Gamma in tenths: 10 is neutral.
+    ("adjGamma", "Gamma", 2, 30,
+        |a| (a.gamma * 10.0).round() as i32, |a, v| a.gamma = v as f32 / 10.0),
+    ("adjSharpen", "Sharpen", 0, 200,
+        |a| (a.sharpen * 100.0).round() as i32, |a, v| a.sharpen = v as f32 / 100.0),
+];
+
+const TOGGLES: [(&str, &str, fn(&AdjustmentSettings) -> bool, fn(&mut AdjustmentSettings, bool)); 3] = [
+    ("adjAutoLevels", "Auto-levels", |a| a.auto_levels, |a, v| a.auto_levels = v),
+    ("adjWhiteBalance", "Auto white balance", |a| a.white_balance, |a, v| a.white_balance = v),
+    ("adjDenoise", "Reduce noise", |a| a.denoise, |a, v| a.denoise = v),
+];
+
+#[component]
+pub fn AdjustmentOptions(state: AppState) -> impl IntoView {
+    view! {
+        <div class="options-heading">"Corrections"</div>
+        {TOGGLES
+            .into_iter()
+            .map(|(id, label, get, set)| {
+                view! {
+                    <div class="checkbox-group">
+                        <input
+                            type="checkbox"
+                            id=id
+                            checked=move || get(&state.adjustments.get())
+                            on:change=move |ev| {
+                                let checked = event_target_checked(&ev);
+                                state.adjustments.update(|a| set(a, checked));
+                            }
+                        />
+                        <label for=id>{label}</label>
+                    </div>
+                }
+            })
+            .collect_view()}
+        {SLIDERS
+            .into_iter()
+            .map(|(id, label, min, max, get, set)| {
+                view! {
+                    <div class="select-group">
+                        <label for=id>{label}</label>
+                        <input
+                            type="range"
+                            id=id
+                            min=min
+                            max=max
+                            step="1"
+                            prop:value=move || get(&state.adjustments.get()).to_string()
+                            on:input=move |ev| {
+                                if let Ok(value) = event_target_value(&ev).parse::<i32>() {
+                                    state.adjustments.update(|a| set(a, value.clamp(min, max)));
+                                }
+                            }
+                        />
+                        <span class="range-value">{move || get(&state.adjustments.get())}</span>
+                    </div>
+                }
+            })
+            .collect_view()}
+        <Show when=move || !state.adjustments.get().is_identity()>
+            <button
+                type="button"
+                class="secondary-button"
+                on:click=move |_| state.adjustments.set(AdjustmentSettings::default())
+            >
+                "Reset corrections"
+            </button>
+        </Show>
+    }
+}
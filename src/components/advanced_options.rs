@@ -1,8 +1,17 @@
 use leptos::prelude::*;
 use crate::state::AppState;
+use crate::backend::BackendProvider;
+use crate::components::{AdjustmentOptions, WatermarkOptions};
+
+/// Comma-separated globs from a text input.
+fn parse_globs(text: &str) -> Vec<String> {
+    text.split(',').map(str::trim).filter(|g| !g.is_empty()).map(String::from).collect()
+}
 
 #[component]
 pub fn AdvancedOptions(state: AppState) -> impl IntoView {
+    let has_folder_picker = BackendProvider::new().capabilities().supports_folder_picker;
+
     view! {
         <div>
             <div
@@ -40,6 +49,287 @@ pub fn AdvancedOptions(state: AppState) -> impl IntoView {
                         />
                         <label for="pngLossy">"PNG lossy compression"</label>
                     </div>
+                    <div class="checkbox-group">
+                        <input
+                            type="checkbox"
+                            id="jxlLosslessJpeg"
+                            checked=move || state.jxl_lossless_jpeg.get()
+                            on:change=move |ev| {
+                                state.jxl_lossless_jpeg.set(event_target_checked(&ev));
+                            }
+                        />
+                        <label for="jxlLosslessJpeg">"Lossless JPEG → JXL recompression"</label>
+                    </div>
+                    <div class="select-group">
+                        <label for="tiffCompression">"TIFF compression"</label>
+                        <select
+                            id="tiffCompression"
+                            prop:value=move || state.tiff_compression.get()
+                            on:change=move |ev| {
+                                state.tiff_compression.set(event_target_value(&ev));
+                            }
+                        >
+                            <option value="lzw">"LZW"</option>
+                            <option value="deflate">"Deflate"</option>
+                            <option value="packbits">"PackBits"</option>
+                            <option value="none">"None"</option>
+                        </select>
+                    </div>
+                    <div class="checkbox-group">
+                        <input
+                            type="checkbox"
+                            id="splitPages"
+                            checked=move || state.split_pages.get()
+                            on:change=move |ev| {
+                                state.split_pages.set(event_target_checked(&ev));
+                            }
+                        />
+                        <label for="splitPages">"Split multi-page TIFF into separate outputs"</label>
+                    </div>
+                    <div class="checkbox-group">
+                        <input
+                            type="checkbox"
+                            id="dither"
+                            checked=move || state.dither.get()
+                            on:change=move |ev| {
+                                state.dither.set(event_target_checked(&ev));
+                            }
+                        />
+                        <label for="dither">"Dither when reducing 16-bit images to 8-bit"</label>
+                    </div>
+                    <div class="select-group">
+                        <label for="colorProfile">"Colour profile"</label>
+                        <select
+                            id="colorProfile"
+                            prop:value=move || state.color_profile.get()
+                            on:change=move |ev| {
+                                state.color_profile.set(event_target_value(&ev));
+                            }
+                        >
+                            <option value="srgb">"Convert to sRGB"</option>
+                            <option value="keep">"Keep embedded profile"</option>
+                            <option value="ignore">"Ignore profile"</option>
+                        </select>
+                    </div>
+                    <div class="checkbox-group">
+                        <input
+                            type="checkbox"
+                            id="autoTrim"
+                            checked=move || state.auto_trim.get()
+                            on:change=move |ev| {
+                                state.auto_trim.set(event_target_checked(&ev));
+                            }
+                        />
+                        <label for="autoTrim">"Auto-trim uniform / transparent borders"</label>
+                    </div>
+                    <div class="select-group">
+                        <label for="rotate">"Rotate"</label>
+                        <select
+                            id="rotate"
+                            prop:value=move || state.rotate.get()
+                            on:change=move |ev| {
+                                state.rotate.set(event_target_value(&ev));
+                            }
+                        >
+                            <option value="0">"None"</option>
+                            <option value="90">"90° clockwise"</option>
+                            <option value="180">"180°"</option>
+                            <option value="270">"90° counter-clockwise"</option>
+                        </select>
+                    </div>
+                    <div class="checkbox-group">
+                        <input
+                            type="checkbox"
+                            id="flipHorizontal"
+                            checked=move || state.flip_horizontal.get()
+                            on:change=move |ev| {
+                                state.flip_horizontal.set(event_target_checked(&ev));
+                            }
+                        />
+                        <label for="flipHorizontal">"Flip horizontally"</label>
+                    </div>
+                    <div class="checkbox-group">
+                        <input
+                            type="checkbox"
+                            id="flipVertical"
+                            checked=move || state.flip_vertical.get()
+                            on:change=move |ev| {
+                                state.flip_vertical.set(event_target_checked(&ev));
+                            }
+                        />
+                        <label for="flipVertical">"Flip vertically"</label>
+                    </div>
+                    <div class="select-group">
+                        <label for="aspectCrop">"Crop to aspect ratio"</label>
+                        <select
+                            id="aspectCrop"
+                            prop:value=move || state.aspect_crop.get()
+                            on:change=move |ev| {
+                                state.aspect_crop.set(event_target_value(&ev));
+                            }
+                        >
+                            <option value="">"Original"</option>
+                            <option value="1:1">"1:1"</option>
+                            <option value="4:3">"4:3"</option>
+                            <option value="3:2">"3:2"</option>
+                            <option value="16:9">"16:9"</option>
+                            <option value="9:16">"9:16"</option>
+                        </select>
+                    </div>
+                    <div class="select-group">
+                        <label for="resizeWidth">"Fit within (px)"</label>
+                        <input
+                            type="number"
+                            id="resizeWidth"
+                            min="1"
+                            placeholder="Width"
+                            prop:value=move || state.resize_width.get().map(|w| w.to_string()).unwrap_or_default()
+                            on:change=move |ev| {
+                                state.resize_width.set(event_target_value(&ev).parse::<u32>().ok().filter(|w| *w > 0));
+                            }
+                        />
+                        <input
+                            type="number"
+                            id="resizeHeight"
+                            min="1"
+                            placeholder="Height"
+                            prop:value=move || state.resize_height.get().map(|h| h.to_string()).unwrap_or_default()
+                            on:change=move |ev| {
+                                state.resize_height.set(event_target_value(&ev).parse::<u32>().ok().filter(|h| *h > 0));
+                            }
+                        />
+                    </div>
+                    <AdjustmentOptions state=state />
+                    <WatermarkOptions state=state />
+                    <div class="select-group">
+                        <label for="filenameTemplate">"Filename template"</label>
+                        <input
+                            type="text"
+                            id="filenameTemplate"
+                            placeholder="{stem}.{ext}"
+                            title=format!("Tokens: {}", naming::TEMPLATE_TOKENS.map(|t| format!("{{{t}}}")).join(" "))
+                            prop:value=move || state.filename_template.get()
+                            on:change=move |ev| {
+                                state.filename_template.set(event_target_value(&ev));
+                            }
+                        />
+                    </div>
+                    <Show when=move || has_folder_picker>
+                        <div class="select-group">
+                            <label for="inPlaceSuffix">"In-place suffix"</label>
+                            <input
+                                type="text"
+                                id="inPlaceSuffix"
+                                placeholder="Empty replaces the original"
+                                prop:value=move || state.in_place_suffix.get()
+                                on:change=move |ev| {
+                                    state.in_place_suffix.set(event_target_value(&ev));
+                                }
+                            />
+                        </div>
+                        <div class="checkbox-group">
+                            <input
+                                type="checkbox"
+                                id="preserveFileTimes"
+                                checked=move || state.preserve_file_times.get()
+                                on:change=move |ev| {
+                                    state.preserve_file_times.set(event_target_checked(&ev));
+                                }
+                            />
+                            <label for="preserveFileTimes">"In place: keep modification times"</label>
+                        </div>
+                        <div class="checkbox-group">
+                            <input
+                                type="checkbox"
+                                id="preservePermissions"
+                                checked=move || state.preserve_permissions.get()
+                                on:change=move |ev| {
+                                    state.preserve_permissions.set(event_target_checked(&ev));
+                                }
+                            />
+                            <label for="preservePermissions">"In place: keep file permissions"</label>
+                        </div>
+                        <div class="select-group">
+                            <label for="folderInclude">"Folder include"</label>
+                            <input
+                                type="text"
+                                id="folderInclude"
+                                placeholder="*.png, raw/**/*.tif"
+                                prop:value=move || state.scan_filter.get().include.join(", ")
+                                on:change=move |ev| {
+                                    state.scan_filter.update(|f| f.include = parse_globs(&event_target_value(&ev)));
+                                }
+                            />
+                        </div>
+                        <div class="select-group">
+                            <label for="folderExclude">"Folder exclude"</label>
+                            <input
+                                type="text"
+                                id="folderExclude"
+                                placeholder="thumbs, **/node_modules"
+                                prop:value=move || state.scan_filter.get().exclude.join(", ")
+                                on:change=move |ev| {
+                                    state.scan_filter.update(|f| f.exclude = parse_globs(&event_target_value(&ev)));
+                                }
+                            />
+                        </div>
+                        <div class="select-group">
+                            <label for="folderMaxSize">"Largest file in a folder (MB)"</label>
+                            <input
+                                type="number"
+                                id="folderMaxSize"
+                                min="0"
+                                step="0.1"
+                                placeholder="No limit"
+                                prop:value=move || {
+                                    state
+                                        .scan_filter
+                                        .get()
+                                        .max_size
+                                        .map(|bytes| (bytes as f64 / 1_000_000.0).to_string())
+                                        .unwrap_or_default()
+                                }
+                                on:change=move |ev| {
+                                    let max_size = event_target_value(&ev)
+                                        .trim()
+                                        .parse::<f64>()
+                                        .ok()
+                                        .filter(|mb| *mb > 0.0)
+                                        .map(|mb| (mb * 1_000_000.0) as u64);
+                                    state.scan_filter.update(|f| f.max_size = max_size);
+                                }
+                            />
+                        </div>
+                    </Show>
+                    <div class="select-group">
+                        <label for="pdfPageSize">"PDF page size"</label>
+                        <select
+                            id="pdfPageSize"
+                            prop:value=move || state.pdf_page_size.get()
+                            on:change=move |ev| {
+                                state.pdf_page_size.set(event_target_value(&ev));
+                            }
+                        >
+                            <option value="fit">"Fit to image"</option>
+                            <option value="a4">"A4"</option>
+                            <option value="letter">"Letter"</option>
+                        </select>
+                    </div>
+                    <div class="select-group">
+                        <label for="pdfMargin">"PDF margin (mm)"</label>
+                        <input
+                            type="number"
+                            id="pdfMargin"
+                            min="0"
+                            max="50"
+                            step="1"
+                            prop:value=move || state.pdf_margin_mm.get().to_string()
+                            on:change=move |ev| {
+                                let value = event_target_value(&ev).parse::<f32>().unwrap_or(0.0);
+                                state.pdf_margin_mm.set(value.clamp(0.0, 50.0));
+                            }
+                        />
+                    </div>
                 </div>
             </Show>
         </div>
@@ -1,29 +1,16 @@
 use leptos::prelude::*;
 use crate::state::{AppState, CompressionResult, PendingSaveOptions};
-use crate::backend::{BackendProvider, AppBackend, CompressionRequest, SaveFilesRequest, SaveZipRequest, CollisionCheckRequest, ResolveUniqueFilenamesRequest, FileSaveData, BackendError};
+use crate::backend::{BackendProvider, AppBackend, BatchProgress, SaveFilesRequest, SaveZipRequest, SavePdfRequest, CollisionCheckRequest, ResolveUniqueFilenamesRequest, FileSaveData, BackendError, InPlaceRequest};
 use crate::utils;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::spawn_local;
 use js_sys;
 
-/// Sanitize a filename by removing path separators and dangerous characters
-fn sanitize_filename(name: &str) -> String {
-    name.replace('\\', "_")
-        .replace('/', "_")
-        .replace(':', "_")
-        .replace('*', "_")
-        .replace('?', "_")
-        .replace('"', "_")
-        .replace('<', "_")
-        .replace('>', "_")
-        .replace('|', "_")
-        .trim()
-        .to_string()
-}
+use naming::{sanitize_filename, sanitize_relative_path};
 
 /// Check if a filename edit is "dirty" (different from initial value after sanitization)
 fn is_dirty(edit: &str, initial: &str) -> bool {
-    sanitize_filename(edit) != sanitize_filename(initial)
+    sanitize_relative_path(edit) != sanitize_relative_path(initial)
 }
 
 /// Build the final output filenames considering collision overrides
@@ -50,7 +37,7 @@ fn build_output_filenames(
                 if let Some(index) = collision_files.iter().position(|c| c == &default_name) {
                     collision_edits
                         .get(index)
-                        .map(|edit| sanitize_filename(edit))
+                        .map(|edit| sanitize_relative_path(edit))
                         .unwrap_or_else(|| default_name)
                 } else {
                     default_name
@@ -85,7 +72,7 @@ fn build_proposed_filenames_for_collision_rename(
             if dirty {
                 let edit = collision_edits
                     .get(i)
-                    .map(|s| sanitize_filename(s))
+                    .map(|s| sanitize_relative_path(s))
                     .unwrap_or_else(|| default_name.clone());
                 out.push(edit);
             } else {
@@ -115,71 +102,41 @@ fn prepare_files_payload_with_overrides(
         .collect()
 }
 
-fn generate_filename_from_result(result: &CompressionResult) -> String {
-    result.display_export_filename()
+/// Folder of the input at `path` below a picked or dropped folder, if it came from one.
+fn relative_dir_for(relative_dirs: &[(String, String)], path: &str) -> Option<String> {
+    relative_dirs.iter().find(|(p, _)| p == path).map(|(_, dir)| dir.clone())
 }
 
-fn generate_unique_filename(folder: &str, original: &str, existing: &[String]) -> String {
-    let full_path = std::path::Path::new(folder).join(original);
-    let is_taken = existing.iter().any(|name| name == original) || full_path.exists();
-
-    if !is_taken {
-        return original.to_string();
-    }
-
-    let original_path = std::path::Path::new(original);
-    let stem = original_path
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("compressed");
-    let ext = original_path.extension().and_then(|e| e.to_str());
-
-    for index in 1..10_000 {
-        let candidate = match ext {
-            Some(ext) => format!("{} ({}).{}", stem, index, ext),
-            None => format!("{} ({})", stem, index),
-        };
-
-        let candidate_path = std::path::Path::new(folder).join(&candidate);
-        let candidate_taken = existing.iter().any(|name| name == &candidate) || candidate_path.exists();
-
-        if !candidate_taken {
-            return candidate;
-        }
-    }
-
-    original.to_string()
+fn generate_filename_from_result(result: &CompressionResult) -> String {
+    result.display_export_filename()
 }
 
+/// Files payload with the default export names; with `auto_rename`, names that are in
+/// `existing` or repeat an earlier file in the same folder get the next free ` (n)` suffix.
 fn prepare_files_payload(
     results: &[CompressionResult],
-    output_folder: &str,
     auto_rename: bool,
     existing: &[String],
 ) -> Vec<FileSaveData> {
-    let mut files_to_save = Vec::new();
-    let mut taken_names = existing.to_vec();
-
-    for result in results {
-        let initial_filename = generate_filename_from_result(result);
-        let filename = if auto_rename {
-            generate_unique_filename(output_folder, &initial_filename, &taken_names)
-        } else {
-            initial_filename
-        };
-
-        taken_names.push(filename.clone());
+    let initial_names: Vec<String> = results.iter().map(generate_filename_from_result).collect();
+    let filenames = if auto_rename {
+        naming::uniquify_paths(&initial_names, |name| existing.iter().any(|e| e == name))
+            .unwrap_or(initial_names)
+    } else {
+        initial_names
+    };
+    prepare_files_payload_with_overrides(results, filenames)
+}
 
-        files_to_save.push(FileSaveData {
-            filename,
-            data: result.data.clone(),
-        });
-    }
+fn build_zip_filename() -> String {
+    build_bundle_filename("zip")
+}
 
-    files_to_save
+fn build_pdf_filename() -> String {
+    build_bundle_filename("pdf")
 }
 
-fn build_zip_filename() -> String {
+fn build_bundle_filename(ext: &str) -> String {
     let now = js_sys::Date::new_0();
     let hours = now.get_hours();
     let minutes = now.get_minutes();
@@ -189,10 +146,152 @@ fn build_zip_filename() -> String {
     let day = now.get_date();
 
     format!(
-        "{hours:02}-{minutes:02}-{seconds:02}-{year:04}-{month:02}-{day:02}-panda-pixel-app.zip"
+        "{hours:02}-{minutes:02}-{seconds:02}-{year:04}-{month:02}-{day:02}-panda-pixel-app.{ext}"
     )
 }
 
+/// Bundle every successful result into one PDF (one image per page) and save or download it.
+async fn save_as_pdf(state: AppState, backend: BackendProvider) {
+    let results: Vec<CompressionResult> = state
+        .results
+        .get_untracked()
+        .into_iter()
+        .filter(|r| !r.data.is_empty() && r.mime_type != "application/error")
+        .collect();
+
+    if results.is_empty() {
+        state.error.set(Some("No compressed files to save".to_string()));
+        state.status.set(None);
+        return;
+    }
+
+    let is_tauri_backend = backend.is_tauri();
+
+    let output_folder = match backend.select_output_folder().await {
+        Ok(folder) => folder,
+        Err(BackendError::Cancelled) => return,
+        Err(e) => {
+            state.status.set(None);
+            state.error.set(Some(e.to_string()));
+            return;
+        }
+    };
+
+    // Never overwrite an existing PDF on desktop.
+    let pdf_filename = build_pdf_filename();
+    let pdf_filename = if is_tauri_backend {
+        match backend
+            .resolve_unique_filenames(ResolveUniqueFilenamesRequest {
+                output_folder: output_folder.clone(),
+                filenames: vec![pdf_filename.clone()],
+            })
+            .await
+        {
+            Ok(mut v) => v.pop().unwrap_or(pdf_filename),
+            Err(e) => {
+                state.status.set(None);
+                state.error.set(Some(e.to_string()));
+                return;
+            }
+        }
+    } else {
+        pdf_filename
+    };
+
+    let files: Vec<FileSaveData> = results
+        .iter()
+        .map(|result| FileSaveData {
+            filename: generate_filename_from_result(result),
+            data: result.data.clone(),
+        })
+        .collect();
+
+    match backend
+        .save_files_as_pdf(SavePdfRequest {
+            output_folder: output_folder.clone(),
+            pdf_filename,
+            files,
+            page_size: state.pdf_page_size.get_untracked(),
+            margin_mm: state.pdf_margin_mm.get_untracked(),
+        })
+        .await
+    {
+        Ok(saved_pdf_path) => {
+            state.error.set(None);
+            let verb = if is_tauri_backend { "Saved" } else { "Downloaded" };
+            state.status.set(Some(format!(
+                "{verb} PDF: {} ({} page(s))",
+                utils::basename(&saved_pdf_path),
+                results.len()
+            )));
+            let _ = backend.open_in_file_manager(output_folder).await;
+        }
+        Err(e) => {
+            state.status.set(None);
+            state.error.set(Some(e.to_string()));
+        }
+    }
+}
+
+/// Write every result back where its input is (desktop), backing the inputs up first.
+async fn optimize_in_place(state: AppState, backend: BackendProvider) {
+    let results: Vec<CompressionResult> = state
+        .results
+        .get_untracked()
+        .into_iter()
+        .filter(|r| !r.data.is_empty() && r.mime_type != "application/error")
+        .collect();
+    if results.is_empty() {
+        state.error.set(Some("No compressed files to write".to_string()));
+        return;
+    }
+    if results.iter().any(|r| r.page.is_some()) {
+        state.error.set(Some("Split pages can't be written in place; save them to a folder instead".to_string()));
+        return;
+    }
+
+    let request = InPlaceRequest {
+        original_paths: results.iter().map(|r| r.original_path.clone()).collect(),
+        files: results
+            .iter()
+            .map(|result| FileSaveData {
+                filename: generate_filename_from_result(result),
+                data: result.data.clone(),
+            })
+            .collect(),
+        suffix: state.in_place_suffix.get_untracked().trim().to_string(),
+        preserve_times: state.preserve_file_times.get_untracked(),
+        preserve_permissions: state.preserve_permissions.get_untracked(),
+    };
+    match backend.optimize_in_place(request).await {
+        Ok(summary) => {
+            state.error.set(None);
+            state.status.set(Some(format!(
+                "Optimized {} file(s) in place; originals backed up to {}",
+                summary.paths.len(),
+                summary.backup_dir
+            )));
+        }
+        Err(e) => {
+            state.status.set(None);
+            state.error.set(Some(e.to_string()));
+        }
+    }
+}
+
+async fn undo_last_in_place_run(state: AppState, backend: BackendProvider) {
+    match backend.undo_last_in_place_run().await {
+        Ok(summary) => {
+            state.error.set(None);
+            state.status.set(Some(format!("Undo restored {} file(s)", summary.paths.len())));
+        }
+        Err(e) => {
+            state.status.set(None);
+            state.error.set(Some(e.to_string()));
+        }
+    }
+}
+
 async fn save_with_options(
     state: AppState,
     backend: BackendProvider,
@@ -200,7 +299,7 @@ async fn save_with_options(
     folder_override: Option<String>,
     manual_filenames: Option<Vec<String>>,
 ) {
-    let results = state.results.get_untracked();
+    let results = state.unsaved_results();
 
     if results.is_empty() {
         state.error.set(Some("No compressed files to save".to_string()));
@@ -283,7 +382,7 @@ async fn save_with_options(
             }
         }
     } else {
-        prepare_files_payload(&results, &output_folder, options.auto_rename, &collisions)
+        prepare_files_payload(&results, options.auto_rename, &collisions)
     };
 
     if options.as_zip {
@@ -405,11 +504,13 @@ pub fn CompressButton(state: AppState) -> impl IntoView {
     };
 
     let has_empty_name = move || {
-        state.collision_name_edits.get().iter().any(|name| sanitize_filename(name).is_empty())
+        state.collision_name_edits.get().iter().any(|name| sanitize_relative_path(name).is_empty())
     };
 
     let compress_files = move |_| {
         spawn_local(async move {
+            use std::cell::RefCell;
+            use std::rc::Rc;
             use std::sync::atomic::{AtomicBool, Ordering};
             use std::sync::Arc;
 
@@ -420,50 +521,106 @@ pub fn CompressButton(state: AppState) -> impl IntoView {
 
             let files = state.files.get_untracked();
             let file_paths: Vec<String> = files.iter().map(|f| f.path.clone()).collect();
-            let compression_level = state.compression_level.get_untracked();
-            let output_format = state.output_format.get_untracked();
-            let oxipng = state.oxipng.get_untracked();
-            let png_lossy = state.png_lossy.get_untracked();
+            let relative_dirs: Rc<Vec<(String, String)>> = Rc::new(
+                files
+                    .iter()
+                    .filter_map(|f| Some((f.path.clone(), f.relative_dir()?.to_string())))
+                    .collect(),
+            );
+            let request = state.compression_request(file_paths);
+            let output_format = request.output_format.clone();
+            if !request.filename_template.is_empty() {
+                if let Err(e) = naming::validate_template(&request.filename_template) {
+                    state.error.set(Some(e));
+                    state.is_compressing.set(false);
+                    return;
+                }
+            }
 
             if utils::is_dev_mode() {
-                let file_count = file_paths.len();
-                let sample_names: Vec<String> = file_paths
+                let file_count = request.file_paths.len();
+                let sample_names: Vec<String> = request
+                    .file_paths
                     .iter()
                     .take(3)
                     .map(|p| utils::basename(p))
                     .collect();
                 web_sys::console::log_1(&wasm_bindgen::JsValue::from_str(&format!(
                     "🔧 compress_batch: {} files, format={}, level={}, sample={:?}",
-                    file_count, output_format, compression_level, sample_names
+                    file_count, output_format, request.compression_level, sample_names
                 )));
             }
 
-            state.progress.set(25.0);
-            utils::sleep_ms(50).await;
-
+            let backend = BackendProvider::new();
+            let streamed = backend.capabilities().supports_progress_events;
             let done = Arc::new(AtomicBool::new(false));
-            let done_ticker = done.clone();
-            let state_ticker = state.clone();
-            spawn_local(async move {
-                for step in [50.0_f64, 75.0_f64] {
-                    utils::sleep_ms(450).await;
-                    if done_ticker.load(Ordering::SeqCst) {
-                        break;
+            state.results.set(Vec::new());
+            if !streamed {
+                // No per-file events: step the bar on a timer while the batch runs.
+                state.progress.set(25.0);
+                utils::sleep_ms(50).await;
+
+                let done_ticker = done.clone();
+                let state_ticker = state.clone();
+                spawn_local(async move {
+                    for step in [50.0_f64, 75.0_f64] {
+                        utils::sleep_ms(450).await;
+                        if done_ticker.load(Ordering::SeqCst) {
+                            break;
+                        }
+                        if state_ticker.is_compressing.get_untracked() {
+                            state_ticker.progress.set(step);
+                        }
                     }
-                    if state_ticker.is_compressing.get_untracked() {
-                        state_ticker.progress.set(step);
+                });
+            }
+
+            // Finished files by input index, shown in the results list as they arrive.
+            let finished: Rc<RefCell<Vec<Option<Vec<CompressionResult>>>>> =
+                Rc::new(RefCell::new(vec![None; request.file_paths.len()]));
+            let failures: Rc<RefCell<Vec<(usize, String)>>> = Rc::new(RefCell::new(Vec::new()));
+            let on_progress = {
+                let finished = finished.clone();
+                let failures = failures.clone();
+                let output_format = output_format.clone();
+                let relative_dirs = relative_dirs.clone();
+                Rc::new(move |event: BatchProgress| {
+                    let (done, total) = match event {
+                        BatchProgress::Started { index, path, .. } => {
+                            utils::product_log(&format!("⏳ Compressing #{}: {}", index + 1, utils::basename(&path)));
+                            return;
+                        }
+                        BatchProgress::Finished { index, path, results, done, total, .. } => {
+                            utils::product_log(&format!("✅ Compressed {} ({}/{})", utils::basename(&path), done, total));
+                            let results = results
+                                .into_iter()
+                                .map(|mut r| {
+                                    if r.requested_output_format.is_none() {
+                                        r.requested_output_format = Some(output_format.clone());
+                                    }
+                                    r.relative_dir = relative_dir_for(&relative_dirs, &r.original_path);
+                                    r
+                                })
+                                .collect();
+                            if let Some(slot) = finished.borrow_mut().get_mut(index) {
+                                *slot = Some(results);
+                            }
+                            state.results.set(finished.borrow().iter().flatten().flatten().cloned().collect());
+                            (done, total)
+                        }
+                        BatchProgress::Failed { index, path, error, done, total, .. } => {
+                            failures.borrow_mut().push((index, format!("{}: {}", utils::basename(&path), error)));
+                            (done, total)
+                        }
+                    };
+                    if total > 0 {
+                        state.progress.set(done as f64 * 100.0 / total as f64);
                     }
-                }
-            });
-
-            let batch_result = BackendProvider::new()
-                .compress_batch(CompressionRequest {
-                    file_paths,
-                    compression_level,
-                    output_format: output_format.clone(),
-                    oxipng,
-                    png_lossy,
-                })
+                }) as Rc<dyn Fn(BatchProgress)>
+            };
+
+            let batch_result = backend
+                .compress_batch_with_progress(request, on_progress)
                 .await;
 
             done.store(true, Ordering::SeqCst);
@@ -478,18 +635,39 @@ pub fn CompressButton(state: AppState) -> impl IntoView {
                             if r.requested_output_format.is_none() {
                                 r.requested_output_format = snap.clone();
                             }
+                            r.relative_dir = relative_dir_for(&relative_dirs, &r.original_path);
                             r
                         })
                         .collect();
                     state.results.set(results);
                     state.has_compressed.set(true);
                     state.progress.set(100.0);
+                    let mut failures = failures.take();
+                    if !failures.is_empty() {
+                        failures.sort();
+                        let messages: Vec<&str> = failures.iter().map(|(_, msg)| msg.as_str()).collect();
+                        state.error.set(Some(format!(
+                            "Failed to compress {} file(s): {}",
+                            failures.len(),
+                            messages.join("; ")
+                        )));
+                    }
                     utils::product_log(&format!(
                         "✅ Compression finished: {} result(s) ready to save or download",
                         n
                     ));
                     utils::sleep_ms(220).await;
                 }
+                Err(BackendError::Cancelled) => {
+                    // Files that finished before the cancel were already streamed into results.
+                    let kept = state.results.get_untracked().len();
+                    state.has_compressed.set(kept > 0);
+                    state.status.set(Some(format!(
+                        "Compression cancelled; kept {} finished result(s)",
+                        kept
+                    )));
+                    utils::product_log(&format!("⏹️ Compression cancelled: kept {} result(s)", kept));
+                }
                 Err(e) => {
                     state.progress.set(0.0);
                     state.error.set(Some(e.to_string()));
@@ -517,6 +695,21 @@ pub fn CompressButton(state: AppState) -> impl IntoView {
                                 >
                                     {button_text}
                                 </button>
+                                <Show when=move || state.is_compressing.get()>
+                                    <button
+                                        type="button"
+                                        class="secondary-button cancel-button"
+                                        on:click=move |_| {
+                                            spawn_local(async move {
+                                                if let Err(e) = BackendProvider::new().cancel_batch().await {
+                                                    state.error.set(Some(e.to_string()));
+                                                }
+                                            });
+                                        }
+                                    >
+                                        "Cancel"
+                                    </button>
+                                </Show>
                             }
                         }
                     >
@@ -527,26 +720,41 @@ pub fn CompressButton(state: AppState) -> impl IntoView {
                                     <Show
                                         when=has_single_result
                                         fallback=move || view! {
-                                            <button
-                                                type="button"
-                                                class="convert-button show save-zip-button"
-                                                disabled=move || state.is_compressing.get()
-                                                on:click=move |_| {
-                                                    spawn_save_with_options(
-                                                        state.clone(),
-                                                        BackendProvider::new(),
-                                                        PendingSaveOptions {
-                                                            overwrite: false,
-                                                            auto_rename: false,
-                                                            as_zip: true,
-                                                        },
-                                                        None,
-                                                        None,
-                                                    );
-                                                }
-                                            >
-                                                "Save All (.zip)"
-                                            </button>
+                                            <div class="save-actions save-actions-web">
+                                                <button
+                                                    type="button"
+                                                    class="convert-button show save-zip-button"
+                                                    disabled=move || state.is_compressing.get()
+                                                    on:click=move |_| {
+                                                        spawn_save_with_options(
+                                                            state.clone(),
+                                                            BackendProvider::new(),
+                                                            PendingSaveOptions {
+                                                                overwrite: false,
+                                                                auto_rename: false,
+                                                                as_zip: true,
+                                                            },
+                                                            None,
+                                                            None,
+                                                        );
+                                                    }
+                                                >
+                                                    "Save All (.zip)"
+                                                </button>
+                                                <button
+                                                    type="button"
+                                                    class="convert-button show save-zip-button"
+                                                    disabled=move || state.is_compressing.get()
+                                                    on:click=move |_| {
+                                                        let state = state.clone();
+                                                        spawn_local(async move {
+                                                            save_as_pdf(state, BackendProvider::new()).await;
+                                                        });
+                                                    }
+                                                >
+                                                    "Save as PDF"
+                                                </button>
+                                            </div>
                                         }
                                     >
                                         <div class="save-actions save-actions-web">
@@ -590,11 +798,24 @@ pub fn CompressButton(state: AppState) -> impl IntoView {
                                             >
                                                 "Save as .zip"
                                             </button>
+                                            <button
+                                                type="button"
+                                                class="convert-button show save-zip-button"
+                                                disabled=move || state.is_compressing.get()
+                                                on:click=move |_| {
+                                                    let state = state.clone();
+                                                    spawn_local(async move {
+                                                        save_as_pdf(state, BackendProvider::new()).await;
+                                                    });
+                                                }
+                                            >
+                                                "Save as PDF"
+                                            </button>
                                         </div>
                                     </Show>
                                 }
                             >
-                                // Desktop mode: show both Save Files and Save All (.zip)
+                                // Desktop mode: Save Files, Save All (.zip), Save as PDF and in-place
                                 <button
                                     type="button"
                                     class="convert-button show save-primary-button"
@@ -637,6 +858,44 @@ pub fn CompressButton(state: AppState) -> impl IntoView {
                                         "Save All (.zip)"
                                     </button>
                                 </Show>
+                                <button
+                                    type="button"
+                                    class="convert-button show save-zip-button"
+                                    disabled=move || state.is_compressing.get()
+                                    on:click=move |_| {
+                                        let state = state.clone();
+                                        spawn_local(async move {
+                                            save_as_pdf(state, BackendProvider::new()).await;
+                                        });
+                                    }
+                                >
+                                    "Save as PDF"
+                                </button>
+                                <button
+                                    type="button"
+                                    class="convert-button show save-zip-button"
+                                    title="Write each output back where its original is; originals are backed up first"
+                                    disabled=move || state.is_compressing.get()
+                                    on:click=move |_| {
+                                        spawn_local(async move {
+                                            optimize_in_place(state, BackendProvider::new()).await;
+                                        });
+                                    }
+                                >
+                                    "Optimize in Place"
+                                </button>
+                                <button
+                                    type="button"
+                                    class="secondary-button"
+                                    disabled=move || state.is_compressing.get()
+                                    on:click=move |_| {
+                                        spawn_local(async move {
+                                            undo_last_in_place_run(state, BackendProvider::new()).await;
+                                        });
+                                    }
+                                >
+                                    "Undo Last In-Place Run"
+                                </button>
                             </Show>
                         </div>
                     </Show>
@@ -761,13 +1020,13 @@ pub fn CompressButton(state: AppState) -> impl IntoView {
                                         spawn_local(async move {
                                             let Some(folder) = state.pending_save_folder.get_untracked() else { return };
                                             let Some(previous_options) = state.pending_save_options.get_untracked() else { return };
-                                            let results = state.results.get_untracked();
+                                            let results = state.unsaved_results();
 
                                             let collision_files = state.collision_files.get_untracked();
                                             let collision_edits = state.collision_name_edits.get_untracked();
                                             let collision_initial = state.collision_initial_snapshot.get_untracked();
 
-                                            if collision_edits.iter().any(|name| sanitize_filename(name).is_empty()) {
+                                            if collision_edits.iter().any(|name| sanitize_relative_path(name).is_empty()) {
                                                 state.error.set(Some("File name cannot be empty".to_string()));
                                                 return;
                                             }
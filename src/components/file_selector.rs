@@ -13,6 +13,18 @@ pub fn FileSelector(state: AppState) -> impl IntoView {
     let backend_for_select = backend.clone();
 
     let has_native_dialogs = backend.capabilities().supports_native_dialogs;
+    let has_folder_picker = backend.capabilities().supports_folder_picker;
+
+    // Native drops are expanded by `handle_dropped_files` from index.html, which reads the
+    // folder filter from here.
+    Effect::new(move |_| {
+        let filter = state.scan_filter.get();
+        if let (Some(win), Ok(json)) = (window(), serde_json::to_string(&filter)) {
+            if let Ok(value) = js_sys::JSON::parse(&json) {
+                let _ = js_sys::Reflect::set(&win, &JsValue::from_str("__SCAN_FILTER"), &value);
+            }
+        }
+    });
 
     let reset_state_for_new_files = move |new_files: Vec<crate::state::FileInfo>| {
         state.is_compressing.set(false);
@@ -66,7 +78,7 @@ pub fn FileSelector(state: AppState) -> impl IntoView {
                     let name = file.name();
                     let size = file.size() as u64;
                     let path = format!("browser://{}", name);
-                    files.push(crate::state::FileInfo { path: path.clone(), name: name.clone(), size });
+                    files.push(crate::state::FileInfo { path: path.clone(), name: name.clone(), size, relative_path: None });
                     
                     // Store file object for later compression
                     let file_obj = js_sys::Object::new();
@@ -129,6 +141,19 @@ pub fn FileSelector(state: AppState) -> impl IntoView {
         });
     };
 
+    let select_folder_backend = move || {
+        spawn_local(async move {
+            match BackendProvider::new().select_folder(state.scan_filter.get_untracked()).await {
+                Ok(files) => {
+                    utils::product_log(&format!("📂 Selected folder with {} image(s)", files.len()));
+                    reset_state_for_new_files(files);
+                }
+                Err(BackendError::Cancelled) => {}
+                Err(e) => state.error.set(Some(e.to_string())),
+            }
+        });
+    };
+
     let select_files_browser = move || {
         if let Some(input) = file_input_ref.get() {
             input.click();
@@ -143,7 +168,7 @@ pub fn FileSelector(state: AppState) -> impl IntoView {
             <input
                 type="file"
                 multiple=true
-                accept="image/png,image/jpeg,image/jpg,image/bmp,image/tiff,image/webp,image/ico"
+                accept="image/png,image/jpeg,image/jpg,image/bmp,image/tiff,image/webp,image/ico,image/jxl,.jxl,.qoi,.pnm,.pbm,.pgm,.ppm,.pam,.tga,.dds,.hdr,.exr"
                 style="display: none;"
                 node_ref=file_input_ref
                 on:change=move |ev| {
@@ -172,8 +197,22 @@ pub fn FileSelector(state: AppState) -> impl IntoView {
             >
                 "📁 Select Images"
             </button>
+            <Show when=move || has_folder_picker>
+                <button
+                    type="button"
+                    class="upload-button"
+                    on:click=move |_| {
+                        if !state.is_compressing.get_untracked() {
+                            select_folder_backend();
+                        }
+                    }
+                    disabled=move || state.is_compressing.get()
+                >
+                    "📂 Select Folder"
+                </button>
+            </Show>
             <p style="color: #d1d5db; font-size: 0.875rem;">
-                "PNG, JPEG, BMP, TIFF, WebP, ICO supported"
+                "PNG, JPEG, BMP, TIFF, WebP, ICO, JXL, QOI, PNM, TGA, DDS, HDR, EXR supported"
             </p>
 
         </div>
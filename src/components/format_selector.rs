@@ -12,6 +12,7 @@ pub fn FormatSelector(state: AppState) -> impl IntoView {
         ("tiff", "TIFF", "#F44336"),
         ("bmp", "BMP", "#BDBDBD"),
         ("ico", "ICO", "#009688"),
+        ("jxl", "JXL", "#FF9800"),
     ];
 
     view! {
@@ -8,6 +8,10 @@ pub mod error_display;
 pub mod status_display;
 pub mod results_list;
 pub mod files_list;
+pub mod watermark_options;
+pub mod adjustment_options;
+pub mod watch_folder;
+pub mod preset_selector;
 
 pub use file_selector::FileSelector;
 pub use quality_slider::QualitySlider;
@@ -19,3 +23,7 @@ pub use error_display::ErrorDisplay;
 pub use status_display::StatusDisplay;
 pub use results_list::ResultsList;
 pub use files_list::FilesList;
+pub use watermark_options::WatermarkOptions;
+pub use adjustment_options::AdjustmentOptions;
+pub use watch_folder::WatchFolder;
+pub use preset_selector::PresetSelector;
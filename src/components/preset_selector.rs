@@ -0,0 +1,161 @@
+use leptos::prelude::*;
+use crate::state::AppState;
+use crate::backend::{BackendProvider, AppBackend, BackendError, PresetEntry};
+use crate::utils;
+use wasm_bindgen_futures::spawn_local;
+
+/// Show `result`'s preset list, or its error.
+fn update_presets(state: AppState, result: Result<Vec<PresetEntry>, BackendError>, status: String) {
+    match result {
+        Ok(presets) => {
+            state.presets.set(presets);
+            state.error.set(None);
+            state.status.set(Some(status));
+        }
+        Err(BackendError::Cancelled) => {}
+        Err(e) => state.error.set(Some(e.to_string())),
+    }
+}
+
+fn apply_selected(state: AppState, name: String) {
+    state.selected_preset.set(name.clone());
+    let Some(preset) = state.presets.get_untracked().into_iter().find(|p| p.name == name) else {
+        return;
+    };
+    if state.apply_preset(&preset.options) {
+        state.status.set(Some(format!("Applied preset {}", preset.name)));
+    } else {
+        state.status.set(Some(format!(
+            "Applied preset {}; some of its transforms can't be shown here and were skipped",
+            preset.name
+        )));
+    }
+}
+
+/// Named compression presets: built-ins everywhere plus the server's in the browser; on the
+/// desktop the current settings can be saved as a preset and presets imported and exported.
+#[component]
+pub fn PresetSelector(state: AppState) -> impl IntoView {
+    let backend = BackendProvider::new();
+    let can_edit = backend.capabilities().supports_preset_editing;
+    let new_name = RwSignal::new(String::new());
+
+    spawn_local(async move {
+        match backend.list_presets().await {
+            Ok(presets) => state.presets.set(presets),
+            Err(e) => utils::product_log(&format!("⚠️ Could not load presets: {}", e)),
+        }
+    });
+
+    let selected_is_builtin = move || {
+        let name = state.selected_preset.get();
+        state.presets.with(|presets| presets.iter().any(|p| p.name == name && p.builtin))
+    };
+
+    let save = move |_| {
+        let name = new_name.get_untracked().trim().to_string();
+        if name.is_empty() {
+            state.error.set(Some("Enter a name for the preset".to_string()));
+            return;
+        }
+        let request = state.compression_request(Vec::new());
+        spawn_local(async move {
+            let result = BackendProvider::new().save_preset(name.clone(), request).await;
+            if result.is_ok() {
+                state.selected_preset.set(name.clone());
+                new_name.set(String::new());
+            }
+            update_presets(state, result, format!("Saved preset {}", name));
+        });
+    };
+
+    let delete = move |_| {
+        let name = state.selected_preset.get_untracked();
+        if name.is_empty() {
+            return;
+        }
+        spawn_local(async move {
+            let result = BackendProvider::new().delete_preset(name.clone()).await;
+            if result.is_ok() {
+                state.selected_preset.set(String::new());
+            }
+            update_presets(state, result, format!("Deleted preset {}", name));
+        });
+    };
+
+    let import = move |_| {
+        spawn_local(async move {
+            let result = BackendProvider::new().import_presets().await;
+            update_presets(state, result, "Imported presets".to_string());
+        });
+    };
+
+    let export = move |_| {
+        let selected = state.selected_preset.get_untracked();
+        let names = if selected.is_empty() {
+            state.presets.get_untracked().into_iter().filter(|p| !p.builtin).map(|p| p.name).collect()
+        } else {
+            vec![selected]
+        };
+        if names.is_empty() {
+            state.error.set(Some("Select a preset to export".to_string()));
+            return;
+        }
+        spawn_local(async move {
+            match BackendProvider::new().export_presets(names).await {
+                Ok(path) => state.status.set(Some(format!("Exported presets to {}", path))),
+                Err(BackendError::Cancelled) => {}
+                Err(e) => state.error.set(Some(e.to_string())),
+            }
+        });
+    };
+
+    view! {
+        <div class="select-group">
+            <label for="preset">"Preset"</label>
+            <select
+                id="preset"
+                prop:value=move || state.selected_preset.get()
+                on:change=move |ev| apply_selected(state, event_target_value(&ev))
+            >
+                <option value="">"Custom"</option>
+                <For
+                    each=move || state.presets.get()
+                    key=|preset| preset.name.clone()
+                    children=move |preset| {
+                        let title = preset.description.clone();
+                        view! {
+                            <option value=preset.name.clone() title=title>{preset.name.clone()}</option>
+                        }
+                    }
+                />
+            </select>
+            <Show when=move || can_edit>
+                <input
+                    type="text"
+                    id="presetName"
+                    placeholder="New preset name"
+                    prop:value=move || new_name.get()
+                    on:input=move |ev| new_name.set(event_target_value(&ev))
+                />
+                <button type="button" class="secondary-button" on:click=save>
+                    "Save Preset"
+                </button>
+                <button
+                    type="button"
+                    class="secondary-button"
+                    disabled=move || state.selected_preset.get().is_empty() || selected_is_builtin()
+                    on:click=delete
+                >
+                    "Delete Preset"
+                </button>
+                <button type="button" class="secondary-button" on:click=import>
+                    "Import…"
+                </button>
+                <button type="button" class="secondary-button" on:click=export>
+                    "Export…"
+                </button>
+            </Show>
+        </div>
+    }
+}
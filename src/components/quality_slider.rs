@@ -1,6 +1,9 @@
 use leptos::prelude::*;
 use crate::state::AppState;
 
+/// Where the numeric slider starts when custom quality is switched on
+const DEFAULT_CUSTOM_QUALITY: u8 = 75;
+
 #[component]
 pub fn QualitySlider(state: AppState) -> impl IntoView {
     let quality_values = vec!["low", "mid", "max"];
@@ -47,6 +50,37 @@ pub fn QualitySlider(state: AppState) -> impl IntoView {
                 <span>"Max"</span>
             </div>
             <div class="quality-note">"Max = Smaller File • Low = Better Image Quality"</div>
+            <div class="checkbox-group">
+                <input
+                    type="checkbox"
+                    id="customQuality"
+                    checked=move || state.quality.get().is_some()
+                    on:change=move |ev| {
+                        let quality = event_target_checked(&ev).then_some(DEFAULT_CUSTOM_QUALITY);
+                        state.quality.set(quality);
+                    }
+                />
+                <label for="customQuality">"Custom quality (0–100)"</label>
+            </div>
+            <Show when=move || state.quality.get().is_some()>
+                <div class="quality-value">
+                    {move || format!("Quality {}", state.quality.get().unwrap_or(DEFAULT_CUSTOM_QUALITY))}
+                </div>
+                <div class="quality-slider-container">
+                    <input
+                        type="range"
+                        class="quality-slider"
+                        min="0"
+                        max="100"
+                        value=move || state.quality.get().unwrap_or(DEFAULT_CUSTOM_QUALITY).to_string()
+                        on:input=move |ev| {
+                            let value: u8 = event_target_value(&ev).parse().unwrap_or(DEFAULT_CUSTOM_QUALITY);
+                            state.quality.set(Some(value.min(100)));
+                        }
+                    />
+                </div>
+                <div class="quality-note">"Used by every format instead of the level's quality table"</div>
+            </Show>
         </div>
     }
 }
@@ -1,7 +1,9 @@
+use crate::backend::{AppBackend, BackendProvider};
 use crate::state::{AppState, CompressionResult};
 use crate::utils;
 use leptos::prelude::*;
 use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::spawn_local;
 
 fn format_bytes(bytes: u64) -> String {
     if bytes == 0 {
@@ -66,6 +68,18 @@ fn download_single_file(result: &CompressionResult) {
     ));
 }
 
+/// Open an output the backend already wrote (folder watch) with the system's default app.
+fn open_saved_file(result: &CompressionResult) {
+    let Some(path) = result.compressed_path.clone() else {
+        return;
+    };
+    spawn_local(async move {
+        if let Err(e) = BackendProvider::new().open_in_file_manager(path).await {
+            utils::product_log(&format!("❌ Failed to open output: {}", e));
+        }
+    });
+}
+
 #[component]
 pub fn ResultsList(state: AppState) -> impl IntoView {
     view! {
@@ -74,7 +88,7 @@ pub fn ResultsList(state: AppState) -> impl IntoView {
                 <h3 style="margin-bottom: 1rem; color: #ffffff;">"Compression Results"</h3>
                 <For
                     each=move || state.results.get()
-                    key=|result| result.original_path.clone()
+                    key=|result| (result.original_path.clone(), result.page)
                     children=move |result| {
                         let row = result.clone();
                         let result_for_click = result.clone();
@@ -82,7 +96,11 @@ pub fn ResultsList(state: AppState) -> impl IntoView {
                             <div
                                 class="result-item"
                                 on:click=move |_| {
-                                    download_single_file(&result_for_click);
+                                    if result_for_click.is_saved() {
+                                        open_saved_file(&result_for_click);
+                                    } else {
+                                        download_single_file(&result_for_click);
+                                    }
                                 }
                                 style="cursor: pointer;"
                             >
@@ -96,6 +114,13 @@ pub fn ResultsList(state: AppState) -> impl IntoView {
                                             " ("
                                             {format!("{:+.1}%", row.savings_percent)}
                                             ")"
+                                            {row
+                                                .trimmed
+                                                .map(|t| format!(" · trimmed to {}×{}", t.width, t.height))}
+                                            {row
+                                                .bit_depth
+                                                .filter(|bits| *bits > 8)
+                                                .map(|bits| format!(" · {}-bit", bits))}
                                         </p>
                                     </div>
                                 </div>
@@ -0,0 +1,137 @@
+use leptos::prelude::*;
+use crate::state::AppState;
+use crate::backend::{BackendProvider, AppBackend, BackendError, FolderWatchEvent};
+use crate::utils;
+use std::rc::Rc;
+use wasm_bindgen_futures::spawn_local;
+
+/// Show each processed file in the results list; a file changed again replaces its rows.
+fn handle_watch_event(state: AppState, event: FolderWatchEvent) {
+    match event {
+        FolderWatchEvent::Compressed { results } => {
+            let Some(input) = results.first().map(|r| r.original_path.clone()) else {
+                return;
+            };
+            utils::product_log(&format!("👀 Compressed {}", utils::basename(&input)));
+            state.results.update(|list| {
+                list.retain(|r| r.original_path != input);
+                list.extend(results);
+            });
+        }
+        FolderWatchEvent::Failed { path, error } => {
+            state.error.set(Some(format!("{}: {}", utils::basename(&path), error)));
+        }
+    }
+}
+
+async fn pick_folder(state: AppState, target: RwSignal<String>) {
+    match BackendProvider::new().select_output_folder().await {
+        Ok(folder) => target.set(folder),
+        Err(BackendError::Cancelled) => {}
+        Err(e) => state.error.set(Some(e.to_string())),
+    }
+}
+
+async fn start_watch(state: AppState) {
+    let input = state.watch_input.get_untracked();
+    let output = state.watch_output.get_untracked();
+    if input.is_empty() || output.is_empty() {
+        state.error.set(Some("Choose a folder to watch and an output folder first".to_string()));
+        return;
+    }
+    let request = state.compression_request(Vec::new());
+    if !request.filename_template.is_empty() {
+        if let Err(e) = naming::validate_template(&request.filename_template) {
+            state.error.set(Some(e));
+            return;
+        }
+    }
+
+    let on_event = Rc::new(move |event| handle_watch_event(state, event)) as Rc<dyn Fn(FolderWatchEvent)>;
+    match BackendProvider::new()
+        .start_folder_watch(input, output, request, state.scan_filter.get_untracked(), on_event)
+        .await
+    {
+        Ok(status) => {
+            state.watching.set(true);
+            state.error.set(None);
+            state.status.set(Some(format!(
+                "Watching {} → {}",
+                utils::basename(&status.input),
+                utils::basename(&status.output)
+            )));
+        }
+        Err(e) => {
+            state.status.set(None);
+            state.error.set(Some(e.to_string()));
+        }
+    }
+}
+
+async fn stop_watch(state: AppState) {
+    if let Err(e) = BackendProvider::new().stop_folder_watch().await {
+        state.error.set(Some(e.to_string()));
+    }
+    state.watching.set(false);
+    state.status.set(Some("Stopped watching".to_string()));
+}
+
+/// Desktop hot folder: new and changed images in one folder are compressed into another with
+/// the current settings, and listed in the results as they finish.
+#[component]
+pub fn WatchFolder(state: AppState) -> impl IntoView {
+    let has_folder_watch = BackendProvider::new().capabilities().supports_folder_watch;
+    let folder_label = |path: String| {
+        if path.is_empty() {
+            "Not chosen".to_string()
+        } else {
+            path
+        }
+    };
+
+    view! {
+        <Show when=move || has_folder_watch>
+            <div class="select-group">
+                <label>"Watch folder"</label>
+                <p style="color: #d1d5db; font-size: 0.875rem;">
+                    {move || folder_label(state.watch_input.get())}
+                    " → "
+                    {move || folder_label(state.watch_output.get())}
+                </p>
+                <button
+                    type="button"
+                    class="secondary-button"
+                    disabled=move || state.watching.get()
+                    on:click=move |_| {
+                        spawn_local(pick_folder(state, state.watch_input));
+                    }
+                >
+                    "Choose Watched Folder"
+                </button>
+                <button
+                    type="button"
+                    class="secondary-button"
+                    disabled=move || state.watching.get()
+                    on:click=move |_| {
+                        spawn_local(pick_folder(state, state.watch_output));
+                    }
+                >
+                    "Choose Output Folder"
+                </button>
+                <button
+                    type="button"
+                    class="secondary-button"
+                    on:click=move |_| {
+                        if state.watching.get_untracked() {
+                            spawn_local(stop_watch(state));
+                        } else {
+                            spawn_local(start_watch(state));
+                        }
+                    }
+                >
+                    {move || if state.watching.get() { "Stop Watching" } else { "Start Watching" }}
+                </button>
+            </div>
+        </Show>
+    }
+}
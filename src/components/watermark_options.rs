@@ -0,0 +1,211 @@
+use leptos::prelude::*;
+use crate::state::{AppState, WatermarkSettings};
+use crate::backend::{BackendProvider, AppBackend, BackendError};
+use crate::utils;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::spawn_local;
+use web_sys::{window, HtmlInputElement};
+
+const GRAVITIES: [(&str, &str); 9] = [
+    ("northwest", "Top left"),
+    ("north", "Top"),
+    ("northeast", "Top right"),
+    ("west", "Left"),
+    ("center", "Center"),
+    ("east", "Right"),
+    ("southwest", "Bottom left"),
+    ("south", "Bottom"),
+    ("southeast", "Bottom right"),
+];
+
+#[component]
+pub fn WatermarkOptions(state: AppState) -> impl IntoView {
+    let logo_input_ref: NodeRef<leptos::html::Input> = NodeRef::new();
+    let backend = BackendProvider::new();
+    let is_desktop = backend.is_tauri();
+
+    // Desktop: start from the saved preset.
+    if is_desktop {
+        spawn_local(async move {
+            match backend.load_watermark_preset().await {
+                Ok(Some(preset)) => {
+                    state.watermark.set(preset);
+                    state.watermark_enabled.set(true);
+                }
+                Ok(None) => {}
+                Err(e) => utils::product_log(&format!("⚠️ Could not load watermark preset: {}", e)),
+            }
+        });
+    }
+
+    let update = move |f: &dyn Fn(&mut WatermarkSettings)| state.watermark.update(|w| f(w));
+
+    // Handlers build their own provider so they stay `Copy` inside the nested `Show`s.
+    let choose_logo = move |_| {
+        if !is_desktop {
+            if let Some(input) = logo_input_ref.get() {
+                input.click();
+            }
+            return;
+        }
+        spawn_local(async move {
+            match BackendProvider::new().select_files().await {
+                Ok(files) => {
+                    if let Some(file) = files.into_iter().next() {
+                        state.watermark.update(|w| w.image_path = Some(file.path));
+                    }
+                }
+                Err(BackendError::Cancelled) => {}
+                Err(e) => state.error.set(Some(e.to_string())),
+            }
+        });
+    };
+
+    let save_preset = move |_| {
+        let preset = state.watermark.get_untracked();
+        spawn_local(async move {
+            match BackendProvider::new().save_watermark_preset(Some(preset)).await {
+                Ok(()) => state.status.set(Some("Watermark preset saved".to_string())),
+                Err(e) => state.error.set(Some(e.to_string())),
+            }
+        });
+    };
+
+    view! {
+        <div class="checkbox-group">
+            <input
+                type="checkbox"
+                id="watermarkEnabled"
+                checked=move || state.watermark_enabled.get()
+                on:change=move |ev| {
+                    state.watermark_enabled.set(event_target_checked(&ev));
+                }
+            />
+            <label for="watermarkEnabled">"Apply watermark"</label>
+        </div>
+        <Show when=move || state.watermark_enabled.get()>
+            <input
+                type="file"
+                accept="image/png,image/webp,image/jpeg"
+                style="display: none;"
+                node_ref=logo_input_ref
+                on:change=move |ev| {
+                    let input: HtmlInputElement = ev.target().unwrap().dyn_into().unwrap();
+                    if let Some(file) = input.files().and_then(|files| files.get(0)) {
+                        if let Some(win) = window() {
+                            let _ = js_sys::Reflect::set(&win, &JsValue::from_str("__WATERMARK_LOGO_FILE"), &file);
+                        }
+                        state.watermark.update(|w| w.image_path = Some(file.name()));
+                    }
+                    input.set_value("");
+                }
+            />
+            <div class="select-group">
+                <label>"Logo"</label>
+                <button type="button" class="secondary-button" on:click=choose_logo>
+                    {move || {
+                        state
+                            .watermark
+                            .get()
+                            .image_path
+                            .map(|p| utils::basename(&p))
+                            .unwrap_or_else(|| "Choose image…".to_string())
+                    }}
+                </button>
+                <Show when=move || state.watermark.get().image_path.is_some()>
+                    <button
+                        type="button"
+                        class="secondary-button"
+                        on:click=move |_| state.watermark.update(|w| w.image_path = None)
+                    >
+                        "Clear"
+                    </button>
+                </Show>
+            </div>
+            <div class="select-group">
+                <label for="watermarkText">"Text (used without a logo)"</label>
+                <input
+                    type="text"
+                    id="watermarkText"
+                    prop:value=move || state.watermark.get().text
+                    on:input=move |ev| {
+                        let value = event_target_value(&ev);
+                        update(&|w| w.text = value.clone());
+                    }
+                />
+                <input
+                    type="color"
+                    id="watermarkColor"
+                    prop:value=move || state.watermark.get().color
+                    on:input=move |ev| {
+                        let value = event_target_value(&ev);
+                        update(&|w| w.color = value.clone());
+                    }
+                />
+            </div>
+            <div class="select-group">
+                <label for="watermarkGravity">"Position"</label>
+                <select
+                    id="watermarkGravity"
+                    prop:value=move || state.watermark.get().gravity
+                    on:change=move |ev| {
+                        let value = event_target_value(&ev);
+                        update(&|w| w.gravity = value.clone());
+                    }
+                >
+                    {GRAVITIES
+                        .iter()
+                        .map(|(value, label)| view! { <option value=*value>{*label}</option> })
+                        .collect_view()}
+                </select>
+            </div>
+            <div class="select-group">
+                <label for="watermarkScale">"Size (% of width)"</label>
+                <input
+                    type="number"
+                    id="watermarkScale"
+                    min="1"
+                    max="100"
+                    step="1"
+                    prop:value=move || ((state.watermark.get().scale * 100.0).round() as u32).to_string()
+                    on:change=move |ev| {
+                        let percent = event_target_value(&ev).parse::<f32>().unwrap_or(25.0);
+                        update(&|w| w.scale = percent.clamp(1.0, 100.0) / 100.0);
+                    }
+                />
+            </div>
+            <div class="select-group">
+                <label for="watermarkOpacity">"Opacity (%)"</label>
+                <input
+                    type="number"
+                    id="watermarkOpacity"
+                    min="0"
+                    max="100"
+                    step="5"
+                    prop:value=move || ((state.watermark.get().opacity * 100.0).round() as u32).to_string()
+                    on:change=move |ev| {
+                        let percent = event_target_value(&ev).parse::<f32>().unwrap_or(50.0);
+                        update(&|w| w.opacity = percent.clamp(0.0, 100.0) / 100.0);
+                    }
+                />
+            </div>
+            <div class="checkbox-group">
+                <input
+                    type="checkbox"
+                    id="watermarkTile"
+                    checked=move || state.watermark.get().tile
+                    on:change=move |ev| {
+                        let tile = event_target_checked(&ev);
+                        update(&|w| w.tile = tile);
+                    }
+                />
+                <label for="watermarkTile">"Tile across the image"</label>
+            </div>
+            <Show when=move || is_desktop>
+                <button type="button" class="secondary-button" on:click=save_preset>
+                    "Save watermark preset"
+                </button>
+            </Show>
+        </Show>
+    }
+}
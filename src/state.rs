@@ -1,3 +1,4 @@
+use crate::backend::{CompressionRequest, PresetEntry, PresetOptions, UserSettings};
 use leptos::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
@@ -7,6 +8,17 @@ pub struct FileInfo {
     pub path: String,
     pub name: String,
     pub size: u64,
+    /// `/`-separated path below a picked or dropped folder (`photos/2024/a.png`); `None` for
+    /// files chosen directly.
+    #[serde(default)]
+    pub relative_path: Option<String>,
+}
+
+impl FileInfo {
+    /// Folder part of `relative_path` (`photos/2024`), where the output is saved.
+    pub fn relative_dir(&self) -> Option<&str> {
+        self.relative_path.as_deref()?.rsplit_once('/').map(|(dir, _)| dir)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,18 +33,142 @@ pub struct CompressionResult {
     /// UI output format at compress time (`webp`, `jpeg`, `original`, …). Drives display/download extensions.
     #[serde(default)]
     pub requested_output_format: Option<String>,
+    /// 1-based page number when a multi-page input was split into separate results.
+    #[serde(default)]
+    pub page: Option<u32>,
+    /// Content rectangle kept by auto-trim, in input pixels.
+    #[serde(default)]
+    pub trimmed: Option<TrimRect>,
+    /// Bits per channel of the output (8, 10 for AVIF, 16 for high-depth PNG/TIFF).
+    #[serde(default)]
+    pub bit_depth: Option<u8>,
+    /// Name rendered by the backend from the filename template; preferred over the default
+    /// `{stem}.{ext}` when present.
+    #[serde(default)]
+    pub suggested_filename: Option<String>,
+    /// Folder of the input below a picked or dropped folder (`photos/2024`); outputs keep it
+    /// when saved to a folder or ZIP. Set by the frontend from `FileInfo::relative_path`.
+    #[serde(default)]
+    pub relative_dir: Option<String>,
+    /// Desktop handle for `data` while it is still held by the backend (streamed batches);
+    /// `data` is empty until fetched with `take_batch_output`.
+    #[serde(default)]
+    pub output_id: Option<String>,
+}
+
+/// Watermark settings; also the shape of the desktop watermark preset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WatermarkSettings {
+    /// Logo path (desktop) or file name (web; the file itself is kept on `window`)
+    pub image_path: Option<String>,
+    pub text: String,
+    pub color: String,
+    /// `center`, `north`, `southeast`, …
+    pub gravity: String,
+    pub margin: u32,
+    /// Width relative to the image, 0.0–1.0
+    pub scale: f32,
+    pub opacity: f32,
+    pub tile: bool,
+}
+
+impl Default for WatermarkSettings {
+    fn default() -> Self {
+        Self {
+            image_path: None,
+            text: String::new(),
+            color: "#ffffff".to_string(),
+            gravity: "southeast".to_string(),
+            margin: 16,
+            scale: 0.25,
+            opacity: 0.5,
+            tile: false,
+        }
+    }
+}
+
+/// Which files a folder pick or drop keeps; the shape of the desktop `ScanFilter`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ScanFilterSettings {
+    /// Globs such as `*.png` or `raw/**/*.tif`; empty keeps every supported image
+    pub include: Vec<String>,
+    /// Globs for files or whole folders to skip (`thumbs`, `**/node_modules`)
+    pub exclude: Vec<String>,
+    /// Size limits in bytes
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+}
+
+/// Colour corrections; field names and units match the backend `Adjustments`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdjustmentSettings {
+    pub denoise: bool,
+    pub white_balance: bool,
+    pub auto_levels: bool,
+    /// -1.0 to 1.0
+    pub brightness: f32,
+    /// -1.0 to 1.0
+    pub contrast: f32,
+    /// 0.1 to 10.0, 1.0 is neutral
+    pub gamma: f32,
+    /// -1.0 to 1.0
+    pub saturation: f32,
+    /// Unsharp mask amount, 0.0 disables
+    pub sharpen: f32,
+}
+
+impl Default for AdjustmentSettings {
+    fn default() -> Self {
+        Self {
+            denoise: false,
+            white_balance: false,
+            auto_levels: false,
+            brightness: 0.0,
+            contrast: 0.0,
+            gamma: 1.0,
+            saturation: 0.0,
+            sharpen: 0.0,
+        }
+    }
+}
+
+impl AdjustmentSettings {
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TrimRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
 }
 
 impl CompressionResult {
-    /// Filename (stem + extension) shown to the user and used for downloads.
+    /// Written to `compressed_path` by the backend (folder watch) rather than held in `data`.
+    pub fn is_saved(&self) -> bool {
+        self.data.is_empty() && self.compressed_path.is_some()
+    }
+
+    /// Filename (stem + extension) shown to the user and used for downloads, below
+    /// `relative_dir` when the input came from a folder.
     pub fn display_export_filename(&self) -> String {
-        let path = Path::new(&self.original_path);
-        let stem = path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("compressed");
-        let ext = self.export_extension();
-        format!("{stem}.{ext}")
+        let name = match self.suggested_filename.as_deref().filter(|n| !n.is_empty()) {
+            Some(name) => name.to_string(),
+            None => {
+                let ext = self.export_extension();
+                naming::output_filename(naming::file_stem(&self.original_path), &ext, self.page)
+            }
+        };
+        match self.relative_dir.as_deref().filter(|d| !d.is_empty()) {
+            Some(dir) => format!("{}/{}", dir, name),
+            None => name,
+        }
     }
 
     fn export_extension(&self) -> String {
@@ -47,6 +183,7 @@ impl CompressionResult {
                 "tiff" => "tiff".to_string(),
                 "bmp" => "bmp".to_string(),
                 "ico" => "ico".to_string(),
+                "jxl" => "jxl".to_string(),
                 _ => self.ext_from_mime_or_path(),
             };
         }
@@ -65,16 +202,7 @@ impl CompressionResult {
 }
 
 fn ext_from_mime_type(mime: &str) -> Option<String> {
-    match mime {
-        "image/webp" => Some("webp".into()),
-        "image/avif" => Some("avif".into()),
-        "image/jpeg" => Some("jpg".into()),
-        "image/png" => Some("png".into()),
-        "image/tiff" => Some("tiff".into()),
-        "image/bmp" => Some("bmp".into()),
-        "image/x-icon" => Some("ico".into()),
-        _ => None,
-    }
+    naming::extension_for_mime(mime).map(String::from)
 }
 
 /// Pending save options stored in AppState for collision modal access
@@ -92,6 +220,47 @@ pub struct AppState {
     pub output_format: RwSignal<String>,
     pub oxipng: RwSignal<bool>,
     pub png_lossy: RwSignal<bool>,
+    /// Encoder quality (0–100) chosen on the slider; `None` follows `compression_level`
+    pub quality: RwSignal<Option<u8>>,
+    pub jxl_lossless_jpeg: RwSignal<bool>,
+    pub tiff_compression: RwSignal<String>,
+    pub split_pages: RwSignal<bool>,
+    /// Dither 16-bit input when the output format only holds 8 bits
+    pub dither: RwSignal<bool>,
+    /// Embedded ICC profile handling: `srgb`, `keep` or `ignore`
+    pub color_profile: RwSignal<String>,
+    /// PDF export page size: `fit`, `a4` or `letter`
+    pub pdf_page_size: RwSignal<String>,
+    pub pdf_margin_mm: RwSignal<f32>,
+    /// Output name template (`{stem}-{width}w.{ext}`, …); empty means `{stem}.{ext}`
+    pub filename_template: RwSignal<String>,
+    /// Clockwise rotation in degrees: `0`, `90`, `180` or `270`
+    pub rotate: RwSignal<String>,
+    pub flip_horizontal: RwSignal<bool>,
+    pub flip_vertical: RwSignal<bool>,
+    /// Centered aspect-ratio crop such as `16:9`; empty keeps the full frame
+    pub aspect_crop: RwSignal<String>,
+    pub auto_trim: RwSignal<bool>,
+    /// Fit within this box, keeping the aspect ratio; `None` leaves that side free
+    pub resize_width: RwSignal<Option<u32>>,
+    pub resize_height: RwSignal<Option<u32>>,
+    pub watermark_enabled: RwSignal<bool>,
+    pub watermark: RwSignal<WatermarkSettings>,
+    pub adjustments: RwSignal<AdjustmentSettings>,
+    /// Built-in and saved presets, and the name of the one last applied (empty for none)
+    pub presets: RwSignal<Vec<PresetEntry>>,
+    pub selected_preset: RwSignal<String>,
+    /// Filter applied when a folder is picked or dropped (desktop)
+    pub scan_filter: RwSignal<ScanFilterSettings>,
+    /// In-place optimization: suffix for a copy next to each original (empty replaces it),
+    /// and whether outputs keep the original's modification time and permissions
+    pub in_place_suffix: RwSignal<String>,
+    pub preserve_file_times: RwSignal<bool>,
+    pub preserve_permissions: RwSignal<bool>,
+    /// Folder watch (desktop): the folders picked for it and whether it is running
+    pub watch_input: RwSignal<String>,
+    pub watch_output: RwSignal<String>,
+    pub watching: RwSignal<bool>,
     pub progress: RwSignal<f64>,
     pub results: RwSignal<Vec<CompressionResult>>,
     pub error: RwSignal<Option<String>>,
@@ -118,6 +287,34 @@ impl AppState {
             output_format: RwSignal::new("webp".to_string()),
             oxipng: RwSignal::new(true),
             png_lossy: RwSignal::new(true),
+            quality: RwSignal::new(None),
+            jxl_lossless_jpeg: RwSignal::new(true),
+            tiff_compression: RwSignal::new("lzw".to_string()),
+            split_pages: RwSignal::new(false),
+            dither: RwSignal::new(false),
+            color_profile: RwSignal::new("srgb".to_string()),
+            pdf_page_size: RwSignal::new("fit".to_string()),
+            pdf_margin_mm: RwSignal::new(0.0),
+            filename_template: RwSignal::new(String::new()),
+            rotate: RwSignal::new("0".to_string()),
+            flip_horizontal: RwSignal::new(false),
+            flip_vertical: RwSignal::new(false),
+            aspect_crop: RwSignal::new(String::new()),
+            auto_trim: RwSignal::new(false),
+            resize_width: RwSignal::new(None),
+            resize_height: RwSignal::new(None),
+            watermark_enabled: RwSignal::new(false),
+            watermark: RwSignal::new(WatermarkSettings::default()),
+            adjustments: RwSignal::new(AdjustmentSettings::default()),
+            presets: RwSignal::new(Vec::new()),
+            selected_preset: RwSignal::new(String::new()),
+            scan_filter: RwSignal::new(ScanFilterSettings::default()),
+            in_place_suffix: RwSignal::new(String::new()),
+            preserve_file_times: RwSignal::new(true),
+            preserve_permissions: RwSignal::new(true),
+            watch_input: RwSignal::new(String::new()),
+            watch_output: RwSignal::new(String::new()),
+            watching: RwSignal::new(false),
             progress: RwSignal::new(0.0),
             results: RwSignal::new(Vec::new()),
             error: RwSignal::new(None),
@@ -135,6 +332,158 @@ impl AppState {
         }
     }
 
+    /// Transform list for the compression request: trim, rotate, flip, crop, then resize
+    pub fn transforms(&self) -> Vec<serde_json::Value> {
+        let mut transforms = Vec::new();
+        if self.auto_trim.get_untracked() {
+            transforms.push(serde_json::json!({ "op": "trim" }));
+        }
+        let degrees = self.rotate.get_untracked().parse::<u32>().unwrap_or(0);
+        if degrees % 360 != 0 {
+            transforms.push(serde_json::json!({ "op": "rotate", "degrees": degrees }));
+        }
+        if self.flip_horizontal.get_untracked() {
+            transforms.push(serde_json::json!({ "op": "flip_horizontal" }));
+        }
+        if self.flip_vertical.get_untracked() {
+            transforms.push(serde_json::json!({ "op": "flip_vertical" }));
+        }
+        let aspect = self.aspect_crop.get_untracked();
+        if let Some((w, h)) = aspect.split_once(':') {
+            if let (Ok(width), Ok(height)) = (w.parse::<u32>(), h.parse::<u32>()) {
+                transforms.push(serde_json::json!({
+                    "op": "aspect_crop",
+                    "width": width,
+                    "height": height,
+                }));
+            }
+        }
+        let (width, height) = (self.resize_width.get_untracked(), self.resize_height.get_untracked());
+        if width.is_some() || height.is_some() {
+            transforms.push(serde_json::json!({ "op": "resize", "width": width, "height": height }));
+        }
+        transforms
+    }
+
+    /// Settings kept across desktop sessions, as they are now
+    pub fn user_settings(&self) -> UserSettings {
+        UserSettings {
+            compression_level: self.compression_level.get(),
+            output_format: self.output_format.get(),
+            oxipng: self.oxipng.get(),
+            png_lossy: self.png_lossy.get(),
+            quality: self.quality.get(),
+        }
+    }
+
+    /// Restore settings saved in an earlier session
+    pub fn apply_user_settings(&self, settings: UserSettings) {
+        self.compression_level.set(settings.compression_level);
+        self.output_format.set(settings.output_format);
+        self.oxipng.set(settings.oxipng);
+        self.png_lossy.set(settings.png_lossy);
+        self.quality.set(settings.quality);
+    }
+
+    /// Load a preset's settings into the panel. The watermark is left alone. Returns `false`
+    /// when the preset has transforms the panel cannot show (pixel crops, free rotation), which
+    /// are skipped.
+    pub fn apply_preset(&self, options: &PresetOptions) -> bool {
+        let level = match options.png_quality.as_str() {
+            "70-90" => "low",
+            "20-60" => "max",
+            _ => "mid",
+        };
+        self.compression_level.set(level.to_string());
+        self.output_format.set(options.output_format().to_string());
+        self.oxipng.set(options.oxipng);
+        self.png_lossy.set(options.png_lossy);
+        self.quality.set(options.quality);
+        self.jxl_lossless_jpeg.set(options.jxl_lossless_jpeg);
+        self.tiff_compression.set(options.tiff_compression.clone());
+        self.split_pages.set(options.split_pages);
+        self.dither.set(options.dither);
+        self.color_profile.set(options.color_profile.clone());
+        self.adjustments.set(options.adjustments.clone());
+        self.filename_template.set(options.filename_template.clone().unwrap_or_default());
+
+        self.auto_trim.set(false);
+        self.rotate.set("0".to_string());
+        self.flip_horizontal.set(false);
+        self.flip_vertical.set(false);
+        self.aspect_crop.set(String::new());
+        self.resize_width.set(None);
+        self.resize_height.set(None);
+        let dimension = |transform: &serde_json::Value, key: &str| {
+            transform[key].as_u64().and_then(|v| u32::try_from(v).ok())
+        };
+        let mut complete = true;
+        for transform in &options.transforms {
+            match transform["op"].as_str().unwrap_or_default() {
+                "trim" => self.auto_trim.set(true),
+                "flip_horizontal" => self.flip_horizontal.set(true),
+                "flip_vertical" => self.flip_vertical.set(true),
+                "rotate" => match transform["degrees"].as_f64() {
+                    Some(degrees) if degrees.fract() == 0.0 && (degrees as i64) % 90 == 0 => {
+                        self.rotate.set((degrees as i64).rem_euclid(360).to_string());
+                    }
+                    _ => complete = false,
+                },
+                "aspect_crop" => match (dimension(transform, "width"), dimension(transform, "height")) {
+                    (Some(width), Some(height)) => self.aspect_crop.set(format!("{}:{}", width, height)),
+                    _ => complete = false,
+                },
+                "resize" => {
+                    self.resize_width.set(dimension(transform, "width"));
+                    self.resize_height.set(dimension(transform, "height"));
+                }
+                _ => complete = false,
+            }
+        }
+        complete
+    }
+
+    /// Watermark for the compression request; `None` when disabled or when neither a logo
+    /// nor text is set
+    pub fn watermark_request(&self) -> Option<WatermarkSettings> {
+        if !self.watermark_enabled.get_untracked() {
+            return None;
+        }
+        let watermark = self.watermark.get_untracked();
+        let has_logo = watermark.image_path.as_deref().is_some_and(|p| !p.is_empty());
+        (has_logo || !watermark.text.trim().is_empty()).then_some(watermark)
+    }
+
+    /// Compression request for `file_paths` with the current settings
+    pub fn compression_request(&self, file_paths: Vec<String>) -> CompressionRequest {
+        CompressionRequest {
+            file_paths,
+            compression_level: self.compression_level.get_untracked(),
+            output_format: self.output_format.get_untracked(),
+            oxipng: self.oxipng.get_untracked(),
+            png_lossy: self.png_lossy.get_untracked(),
+            quality: self.quality.get_untracked(),
+            jxl_lossless_jpeg: self.jxl_lossless_jpeg.get_untracked(),
+            tiff_compression: self.tiff_compression.get_untracked(),
+            split_pages: self.split_pages.get_untracked(),
+            dither: self.dither.get_untracked(),
+            color_profile: self.color_profile.get_untracked(),
+            transforms: self.transforms(),
+            adjustments: self.adjustments.get_untracked(),
+            watermark: self.watermark_request(),
+            filename_template: self.filename_template.get_untracked().trim().to_string(),
+        }
+    }
+
+    /// Results whose bytes still need saving; folder-watch results are already on disk
+    pub fn unsaved_results(&self) -> Vec<CompressionResult> {
+        self.results
+            .get_untracked()
+            .into_iter()
+            .filter(|r| !r.is_saved())
+            .collect()
+    }
+
     /// Reset collision modal state when modal closes
     pub fn reset_collision_state(&self) {
         self.show_collision_modal.set(false);
@@ -60,6 +60,32 @@ pub async fn invoke_tauri<T>(cmd: &str, args: JsValue) -> Result<T, String>
 where
     T: serde::de::DeserializeOwned,
 {
+    let result = invoke_value(cmd, args).await?;
+
+    let json_str = js_sys::JSON::stringify(&result)
+        .map_err(|_| "Failed to stringify")?
+        .as_string()
+        .ok_or("Not a string")?;
+
+    serde_json::from_str(&json_str).map_err(|e| format!("Deserialize error: {}", e))
+}
+
+/// Invoke a command that takes a raw byte body (`tauri::ipc::Request`) instead of JSON args.
+pub async fn invoke_tauri_raw<T>(cmd: &str, body: &[u8]) -> Result<T, String>
+where
+    T: serde::de::DeserializeOwned,
+{
+    invoke_tauri(cmd, js_sys::Uint8Array::from(body).into()).await
+}
+
+/// Invoke a command that returns raw bytes (`tauri::ipc::Response`) instead of JSON.
+pub async fn invoke_tauri_bytes(cmd: &str, args: JsValue) -> Result<Vec<u8>, String> {
+    let result = invoke_value(cmd, args).await?;
+    // An `ArrayBuffer`; `Uint8Array::new` also accepts a plain number array.
+    Ok(js_sys::Uint8Array::new(&result).to_vec())
+}
+
+async fn invoke_value(cmd: &str, args: JsValue) -> Result<JsValue, String> {
     if !is_tauri_available() {
         // This is expected when running in browser mode - not an error, just informational
         return Err("Tauri API not available (running in browser). Use 'cargo tauri dev' for full file system access.".to_string());
@@ -115,10 +141,70 @@ where
     .await
     .map_err(|e| format!("Promise rejected: {:?}", e))?;
 
-    let json_str = js_sys::JSON::stringify(&result)
-        .map_err(|_| "Failed to stringify")?
-        .as_string()
-        .ok_or("Not a string")?;
+    Ok(result)
+}
 
-    serde_json::from_str(&json_str).map_err(|e| format!("Deserialize error: {}", e))
+/// A `__TAURI__.event.listen` subscription; dropping it unlistens and frees the handler.
+pub struct TauriListener {
+    unlisten: js_sys::Function,
+    _handler: Closure<dyn FnMut(JsValue)>,
+}
+
+impl Drop for TauriListener {
+    fn drop(&mut self) {
+        let _ = self.unlisten.call0(&JsValue::NULL);
+    }
+}
+
+/// Subscribe to a Tauri event; `handler` receives each event's deserialized `payload`.
+pub async fn listen_tauri<T>(event: &str, mut handler: impl FnMut(T) + 'static) -> Result<TauriListener, String>
+where
+    T: serde::de::DeserializeOwned + 'static,
+{
+    if !is_tauri_available() {
+        return Err("Tauri API not available".to_string());
+    }
+
+    let window = web_sys::window().ok_or("No window object")?;
+    let event_api = js_sys::Reflect::get(&window, &JsValue::from_str("__TAURI__"))
+        .and_then(|tauri| js_sys::Reflect::get(&tauri, &JsValue::from_str("event")))
+        .ok()
+        .filter(|api| !api.is_undefined())
+        .ok_or("Tauri event API not available")?;
+    let listen = js_sys::Reflect::get(&event_api, &JsValue::from_str("listen"))
+        .ok()
+        .and_then(|f| f.dyn_ref::<js_sys::Function>().cloned())
+        .ok_or("listen function not found")?;
+
+    let event_name = event.to_string();
+    let handler = Closure::<dyn FnMut(JsValue)>::new(move |event: JsValue| {
+        let payload = js_sys::Reflect::get(&event, &JsValue::from_str("payload"))
+            .ok()
+            .and_then(|p| js_sys::JSON::stringify(&p).ok())
+            .and_then(|s| s.as_string());
+        match payload.map(|json| serde_json::from_str::<T>(&json)) {
+            Some(Ok(payload)) => handler(payload),
+            Some(Err(e)) => web_sys::console::warn_1(&JsValue::from_str(&format!(
+                "Ignoring malformed {} event: {}",
+                event_name, e
+            ))),
+            None => web_sys::console::warn_1(&JsValue::from_str(&format!(
+                "Ignoring {} event without payload",
+                event_name
+            ))),
+        }
+    });
+
+    let promise = listen
+        .call2(&event_api, &JsValue::from_str(event), handler.as_ref().unchecked_ref())
+        .map_err(|_| "Failed to call listen")?;
+    let unlisten = wasm_bindgen_futures::JsFuture::from(
+        promise.dyn_into::<js_sys::Promise>().map_err(|_| "Not a promise")?
+    )
+    .await
+    .map_err(|e| format!("listen rejected: {:?}", e))?
+    .dyn_into::<js_sys::Function>()
+    .map_err(|_| "listen did not return an unlisten function")?;
+
+    Ok(TauriListener { unlisten, _handler: handler })
 }